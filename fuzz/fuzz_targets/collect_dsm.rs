@@ -0,0 +1,38 @@
+#![no_main]
+
+use galileo_osnma::bitfields::{DsmHeader, DsmKroot, DsmPkr, DsmType, NmaHeader};
+use galileo_osnma::dsm::CollectDsm;
+use galileo_osnma::merkle_tree::MerkleTree;
+use galileo_osnma::types::{DsmBlock, DSM_BLOCK_BYTES};
+use libfuzzer_sys::fuzz_target;
+
+// Feeds a sequence of (DSM header, DSM block) pairs to CollectDsm and parses
+// every DSM that gets completed, as the HKROOT processing in Osnma does.
+fuzz_target!(|data: &[u8]| {
+    let mut collect = CollectDsm::new();
+    for chunk in data.chunks_exact(1 + DSM_BLOCK_BYTES) {
+        let header = DsmHeader(chunk[..1].try_into().unwrap());
+        let block: &DsmBlock = chunk[1..].try_into().unwrap();
+        let Some(dsm) = collect.feed(header, block) else {
+            continue;
+        };
+        match dsm.dsm_type() {
+            DsmType::Kroot => {
+                let dsm_kroot = DsmKroot(dsm.data());
+                if dsm_kroot.try_ecdsa_function().is_some() {
+                    let _ = dsm_kroot.kroot();
+                    let _ = dsm_kroot.digital_signature();
+                    let _ = dsm_kroot.padding();
+                }
+                let _ = dsm_kroot.check_padding(NmaHeader::new(0x52));
+            }
+            DsmType::Pkr => {
+                let dsm_pkr = DsmPkr(dsm.data());
+                let tree = MerkleTree::new([0; 32]);
+                let _ = dsm_pkr.check_padding(&[0; 32]);
+                let _ = tree.validate_pkr(dsm_pkr);
+                let _ = tree.validate_alert_message(dsm_pkr);
+            }
+        }
+    }
+});
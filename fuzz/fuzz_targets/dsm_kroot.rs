@@ -0,0 +1,39 @@
+#![no_main]
+
+use galileo_osnma::bitfields::{DsmKroot, NmaHeader};
+use galileo_osnma::tesla::{Chain, Key};
+use galileo_osnma::types::DSM_BLOCK_BYTES;
+use galileo_osnma::PublicKey;
+use libfuzzer_sys::fuzz_target;
+use p256::ecdsa::VerifyingKey;
+
+// Parses arbitrary DSM-KROOT messages of any of the sizes allowed by NB_DK
+// and attempts to validate them against a fixed public key.
+fuzz_target!(|data: &[u8]| {
+    let Some((&nma_header, data)) = data.split_first() else {
+        return;
+    };
+    let num_blocks = data.len() / DSM_BLOCK_BYTES;
+    if !(7..=14).contains(&num_blocks) {
+        return;
+    }
+    let dsm_kroot = DsmKroot(&data[..num_blocks * DSM_BLOCK_BYTES]);
+    let _ = dsm_kroot.number_of_blocks();
+    let _ = dsm_kroot.tag_size();
+    let _ = dsm_kroot.alpha();
+    if dsm_kroot.try_ecdsa_function().is_some() {
+        let _ = dsm_kroot.kroot();
+        let _ = dsm_kroot.digital_signature();
+        let _ = dsm_kroot.padding();
+    }
+    let nma_header = NmaHeader::new(nma_header);
+    let _ = dsm_kroot.check_padding(nma_header);
+    let _ = Chain::from_dsm_kroot(dsm_kroot);
+    let pubkey = [
+        3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33, 117, 116, 91, 202, 57, 34, 72, 200, 202,
+        10, 169, 253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218,
+    ];
+    let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    let pubkey = PublicKey::from_p256(pubkey, dsm_kroot.public_key_id()).force_valid();
+    let _ = Key::from_dsm_kroot(nma_header, dsm_kroot, &pubkey);
+});
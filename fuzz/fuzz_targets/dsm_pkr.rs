@@ -0,0 +1,32 @@
+#![no_main]
+
+use galileo_osnma::bitfields::DsmPkr;
+use galileo_osnma::merkle_tree::MerkleTree;
+use galileo_osnma::types::{MerkleTreeNode, DSM_BLOCK_BYTES};
+use libfuzzer_sys::fuzz_target;
+
+// Parses arbitrary DSM-PKR messages of any of the sizes allowed by NB_DP
+// and attempts to validate them against a fixed Merkle tree root.
+fuzz_target!(|data: &[u8]| {
+    let num_blocks = data.len() / DSM_BLOCK_BYTES;
+    if !(13..=16).contains(&num_blocks) {
+        return;
+    }
+    let dsm_pkr = DsmPkr(&data[..num_blocks * DSM_BLOCK_BYTES]);
+    let _ = dsm_pkr.number_of_blocks();
+    let _ = dsm_pkr.message_id();
+    for node in 0..4 {
+        let _ = dsm_pkr.intermediate_tree_node(node);
+    }
+    let _ = dsm_pkr.new_public_key_type();
+    let _ = dsm_pkr.new_public_key_id();
+    let _ = dsm_pkr.key_size();
+    let _ = dsm_pkr.new_public_key();
+    let _ = dsm_pkr.padding();
+    let _ = dsm_pkr.merkle_tree_leaf();
+    let root: MerkleTreeNode = [0; 32];
+    let _ = dsm_pkr.check_padding(&root);
+    let tree = MerkleTree::new(root);
+    let _ = tree.validate_pkr(dsm_pkr);
+    let _ = tree.validate_alert_message(dsm_pkr);
+});
@@ -0,0 +1,168 @@
+#![no_main]
+
+use galileo_osnma::bitfields::{
+    ChainAndPubkeyStatus, HashFunction, MacFunction, NmaStatus, OwnedDsmKroot,
+};
+use galileo_osnma::builders::{DsmKrootBuilder, NmaHeaderBuilder};
+use galileo_osnma::storage::FullStorage;
+use galileo_osnma::tesla::{Chain, Key};
+use galileo_osnma::types::{
+    OsnmaDataMessage, DSM_BLOCK_BYTES, HKROOT_MESSAGE_BYTES, HKROOT_SECTION_BYTES,
+    MACK_MESSAGE_BYTES, MACK_SECTION_BYTES,
+};
+use galileo_osnma::{Gst, Osnma, PublicKey, Svn};
+use libfuzzer_sys::fuzz_target;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use std::sync::OnceLock;
+
+// Parameters of the TESLA chain: 128-bit keys, 40-bit tags and MAC look-up
+// table 34. The KROOT is the key of the subframe before WNK and TOWHK.
+const KROOT_WN: u16 = 1177;
+const KROOT_TOWH: u8 = 48;
+const KEY_BYTES: usize = 16;
+// Position of the TESLA key in the MACK message, after 6 tags and Tag-Info
+// sections of 56 bits
+const KEY_START: usize = 6 * 56 / 8;
+// Number of keys of the chain, which covers the pages of the longest inputs
+const CHAIN_KEYS: usize = 512;
+
+// Osnma black box that has verified a DSM-KROOT, together with the keys of
+// its TESLA chain. The DSM-KROOT is signed with a fixed ECDSA P-256 key.
+struct Seed {
+    osnma: Osnma<FullStorage>,
+    // GST of the KROOT
+    kroot_gst: Gst,
+    // Keys of the chain, starting with the KROOT
+    keys: Vec<[u8; KEY_BYTES]>,
+}
+
+impl Seed {
+    fn new() -> Seed {
+        let signing_key = SigningKey::from_slice(&[0x5a; 32]).unwrap();
+        let pubkey = PublicKey::from_p256(*signing_key.verifying_key(), 0).force_valid();
+        let kroot_time = Gst::new(KROOT_WN, u32::from(KROOT_TOWH) * 3600);
+        let kroot_gst = kroot_time.add_seconds(-30);
+        let nma_header = NmaHeaderBuilder {
+            nma_status: NmaStatus::Operational,
+            chain_id: 1,
+            chain_and_pubkey_status: ChainAndPubkeyStatus::Nominal,
+        }
+        .build()
+        .unwrap();
+        let builder = |kroot| DsmKrootBuilder {
+            public_key_id: 0,
+            kroot_chain_id: 1,
+            hash_function: HashFunction::Sha256,
+            mac_function: MacFunction::HmacSha256,
+            key_size: 8 * KEY_BYTES,
+            tag_size: 40,
+            mac_lookup_table: 34,
+            kroot_wn: KROOT_WN,
+            kroot_towh: KROOT_TOWH,
+            alpha: 0x25d3964da3a2,
+            kroot,
+        };
+
+        // The chain parameters do not depend on the KROOT, so they are
+        // extracted from a DSM-KROOT with a placeholder KROOT and signature.
+        let placeholder = builder(&[0; KEY_BYTES])
+            .build(nma_header, &[0; 64])
+            .unwrap();
+        let chain = Chain::from_dsm_kroot(placeholder.dsm_kroot()).unwrap();
+        let last_gst = kroot_gst.add_subframes(i32::try_from(CHAIN_KEYS).unwrap() - 1);
+        let mut key = Key::from_slice(&[0xa5; KEY_BYTES], last_gst, &chain).force_valid();
+        let mut keys = Vec::with_capacity(CHAIN_KEYS);
+        for _ in 0..CHAIN_KEYS {
+            keys.push(<[u8; KEY_BYTES]>::try_from(key.data()).unwrap());
+            key = key.one_way_function();
+        }
+        keys.reverse();
+
+        let builder = builder(&keys[0]);
+        let message = builder.signature_message(nma_header).unwrap();
+        let signature: Signature = signing_key.sign(message.as_ref());
+        let dsm_kroot: OwnedDsmKroot = builder.build(nma_header, &signature.to_bytes()).unwrap();
+
+        // Each DSM block is transmitted by a different satellite in the
+        // subframe of KROOT_TIME.
+        let hkroots = dsm_kroot
+            .dsm_kroot()
+            .0
+            .chunks_exact(DSM_BLOCK_BYTES)
+            .enumerate()
+            .map(|(j, block)| {
+                let mut hkroot = [0; HKROOT_MESSAGE_BYTES];
+                hkroot[0] = nma_header.data();
+                hkroot[1] = u8::try_from(j).unwrap();
+                hkroot[2..].copy_from_slice(block);
+                hkroot
+            })
+            .collect::<Vec<_>>();
+        let mut mack = [0xff; MACK_MESSAGE_BYTES];
+        mack[KEY_START..KEY_START + KEY_BYTES].copy_from_slice(&keys[1]);
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+        for page in 0..HKROOT_MESSAGE_BYTES {
+            let gst = kroot_time.add_seconds(2 * i32::try_from(page).unwrap());
+            for (j, hkroot) in hkroots.iter().enumerate() {
+                let mut osnma_data = OsnmaDataMessage::default();
+                osnma_data[..HKROOT_SECTION_BYTES].copy_from_slice(&hkroot[page..page + 1]);
+                osnma_data[HKROOT_SECTION_BYTES..].copy_from_slice(
+                    &mack[page * MACK_SECTION_BYTES..(page + 1) * MACK_SECTION_BYTES],
+                );
+                osnma.feed_osnma(&osnma_data, Svn::try_from(j + 1).unwrap(), gst);
+            }
+        }
+        assert!(osnma.tesla_key().is_some());
+        Seed {
+            osnma,
+            kroot_gst,
+            keys,
+        }
+    }
+
+    // Replaces the part of the TESLA key that is transmitted in the page at
+    // `gst` by the key of the chain.
+    fn insert_key(&self, osnma_data: &mut OsnmaDataMessage, gst: Gst) {
+        let Some(key) = usize::try_from(gst.gst_subframe().subframes_difference(self.kroot_gst))
+            .ok()
+            .and_then(|n| self.keys.get(n))
+        else {
+            return;
+        };
+        let page = usize::try_from(gst.tow() % 30 / 2).unwrap();
+        let mack = &mut osnma_data[HKROOT_SECTION_BYTES..];
+        for (j, byte) in mack.iter_mut().enumerate() {
+            let pos = page * MACK_SECTION_BYTES + j;
+            if (KEY_START..KEY_START + KEY_BYTES).contains(&pos) {
+                *byte = key[pos - KEY_START];
+            }
+        }
+    }
+}
+
+static SEED: OnceLock<Seed> = OnceLock::new();
+
+// Feeds arbitrary OSNMA data messages from several satellites to an Osnma
+// black box, advancing the GST by one page (2 seconds) per message. The black
+// box has verified a DSM-KROOT, and unless the most significant bit of the
+// first byte of the message is set, the TESLA key in the MACK message is
+// replaced by the key of the chain, so that the keys can be verified and the
+// MACK messages are processed.
+fuzz_target!(|data: &[u8]| {
+    let seed = SEED.get_or_init(Seed::new);
+    let mut osnma = seed.osnma.clone();
+    let mut gst = seed.kroot_gst.add_subframes(2);
+    for chunk in data.chunks_exact(1 + HKROOT_SECTION_BYTES + MACK_SECTION_BYTES) {
+        let Ok(svn) = Svn::try_from(1 + usize::from(chunk[0] & 0x7f) % 36) else {
+            continue;
+        };
+        let mut osnma_data: OsnmaDataMessage = chunk[1..].try_into().unwrap();
+        if chunk[0] & 0x80 == 0 {
+            seed.insert_key(&mut osnma_data, gst);
+        }
+        osnma.feed_osnma(&osnma_data, svn, gst);
+        let _ = osnma.get_ced_and_status(svn);
+        let _ = osnma.get_timing_parameters(svn);
+        gst = gst.add_seconds(2);
+    }
+});
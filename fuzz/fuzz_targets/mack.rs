@@ -0,0 +1,35 @@
+#![no_main]
+
+use galileo_osnma::bitfields::Mack;
+use galileo_osnma::types::{MackMessage, MACK_MESSAGE_BYTES};
+use libfuzzer_sys::fuzz_target;
+
+// Key and tag sizes in bits allowed by the KS and TS fields of the DSM-KROOT.
+const KEY_SIZES: [usize; 9] = [96, 104, 112, 120, 128, 160, 192, 224, 256];
+const TAG_SIZES: [usize; 5] = [20, 24, 28, 32, 40];
+
+// Splits an arbitrary MACK message into its Tag-Info sections using any of
+// the valid key and tag sizes.
+fuzz_target!(|data: &[u8]| {
+    let Some((&sizes, data)) = data.split_first() else {
+        return;
+    };
+    let Some(mack) = data.get(..MACK_MESSAGE_BYTES) else {
+        return;
+    };
+    let mack: &MackMessage = mack.try_into().unwrap();
+    let key_size = KEY_SIZES[usize::from(sizes & 0xf) % KEY_SIZES.len()];
+    let tag_size = TAG_SIZES[usize::from(sizes >> 4) % TAG_SIZES.len()];
+    let mack = Mack::new(mack, key_size, tag_size);
+    let _ = mack.tag0();
+    let _ = mack.macseq();
+    let _ = mack.cop();
+    let _ = mack.key();
+    for n in 1..mack.num_tags() {
+        let tag_and_info = mack.tag_and_info(n);
+        let _ = tag_and_info.tag();
+        let _ = tag_and_info.prnd();
+        let _ = tag_and_info.adkd();
+        let _ = tag_and_info.cop();
+    }
+});
@@ -0,0 +1,547 @@
+//! # C bindings for galileo-osnma
+//!
+//! This crate exposes the [`Osnma`] black box of the
+//! [galileo-osnma](https://github.com/daniestevez/galileo-osnma) crate to C
+//! through an opaque [`GalileoOsnma`] handle. The C header
+//! `galileo_osnma.h` is generated with
+//! [cbindgen](https://github.com/mozilla/cbindgen) in the `OUT_DIR` of the
+//! build script when this crate is built.
+//!
+//! All the functions return a [`GalileoOsnmaError`] code. Pointers passed to
+//! the functions are checked for NULL, and panics are caught before they
+//! reach the C caller, in which case [`GalileoOsnmaError::Panic`] is
+//! returned.
+
+#![warn(missing_docs)]
+
+use galileo_osnma::{
+    storage::{FullStorage, SmallStorage},
+    types::{INAV_WORD_BYTES, MERKLE_TREE_NODE_BYTES},
+    Gst, InavBand, Osnma, PublicKey, Svn, Validated,
+};
+use std::ffi::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Size in bytes of an INAV word.
+pub const GALILEO_OSNMA_INAV_WORD_BYTES: usize = 16;
+/// Size in bytes of the OSNMA data in an INAV page.
+pub const GALILEO_OSNMA_OSNMA_DATA_BYTES: usize = 5;
+/// Size in bytes of the Merkle tree root.
+pub const GALILEO_OSNMA_MERKLE_ROOT_BYTES: usize = 32;
+/// Size in bytes of the buffer required to hold the CED and health status
+/// data (549 bits).
+pub const GALILEO_OSNMA_CED_AND_STATUS_BYTES: usize = 69;
+/// Size in bytes of the buffer required to hold the timing parameters data
+/// (141 bits).
+pub const GALILEO_OSNMA_TIMING_PARAMETERS_BYTES: usize = 18;
+
+const _: () = assert!(GALILEO_OSNMA_INAV_WORD_BYTES == INAV_WORD_BYTES);
+const _: () = assert!(GALILEO_OSNMA_MERKLE_ROOT_BYTES == MERKLE_TREE_NODE_BYTES);
+
+/// Error codes returned by the C API.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum GalileoOsnmaError {
+    /// The function completed successfully.
+    Ok = 0,
+    /// A required pointer argument was NULL.
+    NullPointer,
+    /// The SVN is outside of the range 1-36.
+    InvalidSvn,
+    /// The TOW is greater or equal to 604800.
+    InvalidTow,
+    /// The public key is not a valid SEC1 encoded P-256 or P-521 key.
+    InvalidPublicKey,
+    /// The public key ID is greater than 15.
+    InvalidPkid,
+    /// The storage size is not a [`GalileoOsnmaStorage`] value.
+    InvalidStorage,
+    /// The band is not a [`GalileoOsnmaInavBand`] value.
+    InvalidBand,
+    /// Neither a Merkle tree root nor a public key was given.
+    MissingKeyMaterial,
+    /// There is no authenticated data for the requested SVN.
+    NotAvailable,
+    /// The buffer given by the caller is too small to hold the data.
+    BufferTooSmall,
+    /// An internal error (a Rust panic) happened.
+    Panic,
+}
+
+/// Storage size of the OSNMA black box.
+///
+/// The functions of the C API take the storage size as a `uint32_t`, since
+/// an enum argument with a value outside of the enum would be undefined
+/// behaviour in Rust.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum GalileoOsnmaStorage {
+    /// Storage for all the 36 satellites (uses `FullStorage`).
+    Full = 0,
+    /// Storage for a reduced number of satellites, intended for small
+    /// microcontrollers (uses `SmallStorage`).
+    Small = 1,
+}
+
+/// Galileo band with INAV data.
+///
+/// The functions of the C API take the band as a `uint32_t`, in the same way
+/// as the [`GalileoOsnmaStorage`].
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum GalileoOsnmaInavBand {
+    /// E1B band.
+    E1B = 0,
+    /// E5b band.
+    E5B = 1,
+}
+
+// Implements the conversion from the integer values received from C, which
+// might be outside of the enum.
+macro_rules! impl_try_from_u32 {
+    ($ty:ident, $error:ty, $invalid:expr, [$($variant:ident),*]) => {
+        impl TryFrom<u32> for $ty {
+            type Error = $error;
+
+            fn try_from(value: u32) -> Result<$ty, $error> {
+                $(
+                    if value == $ty::$variant as u32 {
+                        return Ok($ty::$variant);
+                    }
+                )*
+                Err($invalid)
+            }
+        }
+    };
+}
+
+impl_try_from_u32!(
+    GalileoOsnmaError,
+    (),
+    (),
+    [
+        Ok,
+        NullPointer,
+        InvalidSvn,
+        InvalidTow,
+        InvalidPublicKey,
+        InvalidPkid,
+        InvalidStorage,
+        InvalidBand,
+        MissingKeyMaterial,
+        NotAvailable,
+        BufferTooSmall,
+        Panic
+    ]
+);
+impl_try_from_u32!(
+    GalileoOsnmaStorage,
+    GalileoOsnmaError,
+    GalileoOsnmaError::InvalidStorage,
+    [Full, Small]
+);
+impl_try_from_u32!(
+    GalileoOsnmaInavBand,
+    GalileoOsnmaError,
+    GalileoOsnmaError::InvalidBand,
+    [E1B, E5B]
+);
+
+impl From<GalileoOsnmaInavBand> for InavBand {
+    fn from(band: GalileoOsnmaInavBand) -> InavBand {
+        match band {
+            GalileoOsnmaInavBand::E1B => InavBand::E1B,
+            GalileoOsnmaInavBand::E5B => InavBand::E5B,
+        }
+    }
+}
+
+/// Metadata of authenticated navigation data.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct GalileoOsnmaNavData {
+    /// Week number of the GST of the data.
+    pub wn: u16,
+    /// Time of week of the GST of the data.
+    pub tow: u32,
+    /// Sum of the length in bits of the tags that authenticated the data.
+    pub authbits: u16,
+    /// Length of the data in bits.
+    ///
+    /// The data is written MSB-first into the buffer, and occupies
+    /// `num_bytes` bytes.
+    pub num_bits: usize,
+    /// Size in bytes of the buffer required to hold the data.
+    pub num_bytes: usize,
+}
+
+/// OSNMA black box.
+///
+/// This is an opaque handle that is created with [`galileo_osnma_new`] and
+/// destroyed with [`galileo_osnma_free`].
+pub struct GalileoOsnma {
+    inner: Inner,
+}
+
+// The handle is always heap allocated, so the size difference between the
+// variants does not matter.
+#[allow(clippy::large_enum_variant)]
+enum Inner {
+    Full(Osnma<FullStorage>),
+    Small(Osnma<SmallStorage>),
+}
+
+macro_rules! with_osnma {
+    ($handle:expr, $osnma:ident => $e:expr) => {
+        match $handle {
+            Inner::Full($osnma) => $e,
+            Inner::Small($osnma) => $e,
+        }
+    };
+}
+
+// Runs f catching any panics, since unwinding into C is undefined behaviour.
+fn guard<F: FnOnce() -> GalileoOsnmaError>(f: F) -> GalileoOsnmaError {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(GalileoOsnmaError::Panic)
+}
+
+fn parse_svn(svn: u8) -> Result<Svn, GalileoOsnmaError> {
+    Svn::try_from(svn).map_err(|_| GalileoOsnmaError::InvalidSvn)
+}
+
+fn parse_gst(wn: u16, tow: u32) -> Result<Gst, GalileoOsnmaError> {
+    if tow >= 604800 {
+        return Err(GalileoOsnmaError::InvalidTow);
+    }
+    Ok(Gst::new(wn, tow))
+}
+
+fn parse_pubkey(sec1: &[u8], pkid: u8) -> Result<PublicKey<Validated>, GalileoOsnmaError> {
+    // The PKID field of the DSM-KROOT has 4 bits
+    if pkid > 15 {
+        return Err(GalileoOsnmaError::InvalidPkid);
+    }
+    let pubkey = match sec1.len() {
+        // compressed or uncompressed P-256 point
+        33 | 65 => p256::ecdsa::VerifyingKey::from_sec1_bytes(sec1)
+            .map(|k| PublicKey::from_p256(k, pkid))
+            .map_err(|_| GalileoOsnmaError::InvalidPublicKey)?,
+        // compressed or uncompressed P-521 point
+        #[cfg(feature = "p521")]
+        67 | 133 => p521::ecdsa::VerifyingKey::from_sec1_bytes(sec1)
+            .map(|k| PublicKey::from_p521(k, pkid))
+            .map_err(|_| GalileoOsnmaError::InvalidPublicKey)?,
+        _ => return Err(GalileoOsnmaError::InvalidPublicKey),
+    };
+    Ok(pubkey.force_valid())
+}
+
+/// Creates a new OSNMA black box.
+///
+/// The black box is constructed using a Merkle tree root, an ECDSA public
+/// key, or both. At least one of them must be given. `storage` is one of the
+/// [`GalileoOsnmaStorage`] values.
+///
+/// `merkle_root` points to the 32-byte Merkle tree root, or is NULL if no
+/// Merkle tree root is used. `pubkey` points to a SEC1 encoded (compressed
+/// or uncompressed) P-256 or P-521 public key of length `pubkey_len`, or is
+/// NULL if no public key is used. `pkid` is the public key ID of this public
+/// key, which must be in the range 0-15, and it is ignored if `pubkey` is
+/// NULL.
+///
+/// If `only_slowmac` is true, only ADKD=12 (Slow MAC) will be processed.
+///
+/// On success, the handle of the new black box is written to `out`, and it
+/// must be released with [`galileo_osnma_free`].
+///
+/// # Safety
+///
+/// `merkle_root` must be NULL or point to 32 readable bytes. `pubkey` must be
+/// NULL or point to `pubkey_len` readable bytes. `out` must be NULL or point
+/// to writable memory for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn galileo_osnma_new(
+    storage: u32,
+    merkle_root: *const u8,
+    pubkey: *const u8,
+    pubkey_len: usize,
+    pkid: u8,
+    only_slowmac: bool,
+    out: *mut *mut GalileoOsnma,
+) -> GalileoOsnmaError {
+    guard(|| {
+        if out.is_null() {
+            return GalileoOsnmaError::NullPointer;
+        }
+        let storage = match GalileoOsnmaStorage::try_from(storage) {
+            Ok(storage) => storage,
+            Err(e) => return e,
+        };
+        let merkle_root = if merkle_root.is_null() {
+            None
+        } else {
+            Some(
+                merkle_root
+                    .cast::<[u8; MERKLE_TREE_NODE_BYTES]>()
+                    .read_unaligned(),
+            )
+        };
+        let pubkey = if pubkey.is_null() {
+            None
+        } else {
+            match parse_pubkey(std::slice::from_raw_parts(pubkey, pubkey_len), pkid) {
+                Ok(k) => Some(k),
+                Err(e) => return e,
+            }
+        };
+        macro_rules! build {
+            () => {
+                match (merkle_root, pubkey) {
+                    (Some(root), pubkey) => Osnma::from_merkle_tree(root, pubkey, only_slowmac),
+                    (None, Some(pubkey)) => Osnma::from_pubkey(pubkey, only_slowmac),
+                    (None, None) => return GalileoOsnmaError::MissingKeyMaterial,
+                }
+            };
+        }
+        let inner = match storage {
+            GalileoOsnmaStorage::Full => Inner::Full(build!()),
+            GalileoOsnmaStorage::Small => Inner::Small(build!()),
+        };
+        *out = Box::into_raw(Box::new(GalileoOsnma { inner }));
+        GalileoOsnmaError::Ok
+    })
+}
+
+/// Destroys an OSNMA black box.
+///
+/// Passing NULL is allowed and does nothing.
+///
+/// # Safety
+///
+/// `osnma` must be NULL or a handle returned by [`galileo_osnma_new`] that
+/// has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn galileo_osnma_free(osnma: *mut GalileoOsnma) {
+    if !osnma.is_null() {
+        drop(Box::from_raw(osnma));
+    }
+}
+
+/// Feeds an INAV word into the OSNMA black box.
+///
+/// `word` points to the 16-byte INAV word, `svn` is the SVN of the satellite
+/// that transmitted it, `wn` and `tow` give the GST at the start of the INAV
+/// page transmission, and `band` is the band in which the word was received,
+/// given as one of the [`GalileoOsnmaInavBand`] values.
+///
+/// # Safety
+///
+/// `osnma` must be NULL or a valid handle. `word` must be NULL or point to 16
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn galileo_osnma_feed_inav(
+    osnma: *mut GalileoOsnma,
+    word: *const u8,
+    svn: u8,
+    wn: u16,
+    tow: u32,
+    band: u32,
+) -> GalileoOsnmaError {
+    guard(|| {
+        let (Some(osnma), false) = (osnma.as_mut(), word.is_null()) else {
+            return GalileoOsnmaError::NullPointer;
+        };
+        let word = word
+            .cast::<[u8; GALILEO_OSNMA_INAV_WORD_BYTES]>()
+            .read_unaligned();
+        let (svn, gst, band) = match (
+            parse_svn(svn),
+            parse_gst(wn, tow),
+            GalileoOsnmaInavBand::try_from(band),
+        ) {
+            (Ok(svn), Ok(gst), Ok(band)) => (svn, gst, band),
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return e,
+        };
+        with_osnma!(&mut osnma.inner, o => o.feed_inav(&word, svn, gst, band.into()));
+        GalileoOsnmaError::Ok
+    })
+}
+
+/// Feeds the OSNMA data of an INAV page into the OSNMA black box.
+///
+/// `data` points to the 5 bytes of OSNMA data (the HKROOT and MACK
+/// sections), `svn` is the SVN of the satellite that transmitted it, and `wn`
+/// and `tow` give the GST at the start of the INAV page transmission. Only
+/// the OSNMA data of the E1B band should be fed.
+///
+/// # Safety
+///
+/// `osnma` must be NULL or a valid handle. `data` must be NULL or point to 5
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn galileo_osnma_feed_osnma(
+    osnma: *mut GalileoOsnma,
+    data: *const u8,
+    svn: u8,
+    wn: u16,
+    tow: u32,
+) -> GalileoOsnmaError {
+    guard(|| {
+        let (Some(osnma), false) = (osnma.as_mut(), data.is_null()) else {
+            return GalileoOsnmaError::NullPointer;
+        };
+        let data = data
+            .cast::<[u8; GALILEO_OSNMA_OSNMA_DATA_BYTES]>()
+            .read_unaligned();
+        let (svn, gst) = match (parse_svn(svn), parse_gst(wn, tow)) {
+            (Ok(svn), Ok(gst)) => (svn, gst),
+            (Err(e), _) | (_, Err(e)) => return e,
+        };
+        with_osnma!(&mut osnma.inner, o => o.feed_osnma(&data, svn, gst));
+        GalileoOsnmaError::Ok
+    })
+}
+
+// Selects which data is retrieved by get_data.
+#[derive(Copy, Clone)]
+enum DataKind {
+    CedAndStatus,
+    TimingParameters,
+}
+
+unsafe fn get_data(
+    osnma: *const GalileoOsnma,
+    kind: DataKind,
+    svn: u8,
+    buf: *mut u8,
+    buf_len: usize,
+    info: *mut GalileoOsnmaNavData,
+) -> GalileoOsnmaError {
+    guard(|| {
+        // A NULL buffer with a length of 0 queries the size of the data,
+        // which is only useful if the metadata is written.
+        let size_query = buf.is_null() && buf_len == 0;
+        let Some(osnma) = osnma.as_ref() else {
+            return GalileoOsnmaError::NullPointer;
+        };
+        if (buf.is_null() && !size_query) || (size_query && info.is_null()) {
+            return GalileoOsnmaError::NullPointer;
+        }
+        let svn = match parse_svn(svn) {
+            Ok(svn) => svn,
+            Err(e) => return e,
+        };
+        let data = with_osnma!(&osnma.inner, o => match kind {
+            DataKind::CedAndStatus => o.get_ced_and_status(svn),
+            DataKind::TimingParameters => o.get_timing_parameters(svn),
+        });
+        let Some(data) = data else {
+            return GalileoOsnmaError::NotAvailable;
+        };
+        let bits = data.data();
+        let num_bytes = (bits.len() + 7) / 8;
+        if let Some(info) = info.as_mut() {
+            *info = GalileoOsnmaNavData {
+                wn: data.gst().wn(),
+                tow: data.gst().tow(),
+                authbits: data.authbits(),
+                num_bits: bits.len(),
+                num_bytes,
+            };
+        }
+        if size_query {
+            return GalileoOsnmaError::Ok;
+        }
+        if buf_len < num_bytes {
+            return GalileoOsnmaError::BufferTooSmall;
+        }
+        let buf = std::slice::from_raw_parts_mut(buf, num_bytes);
+        buf.fill(0);
+        for (j, bit) in bits.iter().enumerate() {
+            if *bit {
+                buf[j / 8] |= 0x80 >> (j % 8);
+            }
+        }
+        GalileoOsnmaError::Ok
+    })
+}
+
+/// Retrieves the authenticated CED and health status data of a satellite.
+///
+/// The data is written MSB-first into `buf`, which has a size of `buf_len`
+/// bytes. A buffer of [`GALILEO_OSNMA_CED_AND_STATUS_BYTES`] bytes is always
+/// large enough. If `info` is not NULL, the metadata of the data is written
+/// to it. The metadata is also written when the buffer is too small, so that
+/// the caller can learn the required size.
+///
+/// The size can also be queried by passing a NULL `buf` with a `buf_len` of
+/// 0 and a non-NULL `info`. In this case only the metadata is written, and
+/// [`GalileoOsnmaError::Ok`] is returned if the data is available. The
+/// required size is given in the `num_bytes` field of `info`.
+///
+/// [`GalileoOsnmaError::NotAvailable`] is returned if there is no
+/// authenticated data for `svn`.
+///
+/// # Safety
+///
+/// `osnma` must be NULL or a valid handle. `buf` must be NULL or point to
+/// `buf_len` writable bytes. NULL is only accepted for `buf` if `buf_len` is
+/// 0. `info` must be NULL or point to a writable [`GalileoOsnmaNavData`].
+#[no_mangle]
+pub unsafe extern "C" fn galileo_osnma_get_ced_and_status(
+    osnma: *const GalileoOsnma,
+    svn: u8,
+    buf: *mut u8,
+    buf_len: usize,
+    info: *mut GalileoOsnmaNavData,
+) -> GalileoOsnmaError {
+    get_data(osnma, DataKind::CedAndStatus, svn, buf, buf_len, info)
+}
+
+/// Retrieves the authenticated timing parameters data of a satellite.
+///
+/// This works in the same way as [`galileo_osnma_get_ced_and_status`]. A
+/// buffer of [`GALILEO_OSNMA_TIMING_PARAMETERS_BYTES`] bytes is always large
+/// enough.
+///
+/// # Safety
+///
+/// `osnma` must be NULL or a valid handle. `buf` must be NULL or point to
+/// `buf_len` writable bytes. NULL is only accepted for `buf` if `buf_len` is
+/// 0. `info` must be NULL or point to a writable [`GalileoOsnmaNavData`].
+#[no_mangle]
+pub unsafe extern "C" fn galileo_osnma_get_timing_parameters(
+    osnma: *const GalileoOsnma,
+    svn: u8,
+    buf: *mut u8,
+    buf_len: usize,
+    info: *mut GalileoOsnmaNavData,
+) -> GalileoOsnmaError {
+    get_data(osnma, DataKind::TimingParameters, svn, buf, buf_len, info)
+}
+
+/// Returns a static NUL-terminated description of an error code.
+///
+/// `error` is one of the [`GalileoOsnmaError`] values. A description is also
+/// returned for other values.
+#[no_mangle]
+pub extern "C" fn galileo_osnma_error_string(error: u32) -> *const c_char {
+    let Ok(error) = GalileoOsnmaError::try_from(error) else {
+        return b"unknown error\0".as_ptr().cast();
+    };
+    let s: &'static [u8] = match error {
+        GalileoOsnmaError::Ok => b"no error\0",
+        GalileoOsnmaError::NullPointer => b"NULL pointer argument\0",
+        GalileoOsnmaError::InvalidSvn => b"SVN out of range 1-36\0",
+        GalileoOsnmaError::InvalidTow => b"TOW out of range\0",
+        GalileoOsnmaError::InvalidPublicKey => b"invalid public key\0",
+        GalileoOsnmaError::InvalidPkid => b"public key ID out of range 0-15\0",
+        GalileoOsnmaError::InvalidStorage => b"invalid storage size\0",
+        GalileoOsnmaError::InvalidBand => b"invalid INAV band\0",
+        GalileoOsnmaError::MissingKeyMaterial => b"no Merkle tree root or public key given\0",
+        GalileoOsnmaError::NotAvailable => b"no authenticated data available\0",
+        GalileoOsnmaError::BufferTooSmall => b"buffer too small\0",
+        GalileoOsnmaError::Panic => b"internal error\0",
+    };
+    s.as_ptr().cast()
+}
@@ -0,0 +1,399 @@
+/*
+ * Test program for the galileo-osnma C API.
+ *
+ * This is compiled and run by tests/c_api.rs. It returns a non-zero exit
+ * status if any of the checks fails.
+ *
+ * When run without arguments, it checks the construction of the black box
+ * and the error paths of the API. When run as
+ *
+ *     galileo_osnma_c_test PAGES MERKLE_ROOT
+ *
+ * it feeds the pages in the file PAGES into a black box constructed with the
+ * Merkle tree root MERKLE_ROOT (in hex), and writes the authenticated data to
+ * stdout, so that tests/c_api.rs can compare it with the data obtained from
+ * the Rust API. Each line in PAGES is either
+ *
+ *     P SVN WN TOW BAND WORD OSNMA
+ *
+ * where BAND is 0 for E1B and 1 for E5b, WORD is the INAV word in hex, or '-'
+ * if the page does not contain an INAV word, and OSNMA is the OSNMA data in
+ * hex, or
+ *
+ *     Q
+ *
+ * which requests writing the authenticated data of all the satellites.
+ */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "galileo_osnma.h"
+
+static int failures = 0;
+
+#define CHECK_ERR(call, expected)                                           \
+    do {                                                                    \
+        GalileoOsnmaError err_ = (call);                                    \
+        if (err_ != (expected)) {                                           \
+            fprintf(stderr, "%s:%d: %s returned %d (%s), expected %d\n",    \
+                    __FILE__, __LINE__, #call, (int)err_,                   \
+                    galileo_osnma_error_string(err_), (int)(expected));     \
+            failures++;                                                     \
+        }                                                                   \
+    } while (0)
+
+/* OSNMA ECDSA P-256 public key (uncompressed SEC1 encoding) */
+static const uint8_t PUBKEY[65] = {
+    0x04, 0x97, 0xeb, 0x43, 0x78, 0x9a, 0xa0, 0xf6, 0xd0, 0x52, 0xa6, 0x38,
+    0x46, 0x8e, 0xcf, 0x52, 0x78, 0xe6, 0xf6, 0xdf, 0x84, 0x65, 0xec, 0xb8,
+    0xd8, 0xb8, 0x4b, 0x8c, 0x7a, 0x35, 0x01, 0xf7, 0x3b, 0xe3, 0xa7, 0xfd,
+    0x97, 0x32, 0x45, 0x90, 0xe3, 0x21, 0x57, 0x33, 0x83, 0x58, 0xf7, 0xb7,
+    0x6b, 0x42, 0x72, 0x82, 0xe3, 0xd0, 0x46, 0xce, 0xdc, 0x25, 0x86, 0x64,
+    0xd6, 0xfb, 0xd9, 0xb7, 0x6b,
+};
+static const uint8_t PKID = 1;
+
+static void test_construction(void)
+{
+    GalileoOsnma *osnma = NULL;
+    uint8_t merkle_root[GALILEO_OSNMA_MERKLE_ROOT_BYTES] = {0};
+    uint8_t bad_pubkey[65];
+
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_FULL, NULL, NULL, 0, 0,
+                                false, &osnma),
+              GALILEO_OSNMA_ERROR_MISSING_KEY_MATERIAL);
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_FULL, merkle_root, NULL,
+                                0, 0, false, NULL),
+              GALILEO_OSNMA_ERROR_NULL_POINTER);
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_FULL, NULL, PUBKEY, 10,
+                                PKID, false, &osnma),
+              GALILEO_OSNMA_ERROR_INVALID_PUBLIC_KEY);
+    memcpy(bad_pubkey, PUBKEY, sizeof(bad_pubkey));
+    bad_pubkey[64] ^= 1; /* the point is no longer on the curve */
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_FULL, NULL, bad_pubkey,
+                                sizeof(bad_pubkey), PKID, false, &osnma),
+              GALILEO_OSNMA_ERROR_INVALID_PUBLIC_KEY);
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_FULL, NULL, PUBKEY,
+                                sizeof(PUBKEY), 16, false, &osnma),
+              GALILEO_OSNMA_ERROR_INVALID_PKID);
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_SMALL + 1, merkle_root,
+                                NULL, 0, 0, false, &osnma),
+              GALILEO_OSNMA_ERROR_INVALID_STORAGE);
+    if (osnma != NULL) {
+        fprintf(stderr, "handle written on error\n");
+        failures++;
+    }
+
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_SMALL, merkle_root, NULL,
+                                0, 0, true, &osnma),
+              GALILEO_OSNMA_ERROR_OK);
+    galileo_osnma_free(osnma);
+    osnma = NULL;
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_FULL, merkle_root,
+                                PUBKEY, sizeof(PUBKEY), PKID, false, &osnma),
+              GALILEO_OSNMA_ERROR_OK);
+    galileo_osnma_free(osnma);
+    osnma = NULL;
+    /* The PKID is ignored if there is no public key */
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_FULL, merkle_root, NULL,
+                                0, 255, false, &osnma),
+              GALILEO_OSNMA_ERROR_OK);
+    galileo_osnma_free(osnma);
+    galileo_osnma_free(NULL);
+}
+
+static void test_feed_and_get(GalileoOsnmaStorage storage)
+{
+    GalileoOsnma *osnma = NULL;
+    uint8_t word[GALILEO_OSNMA_INAV_WORD_BYTES] = {0};
+    uint8_t osnma_data[GALILEO_OSNMA_OSNMA_DATA_BYTES] = {0};
+    uint8_t ced[GALILEO_OSNMA_CED_AND_STATUS_BYTES];
+    uint8_t timing[GALILEO_OSNMA_TIMING_PARAMETERS_BYTES];
+    GalileoOsnmaNavData info;
+    uint32_t tow;
+    uint8_t svn;
+
+    CHECK_ERR(galileo_osnma_new(storage, NULL, PUBKEY, sizeof(PUBKEY), PKID,
+                                false, &osnma),
+              GALILEO_OSNMA_ERROR_OK);
+    if (osnma == NULL) {
+        return;
+    }
+
+    /* Feed one subframe of INAV words and OSNMA data for a few satellites */
+    for (tow = 1234 * 30; tow < 1234 * 30 + 30; tow += 2) {
+        for (svn = 1; svn <= 4; ++svn) {
+            word[0] = (uint8_t)((((tow / 2) % 15) + 1) << 2);
+            CHECK_ERR(galileo_osnma_feed_inav(osnma, word, svn, 1300, tow,
+                                              GALILEO_OSNMA_INAV_BAND_E1B),
+                      GALILEO_OSNMA_ERROR_OK);
+            CHECK_ERR(galileo_osnma_feed_osnma(osnma, osnma_data, svn, 1300,
+                                               tow),
+                      GALILEO_OSNMA_ERROR_OK);
+        }
+    }
+
+    CHECK_ERR(galileo_osnma_feed_inav(osnma, word, 0, 1300, 0,
+                                      GALILEO_OSNMA_INAV_BAND_E1B),
+              GALILEO_OSNMA_ERROR_INVALID_SVN);
+    CHECK_ERR(galileo_osnma_feed_inav(osnma, word, 37, 1300, 0,
+                                      GALILEO_OSNMA_INAV_BAND_E5B),
+              GALILEO_OSNMA_ERROR_INVALID_SVN);
+    CHECK_ERR(galileo_osnma_feed_inav(osnma, word, 1, 1300, 604800,
+                                      GALILEO_OSNMA_INAV_BAND_E1B),
+              GALILEO_OSNMA_ERROR_INVALID_TOW);
+    CHECK_ERR(galileo_osnma_feed_inav(osnma, word, 1, 1300, 0,
+                                      GALILEO_OSNMA_INAV_BAND_E5B + 1),
+              GALILEO_OSNMA_ERROR_INVALID_BAND);
+    CHECK_ERR(galileo_osnma_feed_inav(osnma, NULL, 1, 1300, 0,
+                                      GALILEO_OSNMA_INAV_BAND_E1B),
+              GALILEO_OSNMA_ERROR_NULL_POINTER);
+    CHECK_ERR(galileo_osnma_feed_osnma(NULL, osnma_data, 1, 1300, 0),
+              GALILEO_OSNMA_ERROR_NULL_POINTER);
+
+    /* Nothing can be authenticated without OSNMA cryptographic material */
+    for (svn = 1; svn <= 36; ++svn) {
+        CHECK_ERR(galileo_osnma_get_ced_and_status(osnma, svn, ced,
+                                                   sizeof(ced), &info),
+                  GALILEO_OSNMA_ERROR_NOT_AVAILABLE);
+        CHECK_ERR(galileo_osnma_get_timing_parameters(osnma, svn, timing,
+                                                      sizeof(timing), NULL),
+                  GALILEO_OSNMA_ERROR_NOT_AVAILABLE);
+    }
+    CHECK_ERR(galileo_osnma_get_ced_and_status(osnma, 0, ced, sizeof(ced),
+                                               &info),
+              GALILEO_OSNMA_ERROR_INVALID_SVN);
+    CHECK_ERR(galileo_osnma_get_timing_parameters(osnma, 1, NULL,
+                                                  sizeof(timing), &info),
+              GALILEO_OSNMA_ERROR_NULL_POINTER);
+    /* Size queries with a NULL buffer need the metadata */
+    CHECK_ERR(galileo_osnma_get_timing_parameters(osnma, 1, NULL, 0, NULL),
+              GALILEO_OSNMA_ERROR_NULL_POINTER);
+    CHECK_ERR(galileo_osnma_get_ced_and_status(osnma, 1, NULL, 0, &info),
+              GALILEO_OSNMA_ERROR_NOT_AVAILABLE);
+
+    galileo_osnma_free(osnma);
+}
+
+static void test_error_strings(void)
+{
+    const char *unknown = galileo_osnma_error_string(
+        GALILEO_OSNMA_ERROR_PANIC + 1);
+    uint32_t err;
+
+    for (err = GALILEO_OSNMA_ERROR_OK; err <= GALILEO_OSNMA_ERROR_PANIC; ++err) {
+        const char *s = galileo_osnma_error_string(err);
+        if (s == NULL || strlen(s) == 0 || strcmp(s, unknown) == 0) {
+            fprintf(stderr, "missing error string for %lu\n",
+                    (unsigned long)err);
+            failures++;
+        }
+    }
+    if (strcmp(unknown, "unknown error") != 0) {
+        fprintf(stderr, "wrong string for an unknown error: %s\n", unknown);
+        failures++;
+    }
+}
+
+static int parse_hex(const char *hex, uint8_t *out, size_t len)
+{
+    size_t j;
+    unsigned int byte;
+
+    if (strlen(hex) != 2 * len) {
+        return -1;
+    }
+    for (j = 0; j < len; ++j) {
+        if (sscanf(&hex[2 * j], "%2x", &byte) != 1) {
+            return -1;
+        }
+        out[j] = (uint8_t)byte;
+    }
+    return 0;
+}
+
+static int same_info(const GalileoOsnmaNavData *a, const GalileoOsnmaNavData *b)
+{
+    return a->wn == b->wn && a->tow == b->tow && a->authbits == b->authbits
+        && a->num_bits == b->num_bits && a->num_bytes == b->num_bytes;
+}
+
+typedef GalileoOsnmaError (*GetDataFn)(const GalileoOsnma *, uint8_t, uint8_t *,
+                                       size_t, GalileoOsnmaNavData *);
+
+/*
+ * Writes the authenticated data of a satellite to stdout, checking that the
+ * size query, the BUFFER_TOO_SMALL error and the retrieval of the data give
+ * consistent results.
+ */
+static void dump_data(const GalileoOsnma *osnma, const char *name,
+                      GetDataFn get, size_t max_bytes, uint8_t svn)
+{
+    uint8_t buf[GALILEO_OSNMA_CED_AND_STATUS_BYTES + 1];
+    uint8_t copy[GALILEO_OSNMA_CED_AND_STATUS_BYTES + 1];
+    GalileoOsnmaNavData info, info2;
+    GalileoOsnmaError err;
+    size_t j;
+
+    err = get(osnma, svn, NULL, 0, &info);
+    if (err == GALILEO_OSNMA_ERROR_NOT_AVAILABLE) {
+        return;
+    }
+    CHECK_ERR(err, GALILEO_OSNMA_ERROR_OK);
+    if (err != GALILEO_OSNMA_ERROR_OK) {
+        return;
+    }
+    if (info.num_bytes != (info.num_bits + 7) / 8 || info.num_bytes == 0
+        || info.num_bytes > max_bytes) {
+        fprintf(stderr, "E%02u %s: wrong size %zu bits, %zu bytes\n",
+                (unsigned)svn, name, info.num_bits, info.num_bytes);
+        failures++;
+        return;
+    }
+
+    /* The metadata is written even if the buffer is too small */
+    memset(&info2, 0xff, sizeof(info2));
+    CHECK_ERR(get(osnma, svn, buf, info.num_bytes - 1, &info2),
+              GALILEO_OSNMA_ERROR_BUFFER_TOO_SMALL);
+    if (!same_info(&info, &info2)) {
+        fprintf(stderr, "E%02u %s: metadata differs with small buffer\n",
+                (unsigned)svn, name);
+        failures++;
+    }
+
+    /* Only num_bytes bytes of the buffer are written */
+    memset(buf, 0xa5, sizeof(buf));
+    memset(&info2, 0xff, sizeof(info2));
+    CHECK_ERR(get(osnma, svn, buf, info.num_bytes + 1, &info2),
+              GALILEO_OSNMA_ERROR_OK);
+    if (!same_info(&info, &info2)) {
+        fprintf(stderr, "E%02u %s: metadata differs from size query\n",
+                (unsigned)svn, name);
+        failures++;
+    }
+    if (buf[info.num_bytes] != 0xa5) {
+        fprintf(stderr, "E%02u %s: byte after the data was written\n",
+                (unsigned)svn, name);
+        failures++;
+    }
+
+    /* The metadata is optional */
+    CHECK_ERR(get(osnma, svn, copy, info.num_bytes, NULL),
+              GALILEO_OSNMA_ERROR_OK);
+    if (memcmp(buf, copy, info.num_bytes) != 0) {
+        fprintf(stderr, "E%02u %s: data differs without metadata\n",
+                (unsigned)svn, name);
+        failures++;
+    }
+
+    printf("%s %u %u %lu %u %zu ", name, (unsigned)svn, (unsigned)info.wn,
+           (unsigned long)info.tow, (unsigned)info.authbits, info.num_bits);
+    for (j = 0; j < info.num_bytes; ++j) {
+        printf("%02x", buf[j]);
+    }
+    printf("\n");
+}
+
+static void dump_all_data(const GalileoOsnma *osnma)
+{
+    uint8_t svn;
+
+    for (svn = 1; svn <= 36; ++svn) {
+        dump_data(osnma, "ced", galileo_osnma_get_ced_and_status,
+                  GALILEO_OSNMA_CED_AND_STATUS_BYTES, svn);
+        dump_data(osnma, "timing", galileo_osnma_get_timing_parameters,
+                  GALILEO_OSNMA_TIMING_PARAMETERS_BYTES, svn);
+    }
+}
+
+static int feed_pages(const char *path, const char *merkle_root_hex)
+{
+    GalileoOsnma *osnma = NULL;
+    uint8_t merkle_root[GALILEO_OSNMA_MERKLE_ROOT_BYTES];
+    uint8_t word[GALILEO_OSNMA_INAV_WORD_BYTES];
+    uint8_t osnma_data[GALILEO_OSNMA_OSNMA_DATA_BYTES];
+    char line[256], word_hex[64], osnma_hex[64];
+    unsigned int svn, wn, band;
+    unsigned long tow;
+    FILE *f;
+
+    if (parse_hex(merkle_root_hex, merkle_root, sizeof(merkle_root)) != 0) {
+        fprintf(stderr, "invalid Merkle tree root %s\n", merkle_root_hex);
+        return 2;
+    }
+    f = fopen(path, "r");
+    if (f == NULL) {
+        perror(path);
+        return 2;
+    }
+    CHECK_ERR(galileo_osnma_new(GALILEO_OSNMA_STORAGE_FULL, merkle_root, NULL,
+                                0, 0, false, &osnma),
+              GALILEO_OSNMA_ERROR_OK);
+    if (osnma == NULL) {
+        fclose(f);
+        return 1;
+    }
+
+    while (fgets(line, sizeof(line), f) != NULL) {
+        if (line[0] == 'Q') {
+            dump_all_data(osnma);
+            continue;
+        }
+        if (sscanf(line, "P %u %u %lu %u %63s %63s", &svn, &wn, &tow, &band,
+                   word_hex, osnma_hex) != 6
+            || parse_hex(osnma_hex, osnma_data, sizeof(osnma_data)) != 0) {
+            fprintf(stderr, "invalid line in %s: %s", path, line);
+            failures++;
+            break;
+        }
+        if (strcmp(word_hex, "-") != 0) {
+            if (parse_hex(word_hex, word, sizeof(word)) != 0) {
+                fprintf(stderr, "invalid INAV word in %s: %s", path, line);
+                failures++;
+                break;
+            }
+            CHECK_ERR(galileo_osnma_feed_inav(
+                          osnma, word, (uint8_t)svn, (uint16_t)wn,
+                          (uint32_t)tow,
+                          band == 0 ? GALILEO_OSNMA_INAV_BAND_E1B
+                                    : GALILEO_OSNMA_INAV_BAND_E5B),
+                      GALILEO_OSNMA_ERROR_OK);
+        }
+        if (band == 0) {
+            CHECK_ERR(galileo_osnma_feed_osnma(osnma, osnma_data, (uint8_t)svn,
+                                               (uint16_t)wn, (uint32_t)tow),
+                      GALILEO_OSNMA_ERROR_OK);
+        }
+    }
+
+    fclose(f);
+    galileo_osnma_free(osnma);
+    return 0;
+}
+
+int main(int argc, char **argv)
+{
+    if (argc == 3) {
+        if (feed_pages(argv[1], argv[2]) != 0) {
+            return 1;
+        }
+    } else if (argc == 1) {
+        test_construction();
+        test_feed_and_get(GALILEO_OSNMA_STORAGE_FULL);
+        test_feed_and_get(GALILEO_OSNMA_STORAGE_SMALL);
+        test_error_strings();
+    } else {
+        fprintf(stderr, "usage: %s [PAGES MERKLE_ROOT]\n", argv[0]);
+        return 2;
+    }
+
+    if (failures != 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    if (argc == 1) {
+        printf("all checks passed\n");
+    }
+    return 0;
+}
@@ -0,0 +1,201 @@
+//! Compiles the C test program in `tests/c` against the static library and
+//! the generated header, and runs it.
+//!
+//! Besides the checks done by the C test program on its own, the program is
+//! used to feed simulated pages and (if available) an OSNMA test vector
+//! through the C API, and the authenticated data that it obtains is compared
+//! with the data obtained from the Rust API.
+
+use galileo_osnma::{storage::FullStorage, types::MERKLE_TREE_NODE_BYTES, InavBand, Osnma};
+use osnma_sim::{
+    navdata::SyntheticNavData,
+    sim::{Page, SimConfig, Simulator},
+};
+use std::env;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::OnceLock;
+
+// Directory where cargo places the static library built for the tests
+// (target/<profile>/deps). This is the directory of the test executable.
+// `cargo test` does not copy the library to target/<profile>, so the copy
+// there may be stale.
+fn library_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().unwrap().to_path_buf()
+}
+
+// Compiles the C test program, returning its path. The program is only
+// compiled once, since the tests run in parallel.
+fn c_test_program() -> &'static Path {
+    static PROGRAM: OnceLock<PathBuf> = OnceLock::new();
+    PROGRAM.get_or_init(|| {
+        let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let lib_dir = library_dir();
+        let program = lib_dir.join("galileo_osnma_c_test");
+        let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let status = Command::new(cc)
+            .arg("-std=c99")
+            .arg("-Wall")
+            .arg("-Wextra")
+            .arg("-Werror")
+            .arg("-I")
+            .arg(env!("OUT_DIR"))
+            .arg(crate_dir.join("tests").join("c").join("test_api.c"))
+            .arg(lib_dir.join("libgalileo_osnma_ffi.a"))
+            .args(["-lpthread", "-ldl", "-lm"])
+            .arg("-o")
+            .arg(&program)
+            .status()
+            .expect("could not run the C compiler");
+        assert!(status.success(), "C test program failed to compile");
+        program
+    })
+}
+
+fn run_c_test_program(args: &[&str]) -> Output {
+    let output = Command::new(c_test_program())
+        .args(args)
+        .output()
+        .expect("could not run the C test program");
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.status.success(), "C test program failed");
+    output
+}
+
+#[test]
+fn c_test_program_checks() {
+    let output = run_c_test_program(&[]);
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        write!(s, "{b:02x}").unwrap();
+        s
+    })
+}
+
+// Writes the authenticated data of all the satellites in the same format as
+// the C test program.
+fn dump_data(osnma: &Osnma<FullStorage>, out: &mut String) {
+    for svn in galileo_osnma::Svn::iter() {
+        for (name, data) in [
+            ("ced", osnma.get_ced_and_status(svn)),
+            ("timing", osnma.get_timing_parameters(svn)),
+        ] {
+            let Some(data) = data else {
+                continue;
+            };
+            let bits = data.data();
+            // MSB-first packing of the bits, with the last byte padded with
+            // zeros
+            let bytes = bits
+                .chunks(8)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |byte, (j, bit)| byte | (u8::from(*bit) << (7 - j)))
+                })
+                .collect::<Vec<u8>>();
+            writeln!(
+                out,
+                "{name} {} {} {} {} {} {}",
+                u8::from(svn),
+                data.gst().wn(),
+                data.gst().tow(),
+                data.authbits(),
+                bits.len(),
+                hex(&bytes)
+            )
+            .unwrap();
+        }
+    }
+}
+
+// Feeds the pages through the C API and through the Rust API, and checks that
+// both give the same authenticated data after each subframe.
+fn check_pages(name: &str, pages: &[Page], merkle_root: [u8; MERKLE_TREE_NODE_BYTES]) {
+    let mut osnma = Osnma::<FullStorage>::from_merkle_tree(merkle_root, None, false);
+    let mut input = String::new();
+    let mut expected = String::new();
+    for (j, page) in pages.iter().enumerate() {
+        let band = match page.band {
+            InavBand::E1B => 0,
+            InavBand::E5B => 1,
+        };
+        let word = page
+            .word
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |w| hex(w));
+        writeln!(
+            input,
+            "P {} {} {} {band} {word} {}",
+            u8::from(page.svn),
+            page.gst.wn(),
+            page.gst.tow(),
+            hex(&page.osnma)
+        )
+        .unwrap();
+        if let Some(word) = &page.word {
+            osnma.feed_inav(word, page.svn, page.gst, page.band);
+        }
+        if page.band == InavBand::E1B {
+            osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+        }
+        let end_of_subframe = pages.get(j + 1).map_or(true, |next| {
+            next.gst.gst_subframe() != page.gst.gst_subframe()
+        });
+        if end_of_subframe {
+            input.push_str("Q\n");
+            dump_data(&osnma, &mut expected);
+        }
+    }
+    assert!(
+        expected.lines().any(|l| l.starts_with("ced "))
+            && expected.lines().any(|l| l.starts_with("timing ")),
+        "no data was authenticated"
+    );
+
+    let path = library_dir().join(format!("galileo_osnma_c_test_{name}.txt"));
+    std::fs::write(&path, input).unwrap();
+    let output = run_c_test_program(&[path.to_str().unwrap(), &hex(&merkle_root)]);
+    let output = String::from_utf8(output.stdout).unwrap();
+    for (c, rust) in output.lines().zip(expected.lines()) {
+        assert_eq!(c, rust);
+    }
+    assert_eq!(output.lines().count(), expected.lines().count());
+}
+
+#[test]
+fn simulated_pages() {
+    let config = SimConfig {
+        subframes: 12,
+        ..SimConfig::default()
+    };
+    let mut sim = Simulator::new(config, SyntheticNavData::default()).unwrap();
+    let mut pages = Vec::new();
+    while let Some(subframe) = sim.next_subframe() {
+        pages.extend(subframe);
+    }
+    check_pages("simulated", &pages, sim.merkle_root());
+}
+
+// Runs an OSNMA test vector through the C API. The path of a CSV file of the
+// OSNMA test vectors published by the European GNSS Service Centre is given in
+// the OSNMA_TEST_VECTOR environment variable, and the Merkle tree root in hex
+// in the OSNMA_MERKLE_ROOT environment variable.
+#[test]
+#[ignore = "requires the OSNMA_TEST_VECTOR and OSNMA_MERKLE_ROOT environment variables"]
+fn test_vector() {
+    let path = env::var("OSNMA_TEST_VECTOR").unwrap();
+    let merkle_root = env::var("OSNMA_MERKLE_ROOT").unwrap();
+    let merkle_root = merkle_root.trim();
+    assert_eq!(merkle_root.len(), 2 * MERKLE_TREE_NODE_BYTES);
+    let merkle_root =
+        core::array::from_fn(|j| u8::from_str_radix(&merkle_root[2 * j..2 * j + 2], 16).unwrap());
+    let pages = osnma_sim::csv::read_file(Path::new(&path)).unwrap();
+    check_pages("test_vector", &pages, merkle_root);
+}
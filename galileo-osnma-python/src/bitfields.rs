@@ -0,0 +1,411 @@
+//! Python wrappers of the OSNMA message parsers.
+//!
+//! The wrappers own a copy of the message data, and the enumerated fields are
+//! returned as the name of the corresponding Rust enum variant.
+
+use crate::{bits_to_bytes, PyGst};
+use bitvec::field::BitField;
+use galileo_osnma::{
+    bitfields::{DsmKroot, DsmPkr, Mack, NewPublicKeyType},
+    tesla::NmaHeader,
+    types::{MackMessage, DSM_BLOCK_BYTES, MACK_MESSAGE_BYTES},
+    validation::NotValidated,
+};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyModule},
+};
+
+/// NMA header.
+#[pyclass(name = "NmaHeader", module = "galileo_osnma", frozen)]
+#[derive(Debug, Copy, Clone)]
+pub struct PyNmaHeader(NmaHeader<NotValidated>);
+
+#[pymethods]
+impl PyNmaHeader {
+    #[new]
+    fn new(data: u8) -> PyNmaHeader {
+        PyNmaHeader(NmaHeader::new(data))
+    }
+
+    /// NMA status (NMAS) field.
+    #[getter]
+    fn nma_status(&self) -> String {
+        format!("{:?}", self.0.nma_status())
+    }
+
+    /// Chain ID (CID) field.
+    #[getter]
+    fn chain_id(&self) -> u8 {
+        self.0.chain_id()
+    }
+
+    /// Chain and public key status (CPKS) field.
+    #[getter]
+    fn chain_and_pubkey_status(&self) -> String {
+        format!("{:?}", self.0.chain_and_pubkey_status())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "NmaHeader(nma_status={}, chain_id={}, chain_and_pubkey_status={})",
+            self.nma_status(),
+            self.chain_id(),
+            self.chain_and_pubkey_status()
+        )
+    }
+}
+
+// Checks that the DSM has the number of blocks given in its NB field.
+fn check_dsm_len(data: &[u8], number_of_blocks: Option<usize>) -> PyResult<()> {
+    match number_of_blocks {
+        Some(n) if data.len() == n * DSM_BLOCK_BYTES => Ok(()),
+        Some(n) => Err(PyValueError::new_err(format!(
+            "DSM should have {} bytes, but has {} bytes",
+            n * DSM_BLOCK_BYTES,
+            data.len()
+        ))),
+        None => Err(PyValueError::new_err("reserved number of DSM blocks")),
+    }
+}
+
+/// DSM-KROOT message.
+#[pyclass(name = "DsmKroot", module = "galileo_osnma", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyDsmKroot(Vec<u8>);
+
+impl PyDsmKroot {
+    fn dsm(&self) -> DsmKroot<'_> {
+        DsmKroot(&self.0)
+    }
+}
+
+#[pymethods]
+impl PyDsmKroot {
+    #[new]
+    fn new(data: &[u8]) -> PyResult<PyDsmKroot> {
+        if data.is_empty() {
+            return Err(PyValueError::new_err("empty DSM-KROOT"));
+        }
+        check_dsm_len(data, DsmKroot(data).number_of_blocks())?;
+        if DsmKroot(data).key_size().is_none() {
+            return Err(PyValueError::new_err("reserved key size in DSM-KROOT"));
+        }
+        Ok(PyDsmKroot(data.to_vec()))
+    }
+
+    /// Number of DSM-KROOT blocks.
+    #[getter]
+    fn number_of_blocks(&self) -> Option<usize> {
+        self.dsm().number_of_blocks()
+    }
+
+    /// Public key ID (PKID) field.
+    #[getter]
+    fn public_key_id(&self) -> u8 {
+        self.dsm().public_key_id()
+    }
+
+    /// KROOT chain ID (CIDKR) field.
+    #[getter]
+    fn kroot_chain_id(&self) -> u8 {
+        self.dsm().kroot_chain_id()
+    }
+
+    /// Hash function (HF) field.
+    #[getter]
+    fn hash_function(&self) -> String {
+        format!("{:?}", self.dsm().hash_function())
+    }
+
+    /// MAC function (MF) field.
+    #[getter]
+    fn mac_function(&self) -> String {
+        format!("{:?}", self.dsm().mac_function())
+    }
+
+    /// TESLA key size in bits.
+    #[getter]
+    fn key_size(&self) -> Option<usize> {
+        self.dsm().key_size()
+    }
+
+    /// MAC tag size in bits.
+    #[getter]
+    fn tag_size(&self) -> Option<usize> {
+        self.dsm().tag_size()
+    }
+
+    /// MAC look-up table (MACLT) field.
+    #[getter]
+    fn mac_lookup_table(&self) -> u8 {
+        self.dsm().mac_lookup_table()
+    }
+
+    /// KROOT week number (WN_K) field.
+    #[getter]
+    fn kroot_wn(&self) -> u16 {
+        self.dsm().kroot_wn()
+    }
+
+    /// KROOT time of week in hours (TOWH_K) field.
+    #[getter]
+    fn kroot_towh(&self) -> u8 {
+        self.dsm().kroot_towh()
+    }
+
+    /// KROOT GST, computed from the WN_K and TOWH_K fields.
+    #[getter]
+    fn kroot_gst(&self) -> PyResult<PyGst> {
+        PyGst::new(
+            self.dsm().kroot_wn(),
+            u32::from(self.dsm().kroot_towh()) * 3600,
+        )
+    }
+
+    /// Random pattern alpha.
+    #[getter]
+    fn alpha(&self) -> u64 {
+        self.dsm().alpha()
+    }
+
+    /// KROOT field.
+    #[getter]
+    fn kroot<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, self.dsm().kroot())
+    }
+
+    /// ECDSA function, guessed from the size of the signature.
+    #[getter]
+    fn ecdsa_function(&self) -> String {
+        format!("{:?}", self.dsm().ecdsa_function())
+    }
+
+    /// Digital signature (DS) field.
+    #[getter]
+    fn digital_signature<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, self.dsm().digital_signature())
+    }
+
+    /// Padding (P_DK) field.
+    #[getter]
+    fn padding<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, self.dsm().padding())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.dsm())
+    }
+}
+
+/// DSM-PKR message.
+#[pyclass(name = "DsmPkr", module = "galileo_osnma", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyDsmPkr(Vec<u8>);
+
+impl PyDsmPkr {
+    fn dsm(&self) -> DsmPkr<'_> {
+        DsmPkr(&self.0)
+    }
+}
+
+#[pymethods]
+impl PyDsmPkr {
+    #[new]
+    fn new(data: &[u8]) -> PyResult<PyDsmPkr> {
+        if data.is_empty() {
+            return Err(PyValueError::new_err("empty DSM-PKR"));
+        }
+        check_dsm_len(data, DsmPkr(data).number_of_blocks())?;
+        Ok(PyDsmPkr(data.to_vec()))
+    }
+
+    /// Number of DSM-PKR blocks.
+    #[getter]
+    fn number_of_blocks(&self) -> Option<usize> {
+        self.dsm().number_of_blocks()
+    }
+
+    /// Message ID (MID) field.
+    #[getter]
+    fn message_id(&self) -> u8 {
+        self.dsm().message_id()
+    }
+
+    /// The 4 intermediate tree nodes.
+    #[getter]
+    fn intermediate_tree_nodes<'py>(&self, py: Python<'py>) -> Vec<Bound<'py, PyBytes>> {
+        (0..4)
+            .map(|n| PyBytes::new_bound(py, self.dsm().intermediate_tree_node(n)))
+            .collect()
+    }
+
+    /// New public key type (NPKT) field.
+    ///
+    /// This is the name of the ECDSA function for ECDSA keys,
+    /// `"OsnmaAlertMessage"`, or `"Reserved"`.
+    #[getter]
+    fn new_public_key_type(&self) -> String {
+        match self.dsm().new_public_key_type() {
+            NewPublicKeyType::EcdsaKey(f) => format!("{f:?}"),
+            t => format!("{t:?}"),
+        }
+    }
+
+    /// New public key ID (NPKID) field.
+    #[getter]
+    fn new_public_key_id(&self) -> u8 {
+        self.dsm().new_public_key_id()
+    }
+
+    /// New public key (NPK) field, or `None` if the NPKT is reserved.
+    #[getter]
+    fn new_public_key<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        self.dsm()
+            .new_public_key()
+            .map(|k| PyBytes::new_bound(py, k))
+    }
+
+    /// Padding (P_DP) field, or `None` if the NPKT is reserved.
+    #[getter]
+    fn padding<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        self.dsm().padding().map(|p| PyBytes::new_bound(py, p))
+    }
+
+    /// Merkle tree leaf corresponding to this message, or `None` if the NPKT
+    /// is reserved.
+    #[getter]
+    fn merkle_tree_leaf<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        self.dsm()
+            .merkle_tree_leaf()
+            .map(|l| PyBytes::new_bound(py, l))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.dsm())
+    }
+}
+
+/// MACK message.
+///
+/// The MACK message is 60 bytes long. Its parsing depends on the TESLA key
+/// size and the tag size, which are given in the DSM-KROOT.
+#[pyclass(name = "Mack", module = "galileo_osnma", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyMack {
+    data: MackMessage,
+    key_size: usize,
+    tag_size: usize,
+}
+
+impl PyMack {
+    fn mack(&self) -> Mack<'_, NotValidated> {
+        Mack::new(&self.data, self.key_size, self.tag_size)
+    }
+}
+
+#[pymethods]
+impl PyMack {
+    #[new]
+    fn new(data: &[u8], key_size: usize, tag_size: usize) -> PyResult<PyMack> {
+        let data = data.try_into().map_err(|_| {
+            PyValueError::new_err(format!("MACK must have {MACK_MESSAGE_BYTES} bytes"))
+        })?;
+        if ![96, 104, 112, 120, 128, 160, 192, 224, 256].contains(&key_size) {
+            return Err(PyValueError::new_err("invalid key size"));
+        }
+        if ![20, 24, 28, 32, 40].contains(&tag_size) {
+            return Err(PyValueError::new_err("invalid tag size"));
+        }
+        Ok(PyMack {
+            data,
+            key_size,
+            tag_size,
+        })
+    }
+
+    /// Tag0 field.
+    #[getter]
+    fn tag0(&self) -> u64 {
+        self.mack().tag0().load_be()
+    }
+
+    /// MACSEQ field.
+    #[getter]
+    fn macseq(&self) -> u16 {
+        self.mack().macseq()
+    }
+
+    /// COP field of tag0.
+    #[getter]
+    fn cop(&self) -> u8 {
+        self.mack().cop()
+    }
+
+    /// Number of tags, including tag0.
+    #[getter]
+    fn num_tags(&self) -> usize {
+        self.mack().num_tags()
+    }
+
+    /// List of the tags and their info sections, excluding tag0.
+    #[getter]
+    fn tags(&self) -> Vec<PyTag> {
+        let mack = self.mack();
+        (1..mack.num_tags())
+            .map(|j| {
+                let tag = mack.tag_and_info(j);
+                PyTag {
+                    tag: tag.tag().load_be(),
+                    prnd: u8::try_from(tag.prnd()).ok(),
+                    adkd: format!("{:?}", tag.adkd()),
+                    cop: tag.cop(),
+                }
+            })
+            .collect()
+    }
+
+    /// TESLA key field.
+    #[getter]
+    fn key<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &bits_to_bytes(self.mack().key()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.mack())
+    }
+}
+
+/// Tag and its Tag-Info section.
+#[pyclass(name = "Tag", module = "galileo_osnma", frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct PyTag {
+    /// Tag value.
+    tag: u64,
+    /// PRND field, or `None` if it has a reserved value.
+    prnd: Option<u8>,
+    /// ADKD field.
+    adkd: String,
+    /// COP field.
+    cop: u8,
+}
+
+#[pymethods]
+impl PyTag {
+    fn __repr__(&self) -> String {
+        format!(
+            "Tag(tag=0x{:x}, prnd={:?}, adkd={}, cop={})",
+            self.tag, self.prnd, self.adkd, self.cop
+        )
+    }
+}
+
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyNmaHeader>()?;
+    m.add_class::<PyDsmKroot>()?;
+    m.add_class::<PyDsmPkr>()?;
+    m.add_class::<PyMack>()?;
+    m.add_class::<PyTag>()?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Python wrapper of the Galmon transport reader.
+
+use crate::{PyGst, PyInavBand};
+use galileo_osnma::galmon::{pages::PageExtractor, transport::ReadTransport};
+use pyo3::{
+    exceptions::PyIOError,
+    prelude::*,
+    types::{PyBytes, PyModule},
+};
+use std::{fs::File, io::BufReader};
+
+/// Reader for the Galmon transport protocol.
+///
+/// The reader is constructed from the path of a file containing a recorded
+/// Galmon stream. Iterating over the reader yields the Galileo INAV pages
+/// contained in the stream. Other kinds of navmon packets are skipped.
+///
+/// All the INAV pages are given, including the dummy messages. The wrong TOWs
+/// in Galmon data are fixed in the same way as in `galmon-osnma`.
+#[pyclass(name = "ReadTransport", module = "galileo_osnma")]
+pub struct PyReadTransport {
+    read: ReadTransport<BufReader<File>>,
+    pages: PageExtractor,
+}
+
+#[pymethods]
+impl PyReadTransport {
+    #[new]
+    fn new(path: std::path::PathBuf) -> PyResult<PyReadTransport> {
+        let file = File::open(path)?;
+        Ok(PyReadTransport {
+            read: ReadTransport::new(BufReader::new(file)),
+            pages: PageExtractor::new(),
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> PyResult<Option<PyInavPage>> {
+        loop {
+            let Some(packet) = self
+                .read
+                .read_packet()
+                .map_err(|e| PyIOError::new_err(e.to_string()))?
+            else {
+                return Ok(None);
+            };
+            let Some(inav) = packet.gi else {
+                continue;
+            };
+            let gst = self
+                .pages
+                .gst(&inav)
+                .ok_or_else(|| PyIOError::new_err("week number out of range"))?;
+            let band = match inav.sigid {
+                Some(1) => Some(PyInavBand::E1B),
+                Some(5) => Some(PyInavBand::E5B),
+                _ => None,
+            };
+            return Ok(Some(PyInavPage {
+                svn: inav.gnss_sv,
+                gst: PyGst(gst),
+                band,
+                sigid: inav.sigid,
+                inav_word: inav.contents,
+                osnma_data: inav.reserved1,
+            }));
+        }
+    }
+}
+
+/// Galileo INAV page read from a Galmon stream.
+///
+/// The GST is normalized so that the TOW is always in the range
+/// 0-604799, and the wrong TOWs of Galmon data are fixed. `band` is `None` if
+/// the signal ID is not that of an INAV band.
+#[pyclass(name = "InavPage", module = "galileo_osnma", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyInavPage {
+    /// SVN of the satellite that transmitted the page.
+    #[pyo3(get)]
+    svn: u32,
+    /// GST at the start of the page transmission.
+    #[pyo3(get)]
+    gst: PyGst,
+    /// INAV band of the page.
+    #[pyo3(get)]
+    band: Option<PyInavBand>,
+    /// Galmon signal ID.
+    #[pyo3(get)]
+    sigid: Option<u32>,
+    inav_word: Vec<u8>,
+    osnma_data: Option<Vec<u8>>,
+}
+
+#[pymethods]
+impl PyInavPage {
+    /// 16-byte INAV word.
+    #[getter]
+    fn inav_word<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.inav_word)
+    }
+
+    /// 5 bytes of OSNMA data, or `None` if the page has no OSNMA data.
+    #[getter]
+    fn osnma_data<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        self.osnma_data
+            .as_ref()
+            .map(|data| PyBytes::new_bound(py, data))
+    }
+
+    /// INAV word type.
+    ///
+    /// Word type 63 corresponds to dummy messages, whose OSNMA data should be
+    /// discarded.
+    #[getter]
+    fn word_type(&self) -> Option<u8> {
+        self.inav_word.first().map(|b| b >> 2)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "InavPage(svn={}, gst={}, band={:?}, word_type={:?})",
+            self.svn,
+            self.gst.__repr__(),
+            self.band,
+            self.word_type()
+        )
+    }
+}
+
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyReadTransport>()?;
+    m.add_class::<PyInavPage>()?;
+    Ok(())
+}
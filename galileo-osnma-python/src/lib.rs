@@ -0,0 +1,162 @@
+//! # Python bindings for galileo-osnma
+//!
+//! This crate builds the `galileo_osnma` Python extension module using
+//! [PyO3](https://pyo3.rs/). The module wraps the [`Osnma`] black box, the
+//! Galmon transport reader, the [`Gst`] and [`Svn`] types, and the parsers of
+//! the OSNMA messages in [`galileo_osnma::bitfields`], so that recorded
+//! streams can be processed and inspected from Python.
+//!
+//! [`Osnma`]: galileo_osnma::Osnma
+
+// The code generated by the pyo3 macros for functions returning PyResult
+// triggers this lint.
+#![allow(clippy::useless_conversion)]
+
+use galileo_osnma::{types::BitSlice, Gst, InavBand, Svn};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+mod bitfields;
+#[cfg(feature = "galmon")]
+mod galmon;
+mod osnma;
+
+/// Galileo System Time.
+///
+/// A GST is given by a week number and a time of week in seconds.
+#[pyclass(name = "Gst", module = "galileo_osnma", frozen, eq, ord, hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PyGst(pub Gst);
+
+#[pymethods]
+impl PyGst {
+    #[new]
+    fn new(wn: u16, tow: u32) -> PyResult<PyGst> {
+        if tow >= 604800 {
+            return Err(PyValueError::new_err("TOW out of range 0-604799"));
+        }
+        Ok(PyGst(Gst::new(wn, tow)))
+    }
+
+    /// Week number.
+    #[getter]
+    fn wn(&self) -> u16 {
+        self.0.wn()
+    }
+
+    /// Time of week in seconds.
+    #[getter]
+    fn tow(&self) -> u32 {
+        self.0.tow()
+    }
+
+    /// Returns the GST obtained by adding a number of seconds.
+    fn add_seconds(&self, seconds: i32) -> PyGst {
+        PyGst(self.0.add_seconds(seconds))
+    }
+
+    /// Returns the GST at the start of the subframe containing this GST.
+    fn gst_subframe(&self) -> PyGst {
+        PyGst(self.0.gst_subframe())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Gst(wn={}, tow={})", self.0.wn(), self.0.tow())
+    }
+}
+
+/// Galileo SVN (satellite number).
+///
+/// The SVN is an integer between 1 and 36. It is formatted as `"Exx"`.
+#[pyclass(name = "Svn", module = "galileo_osnma", frozen, eq, ord, hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PySvn(u8);
+
+#[pymethods]
+impl PySvn {
+    #[new]
+    fn new(svn: u8) -> PyResult<PySvn> {
+        parse_svn(svn).map(|svn| PySvn(u8::from(svn)))
+    }
+
+    /// Returns a list with all the SVNs.
+    #[staticmethod]
+    fn all() -> Vec<PySvn> {
+        Svn::iter().map(|svn| PySvn(u8::from(svn))).collect()
+    }
+
+    fn __int__(&self) -> u8 {
+        self.0
+    }
+
+    fn __str__(&self) -> String {
+        format!("E{:02}", self.0)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Svn({})", self.0)
+    }
+}
+
+/// Galileo band with INAV data.
+#[pyclass(name = "InavBand", module = "galileo_osnma", frozen, eq, eq_int, hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PyInavBand {
+    /// E1B band.
+    E1B,
+    /// E5b band.
+    E5B,
+}
+
+impl From<PyInavBand> for InavBand {
+    fn from(band: PyInavBand) -> InavBand {
+        match band {
+            PyInavBand::E1B => InavBand::E1B,
+            PyInavBand::E5B => InavBand::E5B,
+        }
+    }
+}
+
+/// SVN argument, which can be given either as an `Svn` or as an `int`.
+#[derive(FromPyObject)]
+pub enum SvnArg {
+    /// SVN given as an `Svn` object.
+    Svn(PySvn),
+    /// SVN given as an integer.
+    Int(u8),
+}
+
+impl TryFrom<SvnArg> for Svn {
+    type Error = PyErr;
+
+    fn try_from(svn: SvnArg) -> PyResult<Svn> {
+        match svn {
+            SvnArg::Svn(svn) => parse_svn(svn.0),
+            SvnArg::Int(svn) => parse_svn(svn),
+        }
+    }
+}
+
+fn parse_svn(svn: u8) -> PyResult<Svn> {
+    Svn::try_from(svn).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+// Packs a BitSlice MSB-first into bytes, padding the last byte with zeros.
+fn bits_to_bytes(bits: &BitSlice) -> Vec<u8> {
+    let mut bytes = vec![0; (bits.len() + 7) / 8];
+    BitSlice::from_slice_mut(&mut bytes)[..bits.len()].copy_from_bitslice(bits);
+    bytes
+}
+
+/// Python bindings for the galileo-osnma Galileo OSNMA implementation.
+#[pymodule]
+#[pyo3(name = "galileo_osnma")]
+fn galileo_osnma_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGst>()?;
+    m.add_class::<PySvn>()?;
+    m.add_class::<PyInavBand>()?;
+    osnma::register(m)?;
+    bitfields::register(m)?;
+    #[cfg(feature = "galmon")]
+    galmon::register(m)?;
+    Ok(())
+}
@@ -0,0 +1,218 @@
+//! Python wrapper of the OSNMA black box.
+
+use crate::{bits_to_bytes, PyGst, PyInavBand, SvnArg};
+use galileo_osnma::{
+    navmessage::NavMessageData,
+    storage::{FullStorage, SmallStorage},
+    types::{InavWord, OsnmaDataMessage, MERKLE_TREE_NODE_BYTES},
+    Osnma, PublicKey, Svn, Validated,
+};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyModule},
+};
+
+/// OSNMA black box.
+///
+/// The black box is constructed from a Merkle tree root, an ECDSA public key
+/// in SEC1 encoding, or both. The `storage` argument selects between the
+/// `"full"` storage (data for all the 36 satellites) and the `"small"`
+/// storage.
+#[pyclass(name = "Osnma", module = "galileo_osnma")]
+pub struct PyOsnma {
+    inner: Inner,
+}
+
+// The object is allocated in the Python heap, so the size difference between
+// the variants does not matter.
+#[allow(clippy::large_enum_variant)]
+enum Inner {
+    Full(Osnma<FullStorage>),
+    Small(Osnma<SmallStorage>),
+}
+
+macro_rules! with_osnma {
+    ($inner:expr, $osnma:ident => $e:expr) => {
+        match $inner {
+            Inner::Full($osnma) => $e,
+            Inner::Small($osnma) => $e,
+        }
+    };
+}
+
+fn parse_pubkey(sec1: &[u8], pkid: u8) -> PyResult<PublicKey<Validated>> {
+    let invalid = |_| PyValueError::new_err("invalid SEC1 public key");
+    let pubkey = match sec1.len() {
+        // compressed or uncompressed P-256 point
+        33 | 65 => PublicKey::from_p256(
+            p256::ecdsa::VerifyingKey::from_sec1_bytes(sec1).map_err(invalid)?,
+            pkid,
+        ),
+        // compressed or uncompressed P-521 point
+        67 | 133 => PublicKey::from_p521(
+            p521::ecdsa::VerifyingKey::from_sec1_bytes(sec1).map_err(invalid)?,
+            pkid,
+        ),
+        _ => return Err(PyValueError::new_err("wrong SEC1 public key length")),
+    };
+    Ok(pubkey.force_valid())
+}
+
+fn to_array<const N: usize>(data: &[u8], what: &str) -> PyResult<[u8; N]> {
+    data.try_into()
+        .map_err(|_| PyValueError::new_err(format!("{what} must have {N} bytes")))
+}
+
+#[pymethods]
+impl PyOsnma {
+    #[new]
+    #[pyo3(signature = (merkle_root=None, pubkey=None, pkid=None, only_slowmac=false, storage="full"))]
+    fn new(
+        merkle_root: Option<&[u8]>,
+        pubkey: Option<&[u8]>,
+        pkid: Option<u8>,
+        only_slowmac: bool,
+        storage: &str,
+    ) -> PyResult<PyOsnma> {
+        let merkle_root = merkle_root
+            .map(|root| to_array::<MERKLE_TREE_NODE_BYTES>(root, "merkle_root"))
+            .transpose()?;
+        let pubkey = match (pubkey, pkid) {
+            (Some(pubkey), Some(pkid)) => Some(parse_pubkey(pubkey, pkid)?),
+            (None, None) => None,
+            _ => {
+                return Err(PyValueError::new_err(
+                    "pubkey and pkid must be given together",
+                ))
+            }
+        };
+        macro_rules! build {
+            () => {
+                match (merkle_root, pubkey) {
+                    (Some(root), pubkey) => Osnma::from_merkle_tree(root, pubkey, only_slowmac),
+                    (None, Some(pubkey)) => Osnma::from_pubkey(pubkey, only_slowmac),
+                    (None, None) => {
+                        return Err(PyValueError::new_err(
+                            "at least either merkle_root or pubkey must be given",
+                        ))
+                    }
+                }
+            };
+        }
+        let inner = match storage {
+            "full" => Inner::Full(build!()),
+            "small" => Inner::Small(build!()),
+            _ => {
+                return Err(PyValueError::new_err(
+                    "storage must be \"full\" or \"small\"",
+                ))
+            }
+        };
+        Ok(PyOsnma { inner })
+    }
+
+    /// Feeds a 16-byte INAV word.
+    ///
+    /// `gst` is the GST at the start of the INAV page transmission.
+    fn feed_inav(
+        &mut self,
+        word: &[u8],
+        svn: SvnArg,
+        gst: PyGst,
+        band: PyInavBand,
+    ) -> PyResult<()> {
+        let word: InavWord = to_array(word, "word")?;
+        let svn = Svn::try_from(svn)?;
+        with_osnma!(&mut self.inner, o => o.feed_inav(&word, svn, gst.0, band.into()));
+        Ok(())
+    }
+
+    /// Feeds the 5 bytes of OSNMA data of an INAV page.
+    ///
+    /// `gst` is the GST at the start of the INAV page transmission.
+    fn feed_osnma(&mut self, data: &[u8], svn: SvnArg, gst: PyGst) -> PyResult<()> {
+        let data: OsnmaDataMessage = to_array(data, "data")?;
+        let svn = Svn::try_from(svn)?;
+        with_osnma!(&mut self.inner, o => o.feed_osnma(&data, svn, gst.0));
+        Ok(())
+    }
+
+    /// Returns the authenticated CED and health status data of a satellite.
+    ///
+    /// Returns `None` if there is no authenticated data.
+    fn get_ced_and_status(&self, svn: SvnArg) -> PyResult<Option<PyNavData>> {
+        let svn = Svn::try_from(svn)?;
+        Ok(with_osnma!(&self.inner, o => o.get_ced_and_status(svn).map(PyNavData::from)))
+    }
+
+    /// Returns the authenticated timing parameters data of a satellite.
+    ///
+    /// Returns `None` if there is no authenticated data.
+    fn get_timing_parameters(&self, svn: SvnArg) -> PyResult<Option<PyNavData>> {
+        let svn = Svn::try_from(svn)?;
+        Ok(with_osnma!(&self.inner, o => o.get_timing_parameters(svn).map(PyNavData::from)))
+    }
+
+    /// Number of one-way function computations used in the last TESLA key
+    /// validation, or `None` if no key has been validated yet.
+    #[getter]
+    fn last_key_validation_hashes(&self) -> Option<usize> {
+        with_osnma!(&self.inner, o => o.last_key_validation_hashes())
+    }
+}
+
+/// Authenticated navigation data.
+///
+/// The data is given as bytes, packed MSB-first and padded with zeros. The
+/// metadata gives the length of the data in bits, the number of
+/// authentication bits, and the GST of the data.
+#[pyclass(name = "NavData", module = "galileo_osnma", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyNavData {
+    data: Vec<u8>,
+    /// Length of the data in bits.
+    #[pyo3(get)]
+    num_bits: usize,
+    /// Sum of the length in bits of the tags that authenticated the data.
+    #[pyo3(get)]
+    authbits: u16,
+    /// GST of the data.
+    #[pyo3(get)]
+    gst: PyGst,
+}
+
+impl From<NavMessageData<'_>> for PyNavData {
+    fn from(data: NavMessageData<'_>) -> PyNavData {
+        PyNavData {
+            data: bits_to_bytes(data.data()),
+            num_bits: data.data().len(),
+            authbits: data.authbits(),
+            gst: PyGst(data.gst()),
+        }
+    }
+}
+
+#[pymethods]
+impl PyNavData {
+    /// Navigation data bytes.
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.data)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "NavData(num_bits={}, authbits={}, gst={})",
+            self.num_bits,
+            self.authbits,
+            self.gst.__repr__()
+        )
+    }
+}
+
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOsnma>()?;
+    m.add_class::<PyNavData>()?;
+    Ok(())
+}
@@ -0,0 +1,94 @@
+"""Tests for the galileo_osnma Python module.
+
+These tests can be run with `python -m unittest` after installing the module
+with `maturin develop`.
+"""
+
+import unittest
+
+import galileo_osnma as osnma
+
+# DSM-KROOT broadcast on 2022-03-07 9:00 UTC
+DSM_KROOT = bytes.fromhex(
+    '22504921049821 25d3964da3a2841e1d'
+    'e4d458c0e9842476e004666cf37958de'
+    '285197a26353f1a4c66d7e3d291853ba'
+    '5a13c9c3484a267770112a13383ea52d'
+    '3a019d5b6e1dd187b9453cdf06ca7f34'
+    'ea1497525aaf18f1f9f1fccb12298977'
+    '35c021b0417393b5'.replace(' ', ''))
+
+
+class TestTypes(unittest.TestCase):
+    def test_gst(self):
+        gst = osnma.Gst(1176, 175619)
+        self.assertEqual(gst.wn, 1176)
+        self.assertEqual(gst.tow, 175619)
+        self.assertEqual(gst.gst_subframe(), osnma.Gst(1176, 175590))
+        self.assertEqual(gst.add_seconds(-175620), osnma.Gst(1175, 604799))
+        with self.assertRaises(ValueError):
+            osnma.Gst(1176, 604800)
+
+    def test_svn(self):
+        self.assertEqual(str(osnma.Svn(3)), 'E03')
+        self.assertEqual(int(osnma.Svn(24)), 24)
+        self.assertEqual(len(osnma.Svn.all()), 36)
+        for svn in [0, 37]:
+            with self.assertRaises(ValueError):
+                osnma.Svn(svn)
+
+
+class TestBitfields(unittest.TestCase):
+    def test_nma_header(self):
+        header = osnma.NmaHeader(0x52)
+        self.assertEqual(header.nma_status, 'Test')
+        self.assertEqual(header.chain_id, 1)
+        self.assertEqual(header.chain_and_pubkey_status, 'Nominal')
+
+    def test_dsm_kroot(self):
+        dsm = osnma.DsmKroot(DSM_KROOT)
+        self.assertEqual(dsm.number_of_blocks, 8)
+        self.assertEqual(dsm.public_key_id, 2)
+        self.assertEqual(dsm.kroot_chain_id, 1)
+        self.assertEqual(dsm.hash_function, 'Sha256')
+        self.assertEqual(dsm.mac_function, 'HmacSha256')
+        self.assertEqual(dsm.key_size, 128)
+        self.assertEqual(dsm.tag_size, 40)
+        self.assertEqual(dsm.mac_lookup_table, 0x21)
+        self.assertEqual(dsm.kroot_gst, osnma.Gst(0x498, 0x21 * 3600))
+        self.assertEqual(dsm.alpha, 0x25d3964da3a2)
+        self.assertEqual(
+            dsm.kroot, bytes.fromhex('841e1de4d458c0e9842476e004666cf3'))
+        self.assertEqual(dsm.ecdsa_function, 'P256Sha256')
+        self.assertEqual(dsm.digital_signature, DSM_KROOT[29:93])
+        self.assertEqual(dsm.padding, DSM_KROOT[93:])
+        with self.assertRaises(ValueError):
+            osnma.DsmKroot(DSM_KROOT[:-1])
+
+    def test_mack(self):
+        mack = osnma.Mack(bytes(60), 128, 40)
+        self.assertEqual(mack.num_tags, 6)
+        self.assertEqual(len(mack.tags), 5)
+        self.assertEqual(len(mack.key), 16)
+        with self.assertRaises(ValueError):
+            osnma.Mack(bytes(60), 100, 40)
+
+
+class TestOsnma(unittest.TestCase):
+    def test_no_data(self):
+        black_box = osnma.Osnma(merkle_root=bytes(32))
+        self.assertIsNone(black_box.get_ced_and_status(osnma.Svn(3)))
+        self.assertIsNone(black_box.get_timing_parameters(3))
+        self.assertIsNone(black_box.last_key_validation_hashes)
+
+    def test_arguments(self):
+        with self.assertRaises(ValueError):
+            osnma.Osnma()
+        with self.assertRaises(ValueError):
+            osnma.Osnma(merkle_root=bytes(31))
+        with self.assertRaises(ValueError):
+            osnma.Osnma(merkle_root=bytes(32), storage='tiny')
+
+
+if __name__ == '__main__':
+    unittest.main()
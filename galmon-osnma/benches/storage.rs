@@ -0,0 +1,106 @@
+//! Benchmarks of the navigation data and MACK message storage.
+//!
+//! These measure the cost of storing and retrieving data in
+//! [`CollectNavMessage`] and [`MackStorage`] with the full constellation and
+//! the history required for Slow MAC, which is the worst case for the lookups
+//! by GST and SVN.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use galileo_osnma::{
+    bitfields::NmaStatus, mack::MackStorage, navmessage::CollectNavMessage, storage::FullStorage,
+    types::InavWord, Gst, InavBand, Svn,
+};
+
+// Word types fed to CollectNavMessage: CED and health status (1 to 5) and
+// timing parameters (6 and 10).
+const WORD_TYPES: [u8; 7] = [1, 2, 3, 4, 5, 6, 10];
+
+fn inav_word(word_type: u8, svn: Svn, gst: Gst) -> InavWord {
+    let mut word = [0; 16];
+    word[0] = word_type << 2;
+    for (j, b) in word.iter_mut().enumerate().skip(1) {
+        *b = (j as u8) ^ u8::from(svn) ^ (gst.tow() as u8);
+    }
+    word
+}
+
+fn feed_subframe(navmessage: &mut CollectNavMessage<FullStorage>, gst: Gst) {
+    for svn in Svn::iter() {
+        for (j, &word_type) in WORD_TYPES.iter().enumerate() {
+            let word = inav_word(word_type, svn, gst);
+            navmessage.feed(&word, svn, gst.add_seconds(2 * j as i32), InavBand::E1B);
+        }
+    }
+}
+
+fn store_macks(mack: &mut MackStorage<FullStorage>, gst: Gst) {
+    for svn in Svn::iter() {
+        mack.store(&[u8::from(svn); 60], svn, gst, NmaStatus::Operational);
+    }
+}
+
+fn navmessage(c: &mut Criterion) {
+    let start = Gst::new(1176, 120930);
+    let mut storage = CollectNavMessage::<FullStorage>::new();
+    let mut gst = start;
+    for _ in 0..13 {
+        feed_subframe(&mut storage, gst);
+        gst = gst.add_seconds(30);
+    }
+
+    c.bench_function("navmessage feed subframe", |b| {
+        let mut storage = storage.clone();
+        let mut gst = gst;
+        b.iter(|| {
+            feed_subframe(&mut storage, gst);
+            gst = gst.add_seconds(30);
+        })
+    });
+
+    // The stored data is not authenticated, so each query searches the whole
+    // history.
+    c.bench_function("navmessage get all SVNs", |b| {
+        b.iter(|| {
+            for svn in Svn::iter() {
+                black_box(storage.get_ced_and_status(svn));
+                black_box(storage.get_timing_parameters(svn));
+            }
+        })
+    });
+}
+
+fn mack(c: &mut Criterion) {
+    let start = Gst::new(1176, 120930);
+    let mut storage = MackStorage::<FullStorage>::new();
+    let mut gst = start;
+    for _ in 0..12 {
+        store_macks(&mut storage, gst);
+        gst = gst.add_seconds(30);
+    }
+
+    c.bench_function("mack store subframe", |b| {
+        let mut storage = storage.clone();
+        let mut gst = gst;
+        b.iter(|| {
+            store_macks(&mut storage, gst);
+            gst = gst.add_seconds(30);
+        })
+    });
+
+    // Retrieve the MACK messages of all the SVNs in all the stored subframes,
+    // as done when processing Slow MAC.
+    let last = gst.add_seconds(-30);
+    c.bench_function("mack get all SVNs and subframes", |b| {
+        b.iter(|| {
+            for j in 0..12 {
+                let gst = last.add_seconds(-30 * j);
+                for svn in Svn::iter() {
+                    black_box(storage.get(svn, gst));
+                }
+            }
+        })
+    });
+}
+
+criterion_group!(benches, navmessage, mack);
+criterion_main!(benches);
@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use galileo_osnma::{
+    galmon::{pages::PageExtractor, transport::ReadTransport},
+    storage::FullStorage,
+    Osnma, PublicKey, Svn, Validated,
+};
+use spki::DecodePublicKey;
+use std::io::Read;
+use std::time::Instant;
+
+/// Measure OSNMA processing throughput on a recorded Galmon protobuf file
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the recorded Galmon protobuf file.
+    input_file: String,
+    /// Merkle tree root in hex.
+    #[arg(long)]
+    merkle_root: Option<String>,
+    /// Path to the P-256 public key in PEM format.
+    #[arg(long)]
+    pubkey: Option<String>,
+    /// P-521 public key in hexadecimal format (SEC1 encoding).
+    #[arg(long)]
+    pubkey_p521: Option<String>,
+    /// ID of the public key.
+    #[arg(long)]
+    pkid: Option<u8>,
+    /// Only process slow MAC data.
+    #[arg(long)]
+    slow_mac_only: bool,
+    /// Number of times to process the file.
+    #[arg(long, default_value_t = 1)]
+    iterations: u32,
+}
+
+fn load_pubkey(path: &str, pkid: u8) -> Result<PublicKey<Validated>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut pem = String::new();
+    file.read_to_string(&mut pem)?;
+    let pubkey = p256::ecdsa::VerifyingKey::from_public_key_pem(&pem)?;
+    Ok(PublicKey::from_p256(pubkey, pkid).force_valid())
+}
+
+fn load_pubkey_p521(hex: &str, pkid: u8) -> Result<PublicKey<Validated>> {
+    let pubkey = hex::decode(hex)?;
+    let pubkey = p521::ecdsa::VerifyingKey::from_sec1_bytes(&pubkey)?;
+    Ok(PublicKey::from_p521(pubkey, pkid).force_valid())
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    if args.merkle_root.is_none() && args.pubkey.is_none() && args.pubkey_p521.is_none() {
+        anyhow::bail!("at least either the Merkle tree root or the public key must be specified");
+    }
+    if args.pubkey.is_some() && args.pubkey_p521.is_some() {
+        anyhow::bail!("the --pubkey and --pubkey-p521 arguments are mutually exclusive");
+    }
+    if (args.pubkey.is_some() || args.pubkey_p521.is_some()) != args.pkid.is_some() {
+        anyhow::bail!(
+            "the --pkid argument needs to be used together with --pubkey or --pubkey-p521"
+        );
+    }
+    let pubkey = if let Some(path) = &args.pubkey {
+        Some(load_pubkey(path, args.pkid.unwrap())?)
+    } else if let Some(hex) = &args.pubkey_p521 {
+        Some(load_pubkey_p521(hex, args.pkid.unwrap())?)
+    } else {
+        None
+    };
+    let merkle: Option<[u8; 32]> = args
+        .merkle_root
+        .as_ref()
+        .map(|merkle| {
+            hex::decode(merkle)
+                .context("failed to parse Merkle tree root")?
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("the Merkle tree root has a wrong length"))
+        })
+        .transpose()?;
+
+    // Parse the whole file in advance, so that only the OSNMA processing is
+    // timed.
+    let file = std::fs::File::open(&args.input_file)?;
+    let mut read = ReadTransport::new(std::io::BufReader::new(file));
+    let mut extractor = PageExtractor::new();
+    let mut pages = Vec::new();
+    while let Some(packet) = read.read_packet()? {
+        pages.extend(extractor.extract(&packet));
+    }
+    println!(
+        "loaded {} INAV pages (last GST {:?})",
+        pages.len(),
+        extractor.last_gst()
+    );
+
+    let mut authenticated = 0u64;
+    let start = Instant::now();
+    for _ in 0..args.iterations {
+        let mut osnma: Osnma<FullStorage> = match merkle {
+            Some(merkle) => Osnma::from_merkle_tree(merkle, pubkey.clone(), args.slow_mac_only),
+            None => Osnma::from_pubkey(pubkey.clone().unwrap(), args.slow_mac_only),
+        };
+        for page in &pages {
+            osnma.feed_inav(&page.inav_word, page.svn, page.gst, page.band);
+            if let Some(osnma_data) = &page.osnma_data {
+                osnma.feed_osnma(osnma_data, page.svn, page.gst);
+            }
+            for svn in Svn::iter() {
+                if osnma.get_ced_and_status(svn).is_some() {
+                    authenticated += 1;
+                }
+                if osnma.get_timing_parameters(svn).is_some() {
+                    authenticated += 1;
+                }
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let total_pages = pages.len() as f64 * f64::from(args.iterations);
+    println!(
+        "processed {} pages in {:.3} s ({:.0} pages/s)",
+        total_pages,
+        elapsed.as_secs_f64(),
+        total_pages / elapsed.as_secs_f64()
+    );
+    println!("authenticated data queries answered: {}", authenticated);
+    Ok(())
+}
@@ -0,0 +1,483 @@
+//! JSON lines output.
+//!
+//! In this output mode, `galmon-osnma` writes one JSON object per event to
+//! the standard output. Each object has an `event` field giving the event
+//! type and a `gst` field with the GST of the event. The events are:
+//!
+//! * `ced_and_status_authenticated` and `timing_parameters_authenticated`.
+//!   New navigation data has been authenticated for a satellite. The data is
+//!   given in hexadecimal together with its authbits.
+//! * The [`OsnmaEvent`]s of the OSNMA black box: `public_key_verified`,
+//!   `public_key_verification_failed`, `kroot_verified`,
+//!   `kroot_verification_failed`, `tesla_key_verified`,
+//!   `tesla_key_verification_failed`, `mack_verification_failed`,
+//!   `alert_message`, `state_transition` and `gst_discrepancy`. Failures
+//!   include the `error` and a human-readable `reason`.
+//! * `tag_verification_failed` and `macseq_verification_failed`. The
+//!   validation of a tag or MACSEQ failed. With `--trace-macs`, the
+//!   successful validations are also written, as `tag_verified` and
+//!   `macseq_verified`, and all these events include the MAC input and key.
+//! * `observer_position`. The position of the receiver reported by Galmon.
+
+use galileo_osnma::{
+    event::{EventHook, OsnmaEvent},
+    snapshot::SnapshotEntry,
+    trace::MacTrace,
+    types::BitSlice,
+    Gst, Svn,
+};
+use serde_json::{json, Value};
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+fn gst_json(gst: Gst) -> Value {
+    json!({ "wn": gst.wn(), "tow": gst.tow() })
+}
+
+// Formats a BitSlice as hex, padding the last byte with zeros.
+fn hex_bits(bits: &BitSlice) -> String {
+    let mut bytes = vec![0; (bits.len() + 7) / 8];
+    BitSlice::from_slice_mut(&mut bytes)[..bits.len()].copy_from_bitslice(bits);
+    hex::encode(bytes)
+}
+
+fn debug<T: core::fmt::Debug>(value: T) -> Value {
+    format!("{value:?}").into()
+}
+
+/// JSON lines writer.
+///
+/// The events are written both from the hooks of the OSNMA black box and from
+/// the main loop. Clones of a `Jsonl` share the same output, so that the
+/// events are written in the order in which they happen.
+#[derive(Debug)]
+pub struct Jsonl<W> {
+    output: Rc<RefCell<Output<W>>>,
+}
+
+#[derive(Debug)]
+struct Output<W> {
+    write: W,
+    // The hooks of the OSNMA black box cannot return errors, so the first
+    // error writing an event is stored here, and no more events are written
+    // after it. The main loop checks it with `output_closed`.
+    error: Option<std::io::Error>,
+}
+
+impl<W> Clone for Jsonl<W> {
+    fn clone(&self) -> Jsonl<W> {
+        Jsonl {
+            output: Rc::clone(&self.output),
+        }
+    }
+}
+
+impl<W: Write> Jsonl<W> {
+    /// Constructs a new JSON lines writer writing to `write`.
+    pub fn new(write: W) -> Jsonl<W> {
+        Jsonl {
+            output: Rc::new(RefCell::new(Output { write, error: None })),
+        }
+    }
+
+    fn write(&self, value: &Value) {
+        let mut output = self.output.borrow_mut();
+        if output.error.is_some() {
+            return;
+        }
+        if let Err(e) = writeln!(output.write, "{value}") {
+            output.error = Some(e);
+        }
+    }
+
+    /// Checks whether writing the events has failed.
+    ///
+    /// This returns `Ok(true)` if the output has been closed, for instance
+    /// because it is piped to a program that has exited, which is not treated
+    /// as an error. Other write errors are returned as errors. The error is
+    /// kept, so it is returned again by later calls, and no more events are
+    /// written.
+    pub fn output_closed(&self) -> std::io::Result<bool> {
+        match &self.output.borrow().error {
+            None => Ok(false),
+            Some(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(true),
+            Some(e) => Err(std::io::Error::new(e.kind(), e.to_string())),
+        }
+    }
+
+    /// Writes an event for newly authenticated navigation data.
+    ///
+    /// The `event` is either `ced_and_status_authenticated` or
+    /// `timing_parameters_authenticated`.
+    pub fn authenticated_data(&self, event: &str, svn: Svn, entry: &SnapshotEntry) {
+        let data = entry.data();
+        self.write(&authenticated_data_json(
+            event,
+            svn,
+            data.gst(),
+            data.authbits(),
+            data.iodnav(),
+            data.data_bytes(),
+        ));
+    }
+
+    /// Writes an event for the observer position.
+    ///
+    /// The position is given both in ECEF coordinates, in meters, and as WGS84
+    /// latitude and longitude, in degrees, and altitude, in meters.
+    pub fn observer_position(
+        &self,
+        gst: Option<Gst>,
+        ecef: [f64; 3],
+        lat_lon_alt: (f64, f64, f64),
+    ) {
+        let (latitude, longitude, altitude) = lat_lon_alt;
+        self.write(&json!({
+            "event": "observer_position",
+            "gst": gst.map(gst_json),
+            "ecef": ecef,
+            "latitude": latitude,
+            "longitude": longitude,
+            "altitude": altitude,
+        }));
+    }
+
+    /// Writes an event for a tag or MACSEQ validation.
+    ///
+    /// If `full` is `true`, the event is written for all the validations and
+    /// includes the MAC input and key. This is used with `--trace-macs`.
+    /// Otherwise, the event is only written for failed validations.
+    pub fn mac_trace(&self, trace: &MacTrace<'_>, full: bool) {
+        if full || !trace.is_valid() {
+            self.write(&mac_trace_json(trace, full));
+        }
+    }
+}
+
+// The JSON lines writer is used as the event hook of the OSNMA black box.
+impl<W: Write> EventHook for Jsonl<W> {
+    fn event(&mut self, event: &OsnmaEvent) {
+        self.write(&osnma_event_json(event));
+    }
+}
+
+fn authenticated_data_json(
+    event: &str,
+    svn: Svn,
+    gst: Gst,
+    authbits: u16,
+    iodnav: Option<u16>,
+    data: &[u8],
+) -> Value {
+    json!({
+        "event": event,
+        "gst": gst_json(gst),
+        "svn": svn.to_string(),
+        "authbits": authbits,
+        "iodnav": iodnav,
+        "data": hex::encode(data),
+    })
+}
+
+fn osnma_event_json(event: &OsnmaEvent) -> Value {
+    let gst = gst_json(event.gst());
+    match event {
+        OsnmaEvent::PublicKeyVerified { npkid, .. } => json!({
+            "event": "public_key_verified",
+            "gst": gst,
+            "npkid": npkid,
+        }),
+        OsnmaEvent::PublicKeyVerificationFailed { error, .. } => json!({
+            "event": "public_key_verification_failed",
+            "gst": gst,
+            "error": debug(error),
+            "reason": error.to_string(),
+        }),
+        OsnmaEvent::KrootVerified {
+            pkid,
+            chain_id,
+            nma_status,
+            kroot_gst,
+            ..
+        } => json!({
+            "event": "kroot_verified",
+            "gst": gst,
+            "pkid": pkid,
+            "chain_id": chain_id,
+            "nma_status": debug(nma_status),
+            "kroot_gst": gst_json(*kroot_gst),
+        }),
+        OsnmaEvent::KrootVerificationFailed { pkid, error, .. } => json!({
+            "event": "kroot_verification_failed",
+            "gst": gst,
+            "pkid": pkid,
+            "error": debug(error),
+            "reason": error.to_string(),
+        }),
+        OsnmaEvent::TeslaKeyVerified {
+            key_gst,
+            chain_id,
+            validated_by,
+            ..
+        } => json!({
+            "event": "tesla_key_verified",
+            "gst": gst,
+            "key_gst": gst_json(*key_gst),
+            "chain_id": chain_id,
+            "validated_by": gst_json(*validated_by),
+        }),
+        OsnmaEvent::TeslaKeyVerificationFailed {
+            key_gst,
+            validated_by,
+            error,
+            ..
+        } => json!({
+            "event": "tesla_key_verification_failed",
+            "gst": gst,
+            "key_gst": gst_json(*key_gst),
+            "validated_by": gst_json(*validated_by),
+            "error": debug(error),
+            "reason": error.to_string(),
+        }),
+        OsnmaEvent::MackVerificationFailed { svn, error, .. } => json!({
+            "event": "mack_verification_failed",
+            "gst": gst,
+            "svn": svn.to_string(),
+            "error": debug(error),
+            "reason": error.to_string(),
+        }),
+        OsnmaEvent::AlertMessage { .. } => json!({
+            "event": "alert_message",
+            "gst": gst,
+        }),
+        OsnmaEvent::StateTransition(transition) => json!({
+            "event": "state_transition",
+            "gst": gst,
+            "from": debug(transition.from),
+            "to": debug(transition.to),
+            "reason": transition.reason.to_string(),
+        }),
+        OsnmaEvent::GstDiscrepancy(discrepancy) => json!({
+            "event": "gst_discrepancy",
+            "gst": gst,
+            "svn": discrepancy.svn().to_string(),
+            "band": debug(discrepancy.band()),
+            "word_type": discrepancy.word_type(),
+            "expected": gst_json(discrepancy.expected()),
+            "decoded": gst_json(discrepancy.decoded()),
+        }),
+    }
+}
+
+fn mac_trace_json(trace: &MacTrace<'_>, full: bool) -> Value {
+    let mut value = match trace {
+        MacTrace::Tag(tag) => json!({
+            "event": if tag.is_valid() { "tag_verified" } else { "tag_verification_failed" },
+            "gst": gst_json(tag.gst),
+            "prna": tag.prna.to_string(),
+            "prnd": tag.prnd,
+            "ctr": tag.ctr,
+            "computed_tag": hex_bits(tag.computed_tag),
+            "received_tag": hex_bits(tag.received_tag),
+        }),
+        MacTrace::Macseq(macseq) => json!({
+            "event": if macseq.is_valid() {
+                "macseq_verified"
+            } else {
+                "macseq_verification_failed"
+            },
+            "gst": gst_json(macseq.gst),
+            "prna": macseq.prna.to_string(),
+            "computed_macseq": format!("{:03x}", macseq.computed_macseq),
+            "received_macseq": format!("{:03x}", macseq.received_macseq),
+        }),
+    };
+    if full {
+        let (mac_input, key) = match trace {
+            MacTrace::Tag(tag) => (tag.mac_input, tag.key),
+            MacTrace::Macseq(macseq) => (macseq.mac_input, macseq.key),
+        };
+        value["mac_input"] = hex::encode(mac_input).into();
+        value["key"] = hex::encode(key).into();
+    }
+    value
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use galileo_osnma::{
+        inav_clock::InavClock,
+        state::{OsnmaState, StateTransition, TransitionReason},
+        tesla::ValidationError,
+        types::InavWord,
+        InavBand,
+    };
+
+    // INAV word 5 containing a GST
+    fn word5(gst: Gst) -> InavWord {
+        let mut word = [0; 16];
+        word[0] = 5 << 2;
+        let bits = BitSlice::from_slice_mut(&mut word);
+        for j in 0..12 {
+            bits.set(73 + j, (gst.wn() >> (11 - j)) & 1 != 0);
+        }
+        for j in 0..20 {
+            bits.set(85 + j, (gst.tow() >> (19 - j)) & 1 != 0);
+        }
+        word
+    }
+
+    #[test]
+    fn authenticated_data() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1177, 175770);
+        assert_eq!(
+            authenticated_data_json(
+                "ced_and_status_authenticated",
+                svn,
+                gst,
+                80,
+                Some(42),
+                &[0x01, 0xab]
+            ),
+            json!({
+                "event": "ced_and_status_authenticated",
+                "gst": { "wn": 1177, "tow": 175770 },
+                "svn": "E11",
+                "authbits": 80,
+                "iodnav": 42,
+                "data": "01ab",
+            })
+        );
+        assert_eq!(
+            authenticated_data_json("timing_parameters_authenticated", svn, gst, 40, None, &[])
+                ["iodnav"],
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn osnma_events() {
+        let gst = Gst::new(1177, 175770);
+        let event = OsnmaEvent::TeslaKeyVerificationFailed {
+            key_gst: gst.add_seconds(30),
+            validated_by: gst,
+            error: ValidationError::WrongOneWayFunction,
+            gst,
+        };
+        assert_eq!(
+            osnma_event_json(&event),
+            json!({
+                "event": "tesla_key_verification_failed",
+                "gst": { "wn": 1177, "tow": 175770 },
+                "key_gst": { "wn": 1177, "tow": 175800 },
+                "validated_by": { "wn": 1177, "tow": 175770 },
+                "error": "WrongOneWayFunction",
+                "reason": ValidationError::WrongOneWayFunction.to_string(),
+            })
+        );
+
+        let event = OsnmaEvent::StateTransition(StateTransition {
+            from: OsnmaState::WarmStart,
+            to: OsnmaState::Operational,
+            reason: TransitionReason::KrootVerified,
+            gst,
+        });
+        assert_eq!(
+            osnma_event_json(&event),
+            json!({
+                "event": "state_transition",
+                "gst": { "wn": 1177, "tow": 175770 },
+                "from": "WarmStart",
+                "to": "Operational",
+                "reason": TransitionReason::KrootVerified.to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn gst_discrepancy() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1177, 175770);
+        let expected = gst.add_seconds(2);
+        let discrepancy = InavClock::new()
+            .check(&word5(gst), svn, InavBand::E1B, expected)
+            .unwrap();
+        assert_eq!(
+            osnma_event_json(&OsnmaEvent::GstDiscrepancy(discrepancy)),
+            json!({
+                "event": "gst_discrepancy",
+                "gst": { "wn": 1177, "tow": 175770 },
+                "svn": "E11",
+                "band": "E1B",
+                "word_type": 5,
+                "expected": { "wn": 1177, "tow": 175772 },
+                "decoded": { "wn": 1177, "tow": 175770 },
+            })
+        );
+    }
+
+    #[test]
+    fn output() {
+        let gst = Gst::new(1177, 175770);
+        let event = OsnmaEvent::AlertMessage { gst };
+        let mut output = Vec::new();
+        let mut jsonl = Jsonl::new(&mut output);
+        // Clones write to the same output
+        let mut clone = jsonl.clone();
+        jsonl.event(&event);
+        clone.event(&event);
+        assert!(!jsonl.output_closed().unwrap());
+        drop((jsonl, clone));
+        let line = format!("{}\n", osnma_event_json(&event));
+        assert_eq!(String::from_utf8(output).unwrap(), line.repeat(2));
+    }
+
+    #[test]
+    fn output_errors() {
+        use std::io::{Error, ErrorKind};
+
+        // Fails the first write with an error of the given kind and then
+        // accepts the writes.
+        struct FailOnce {
+            error: Option<ErrorKind>,
+            written: Vec<u8>,
+        }
+
+        impl Write for FailOnce {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                match self.error.take() {
+                    Some(kind) => Err(Error::from(kind)),
+                    None => self.written.write(buf),
+                }
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let event = OsnmaEvent::AlertMessage {
+            gst: Gst::new(1177, 175770),
+        };
+        for kind in [ErrorKind::BrokenPipe, ErrorKind::Other] {
+            let mut output = FailOnce {
+                error: Some(kind),
+                written: Vec::new(),
+            };
+            let mut jsonl = Jsonl::new(&mut output);
+            assert!(!jsonl.output_closed().unwrap());
+            // The error is latched, so the later events are not written and
+            // the error is reported each time.
+            for _ in 0..3 {
+                jsonl.event(&event);
+                match kind {
+                    ErrorKind::BrokenPipe => assert!(jsonl.output_closed().unwrap()),
+                    _ => assert_eq!(jsonl.output_closed().unwrap_err().kind(), kind),
+                }
+            }
+            drop(jsonl);
+            assert!(output.written.is_empty());
+        }
+    }
+}
@@ -1,16 +1,35 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use galileo_osnma::{
-    galmon::{navmon::nav_mon_message::GalileoInav, transport::ReadTransport,navmon::nav_mon_message::ObserverPosition},
+    galmon::{transport::ReadTransport,navmon::nav_mon_message::ObserverPosition},
     storage::FullStorage,
-    types::{BitSlice, NUM_SVNS},
-    Gst, InavBand, Osnma, PublicKey, Svn, Validated, Wn,
+    trace::MacTrace,
+    types::NUM_SVNS,
+    Osnma, PublicKey, Svn, Validated,
 };
 use spki::DecodePublicKey;
 use std::{io::Read};
 use std::f64::consts::PI;
 use colored::*;
 
+mod jsonl;
+mod passthrough;
+use galileo_osnma::galmon::pages::{Page, PageExtractor};
+use jsonl::Jsonl;
+use passthrough::Passthrough;
+
+// MAC trace hook chosen according to the command line arguments.
+type MacTraceFn = Box<dyn FnMut(&MacTrace<'_>)>;
+
+/// Output format.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+enum Output {
+    /// Log messages.
+    Text,
+    /// JSON, with one object per event, written to stdout.
+    Jsonl,
+}
+
 /// Process OSNMA data reading Galmon protobuf from stdin
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -30,6 +49,20 @@ struct Args {
     /// Only process slow MAC data.
     #[arg(long)]
     slow_mac_only: bool,
+    /// Print the MAC input, key and computed and received values of each tag
+    /// and MACSEQ validation to stderr.
+    #[arg(long)]
+    trace_macs: bool,
+    /// Write the Galmon stream to stdout, forwarding the Galileo INAV words
+    /// only once the navigation data they contain has been authenticated.
+    /// INAV words that are not authenticated and the navigation data of other
+    /// GNSS are dropped.
+    #[arg(long)]
+    passthrough: bool,
+    /// Output format. The log messages are still written to stderr with the
+    /// jsonl output.
+    #[arg(long, value_enum, default_value_t = Output::Text)]
+    output: Output,
 }
 
 fn load_pubkey(path: &str, pkid: u8) -> Result<PublicKey<Validated>> {
@@ -107,6 +140,10 @@ fn main() -> Result<()> {
         );
     }
 
+    if args.passthrough && args.output == Output::Jsonl {
+        anyhow::bail!("the --passthrough and --output jsonl arguments are mutually exclusive");
+    }
+
     let pubkey = if let Some(pubkey_path) = &args.pubkey {
         Some(load_pubkey(pubkey_path, args.pkid.unwrap())?)
     } else if let Some(pubkey_hex) = &args.pubkey_p521 {
@@ -115,7 +152,7 @@ fn main() -> Result<()> {
         None
     };
 
-    let mut osnma: Osnma<FullStorage> = if let Some(merkle) = &args.merkle_root {
+    let osnma: Osnma<FullStorage> = if let Some(merkle) = &args.merkle_root {
         let merkle = hex::decode(merkle)
             .context("failed to parse Merkle tree root")?
             .try_into()
@@ -126,14 +163,38 @@ fn main() -> Result<()> {
         // have checked that at least one of both is not None.
         Osnma::from_pubkey(pubkey.unwrap(), args.slow_mac_only)
     };
+    let jsonl = (args.output == Output::Jsonl).then(|| Jsonl::new(std::io::stdout()));
+    let mac_trace: Option<MacTraceFn> = match &jsonl {
+        None => args.trace_macs.then(|| {
+            Box::new(|trace: &MacTrace<'_>| eprintln!("MAC trace: {trace}")) as Box<_>
+        }),
+        Some(jsonl) => {
+            let jsonl = jsonl.clone();
+            let full = args.trace_macs;
+            Some(Box::new(move |trace: &MacTrace<'_>| jsonl.mac_trace(trace, full)))
+        }
+    };
+    let mut osnma = osnma
+        .with_mac_trace(mac_trace)
+        .with_event_hook(jsonl.clone());
 
     let mut read = ReadTransport::new(std::io::stdin());
-    let mut timing_parameters: [Option<[u8; 18]>; NUM_SVNS] = [None; NUM_SVNS];
-    let mut ced_and_status_data: [Option<[u8; 69]>; NUM_SVNS] = [None; NUM_SVNS];
-    let mut current_subframe = None;
-    let mut last_tow_mod_30 = 0;
+    let mut passthrough = args
+        .passthrough
+        .then(|| Passthrough::new(std::io::stdout().lock()));
+    let mut timing_parameters_generation: [Option<u32>; NUM_SVNS] = [None; NUM_SVNS];
+    let mut ced_and_status_generation: [Option<u32>; NUM_SVNS] = [None; NUM_SVNS];
+    let mut pages = PageExtractor::new();
 
     while let Some(packet) = read.read_packet()? {
+        if let Some(jsonl) = &jsonl {
+            if jsonl.output_closed().context("failed to write to stdout")? {
+                return Ok(());
+            }
+        }
+        if let Some(passthrough) = &mut passthrough {
+            passthrough.feed_packet(&packet)?;
+        }
 
         //display latitute,longitude,altitude-----------------------------------------------------------------------------------------------
         if let Some(
@@ -143,135 +204,73 @@ fn main() -> Result<()> {
         ) = &packet.op
         {
             let (lat, lon, alt) = ecef_to_wgs84(obpos.x, obpos.y, obpos.z);
+            if let Some(jsonl) = &jsonl {
+                jsonl.observer_position(pages.last_gst(), [obpos.x, obpos.y, obpos.z], (lat, lon, alt));
+            }
             log::info!("{}:ECEF={:?},緯度(latitude)={},経度(longitude)={},高度(altitude)={}", "ObserverPosition".cyan(),obpos, lat, lon, alt);
         } 
         //-------------------------------------------------------------------------------------------------------------------------------------
         
-        if let Some(
-            inav @ GalileoInav {
-                contents: inav_word,
-                reserved1: osnma_data,
-                sigid: Some(sigid),
-                ..
-            },
-        ) = &packet.gi
+        if let Some(Page {
+            inav_word,
+            osnma_data,
+            svn,
+            gst,
+            band,
+        }) = pages.extract(&packet)
         {
-            // This is needed because sometimes we can see a TOW of 604801
-            let secs_in_week = 604800;
-            let mut tow = inav.gnss_tow % secs_in_week;
-            let wn = Wn::try_from(inav.gnss_wn).unwrap()
-                + Wn::try_from(inav.gnss_tow / secs_in_week).unwrap();
-
-            // Fix bug in Galmon data:
-            //
-            // Often, the E1B word 16 starting at TOW = 29 mod 30 will have the
-            // TOW of the previous word 16 in the subframe, which starts at TOW
-            // = 15 mod 30. We detect this condition by looking at the last tow
-            // mod 30 that we saw and fixing if needed.
-            if tow % 30 == 15 && last_tow_mod_30 >= 19 {
-                log::debug!(
-                    "fixing wrong TOW for SVN {}; tow = {}, last tow mod 30 = {}",
-                    inav.gnss_sv,
-                    tow,
-                    last_tow_mod_30
-                );
-                tow += 29 - 15; // wn rollover is not possible by this addition
-            }
-            last_tow_mod_30 = tow % 30;
-
-            let gst = Gst::new(wn, tow);
-            if let Some(current) = current_subframe {
-                if current > gst.gst_subframe() {
-                    // Avoid processing INAV words that are in a previous subframe
-                    log::warn!(
-                        "dropping INAV word from previous subframe (current subframe {:?}, \
-			 this INAV word {:?} SVN {} band {})",
-                        current,
-                        gst,
-                        inav.gnss_sv,
-                        sigid
-                    );
-                    continue;
-                }
+            osnma.feed_inav(&inav_word, svn, gst, band);
+            if let Some(osnma_data) = &osnma_data {
+                osnma.feed_osnma(osnma_data, svn, gst);
             }
-            current_subframe = Some(gst.gst_subframe());
-            let svn = Svn::try_from(inav.gnss_sv).unwrap();
-            let band = match sigid {
-                1 => InavBand::E1B,
-                5 => InavBand::E5B,
-                _ => {
-                    log::error!("INAV word received on non-INAV band: sigid = {}", sigid);
-                    continue;
-                }
-            };
-
-            // The OSNMA SIS ICD says that OSNMA is not provided in INAV Dummy
-            // Messages or Alert Pages. The OSNMA field in these pages may not
-            // contain all zeros, but is invalid and should be discarded.
-            //
-            // Here we drop INAV words that are Dummy Messages. There is no way
-            // for us to filter for Alert Pages in Galmon data (the page type
-            // bit is not present), so hopefully these pages don't make it here.
-            let inav_word_type = inav_word[0] >> 2;
-            if inav_word_type == 63 {
-                log::debug!(
-                    "discarding dummy INAV word from {} {:?} at {:?}",
-                    svn,
-                    band,
-                    gst
-                );
-                continue;
-            }
-            //--------------------------------------------------------------------------------
 
-            osnma.feed_inav(inav_word[..].try_into().unwrap(), svn, gst, band);
-            if let Some(osnma_data) = osnma_data {
-                osnma.feed_osnma(osnma_data[..].try_into().unwrap(), svn, gst);
+            let snapshot = osnma.snapshot();
+            if let Some(passthrough) = &mut passthrough {
+                passthrough.queue_inav(&packet, &inav_word, svn, gst);
+                passthrough.release(&osnma, &snapshot)?;
+                passthrough.expire(gst);
             }
-
             for svn in Svn::iter() {
                 let idx = usize::from(svn) - 1;
-                if let Some(data) = osnma.get_ced_and_status(svn) {
-                    let mut data_bytes = [0u8; 69];
-                    let a = BitSlice::from_slice_mut(&mut data_bytes);
-                    let b = data.data();
-                    a[..b.len()].copy_from_bitslice(b);
-                    if !ced_and_status_data[idx]
-                        .map(|d| d == data_bytes)
-                        .unwrap_or(false)
-                    {
-                        
+                if let Some(entry) = snapshot.ced_and_status(svn) {
+                    if ced_and_status_generation[idx] != Some(entry.generation()) {
                         log::info!(
                             "new CED and status for {} authenticated \
                                     (authbits = {}, GST = {:?})",
                             svn,
-                            data.authbits(),
-                            data.gst(),
+                            entry.data().authbits(),
+                            entry.data().gst(),
                         );
-                        ced_and_status_data[idx] = Some(data_bytes);
+                        if let Some(jsonl) = &jsonl {
+                            jsonl.authenticated_data("ced_and_status_authenticated", svn, entry);
+                        }
+                        ced_and_status_generation[idx] = Some(entry.generation());
                     }
                 }
-                if let Some(data) = osnma.get_timing_parameters(svn) {
-                    let mut data_bytes = [0u8; 18];
-                    let a = BitSlice::from_slice_mut(&mut data_bytes);
-                    let b = data.data();
-                    a[..b.len()].copy_from_bitslice(b);
-                    if !timing_parameters[idx]
-                        .map(|d| d == data_bytes)
-                        .unwrap_or(false)
-                    {
+                if let Some(entry) = snapshot.timing_parameters(svn) {
+                    if timing_parameters_generation[idx] != Some(entry.generation()) {
                         log::info!(
                             "new timing parameters for {} authenticated (authbits = {}, GST = {:?})",
-			    svn,
-                            data.authbits(),
-                            data.gst()
-);
-                        timing_parameters[idx] = Some(data_bytes);
+                            svn,
+                            entry.data().authbits(),
+                            entry.data().gst()
+                        );
+                        if let Some(jsonl) = &jsonl {
+                            jsonl.authenticated_data(
+                                "timing_parameters_authenticated",
+                                svn,
+                                entry,
+                            );
+                        }
+                        timing_parameters_generation[idx] = Some(entry.generation());
                     }
                 }
             }
         }
     }
 
+    if let Some(jsonl) = &jsonl {
+        jsonl.output_closed().context("failed to write to stdout")?;
+    }
     Ok(())
 }
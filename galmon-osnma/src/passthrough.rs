@@ -0,0 +1,427 @@
+//! Authenticated-only passthrough of the Galmon stream.
+//!
+//! The passthrough re-emits the Galmon packets read by `galmon-osnma`, but
+//! forwards the Galileo INAV words only once the navigation data that they
+//! contain has been authenticated by OSNMA. INAV words are delayed until their
+//! tags are verified, and dropped if they are not authenticated in time or if
+//! they do not belong to the CED and health status or timing parameters data.
+//!
+//! Some fields of the INAV words authenticated by OSNMA are not part of the
+//! authenticated data, such as the WN and TOW in word 5 and the almanac in word
+//! 10. These fields are set to zero in the forwarded words, so that the
+//! passthrough never forwards unauthenticated INAV data. The word type is kept.
+//! The other fields of the Galmon packets are forwarded as received.
+
+use galileo_osnma::{
+    event::EventHook,
+    galmon::{navmon::NavMonMessage, transport::WriteTransport},
+    navmessage::{ced_and_status_word_position, timing_parameters_word_position},
+    snapshot::AuthenticatedSnapshot,
+    storage::StaticStorage,
+    trace::MacTraceHook,
+    types::{BitSlice, InavWord, NUM_SVNS},
+    Gst, Osnma, Svn,
+};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::ops::Range;
+
+/// Maximum time that an INAV word waits for its authentication, in subframes.
+///
+/// Slow MAC tags are validated with a key transmitted 11 subframes after the
+/// navigation data.
+const MAX_DELAY_SUBFRAMES: i32 = 12;
+
+// Bits of an INAV word that are authenticated by OSNMA, as part of the CED and
+// health status (ADKD=0 and 12) or the timing parameters (ADKD=4).
+fn authenticated_bits(word_type: u8) -> Option<Range<usize>> {
+    ced_and_status_word_position(word_type)
+        .or_else(|| timing_parameters_word_position(word_type))
+        .map(|position| position.word_bits)
+}
+
+#[derive(Debug)]
+struct PendingWord {
+    packet: NavMonMessage,
+    word: InavWord,
+    svn: Svn,
+    gst: Gst,
+}
+
+/// Authenticated-only passthrough writer.
+#[derive(Debug)]
+pub struct Passthrough<W> {
+    write: WriteTransport<W>,
+    // Queued INAV words of each SVN, indexed by the GST of their subframe.
+    pending: [BTreeMap<Gst, Vec<PendingWord>>; NUM_SVNS],
+    // GSTs of the authenticated CED and timing parameters for each SVN, used
+    // to detect when new data has been authenticated.
+    authenticated_gst: [(Option<Gst>, Option<Gst>); NUM_SVNS],
+}
+
+impl<W: Write> Passthrough<W> {
+    /// Constructs a new passthrough writing to `write`.
+    pub fn new(write: W) -> Passthrough<W> {
+        Passthrough {
+            write: WriteTransport::new(write),
+            pending: core::array::from_fn(|_| BTreeMap::new()),
+            authenticated_gst: [(None, None); NUM_SVNS],
+        }
+    }
+
+    /// Processes a packet that does not contain a Galileo INAV word.
+    ///
+    /// Packets without navigation data, such as observer positions or
+    /// reception data, are forwarded immediately. Packets with navigation
+    /// data that is not authenticated by OSNMA, such as the navigation data of
+    /// other GNSS, are dropped.
+    pub fn feed_packet(&mut self, packet: &NavMonMessage) -> std::io::Result<()> {
+        let navigation_data = packet.gi.is_some()
+            || packet.gpsi.is_some()
+            || packet.bid1.is_some()
+            || packet.bid2.is_some()
+            || packet.gloi.is_some()
+            || packet.sbm.is_some()
+            || packet.gpsc.is_some()
+            || packet.rm.is_some()
+            || packet.gf.is_some()
+            || packet.gc.is_some();
+        if !navigation_data {
+            self.write.write_packet(packet)?;
+        }
+        Ok(())
+    }
+
+    /// Queues a packet containing a Galileo INAV word until the word is
+    /// authenticated.
+    ///
+    /// The INAV word `word` was transmitted by the satellite `svn` at `gst`.
+    /// Words that are not part of the navigation data authenticated by OSNMA
+    /// are dropped. In the queued packet, the fields of the word that are not
+    /// authenticated are set to zero.
+    pub fn queue_inav(&mut self, packet: &NavMonMessage, word: &InavWord, svn: Svn, gst: Gst) {
+        let word_type = word[0] >> 2;
+        let Some(authenticated) = authenticated_bits(word_type) else {
+            log::debug!(
+                "passthrough: dropping INAV word type {} from {} at {:?}",
+                word_type,
+                svn,
+                gst
+            );
+            return;
+        };
+        let mut contents = *word;
+        let bits = BitSlice::from_slice_mut(&mut contents);
+        bits[6..authenticated.start].fill(false);
+        bits[authenticated.end..].fill(false);
+        let mut packet = packet.clone();
+        if let Some(inav) = &mut packet.gi {
+            inav.contents = contents.to_vec();
+        }
+        self.pending[usize::from(svn) - 1]
+            .entry(gst.gst_subframe())
+            .or_default()
+            .push(PendingWord {
+                packet,
+                word: *word,
+                svn,
+                gst,
+            });
+    }
+
+    /// Forwards the queued INAV words that have been authenticated.
+    ///
+    /// The snapshot `snapshot` should have been just taken from `osnma`. It
+    /// is used to detect the satellites for which new navigation data has been
+    /// authenticated. The queued words of these satellites that are contained
+    /// in authenticated data whose GST is not older than the word are
+    /// forwarded.
+    pub fn release<S, const KEY_CHECKPOINTS: usize, const MACLT_ENTRIES: usize, T, E>(
+        &mut self,
+        osnma: &Osnma<S, KEY_CHECKPOINTS, MACLT_ENTRIES, T, E>,
+        snapshot: &AuthenticatedSnapshot,
+    ) -> std::io::Result<()>
+    where
+        S: StaticStorage,
+        T: MacTraceHook,
+        E: EventHook,
+    {
+        for svn in Svn::iter() {
+            let idx = usize::from(svn) - 1;
+            let authenticated_gst = (
+                snapshot.ced_and_status(svn).map(|e| e.data().gst()),
+                snapshot.timing_parameters(svn).map(|e| e.data().gst()),
+            );
+            if authenticated_gst != self.authenticated_gst[idx] {
+                self.authenticated_gst[idx] = authenticated_gst;
+                self.release_svn(
+                    svn,
+                    osnma
+                        .ced_and_status_history(svn)
+                        .chain(osnma.timing_parameters_history(svn))
+                        .map(|data| {
+                            (data.gst(), move |word: &InavWord| {
+                                data.contains_inav_word(word)
+                            })
+                        }),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    // Forwards the queued INAV words of `svn` that are contained in some
+    // authenticated data. Each item of `authenticated` gives the GST of some
+    // authenticated data and a function that returns true for the words that
+    // it contains. Only the words whose subframe is not newer than the data
+    // are checked against it.
+    fn release_svn<I, F>(&mut self, svn: Svn, authenticated: I) -> std::io::Result<()>
+    where
+        I: IntoIterator<Item = (Gst, F)>,
+        F: Fn(&InavWord) -> bool,
+    {
+        let pending = &mut self.pending[usize::from(svn) - 1];
+        for (gst, contains) in authenticated {
+            for words in pending.range_mut(..=gst).map(|(_, words)| words) {
+                let mut result = Ok(());
+                words.retain(|pending| {
+                    if result.is_err() || !contains(&pending.word) {
+                        return true;
+                    }
+                    log::debug!(
+                        "passthrough: forwarding authenticated INAV word type {} from {} at {:?}",
+                        pending.word[0] >> 2,
+                        svn,
+                        pending.gst
+                    );
+                    result = self.write.write_packet(&pending.packet).map(|_| ());
+                    false
+                });
+                result?;
+            }
+            pending.retain(|_, words| !words.is_empty());
+        }
+        Ok(())
+    }
+
+    /// Drops the queued INAV words that have waited too long for their
+    /// authentication.
+    ///
+    /// The parameter `gst` gives the current GST.
+    pub fn expire(&mut self, gst: Gst) {
+        let limit = gst.add_seconds(-30 * MAX_DELAY_SUBFRAMES);
+        for pending in &mut self.pending {
+            // The subframes before the subframe of the limit are dropped
+            // entirely. In the subframe of the limit, only the words older
+            // than the limit are dropped.
+            let mut kept = pending.split_off(&limit.gst_subframe());
+            if let Some(words) = kept.get_mut(&limit.gst_subframe()) {
+                words.retain(|pending| {
+                    let expired = pending.gst < limit;
+                    if expired {
+                        log_expired(pending);
+                    }
+                    !expired
+                });
+                if words.is_empty() {
+                    kept.remove(&limit.gst_subframe());
+                }
+            }
+            pending.values().flatten().for_each(log_expired);
+            *pending = kept;
+        }
+    }
+}
+
+fn log_expired(pending: &PendingWord) {
+    log::debug!(
+        "passthrough: dropping unauthenticated INAV word type {} from {} at {:?}",
+        pending.word[0] >> 2,
+        pending.svn,
+        pending.gst
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use galileo_osnma::galmon::{
+        navmon::nav_mon_message::{GalileoInav, GpsInav, ObserverPosition, Type},
+        transport::ReadTransport,
+    };
+
+    fn inav_word(word_type: u8) -> InavWord {
+        let mut word = [0xaa; 16];
+        word[0] = (word_type << 2) | 0x2;
+        word
+    }
+
+    fn inav_packet(word: &InavWord, svn: Svn, gst: Gst) -> NavMonMessage {
+        NavMonMessage {
+            r#type: Type::GalileoInavType.into(),
+            gi: Some(GalileoInav {
+                gnss_wn: gst.wn().into(),
+                gnss_tow: gst.tow(),
+                gnss_id: 2,
+                gnss_sv: u8::from(svn).into(),
+                contents: word.to_vec(),
+                sigid: Some(1),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn read_packets(output: &[u8]) -> Vec<NavMonMessage> {
+        let mut read = ReadTransport::new(output);
+        std::iter::from_fn(|| read.read_packet().unwrap()).collect()
+    }
+
+    fn num_pending<W>(passthrough: &Passthrough<W>) -> usize {
+        passthrough
+            .pending
+            .iter()
+            .flat_map(|pending| pending.values())
+            .map(Vec::len)
+            .sum()
+    }
+
+    fn contents(packet: &NavMonMessage) -> InavWord {
+        packet.gi.as_ref().unwrap().contents[..].try_into().unwrap()
+    }
+
+    #[test]
+    fn feed_packet() {
+        let mut output = Vec::new();
+        let mut passthrough = Passthrough::new(&mut output);
+        let position = NavMonMessage {
+            r#type: Type::ObserverPositionType.into(),
+            op: Some(ObserverPosition::default()),
+            ..Default::default()
+        };
+        passthrough.feed_packet(&position).unwrap();
+        let gps = NavMonMessage {
+            r#type: Type::GpsInavType.into(),
+            gpsi: Some(GpsInav::default()),
+            ..Default::default()
+        };
+        passthrough.feed_packet(&gps).unwrap();
+        drop(passthrough);
+        assert_eq!(read_packets(&output), [position]);
+    }
+
+    #[test]
+    fn release() {
+        let svn = Svn::try_from(11).unwrap();
+        let other_svn = Svn::try_from(12).unwrap();
+        let gst = Gst::new(1177, 175770);
+        let mut output = Vec::new();
+        let mut passthrough = Passthrough::new(&mut output);
+        // Words that are not authenticated by OSNMA are dropped
+        for word_type in [0, 7, 16, 63] {
+            passthrough.queue_inav(
+                &inav_packet(&inav_word(word_type), svn, gst),
+                &inav_word(word_type),
+                svn,
+                gst,
+            );
+        }
+        assert_eq!(num_pending(&passthrough), 0);
+
+        for word_type in [1, 2] {
+            let word = inav_word(word_type);
+            passthrough.queue_inav(&inav_packet(&word, svn, gst), &word, svn, gst);
+        }
+        let word = inav_word(1);
+        passthrough.queue_inav(&inav_packet(&word, other_svn, gst), &word, other_svn, gst);
+        // Only the authenticated words of the SVN are forwarded
+        passthrough
+            .release_svn(
+                svn,
+                [(gst.gst_subframe(), |word: &InavWord| word[0] >> 2 == 1)],
+            )
+            .unwrap();
+        assert_eq!(num_pending(&passthrough), 2);
+        // Words that wait too long are dropped
+        passthrough.expire(gst.add_seconds(30 * MAX_DELAY_SUBFRAMES));
+        assert_eq!(num_pending(&passthrough), 2);
+        passthrough.expire(gst.add_seconds(30 * MAX_DELAY_SUBFRAMES + 1));
+        assert_eq!(num_pending(&passthrough), 0);
+        drop(passthrough);
+
+        // The spare bits at the end of word 1 are not authenticated
+        let mut word = inav_word(1);
+        word[15] &= 0xfc;
+        assert_eq!(read_packets(&output), [inav_packet(&word, svn, gst)]);
+    }
+
+    #[test]
+    fn tampered_wn_tow() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1177, 175770);
+        let genuine = inav_word(5);
+        let mut tampered = genuine;
+        let bits = BitSlice::from_slice_mut(&mut tampered);
+        // Flip the WN and TOW bits
+        for j in 73..105 {
+            let bit = bits[j];
+            bits.set(j, !bit);
+        }
+        let mut output = Vec::new();
+        let mut passthrough = Passthrough::new(&mut output);
+        passthrough.queue_inav(&inav_packet(&tampered, svn, gst), &tampered, svn, gst);
+        // The WN and TOW are not part of the authenticated data, so the
+        // tampered word is authenticated, as done by
+        // NavMessageData::contains_inav_word
+        passthrough
+            .release_svn(
+                svn,
+                [(gst, |word: &InavWord| {
+                    BitSlice::from_slice(word)[..73] == BitSlice::from_slice(&genuine)[..73]
+                })],
+            )
+            .unwrap();
+        drop(passthrough);
+
+        let packets = read_packets(&output);
+        assert_eq!(packets.len(), 1);
+        let forwarded = contents(&packets[0]);
+        let bits = BitSlice::from_slice(&forwarded);
+        assert_eq!(bits[..73], BitSlice::from_slice(&genuine)[..73]);
+        assert!(bits[73..].not_any());
+    }
+
+    #[test]
+    fn unauthenticated_fields() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1177, 175770);
+        let mut output = Vec::new();
+        let mut passthrough = Passthrough::new(&mut output);
+        for word_type in [1, 3, 6, 10] {
+            let word = inav_word(word_type);
+            passthrough.queue_inav(&inav_packet(&word, svn, gst), &word, svn, gst);
+        }
+        passthrough
+            .release_svn(svn, [(gst, |_: &InavWord| true)])
+            .unwrap();
+        drop(passthrough);
+
+        let packets = read_packets(&output);
+        assert_eq!(packets.len(), 4);
+        for (packet, (word_type, authenticated)) in
+            packets
+                .iter()
+                .zip([(1, 6..126), (3, 6..128), (6, 6..105), (10, 86..128)])
+        {
+            let word = inav_word(word_type);
+            let forwarded = contents(packet);
+            let bits = BitSlice::from_slice(&forwarded);
+            let word = BitSlice::from_slice(&word);
+            // The word type is kept
+            assert_eq!(bits[..6], word[..6]);
+            assert_eq!(bits[authenticated.clone()], word[authenticated.clone()]);
+            assert!(bits[6..authenticated.start].not_any());
+            assert!(bits[authenticated.end..].not_any());
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Dissection of OSNMA messages.
+//!
+//! The functions in this module decode all the fields of an OSNMA message using
+//! the structures in [`galileo_osnma::bitfields`] and return them as a JSON
+//! object, with the fields in the order in which they appear in the message.
+//! Binary fields are given as hexadecimal strings.
+
+use anyhow::{Context, Result};
+use galileo_osnma::{
+    bitfields::{
+        DsmHeader, DsmKroot, DsmPkr, DsmType, EcdsaFunction, Mack, NewPublicKeyType, NmaHeader,
+        Prnd,
+    },
+    merkle_tree::MerkleTree,
+    types::{
+        BitSlice, HkrootMessage, MackMessage, MerkleTreeNode, DSM_BLOCK_BYTES,
+        HKROOT_MESSAGE_BYTES, MACK_MESSAGE_BYTES,
+    },
+    validation::NotValidated,
+};
+use serde_json::{json, Map, Value};
+
+/// ECDSA public key used to check DSM-KROOT signatures.
+#[derive(Clone)]
+pub enum Pubkey {
+    /// P-256 key.
+    P256(p256::ecdsa::VerifyingKey),
+    /// P-521 key.
+    P521(p521::ecdsa::VerifyingKey),
+}
+
+/// Trust anchors used to check the messages.
+///
+/// Checks for which the corresponding trust anchor is missing are not
+/// performed, and they are omitted from the output.
+#[derive(Clone, Default)]
+pub struct TrustAnchors {
+    /// Merkle tree root, used to check DSM-PKR messages.
+    pub merkle_root: Option<MerkleTreeNode>,
+    /// ECDSA public key, used to check DSM-KROOT messages.
+    pub pubkey: Option<Pubkey>,
+}
+
+fn hex(data: &[u8]) -> Value {
+    hex::encode(data).into()
+}
+
+// Formats a BitSlice as hex, padding the last byte with zeros.
+fn hex_bits(bits: &BitSlice) -> Value {
+    let mut bytes = vec![0; (bits.len() + 7) / 8];
+    BitSlice::from_slice_mut(&mut bytes)[..bits.len()].copy_from_bitslice(bits);
+    hex(&bytes)
+}
+
+fn debug<T: core::fmt::Debug>(value: T) -> Value {
+    format!("{value:?}").into()
+}
+
+/// Dissects an NMA header.
+pub fn nma_header(header: NmaHeader<NotValidated>) -> Value {
+    json!({
+        "nma_status": debug(header.nma_status()),
+        "chain_id": header.chain_id(),
+        "chain_and_pubkey_status": debug(header.chain_and_pubkey_status()),
+    })
+}
+
+/// Dissects a DSM header.
+pub fn dsm_header(header: DsmHeader) -> Value {
+    json!({
+        "dsm_id": header.dsm_id(),
+        "dsm_block_id": header.dsm_block_id(),
+        "dsm_type": debug(header.dsm_type()),
+    })
+}
+
+/// Dissects an HKROOT message.
+pub fn hkroot(hkroot: &HkrootMessage) -> Value {
+    json!({
+        "nma_header": nma_header(NmaHeader::new(hkroot[0])),
+        "dsm_header": dsm_header(DsmHeader(hkroot[1..2].try_into().unwrap())),
+        "dsm_block": hex(&hkroot[2..]),
+    })
+}
+
+/// Dissects an HKROOT message given as a slice.
+///
+/// An error is returned if the slice does not have the length of an HKROOT
+/// message.
+pub fn hkroot_slice(data: &[u8]) -> Result<Value> {
+    let data: &HkrootMessage = data.try_into().with_context(|| {
+        format!(
+            "HKROOT message should have {HKROOT_MESSAGE_BYTES} bytes, but has {} bytes",
+            data.len()
+        )
+    })?;
+    Ok(hkroot(data))
+}
+
+// Checks that the length of a DSM matches its NB field.
+fn check_dsm_len(data: &[u8], number_of_blocks: Option<usize>) -> Result<()> {
+    let Some(blocks) = number_of_blocks else {
+        anyhow::bail!("reserved value in number of DSM blocks field");
+    };
+    if data.len() != blocks * DSM_BLOCK_BYTES {
+        anyhow::bail!(
+            "DSM should have {} bytes according to its NB field, but has {} bytes",
+            blocks * DSM_BLOCK_BYTES,
+            data.len()
+        );
+    }
+    Ok(())
+}
+
+/// Dissects a DSM message.
+///
+/// The type of DSM is determined from the DSM ID. The NMA header transmitted
+/// together with the DSM is needed to check the padding and signature of a
+/// DSM-KROOT.
+pub fn dsm(
+    dsm_id: u8,
+    data: &[u8],
+    nma_header: Option<NmaHeader<NotValidated>>,
+    anchors: &TrustAnchors,
+) -> Result<Value> {
+    let dsm_type = DsmHeader(&[dsm_id << 4]).dsm_type();
+    let fields = match dsm_type {
+        DsmType::Kroot => dsm_kroot(DsmKroot(data), nma_header, anchors)?,
+        DsmType::Pkr => dsm_pkr(DsmPkr(data), anchors)?,
+    };
+    let mut value = Map::new();
+    value.insert("dsm_id".to_string(), dsm_id.into());
+    value.insert("dsm_type".to_string(), debug(dsm_type));
+    if let Value::Object(fields) = fields {
+        value.extend(fields);
+    }
+    Ok(Value::Object(value))
+}
+
+/// Dissects a DSM-KROOT message.
+pub fn dsm_kroot(
+    dsm: DsmKroot,
+    nma_header: Option<NmaHeader<NotValidated>>,
+    anchors: &TrustAnchors,
+) -> Result<Value> {
+    if dsm.0.is_empty() {
+        anyhow::bail!("empty DSM-KROOT");
+    }
+    check_dsm_len(dsm.0, dsm.number_of_blocks())?;
+    if dsm.key_size().is_none() {
+        anyhow::bail!("reserved value in DSM-KROOT key size field");
+    }
+    let Some(ecdsa_function) = dsm.try_ecdsa_function() else {
+        anyhow::bail!("DSM-KROOT size does not match any ECDSA function");
+    };
+    let mut value = json!({
+        "number_of_blocks": dsm.number_of_blocks(),
+        "public_key_id": dsm.public_key_id(),
+        "kroot_chain_id": dsm.kroot_chain_id(),
+        "hash_function": debug(dsm.hash_function()),
+        "mac_function": debug(dsm.mac_function()),
+        "key_size": dsm.key_size(),
+        "tag_size": dsm.tag_size(),
+        "mac_lookup_table": dsm.mac_lookup_table(),
+        "kroot_wn": dsm.kroot_wn(),
+        "kroot_towh": dsm.kroot_towh(),
+        "alpha": format!("{:012x}", dsm.alpha()),
+        "kroot": hex(dsm.kroot()),
+        "ecdsa_function": debug(ecdsa_function),
+        "digital_signature": hex(dsm.digital_signature()),
+        "padding": hex(dsm.padding()),
+    });
+    let mut checks = Map::new();
+    if let Some(nma_header) = nma_header {
+        checks.insert("padding".to_string(), dsm.check_padding(nma_header).into());
+        if let Some(pubkey) = &anchors.pubkey {
+            let signature = match (pubkey, ecdsa_function) {
+                (Pubkey::P256(key), EcdsaFunction::P256Sha256) => {
+                    dsm.check_signature_p256(nma_header, key)
+                }
+                (Pubkey::P521(key), EcdsaFunction::P521Sha512) => {
+                    dsm.check_signature_p521(nma_header, key)
+                }
+                // the signature cannot be correct if the key type does not
+                // match the ECDSA function
+                _ => false,
+            };
+            checks.insert("signature".to_string(), signature.into());
+        }
+    }
+    if !checks.is_empty() {
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("checks".to_string(), Value::Object(checks));
+    }
+    Ok(value)
+}
+
+/// Dissects a DSM-PKR message.
+pub fn dsm_pkr(dsm: DsmPkr, anchors: &TrustAnchors) -> Result<Value> {
+    if dsm.0.is_empty() {
+        anyhow::bail!("empty DSM-PKR");
+    }
+    check_dsm_len(dsm.0, dsm.number_of_blocks())?;
+    let npkt = dsm.new_public_key_type();
+    let mut value = json!({
+        "number_of_blocks": dsm.number_of_blocks(),
+        "message_id": dsm.message_id(),
+        "intermediate_tree_nodes": (0..4)
+            .map(|n| hex(dsm.intermediate_tree_node(n)))
+            .collect::<Vec<_>>(),
+        "new_public_key_type": debug(npkt),
+        "new_public_key_id": dsm.new_public_key_id(),
+        "key_size": dsm.key_size(),
+        "new_public_key": dsm.new_public_key().map(hex),
+        "padding": dsm.padding().map(hex),
+        "merkle_tree_leaf": dsm.merkle_tree_leaf().map(hex),
+    });
+    if let Some(root) = &anchors.merkle_root {
+        let tree = MerkleTree::new(*root);
+        let merkle_path = match npkt {
+            NewPublicKeyType::EcdsaKey(_) => tree.validate_pkr(dsm).is_ok(),
+            NewPublicKeyType::OsnmaAlertMessage => tree.validate_alert_message(dsm).is_ok(),
+            NewPublicKeyType::Reserved => false,
+        };
+        value.as_object_mut().unwrap().insert(
+            "checks".to_string(),
+            json!({
+                "merkle_path": merkle_path,
+                "padding": dsm.check_padding(root),
+            }),
+        );
+    }
+    Ok(value)
+}
+
+/// Dissects a MACK message.
+///
+/// The key size and tag size, which are needed to parse the MACK message, are
+/// given in the DSM-KROOT.
+pub fn mack(mack: &MackMessage, key_size: usize, tag_size: usize) -> Result<Value> {
+    if ![96, 104, 112, 120, 128, 160, 192, 224, 256].contains(&key_size) {
+        anyhow::bail!("invalid key size {key_size}");
+    }
+    if ![20, 24, 28, 32, 40].contains(&tag_size) {
+        anyhow::bail!("invalid tag size {tag_size}");
+    }
+    let mack = Mack::new(mack, key_size, tag_size);
+    let tags = (1..mack.num_tags())
+        .map(|j| {
+            let tag = mack.tag_and_info(j);
+            let prnd: Value = match tag.prnd() {
+                Prnd::GalileoSvid(svid) => svid.into(),
+                Prnd::GalileoConstellation => 255.into(),
+                Prnd::Reserved => "Reserved".into(),
+            };
+            json!({
+                "tag": hex_bits(tag.tag()),
+                "prnd": prnd,
+                "adkd": debug(tag.adkd()),
+                "cop": tag.cop(),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "tag0": hex_bits(mack.tag0()),
+        "macseq": format!("{:03x}", mack.macseq()),
+        "cop": mack.cop(),
+        "tags": tags,
+        "key": hex_bits(mack.key()),
+    }))
+}
+
+/// Dissects a MACK message given as a slice.
+///
+/// An error is returned if the slice does not have the length of a MACK
+/// message.
+pub fn mack_slice(data: &[u8], key_size: usize, tag_size: usize) -> Result<Value> {
+    let data: &MackMessage = data.try_into().with_context(|| {
+        format!(
+            "MACK message should have {MACK_MESSAGE_BYTES} bytes, but has {} bytes",
+            data.len()
+        )
+    })?;
+    mack(data, key_size, tag_size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn kroot() {
+        // DSM-KROOT broadcast on 2022-03-07 9:00 UTC
+        let data = hex!(
+            "
+            22 50 49 21 04 98 21 25 d3 96 4d a3 a2 84 1e 1d
+            e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3 79 58 de
+            28 51 97 a2 63 53 f1 a4 c6 6d 7e 3d 29 18 53 ba
+            5a 13 c9 c3 48 4a 26 77 70 11 2a 13 38 3e a5 2d
+            3a 01 9d 5b 6e 1d d1 87 b9 45 3c df 06 ca 7f 34
+            ea 14 97 52 5a af 18 f1 f9 f1 fc cb 12 29 89 77
+            35 c0 21 b0 41 73 93 b5"
+        );
+        let value = dsm(
+            1,
+            &data,
+            Some(NmaHeader::new(0x52)),
+            &TrustAnchors::default(),
+        )
+        .unwrap();
+        assert_eq!(value["dsm_type"], "Kroot");
+        assert_eq!(value["public_key_id"], 2);
+        assert_eq!(value["key_size"], 128);
+        assert_eq!(value["tag_size"], 40);
+        assert_eq!(value["alpha"], "25d3964da3a2");
+        assert_eq!(value["kroot"], "841e1de4d458c0e9842476e004666cf3");
+        assert_eq!(value["ecdsa_function"], "P256Sha256");
+        assert_eq!(value["checks"], json!({ "padding": true }));
+
+        assert!(dsm(1, &data[..data.len() - 1], None, &TrustAnchors::default()).is_err());
+    }
+
+    #[test]
+    fn pkr() {
+        // DSM-PKR broadcast on 2023-12-15 00:00 UTC
+        let data = hex!(
+            "
+            71 e5 53 0a 33 d5 cb 60 c9 50 16 b8 ae c7 45 93
+            db cd f2 71 1d 39 9e a2 48 69 17 3c a2 29 37 9a
+            15 31 6f a9 28 5f 5a 1e 44 04 24 13 bd af 18 aa
+            3c f6 84 72 33 97 d7 b8 32 5a ec a1 eb ca 9f 0f
+            64 99 05 42 4c be 48 2a 1a 32 b0 10 64 f8 5d 0c
+            36 df 03 8e 52 ce 12 8e 7e c5 f3 23 e1 65 b1 82
+            a7 15 37 bd b0 10 97 2e b4 a3 b9 0b aa cd 14 94
+            1e f4 0d a2 cb 2b 82 d3 78 b3 15 c0 08 de ce fd
+            8e 12 03 35 78 e5 c7 11 a9 c3 bd dd 1c a4 ee 85
+            f7 c5 1b 36 78 97 cb 40 b8 85 68 a0 c8 97 da 30
+            ef b7 c3 24 e0 22 2c 90 80"
+        );
+        // Obtained from OSNMA_MerkleTree_20231213105954_PKID_1.xml
+        let mut anchors = TrustAnchors {
+            merkle_root: Some(hex!(
+                "0E63F552C8021709043C239032EFFE941BF22C8389032F5F2701E0FBC80148B8"
+            )),
+            pubkey: None,
+        };
+        let value = dsm(12, &data, None, &anchors).unwrap();
+        assert_eq!(value["dsm_type"], "Pkr");
+        assert_eq!(value["message_id"], 1);
+        assert_eq!(
+            value["intermediate_tree_nodes"][0],
+            "e5530a33d5cb60c95016b8aec74593dbcdf2711d399ea24869173ca229379a15"
+        );
+        assert_eq!(value["new_public_key_type"], "EcdsaKey(P256Sha256)");
+        assert_eq!(value["new_public_key_id"], 2);
+        assert_eq!(value["padding"], "24e0222c9080");
+        assert_eq!(
+            value["checks"],
+            json!({ "merkle_path": true, "padding": true })
+        );
+
+        anchors.merkle_root.as_mut().unwrap()[0] ^= 1;
+        let value = dsm(12, &data, None, &anchors).unwrap();
+        assert_eq!(
+            value["checks"],
+            json!({ "merkle_path": false, "padding": false })
+        );
+    }
+
+    #[test]
+    fn mack() {
+        // MACK broadcast on 2022-03-07 9:00 UTC
+        let data = hex!(
+            "
+            11 55 d3 71 f2 1f 30 a8 e4 ec e0 c0 1b 07 6d 17
+            7d 64 03 12 05 d4 02 7e 77 13 15 c0 4c ca 1c 16
+            99 1a 05 48 91 07 a7 f7 0e c5 42 b4 19 da 6a da
+            1c 0a 3d 6f 56 a5 e5 dc 59 a7 00 00"
+        );
+        let value = mack_slice(&data, 128, 40).unwrap();
+        assert_eq!(value["tag0"], "1155d371f2");
+        assert_eq!(value["macseq"], "1f3");
+        assert_eq!(value["tags"].as_array().unwrap().len(), 5);
+        assert_eq!(
+            value["tags"][0],
+            json!({ "tag": "a8e4ece0c0", "prnd": 0x1b, "adkd": "InavCed", "cop": 7 })
+        );
+
+        assert!(mack_slice(&data, 100, 40).is_err());
+        assert!(mack_slice(&data[1..], 128, 40).is_err());
+    }
+}
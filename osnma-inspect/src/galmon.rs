@@ -0,0 +1,116 @@
+//! Dissection of Galmon streams.
+//!
+//! The OSNMA data in the INAV pages of the stream is collected per subframe to
+//! recompose the HKROOT and MACK messages, and the DSM blocks in the HKROOT
+//! messages are collected to recompose the DSM messages. Each of these
+//! messages is dissected and written as soon as it is complete.
+
+use crate::dissect::{self, TrustAnchors};
+use crate::output::{self, Format};
+use anyhow::Result;
+use galileo_osnma::{
+    bitfields::{DsmHeader, DsmKroot, DsmType, NmaHeader},
+    dsm::CollectDsm,
+    galmon::{
+        pages::{Page, PageExtractor},
+        transport::ReadTransport,
+    },
+    subframe::CollectSubframe,
+    Gst,
+};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+
+/// Key and tag sizes used to dissect MACK messages.
+///
+/// The sizes that are `None` are taken from the last DSM-KROOT in the stream.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Sizes {
+    /// TESLA key size in bits.
+    pub key_size: Option<usize>,
+    /// Tag size in bits.
+    pub tag_size: Option<usize>,
+}
+
+fn gst_json(gst: Gst) -> Value {
+    json!({ "wn": gst.wn(), "tow": gst.tow() })
+}
+
+/// Dissects a Galmon stream.
+///
+/// The stream is read from `read` and the dissected messages are written to
+/// `write` in the given format.
+pub fn dissect<R: Read, W: Write>(
+    read: R,
+    mut write: W,
+    format: Format,
+    sizes: Sizes,
+    anchors: &TrustAnchors,
+) -> Result<()> {
+    let mut read = ReadTransport::new(read);
+    let mut collect_subframe = CollectSubframe::new();
+    let mut collect_dsm = CollectDsm::new();
+    let mut kroot_sizes = Sizes::default();
+    let mut pages = PageExtractor::new();
+
+    while let Some(packet) = read.read_packet()? {
+        // The pages are extracted in the same way as in galmon-osnma, which
+        // fixes the wrong TOWs of Galmon data and drops the dummy messages.
+        let Some(Page {
+            osnma_data: Some(osnma_data),
+            svn,
+            gst,
+            ..
+        }) = pages.extract(&packet)
+        else {
+            continue;
+        };
+        // An all-zeros OSNMA field means that there is no OSNMA data.
+        if osnma_data.iter().all(|&x| x == 0) {
+            continue;
+        }
+        let Some((hkroot, mack, subframe_gst)) = collect_subframe.feed(&osnma_data, svn, gst)
+        else {
+            continue;
+        };
+
+        let key_size = sizes.key_size.or(kroot_sizes.key_size);
+        let tag_size = sizes.tag_size.or(kroot_sizes.tag_size);
+        let mack = match (key_size, tag_size) {
+            (Some(key_size), Some(tag_size)) => dissect::mack(mack, key_size, tag_size)
+                .unwrap_or_else(|e| Value::String(format!("error: {e}"))),
+            // the MACK cannot be dissected until the key and tag sizes are known
+            _ => Value::Null,
+        };
+        let value = json!({
+            "message": "subframe",
+            "svn": svn.to_string(),
+            "gst": gst_json(subframe_gst),
+            "hkroot": dissect::hkroot(hkroot),
+            "mack": mack,
+        });
+        output::write(&mut write, &value, format)?;
+
+        let nma_header = NmaHeader::new(hkroot[0]);
+        let dsm_header = DsmHeader(hkroot[1..2].try_into().unwrap());
+        let Some(dsm) = collect_dsm.feed(dsm_header, hkroot[2..].try_into().unwrap()) else {
+            continue;
+        };
+        let dissected = dissect::dsm(dsm.id(), dsm.data(), Some(nma_header), anchors);
+        if dissected.is_ok() && dsm.dsm_type() == DsmType::Kroot {
+            let kroot = DsmKroot(dsm.data());
+            kroot_sizes = Sizes {
+                key_size: kroot.key_size(),
+                tag_size: kroot.tag_size(),
+            };
+        }
+        let value = json!({
+            "message": "dsm",
+            "svn": svn.to_string(),
+            "gst": gst_json(subframe_gst),
+            "dsm": dissected.unwrap_or_else(|e| Value::String(format!("error: {e}"))),
+        });
+        output::write(&mut write, &value, format)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use galileo_osnma::{bitfields::NmaHeader, types::MerkleTreeNode};
+use spki::DecodePublicKey;
+
+mod dissect;
+use dissect::{Pubkey, TrustAnchors};
+#[cfg(feature = "galmon")]
+mod galmon;
+mod output;
+use output::Format;
+
+/// Dissect Galileo OSNMA messages
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+    format: Format,
+    /// Merkle tree root in hex, used to check DSM-PKR messages.
+    #[arg(long, global = true)]
+    merkle_root: Option<String>,
+    /// Path to the P-256 public key in PEM format, used to check DSM-KROOT
+    /// signatures.
+    #[arg(long, global = true)]
+    pubkey: Option<String>,
+    /// P-521 public key in hexadecimal format (SEC1 encoding), used to check
+    /// DSM-KROOT signatures.
+    #[arg(long, global = true)]
+    pubkey_p521: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Dissect a 15-byte HKROOT message given in hex.
+    Hkroot {
+        /// HKROOT message in hex. It can be split into several arguments.
+        #[arg(required = true)]
+        data: Vec<String>,
+    },
+    /// Dissect a complete DSM-KROOT or DSM-PKR message given in hex.
+    Dsm {
+        /// DSM ID, which determines the type of DSM.
+        #[arg(long)]
+        dsm_id: u8,
+        /// NMA header in hex, needed to check the padding and signature of a
+        /// DSM-KROOT.
+        #[arg(long)]
+        nma_header: Option<String>,
+        /// DSM message in hex. It can be split into several arguments.
+        #[arg(required = true)]
+        data: Vec<String>,
+    },
+    /// Dissect a 60-byte MACK message given in hex.
+    Mack {
+        /// TESLA key size in bits.
+        #[arg(long)]
+        key_size: usize,
+        /// Tag size in bits.
+        #[arg(long)]
+        tag_size: usize,
+        /// MACK message in hex. It can be split into several arguments.
+        #[arg(required = true)]
+        data: Vec<String>,
+    },
+    /// Dissect the HKROOT, MACK and DSM messages in a Galmon protobuf stream.
+    #[cfg(feature = "galmon")]
+    Galmon {
+        /// Path to the Galmon protobuf file. The stream is read from stdin if
+        /// this is not given.
+        input_file: Option<String>,
+        /// TESLA key size in bits. By default, this is taken from the last
+        /// DSM-KROOT in the stream.
+        #[arg(long)]
+        key_size: Option<usize>,
+        /// Tag size in bits. By default, this is taken from the last DSM-KROOT
+        /// in the stream.
+        #[arg(long)]
+        tag_size: Option<usize>,
+    },
+}
+
+// Decodes hex, ignoring any whitespace, so that data can be copied from
+// documents that group the bytes.
+fn decode_hex(data: &str) -> Result<Vec<u8>> {
+    let data: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+    hex::decode(data).context("invalid hex data")
+}
+
+fn load_trust_anchors(args: &Args) -> Result<TrustAnchors> {
+    if args.pubkey.is_some() && args.pubkey_p521.is_some() {
+        anyhow::bail!("the --pubkey and --pubkey-p521 arguments are mutually exclusive");
+    }
+    let merkle_root = args
+        .merkle_root
+        .as_ref()
+        .map(|root| -> Result<MerkleTreeNode> {
+            decode_hex(root)?
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("the Merkle tree root must have 32 bytes"))
+        })
+        .transpose()?;
+    let pubkey = if let Some(path) = &args.pubkey {
+        let pem = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read public key from {path}"))?;
+        Some(Pubkey::P256(
+            p256::ecdsa::VerifyingKey::from_public_key_pem(&pem)
+                .context("could not parse P-256 public key")?,
+        ))
+    } else if let Some(hex) = &args.pubkey_p521 {
+        Some(Pubkey::P521(
+            p521::ecdsa::VerifyingKey::from_sec1_bytes(&decode_hex(hex)?)
+                .context("could not parse P-521 public key")?,
+        ))
+    } else {
+        None
+    };
+    Ok(TrustAnchors {
+        merkle_root,
+        pubkey,
+    })
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+    let anchors = load_trust_anchors(&args)?;
+    let stdout = std::io::stdout().lock();
+
+    let value = match &args.command {
+        Command::Hkroot { data } => dissect::hkroot_slice(&decode_hex(&data.concat())?)?,
+        Command::Dsm {
+            dsm_id,
+            nma_header,
+            data,
+        } => {
+            if *dsm_id >= 16 {
+                anyhow::bail!("the DSM ID must be between 0 and 15");
+            }
+            let nma_header = nma_header
+                .as_ref()
+                .map(|h| -> Result<NmaHeader<_>> {
+                    let [h] = decode_hex(h)?[..] else {
+                        anyhow::bail!("the NMA header must have 1 byte");
+                    };
+                    Ok(NmaHeader::new(h))
+                })
+                .transpose()?;
+            dissect::dsm(*dsm_id, &decode_hex(&data.concat())?, nma_header, &anchors)?
+        }
+        Command::Mack {
+            key_size,
+            tag_size,
+            data,
+        } => dissect::mack_slice(&decode_hex(&data.concat())?, *key_size, *tag_size)?,
+        #[cfg(feature = "galmon")]
+        Command::Galmon {
+            input_file,
+            key_size,
+            tag_size,
+        } => {
+            let sizes = galmon::Sizes {
+                key_size: *key_size,
+                tag_size: *tag_size,
+            };
+            return match input_file {
+                Some(path) => {
+                    let file = std::fs::File::open(path)
+                        .with_context(|| format!("could not open {path}"))?;
+                    galmon::dissect(
+                        std::io::BufReader::new(file),
+                        stdout,
+                        args.format,
+                        sizes,
+                        &anchors,
+                    )
+                }
+                None => galmon::dissect(
+                    std::io::stdin().lock(),
+                    stdout,
+                    args.format,
+                    sizes,
+                    &anchors,
+                ),
+            };
+        }
+    };
+    output::write(stdout, &value, args.format)?;
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Output formatting.
+//!
+//! The dissected messages are printed either as JSON, with one object per line,
+//! or in a human-readable form, with one field per line and nested fields
+//! indented.
+
+use serde_json::Value;
+use std::io::Write;
+
+/// Output format.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// Human-readable text.
+    Text,
+    /// JSON, with one object per line.
+    Json,
+}
+
+/// Writes a dissected message in the given format.
+pub fn write<W: Write>(mut w: W, value: &Value, format: Format) -> std::io::Result<()> {
+    match format {
+        Format::Text => {
+            let mut text = String::new();
+            render(&mut text, value, 0);
+            writeln!(w, "{text}")
+        }
+        Format::Json => writeln!(w, "{value}"),
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("-".to_string()),
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(b) => Some(if *b { "ok" } else { "FAILED" }.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+fn render(out: &mut String, value: &Value, indent: usize) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match scalar(value) {
+                    Some(s) => out.push_str(&format!("{pad}{key}: {s}\n")),
+                    None => {
+                        out.push_str(&format!("{pad}{key}:\n"));
+                        render(out, value, indent + 2);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (j, item) in items.iter().enumerate() {
+                match scalar(item) {
+                    Some(s) => out.push_str(&format!("{pad}[{j}] {s}\n")),
+                    None => {
+                        out.push_str(&format!("{pad}[{j}]\n"));
+                        render(out, item, indent + 2);
+                    }
+                }
+            }
+        }
+        _ => {
+            // scalar cannot return None here
+            out.push_str(&format!("{pad}{}\n", scalar(value).unwrap()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn text() {
+        let value = json!({
+            "macseq": "a3f",
+            "tags": [{"prnd": 3, "adkd": "InavCed"}],
+            "nodes": ["00ff"],
+            "checks": {"padding": true, "signature": false},
+            "padding": null,
+        });
+        let mut out = Vec::new();
+        write(&mut out, &value, Format::Text).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "macseq: a3f
+tags:
+  [0]
+    prnd: 3
+    adkd: InavCed
+nodes:
+  [0] 00ff
+checks:
+  padding: ok
+  signature: FAILED
+padding: -
+
+"
+        );
+    }
+}
@@ -0,0 +1,978 @@
+//! Attack scenarios.
+//!
+//! This module contains attacks that modify a stream of INAV pages, and
+//! scenarios that run a receiver on a simulated stream modified by an attack
+//! and check that the receiver reacts as expected. The attacks are those that
+//! OSNMA is designed to resist, such as the replay of MACK messages, the
+//! forgery of navigation data, delaying the signal beyond the key disclosure
+//! time, and the injection of forged DSMs and NMA headers.
+//!
+//! The attacks operate on [`Subframe`]s, which contain the pages transmitted
+//! by all the satellites in a subframe, so they can also be applied to
+//! recorded streams after grouping their pages by subframe.
+
+use crate::{
+    dsm::nma_header,
+    ecdsa::SigningKey,
+    navdata::{NavDataTracker, SyntheticNavData},
+    sim::{Page, SimConfig, Simulator},
+    tesla::ChainParameters,
+};
+use anyhow::{bail, ensure, Result};
+use bitvec::prelude::*;
+use galileo_osnma::{
+    bitfields::{
+        ChainAndPubkeyStatus, DsmHeader, DsmPkr, EcdsaFunction, NewPublicKeyType, NmaStatus,
+    },
+    builders::DsmPkrBuilder,
+    dsm::CollectDsm,
+    state::{OsnmaState, TransitionReason},
+    storage::FullStorage,
+    types::{
+        DsmBlock, HkrootMessage, MackMessage, DSM_BLOCK_BYTES, HKROOT_MESSAGE_BYTES,
+        HKROOT_SECTION_BYTES, MACK_MESSAGE_BYTES, MERKLE_TREE_NODE_BYTES,
+    },
+    Gst, MerkleTreeNode, Osnma, Svn,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ops::Range,
+};
+
+/// Number of pages in a subframe.
+const PAGES_PER_SUBFRAME: usize = 15;
+
+/// Pages transmitted in a subframe.
+///
+/// This contains the pages transmitted by all the satellites during a
+/// subframe, and gives access to the HKROOT and MACK messages that they carry.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Subframe {
+    /// GST at the start of the subframe.
+    pub gst: Gst,
+    /// Pages transmitted in the subframe.
+    pub pages: Vec<Page>,
+}
+
+impl Subframe {
+    /// Creates a subframe from its pages.
+    ///
+    /// The GST of the pages must be within the subframe starting at `gst`.
+    pub fn new(gst: Gst, pages: Vec<Page>) -> Subframe {
+        Subframe { gst, pages }
+    }
+
+    /// Gives the SVNs of the satellites that transmit in the subframe.
+    ///
+    /// The SVNs are given in order of first appearance in the pages.
+    pub fn svns(&self) -> Vec<Svn> {
+        let mut svns = Vec::new();
+        for page in &self.pages {
+            if !svns.contains(&page.svn) {
+                svns.push(page.svn);
+            }
+        }
+        svns
+    }
+
+    /// Gives the HKROOT message transmitted by a satellite.
+    ///
+    /// `None` is returned if some of the pages of the satellite are missing.
+    pub fn hkroot(&self, svn: Svn) -> Option<HkrootMessage> {
+        let mut hkroot = [0; HKROOT_MESSAGE_BYTES];
+        self.read_sections(svn, &mut hkroot, 0)?;
+        Some(hkroot)
+    }
+
+    /// Replaces the HKROOT message transmitted by a satellite.
+    pub fn set_hkroot(&mut self, svn: Svn, hkroot: &HkrootMessage) {
+        self.write_sections(svn, hkroot, 0);
+    }
+
+    /// Gives the MACK message transmitted by a satellite.
+    ///
+    /// `None` is returned if some of the pages of the satellite are missing.
+    pub fn mack(&self, svn: Svn) -> Option<MackMessage> {
+        let mut mack = [0; MACK_MESSAGE_BYTES];
+        self.read_sections(svn, &mut mack, HKROOT_SECTION_BYTES)?;
+        Some(mack)
+    }
+
+    /// Replaces the MACK message transmitted by a satellite.
+    pub fn set_mack(&mut self, svn: Svn, mack: &MackMessage) {
+        self.write_sections(svn, mack, HKROOT_SECTION_BYTES);
+    }
+
+    // Index of a page within the subframe
+    fn page_index(&self, page: &Page) -> Option<usize> {
+        let index = usize::try_from(page.gst.seconds_difference(self.gst) / 2).ok()?;
+        (index < PAGES_PER_SUBFRAME).then_some(index)
+    }
+
+    // Copies the sections of a message transmitted by a satellite into
+    // `message`. `offset` is the position of the section in the OSNMA data
+    // message.
+    fn read_sections(&self, svn: Svn, message: &mut [u8], offset: usize) -> Option<()> {
+        let section_len = message.len() / PAGES_PER_SUBFRAME;
+        let mut present = [false; PAGES_PER_SUBFRAME];
+        for page in self.pages.iter().filter(|p| p.svn == svn) {
+            let j = self.page_index(page)?;
+            message[j * section_len..(j + 1) * section_len]
+                .copy_from_slice(&page.osnma[offset..offset + section_len]);
+            present[j] = true;
+        }
+        present.iter().all(|&p| p).then_some(())
+    }
+
+    fn write_sections(&mut self, svn: Svn, message: &[u8], offset: usize) {
+        let section_len = message.len() / PAGES_PER_SUBFRAME;
+        for k in 0..self.pages.len() {
+            if self.pages[k].svn != svn {
+                continue;
+            }
+            if let Some(j) = self.page_index(&self.pages[k]) {
+                self.pages[k].osnma[offset..offset + section_len]
+                    .copy_from_slice(&message[j * section_len..(j + 1) * section_len]);
+            }
+        }
+    }
+}
+
+/// Attack on a stream of INAV pages.
+pub trait Attack {
+    /// Applies the attack to a subframe.
+    ///
+    /// This is called with all the subframes of the stream in chronological
+    /// order, so that the attack can record the data transmitted before it
+    /// starts. The subframe is only modified if `active` is `true`.
+    fn apply(&mut self, subframe: &mut Subframe, active: bool);
+}
+
+/// No attack.
+///
+/// This leaves the stream unmodified, and serves as a reference for the
+/// other scenarios.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct NoAttack;
+
+impl Attack for NoAttack {
+    fn apply(&mut self, _subframe: &mut Subframe, _active: bool) {}
+}
+
+/// Several attacks applied in sequence.
+#[derive(Default)]
+pub struct Combined(pub Vec<Box<dyn Attack>>);
+
+impl Attack for Combined {
+    fn apply(&mut self, subframe: &mut Subframe, active: bool) {
+        for attack in self.0.iter_mut() {
+            attack.apply(subframe, active);
+        }
+    }
+}
+
+/// Replay of old MACK messages with fresh keys.
+///
+/// The MACK message transmitted by each satellite is replaced by the one that
+/// it transmitted `delay` subframes earlier, except for the TESLA key, which
+/// is taken from the current MACK message. The keys are thus valid, but the
+/// tags authenticate old data.
+#[derive(Debug, Clone)]
+pub struct ReplayMack {
+    delay: usize,
+    key_size_bits: usize,
+    tag_size_bits: usize,
+    history: VecDeque<HashMap<Svn, MackMessage>>,
+}
+
+impl ReplayMack {
+    /// Creates a new MACK replay attack.
+    ///
+    /// The MACK messages are replayed with a delay of `delay` subframes. The
+    /// `chain` parameters give the layout of the MACK messages.
+    pub fn new(delay: usize, chain: &ChainParameters) -> ReplayMack {
+        ReplayMack {
+            delay,
+            key_size_bits: chain.key_size_bits,
+            tag_size_bits: chain.tag_size_bits,
+            history: VecDeque::with_capacity(delay + 1),
+        }
+    }
+}
+
+impl Attack for ReplayMack {
+    fn apply(&mut self, subframe: &mut Subframe, active: bool) {
+        let macks = subframe
+            .svns()
+            .into_iter()
+            .filter_map(|svn| Some((svn, subframe.mack(svn)?)))
+            .collect::<HashMap<_, _>>();
+        self.history.push_back(macks.clone());
+        if self.history.len() <= self.delay {
+            return;
+        }
+        let old = self.history.pop_front().unwrap();
+        if !active {
+            return;
+        }
+        let ts = self.tag_size_bits;
+        let ks = self.key_size_bits;
+        let key_start = (8 * MACK_MESSAGE_BYTES - ks) / (ts + 16) * (ts + 16);
+        for (svn, mack) in macks {
+            let Some(mut replayed) = old.get(&svn).copied() else {
+                continue;
+            };
+            replayed.view_bits_mut::<Msb0>()[key_start..key_start + ks]
+                .copy_from_bitslice(&mack.view_bits::<Msb0>()[key_start..key_start + ks]);
+            subframe.set_mack(svn, &replayed);
+        }
+    }
+}
+
+/// Forgery of navigation data.
+///
+/// The INAV words of the given types transmitted by a satellite are modified
+/// by flipping one bit of the data that is authenticated by OSNMA. The tags
+/// are not modified.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ForgeNavData {
+    /// SVN of the satellite whose data is forged.
+    pub svn: Svn,
+    /// Types of the words that are forged.
+    pub word_types: Vec<u8>,
+}
+
+impl Attack for ForgeNavData {
+    fn apply(&mut self, subframe: &mut Subframe, active: bool) {
+        if !active {
+            return;
+        }
+        for page in subframe.pages.iter_mut().filter(|p| p.svn == self.svn) {
+            let Some(word) = &mut page.word else {
+                continue;
+            };
+            let word_type = word.view_bits::<Msb0>()[..6].load_be::<u8>();
+            if self.word_types.contains(&word_type) {
+                // This bit belongs to the ephemeris in words 1 to 4 and to
+                // the clock and UTC parameters in words 5, 6 and 10.
+                let mut bit = word.view_bits_mut::<Msb0>().get_mut(FORGED_BIT).unwrap();
+                *bit = !*bit;
+            }
+        }
+    }
+}
+
+const FORGED_BIT: usize = 40;
+
+/// Signal delay.
+///
+/// The pages are received `subframes` subframes later than they were
+/// transmitted, so the receiver is given a GST which is later than the GST
+/// at which the data was transmitted. This simulates a spoofer that
+/// retransmits the signal with a delay longer than the key disclosure time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Delay {
+    /// Delay in subframes.
+    pub subframes: i32,
+}
+
+impl Attack for Delay {
+    fn apply(&mut self, subframe: &mut Subframe, active: bool) {
+        if !active {
+            return;
+        }
+        subframe.gst = subframe.gst.add_subframes(self.subframes);
+        for page in subframe.pages.iter_mut() {
+            page.gst = page.gst.add_subframes(self.subframes);
+        }
+    }
+}
+
+/// Splicing of DSM blocks.
+///
+/// The blocks of the DSM with ID `target_dsm_id` with the block IDs in
+/// `block_ids` are replaced by the blocks with the same block IDs of the DSM
+/// with ID `source_dsm_id`. Blocks that have not been recorded yet are
+/// replaced by zeros.
+#[derive(Debug, Clone)]
+pub struct SpliceDsm {
+    source_dsm_id: u8,
+    target_dsm_id: u8,
+    block_ids: Vec<u8>,
+    recorded: HashMap<u8, DsmBlock>,
+}
+
+impl SpliceDsm {
+    /// Creates a new DSM splicing attack.
+    pub fn new(source_dsm_id: u8, target_dsm_id: u8, block_ids: Vec<u8>) -> SpliceDsm {
+        SpliceDsm {
+            source_dsm_id,
+            target_dsm_id,
+            block_ids,
+            recorded: HashMap::new(),
+        }
+    }
+}
+
+impl Attack for SpliceDsm {
+    fn apply(&mut self, subframe: &mut Subframe, active: bool) {
+        for svn in subframe.svns() {
+            let Some(mut hkroot) = subframe.hkroot(svn) else {
+                continue;
+            };
+            let header = DsmHeader(&[hkroot[1]]);
+            let (dsm_id, block_id) = (header.dsm_id(), header.dsm_block_id());
+            if dsm_id == self.source_dsm_id {
+                self.recorded
+                    .insert(block_id, hkroot[2..].try_into().unwrap());
+            } else if active && dsm_id == self.target_dsm_id && self.block_ids.contains(&block_id) {
+                let block = self
+                    .recorded
+                    .get(&block_id)
+                    .copied()
+                    .unwrap_or([0; DSM_BLOCK_BYTES]);
+                hkroot[2..].copy_from_slice(&block);
+                subframe.set_hkroot(svn, &hkroot);
+            }
+        }
+    }
+}
+
+/// Injection of a forged DSM-PKR.
+///
+/// The attacker records the DSM-PKR transmitted in the stream and uses its
+/// intermediate tree nodes to forge a DSM-PKR with the same message ID that
+/// contains an OSNMA Alert Message or a public key chosen by the attacker.
+/// The forged DSM-PKR replaces the DSMs transmitted by all the satellites.
+/// Nothing is injected until a complete DSM-PKR has been recorded.
+#[derive(Debug, Clone)]
+pub struct ForgedDsmPkr {
+    new_public_key_type: NewPublicKeyType,
+    new_public_key_id: u8,
+    new_public_key: Vec<u8>,
+    collect: CollectDsm,
+    blocks: Option<(u8, Vec<DsmBlock>)>,
+    next_block: usize,
+}
+
+impl ForgedDsmPkr {
+    /// Creates an attack that injects a forged OSNMA Alert Message.
+    pub fn alert_message() -> ForgedDsmPkr {
+        // The Alert Message fills a DSM-PKR with 13 blocks
+        let len = 13 * DSM_BLOCK_BYTES - 2 - 4 * MERKLE_TREE_NODE_BYTES;
+        Self::new(NewPublicKeyType::OsnmaAlertMessage, 0, vec![0x5a; len])
+    }
+
+    /// Creates an attack that injects a public key chosen by the attacker.
+    ///
+    /// The public key `key` must be in SEC1 compressed format.
+    pub fn public_key(ecdsa_function: EcdsaFunction, pkid: u8, key: Vec<u8>) -> ForgedDsmPkr {
+        Self::new(NewPublicKeyType::EcdsaKey(ecdsa_function), pkid, key)
+    }
+
+    fn new(
+        new_public_key_type: NewPublicKeyType,
+        new_public_key_id: u8,
+        new_public_key: Vec<u8>,
+    ) -> ForgedDsmPkr {
+        ForgedDsmPkr {
+            new_public_key_type,
+            new_public_key_id,
+            new_public_key,
+            collect: CollectDsm::new(),
+            blocks: None,
+            next_block: 0,
+        }
+    }
+
+    fn record(&mut self, hkroot: &HkrootMessage) {
+        let header = [hkroot[1]];
+        let Some(dsm) = self
+            .collect
+            .feed(DsmHeader(&header), hkroot[2..].try_into().unwrap())
+        else {
+            return;
+        };
+        if !(12..=15).contains(&dsm.id()) {
+            return;
+        }
+        let dsm_id = dsm.id();
+        let genuine = DsmPkr(dsm.data());
+        let builder = DsmPkrBuilder {
+            message_id: genuine.message_id(),
+            intermediate_tree_nodes: core::array::from_fn(|j| *genuine.intermediate_tree_node(j)),
+            new_public_key_type: self.new_public_key_type,
+            new_public_key_id: self.new_public_key_id,
+            new_public_key: &self.new_public_key,
+        };
+        // The attacker does not know the Merkle tree root, so the padding is
+        // computed with a made-up root.
+        let forged = builder
+            .build(&[0; 32])
+            .expect("invalid forged DSM-PKR fields");
+        let blocks = forged
+            .dsm_pkr()
+            .0
+            .chunks_exact(DSM_BLOCK_BYTES)
+            .map(|block| block.try_into().unwrap())
+            .collect();
+        self.blocks = Some((dsm_id, blocks));
+    }
+}
+
+impl Attack for ForgedDsmPkr {
+    fn apply(&mut self, subframe: &mut Subframe, active: bool) {
+        for svn in subframe.svns() {
+            let Some(mut hkroot) = subframe.hkroot(svn) else {
+                continue;
+            };
+            if self.blocks.is_none() {
+                self.record(&hkroot);
+                continue;
+            }
+            if !active {
+                continue;
+            }
+            let (dsm_id, blocks) = self.blocks.as_ref().unwrap();
+            let block_id = self.next_block % blocks.len();
+            self.next_block += 1;
+            hkroot[1] = (dsm_id << 4) | u8::try_from(block_id).unwrap();
+            hkroot[2..].copy_from_slice(&blocks[block_id]);
+            subframe.set_hkroot(svn, &hkroot);
+        }
+    }
+}
+
+/// Tampering of the NMA header.
+///
+/// The NMA header transmitted by all the satellites is replaced by
+/// `nma_header`. This can be used to forge a chain revocation, a public key
+/// revocation or an alert message in the CPKS field.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TamperNmaHeader {
+    /// NMA header that is transmitted.
+    pub nma_header: u8,
+}
+
+impl Attack for TamperNmaHeader {
+    fn apply(&mut self, subframe: &mut Subframe, active: bool) {
+        if !active {
+            return;
+        }
+        for svn in subframe.svns() {
+            if let Some(mut hkroot) = subframe.hkroot(svn) {
+                hkroot[0] = self.nma_header;
+                subframe.set_hkroot(svn, &hkroot);
+            }
+        }
+    }
+}
+
+/// Expected outcome of an attack scenario.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Outcome {
+    /// The navigation data of all the satellites is authenticated at the end
+    /// of the stream.
+    ///
+    /// This is expected when the attack is detected and ignored, and the
+    /// receiver keeps its cryptographic material.
+    Authenticated,
+    /// No new navigation data transmitted by the given satellites during the
+    /// attack is authenticated, and the data of the remaining satellites is
+    /// authenticated at the end of the stream.
+    ///
+    /// Navigation data that was transmitted before the attack started can
+    /// still be authenticated, also if it is broadcast again during the
+    /// attack.
+    Rejected(Vec<Svn>),
+}
+
+/// Stream of INAV pages of an attack scenario.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Stream {
+    /// Stream generated by the [`Simulator`] with a configuration.
+    Simulated(SimConfig),
+    /// Recorded stream, such as an OSNMA test vector read with
+    /// [`read_file`](crate::csv::read_file).
+    Recorded {
+        /// Pages of the stream, in chronological order.
+        pages: Vec<Page>,
+        /// Merkle tree root used by the receiver.
+        merkle_root: MerkleTreeNode,
+    },
+}
+
+impl Stream {
+    // Gives the Merkle tree root, the SVNs of the satellites whose data is
+    // checked at the end of the stream, and the subframes of the stream. For
+    // recorded streams, these are the satellites that transmit in the last
+    // subframe.
+    fn subframes(&self) -> Result<(MerkleTreeNode, Vec<Svn>, Vec<Subframe>)> {
+        match self {
+            Stream::Simulated(config) => {
+                let mut sim =
+                    Simulator::new(config.clone(), SyntheticNavData::new(config.seed, 20))?;
+                let mut subframes = Vec::new();
+                while let Some(pages) = sim.next_subframe() {
+                    let gst = config.start.add_subframes(i32::try_from(subframes.len())?);
+                    subframes.push(Subframe::new(gst, pages));
+                }
+                Ok((sim.merkle_root(), config.svns.clone(), subframes))
+            }
+            Stream::Recorded { pages, merkle_root } => {
+                let (Some(first), Some(last)) = (pages.first(), pages.last()) else {
+                    bail!("the recorded stream is empty");
+                };
+                let start = first.gst.gst_subframe();
+                let num_subframes = last.gst.gst_subframe().subframes_difference(start) + 1;
+                // Subframes without any pages are kept, so that the attack
+                // window counts subframes from the start of the stream.
+                let mut subframes = (0..num_subframes)
+                    .map(|n| Subframe::new(start.add_subframes(n), Vec::new()))
+                    .collect::<Vec<_>>();
+                for page in pages {
+                    let n = page.gst.gst_subframe().subframes_difference(start);
+                    subframes[usize::try_from(n)?].pages.push(page.clone());
+                }
+                let svns = subframes.last().unwrap().svns();
+                Ok((*merkle_root, svns, subframes))
+            }
+        }
+    }
+}
+
+/// Attack scenario.
+///
+/// A scenario takes a stream generated with the [`Simulator`] or recorded,
+/// and applies an attack to the subframes in a window. The stream is
+/// processed by a receiver that uses the Merkle tree root of the stream, and
+/// its outcome is compared with the expected one.
+pub struct Scenario {
+    /// Name of the scenario.
+    pub name: &'static str,
+    /// Stream of INAV pages.
+    pub stream: Stream,
+    /// Attack.
+    pub attack: Box<dyn Attack>,
+    /// Subframes during which the attack is active, counting from the start
+    /// of the stream.
+    pub window: Range<u32>,
+    /// Expected outcome.
+    pub expected: Outcome,
+    /// Expected state of the receiver at the end of the stream.
+    pub state: OsnmaState,
+    /// Expected reason of the last state transition of the receiver.
+    pub last_transition: TransitionReason,
+}
+
+impl Scenario {
+    /// Runs the scenario.
+    ///
+    /// An error is returned if the outcome or the final state of the receiver
+    /// are not the expected ones.
+    pub fn run(mut self) -> Result<()> {
+        let (merkle_root, svns, subframes) = self.stream.subframes()?;
+        let mut osnma = Osnma::<FullStorage>::from_merkle_tree(merkle_root, None, false);
+        let Some(start) = subframes.first().map(|subframe| subframe.gst) else {
+            bail!("{}: the stream is empty", self.name);
+        };
+        let rejected = match &self.expected {
+            Outcome::Authenticated => Vec::new(),
+            Outcome::Rejected(svns) => svns.clone(),
+        };
+        // Navigation data transmitted by the rejected satellites before the
+        // attack.
+        let mut trackers = HashMap::new();
+        let mut genuine = HashSet::new();
+        let mut last_gst = start;
+        for (mut subframe, n) in subframes.into_iter().zip(0..) {
+            self.attack.apply(&mut subframe, self.window.contains(&n));
+            for page in &subframe.pages {
+                if let Some(word) = &page.word {
+                    osnma.feed_inav(word, page.svn, page.gst, page.band);
+                    if n < self.window.start && rejected.contains(&page.svn) {
+                        trackers
+                            .entry(page.svn)
+                            .or_insert_with(NavDataTracker::new)
+                            .feed(word);
+                    }
+                }
+                osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+            }
+            if n < self.window.start {
+                for (&svn, tracker) in &trackers {
+                    let snapshot = tracker.snapshot();
+                    for bits in [snapshot.ced_and_status, snapshot.timing_parameters]
+                        .into_iter()
+                        .flatten()
+                    {
+                        genuine.insert((svn, bits));
+                    }
+                }
+            } else {
+                for &svn in &rejected {
+                    for data in [
+                        osnma.get_ced_and_status(svn),
+                        osnma.get_timing_parameters(svn),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    {
+                        // Data transmitted before the attack can be
+                        // authenticated after it starts, also if it is
+                        // broadcast again during the attack. Any other data
+                        // must not be authenticated.
+                        ensure!(
+                            genuine.contains(&(svn, data.data().to_bitvec())),
+                            "{}: data of {svn} received at {:?} was authenticated",
+                            self.name,
+                            data.gst()
+                        );
+                    }
+                }
+            }
+            last_gst = subframe.gst;
+        }
+
+        ensure!(
+            osnma.state() == self.state,
+            "{}: the receiver is in state {} at the end of the stream (expected {})",
+            self.name,
+            osnma.state(),
+            self.state
+        );
+        let transition = osnma.last_state_transition();
+        ensure!(
+            transition.is_some_and(|t| t.to == self.state && t.reason == self.last_transition),
+            "{}: the last state transition is {} (expected {} to {})",
+            self.name,
+            transition.map_or("none".to_string(), |t| t.to_string()),
+            self.last_transition,
+            self.state
+        );
+
+        // The most recent data that can be authenticated is the one
+        // transmitted two subframes before the last one.
+        let fresh = last_gst.add_subframes(-3);
+        for &svn in svns.iter().filter(|s| !rejected.contains(s)) {
+            let ced = osnma.get_ced_and_status(svn);
+            let timing = osnma.get_timing_parameters(svn);
+            ensure!(
+                ced.is_some() && timing.is_some_and(|t| t.gst() >= fresh),
+                "{}: data of {svn} is not authenticated at the end of the stream",
+                self.name
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Gives the attack scenarios.
+///
+/// These include a reference scenario without any attack and a scenario for
+/// each of the attacks in this module.
+pub fn scenarios() -> Vec<Scenario> {
+    let config = SimConfig::default();
+    let svns = config.svns.clone();
+    let all = config.subframes;
+    let nma_header = |nmas, cpks| nma_header(nmas, config.chain.chain_id, cpks);
+    let mut rng = ChaCha20Rng::seed_from_u64(0xa77ac);
+    let attacker_key = SigningKey::random(EcdsaFunction::P256Sha256, &mut rng).public_key_sec1();
+    let stream = Stream::Simulated(config.clone());
+    vec![
+        Scenario {
+            name: "no attack",
+            stream: stream.clone(),
+            attack: Box::new(NoAttack),
+            window: 0..0,
+            expected: Outcome::Authenticated,
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "MACK replay",
+            stream: stream.clone(),
+            attack: Box::new(ReplayMack::new(1, &config.chain)),
+            window: 10..all,
+            expected: Outcome::Rejected(svns.clone()),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "navigation data forgery",
+            stream: stream.clone(),
+            attack: Box::new(ForgeNavData {
+                svn: svns[0],
+                word_types: vec![1, 6],
+            }),
+            window: 10..all,
+            expected: Outcome::Rejected(vec![svns[0]]),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "signal delay",
+            stream: stream.clone(),
+            attack: Box::new(Delay { subframes: 1 }),
+            window: 10..all,
+            expected: Outcome::Rejected(svns.clone()),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "DSM block splicing",
+            stream: stream.clone(),
+            attack: Box::new(SpliceDsm::new(12, 0, vec![1])),
+            window: 0..all,
+            expected: Outcome::Rejected(svns.clone()),
+            state: OsnmaState::StartFailed,
+            last_transition: TransitionReason::KrootVerificationFailed,
+        },
+        Scenario {
+            name: "forged alert message",
+            stream: stream.clone(),
+            attack: Box::new(ForgedDsmPkr::alert_message()),
+            window: 10..20,
+            expected: Outcome::Authenticated,
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "forged alert message in NMA header",
+            stream: stream.clone(),
+            attack: Box::new(TamperNmaHeader {
+                nma_header: nma_header(NmaStatus::DontUse, ChainAndPubkeyStatus::AlertMessage),
+            }),
+            window: 10..20,
+            expected: Outcome::Authenticated,
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "forged chain revocation",
+            stream: stream.clone(),
+            attack: Box::new(TamperNmaHeader {
+                nma_header: nma_header(NmaStatus::DontUse, ChainAndPubkeyStatus::ChainRevoked),
+            }),
+            window: 10..20,
+            expected: Outcome::Authenticated,
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "forged public key revocation",
+            stream: stream.clone(),
+            attack: Box::new(Combined(vec![
+                Box::new(TamperNmaHeader {
+                    nma_header: nma_header(
+                        NmaStatus::DontUse,
+                        ChainAndPubkeyStatus::PublicKeyRevoked,
+                    ),
+                }),
+                Box::new(ForgedDsmPkr::public_key(
+                    EcdsaFunction::P256Sha256,
+                    config.pkid + 1,
+                    attacker_key,
+                )),
+            ])),
+            window: 10..20,
+            expected: Outcome::Authenticated,
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "NMA status tampering",
+            stream,
+            attack: Box::new(TamperNmaHeader {
+                nma_header: nma_header(NmaStatus::Test, ChainAndPubkeyStatus::Nominal),
+            }),
+            window: 10..all,
+            expected: Outcome::Rejected(svns),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+    ]
+}
+
+/// Gives the attack scenarios for a recorded stream.
+///
+/// The stream is given by its `pages`, which must have been transmitted with
+/// the nominal status and with the chain parameters currently used by
+/// Galileo (see [`ChainParameters::default`]), such as the OSNMA test vectors
+/// of configuration 1. The receiver uses the Merkle tree root `merkle_root`.
+/// The scenarios include a reference scenario without any attack and the
+/// attacks that do not depend on the DSMs of the stream, which are applied
+/// during the second half of the stream.
+pub fn recorded_scenarios(pages: Vec<Page>, merkle_root: MerkleTreeNode) -> Vec<Scenario> {
+    let (Some(first), Some(last)) = (pages.first(), pages.last()) else {
+        return Vec::new();
+    };
+    let all = u32::try_from(
+        last.gst
+            .gst_subframe()
+            .subframes_difference(first.gst.gst_subframe())
+            + 1,
+    )
+    .unwrap();
+    let window = all / 2..all;
+    let target = last.svn;
+    let stream = Stream::Recorded { pages, merkle_root };
+    vec![
+        Scenario {
+            name: "no attack (recorded)",
+            stream: stream.clone(),
+            attack: Box::new(NoAttack),
+            window: 0..0,
+            expected: Outcome::Authenticated,
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "MACK replay (recorded)",
+            stream: stream.clone(),
+            attack: Box::new(ReplayMack::new(1, &ChainParameters::default())),
+            window: window.clone(),
+            expected: Outcome::Rejected(Svn::iter().collect()),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "navigation data forgery (recorded)",
+            stream: stream.clone(),
+            attack: Box::new(ForgeNavData {
+                svn: target,
+                word_types: vec![1, 6],
+            }),
+            window: window.clone(),
+            expected: Outcome::Rejected(vec![target]),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+        Scenario {
+            name: "signal delay (recorded)",
+            stream,
+            attack: Box::new(Delay { subframes: 1 }),
+            window,
+            expected: Outcome::Rejected(Svn::iter().collect()),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::csv::{read_file, read_pages, CsvWriter};
+
+    #[test]
+    fn scenarios() {
+        for scenario in super::scenarios() {
+            scenario.run().unwrap();
+        }
+    }
+
+    #[test]
+    fn undetected_attack() {
+        // A scenario whose outcome does not match the expected one fails
+        let config = SimConfig {
+            subframes: 20,
+            ..SimConfig::default()
+        };
+        let scenario = Scenario {
+            name: "no attack",
+            stream: Stream::Simulated(config.clone()),
+            attack: Box::new(NoAttack),
+            window: 10..20,
+            expected: Outcome::Rejected(config.svns.clone()),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        };
+        assert!(scenario.run().is_err());
+    }
+
+    #[test]
+    fn unexpected_state() {
+        // A scenario whose final state does not match the expected one fails
+        let config = SimConfig {
+            subframes: 20,
+            ..SimConfig::default()
+        };
+        let scenario = Scenario {
+            name: "no attack",
+            stream: Stream::Simulated(config.clone()),
+            attack: Box::new(NoAttack),
+            window: 0..0,
+            expected: Outcome::Authenticated,
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::TeslaKeyVerified,
+        };
+        assert!(scenario.run().is_err());
+        let scenario = Scenario {
+            name: "DSM block splicing",
+            stream: Stream::Simulated(config.clone()),
+            attack: Box::new(SpliceDsm::new(12, 0, vec![1])),
+            window: 0..20,
+            expected: Outcome::Rejected(config.svns.clone()),
+            state: OsnmaState::Operational,
+            last_transition: TransitionReason::KrootVerified,
+        };
+        assert!(scenario.run().is_err());
+    }
+
+    #[test]
+    fn recorded_stream() {
+        // Run the scenarios for recorded streams on a simulated stream that
+        // is written to and read from a CSV file.
+        let mut sim = Simulator::new(SimConfig::default(), SyntheticNavData::default()).unwrap();
+        let start = sim.config().start;
+        let mut writer = CsvWriter::new(start);
+        while let Some(pages) = sim.next_subframe() {
+            for page in &pages {
+                writer.add_page(page).unwrap();
+            }
+        }
+        let mut csv = Vec::new();
+        writer.finish(&mut csv).unwrap();
+        let pages = read_pages(&csv[..], start).unwrap();
+        for scenario in recorded_scenarios(pages, sim.merkle_root()) {
+            scenario.run().unwrap();
+        }
+    }
+
+    // Runs the scenarios for recorded streams on an OSNMA test vector. The
+    // path of a CSV file of the OSNMA test vectors of configuration 1
+    // published by the European GNSS Service Centre is given in the
+    // OSNMA_TEST_VECTOR environment variable, and the Merkle tree root in hex
+    // in the OSNMA_MERKLE_ROOT environment variable.
+    #[test]
+    #[ignore = "requires the OSNMA_TEST_VECTOR and OSNMA_MERKLE_ROOT environment variables"]
+    fn test_vector() {
+        let path = std::env::var("OSNMA_TEST_VECTOR").unwrap();
+        let merkle_root = std::env::var("OSNMA_MERKLE_ROOT").unwrap();
+        let merkle_root = hex::decode(merkle_root.trim()).unwrap().try_into().unwrap();
+        let pages = read_file(std::path::Path::new(&path)).unwrap();
+        for scenario in recorded_scenarios(pages, merkle_root) {
+            scenario.run().unwrap();
+        }
+    }
+
+    #[test]
+    fn subframe_messages() {
+        let mut sim = Simulator::new(SimConfig::default(), SyntheticNavData::default()).unwrap();
+        let gst = sim.config().start;
+        let svn = sim.config().svns[3];
+        let mut subframe = Subframe::new(gst, sim.next_subframe().unwrap());
+        let mack = subframe.mack(svn).unwrap();
+        let hkroot = subframe.hkroot(svn).unwrap();
+        let mut modified = mack;
+        modified[17] ^= 0xff;
+        subframe.set_mack(svn, &modified);
+        assert_eq!(subframe.mack(svn), Some(modified));
+        assert_eq!(subframe.hkroot(svn), Some(hkroot));
+        subframe.pages.retain(|p| p.svn != svn || p.gst != gst);
+        assert_eq!(subframe.mack(svn), None);
+    }
+}
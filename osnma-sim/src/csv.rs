@@ -0,0 +1,315 @@
+//! Input and output in the format of the OSNMA test vectors.
+//!
+//! The OSNMA test vectors published by the European GNSS Service Centre are
+//! CSV files that contain a line for each satellite with the hexadecimal
+//! contents of all the E1-B INAV pages transmitted by the satellite. The name
+//! of the file gives the GST of the first page.
+
+use crate::sim::Page;
+use anyhow::{bail, ensure, Context, Result};
+use bitvec::prelude::*;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use galileo_osnma::{types::OsnmaDataMessage, Gst, InavBand, Svn};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+/// Size of an E1-B INAV page in bits, including the even and odd parts.
+pub const PAGE_BITS: usize = 240;
+
+/// Number of bits covered by the CRC of an INAV page.
+const CRC_BITS: usize = 196;
+
+/// Encodes an E1-B INAV page.
+///
+/// The page is encoded with the even and odd parts in nominal page format,
+/// including the CRC, and with the SAR, spare and SSP fields set to zero. If
+/// the page does not contain an INAV word, the INAV data is set to zero.
+pub fn encode_page(page: &Page) -> [u8; PAGE_BITS / 8] {
+    let mut data = [0; PAGE_BITS / 8];
+    let bits = data.view_bits_mut::<Msb0>();
+    if let Some(word) = &page.word {
+        let word = word.view_bits::<Msb0>();
+        bits[2..114].copy_from_bitslice(&word[..112]);
+        bits[122..138].copy_from_bitslice(&word[112..]);
+    }
+    // Odd part
+    bits.set(120, true);
+    bits[138..178].copy_from_bitslice(page.osnma.view_bits::<Msb0>());
+    let mut crc_data = BitVec::<u8, Msb0>::with_capacity(CRC_BITS);
+    crc_data.extend_from_bitslice(&bits[..114]);
+    crc_data.extend_from_bitslice(&bits[120..202]);
+    bits[202..226].store_be(crc24q(&crc_data));
+    data
+}
+
+/// Decodes an E1-B INAV page.
+///
+/// This is the inverse of [`encode_page`]. The page was transmitted by the
+/// satellite `svn` at the GST `gst`. `None` is returned if the CRC is wrong,
+/// or if the page is an alert page or contains a dummy INAV word, since these
+/// pages do not carry valid OSNMA data.
+pub fn decode_page(data: &[u8; PAGE_BITS / 8], svn: Svn, gst: Gst) -> Option<Page> {
+    let bits = data.view_bits::<Msb0>();
+    let mut crc_data = BitVec::<u8, Msb0>::with_capacity(CRC_BITS);
+    crc_data.extend_from_bitslice(&bits[..114]);
+    crc_data.extend_from_bitslice(&bits[120..202]);
+    if bits[202..226].load_be::<u32>() != crc24q(&crc_data) {
+        return None;
+    }
+    // Alert page
+    if bits[1] {
+        return None;
+    }
+    let mut word = [0; 16];
+    let word_bits = word.view_bits_mut::<Msb0>();
+    word_bits[..112].copy_from_bitslice(&bits[2..114]);
+    word_bits[112..].copy_from_bitslice(&bits[122..138]);
+    // Dummy INAV word
+    if word_bits[..6].load_be::<u8>() == 63 {
+        return None;
+    }
+    let mut osnma = OsnmaDataMessage::default();
+    osnma
+        .view_bits_mut::<Msb0>()
+        .copy_from_bitslice(&bits[138..178]);
+    Some(Page {
+        svn,
+        gst,
+        band: InavBand::E1B,
+        word: Some(word),
+        osnma,
+    })
+}
+
+// Computes the CRC-24Q of a sequence of bits.
+fn crc24q(data: &BitSlice<u8, Msb0>) -> u32 {
+    const POLY: u32 = 0x1864cfb;
+    let mut crc = 0u32;
+    for bit in data.iter().by_vals() {
+        crc ^= u32::from(bit) << 23;
+        crc <<= 1;
+        if crc & (1 << 24) != 0 {
+            crc ^= POLY;
+        }
+    }
+    crc & 0xffffff
+}
+
+/// Gives the file name that corresponds to the GST of the first page.
+///
+/// The name has the format `DD_MON_YYYY_GST_HH_MM_SS.csv`, as used in the
+/// OSNMA test vectors.
+pub fn file_name(gst: Gst) -> String {
+    let time = gst_epoch()
+        + Duration::weeks(i64::from(gst.wn()))
+        + Duration::seconds(i64::from(gst.tow()));
+    let day = time.format("%d");
+    let month = time.format("%b").to_string().to_uppercase();
+    format!("{day}_{month}_{}.csv", time.format("%Y_GST_%H_%M_%S"))
+}
+
+/// Gives the GST of the first page from the file name.
+///
+/// This is the inverse of [`file_name`].
+pub fn parse_file_name(name: &str) -> Result<Gst> {
+    const SECONDS_PER_WEEK: i64 = 7 * 24 * 3600;
+    let time = NaiveDateTime::parse_from_str(name, "%d_%b_%Y_GST_%H_%M_%S.csv")
+        .with_context(|| format!("invalid file name {name}"))?;
+    let seconds = (time - gst_epoch()).num_seconds();
+    ensure!(seconds >= 0, "file name {name} is before the GST epoch");
+    Ok(Gst::new(
+        u16::try_from(seconds / SECONDS_PER_WEEK)?,
+        u32::try_from(seconds % SECONDS_PER_WEEK)?,
+    ))
+}
+
+// 1999-08-22 00:00:00 GST
+fn gst_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1999, 8, 22)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+/// Reads the pages of a CSV file in the format of the OSNMA test vectors.
+///
+/// The GST of the first page is `start`, which can be obtained from the file
+/// name with [`parse_file_name`]. The pages are returned in chronological
+/// order. The pages that cannot be decoded (see [`decode_page`]) are dropped.
+pub fn read_pages<R: BufRead>(reader: R, start: Gst) -> Result<Vec<Page>> {
+    let mut pages = Vec::new();
+    // Skip CSV header
+    for line in reader.lines().skip(1) {
+        let line = line?;
+        let mut fields = line.split(',');
+        let (Some(svn), Some(_), Some(data), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            bail!("wrong number of fields in CSV line");
+        };
+        let svn = svn.parse::<u8>()?;
+        let svn = Svn::try_from(svn).map_err(|_| anyhow::anyhow!("invalid SVN {svn}"))?;
+        let data = hex::decode(data.trim())?;
+        ensure!(
+            data.len() % (PAGE_BITS / 8) == 0,
+            "data of E{svn:02} is not an integer number of pages"
+        );
+        for (j, page) in data.chunks_exact(PAGE_BITS / 8).enumerate() {
+            let gst = start.add_seconds(2 * i32::try_from(j)?);
+            pages.extend(decode_page(page.try_into().unwrap(), svn, gst));
+        }
+    }
+    pages.sort_by_key(|page| page.gst);
+    Ok(pages)
+}
+
+/// Reads the pages of an OSNMA test vector file.
+///
+/// The GST of the first page is obtained from the file name (see
+/// [`read_pages`]).
+pub fn read_file(path: &Path) -> Result<Vec<Page>> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("invalid file name")?;
+    let start = parse_file_name(name)?;
+    read_pages(BufReader::new(File::open(path)?), start)
+}
+
+/// Writer of CSV files in the format of the OSNMA test vectors.
+///
+/// Since the CSV file contains a line for each satellite, the pages are
+/// accumulated in memory and written when [`CsvWriter::finish`] is called.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CsvWriter {
+    start: Gst,
+    // Pages of each satellite, in the order of the first appearance of the
+    // satellite.
+    pages: Vec<(Svn, Vec<u8>)>,
+}
+
+impl CsvWriter {
+    /// Creates a new writer for pages starting at the GST `start`.
+    pub fn new(start: Gst) -> CsvWriter {
+        CsvWriter {
+            start,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Adds a page.
+    ///
+    /// The pages of each satellite must be given in chronological order,
+    /// without gaps and starting at the GST given in [`CsvWriter::new`].
+    pub fn add_page(&mut self, page: &Page) -> Result<()> {
+        let idx = match self.pages.iter().position(|(svn, _)| *svn == page.svn) {
+            Some(idx) => idx,
+            None => {
+                self.pages.push((page.svn, Vec::new()));
+                self.pages.len() - 1
+            }
+        };
+        let data = &mut self.pages[idx].1;
+        let expected_gst = self
+            .start
+            .add_seconds(i32::try_from(data.len() / (PAGE_BITS / 8)).unwrap() * 2);
+        ensure!(
+            page.gst == expected_gst,
+            "page of E{:02} at {:?} is not contiguous",
+            page.svn,
+            page.gst
+        );
+        data.extend_from_slice(&encode_page(page));
+        Ok(())
+    }
+
+    /// Writes the CSV file.
+    pub fn finish<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, "SVN,Num_NAVBits,Nav_Bits_Hex")?;
+        for (svn, data) in &self.pages {
+            writeln!(
+                writer,
+                "{},{},{}",
+                u8::from(*svn),
+                8 * data.len(),
+                hex::encode_upper(data)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        navdata::SyntheticNavData,
+        sim::{SimConfig, Simulator},
+    };
+
+    #[test]
+    fn name() {
+        // 16 August 2023 00:00:00 GST
+        assert_eq!(
+            file_name(Gst::new(1251, 259200)),
+            "16_AUG_2023_GST_00_00_00.csv"
+        );
+    }
+
+    #[test]
+    fn parse_name() {
+        let gst = Gst::new(1251, 259200);
+        assert_eq!(parse_file_name(&file_name(gst)).unwrap(), gst);
+        // Name of the first OSNMA test vector
+        assert_eq!(
+            parse_file_name("16_AUG_2023_GST_05_00_01.csv").unwrap(),
+            Gst::new(1251, 277201)
+        );
+        assert!(parse_file_name("16_AUG_2023.csv").is_err());
+    }
+
+    #[test]
+    fn read_written_pages() {
+        let mut sim = Simulator::new(
+            SimConfig {
+                subframes: 2,
+                ..SimConfig::default()
+            },
+            SyntheticNavData::default(),
+        )
+        .unwrap();
+        let start = sim.config().start;
+        let mut writer = CsvWriter::new(start);
+        let mut pages = Vec::new();
+        while let Some(subframe) = sim.next_subframe() {
+            for page in subframe {
+                writer.add_page(&page).unwrap();
+                pages.push(page);
+            }
+        }
+        let mut csv = Vec::new();
+        writer.finish(&mut csv).unwrap();
+        pages.sort_by_key(|page| page.gst);
+        assert_eq!(read_pages(&csv[..], start).unwrap(), pages);
+
+        // Pages with a wrong CRC are dropped
+        let mut data = encode_page(&pages[0]);
+        assert_eq!(
+            decode_page(&data, pages[0].svn, start),
+            Some(pages[0].clone())
+        );
+        data[10] ^= 1;
+        assert_eq!(decode_page(&data, pages[0].svn, start), None);
+    }
+
+    #[test]
+    fn crc() {
+        // CRC of the empty sequence
+        assert_eq!(crc24q(BitSlice::empty()), 0);
+        // Check value of CRC-24Q for "123456789"
+        assert_eq!(crc24q(b"123456789".view_bits::<Msb0>()), 0xcde703);
+    }
+}
@@ -0,0 +1,134 @@
+//! NMA header and DSM message construction.
+//!
+//! This module builds the NMA header, the DSM-KROOT and DSM-PKR messages, and
+//! splits DSM messages into the DSM blocks transmitted in the HKROOT messages.
+//! The formats are described in Section 3 of the
+//! [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+
+use crate::{ecdsa::SigningKey, merkle::MerkleTree, tesla::ChainParameters};
+use galileo_osnma::{
+    bitfields::{self, ChainAndPubkeyStatus, NmaStatus, OwnedDsmKroot, OwnedDsmPkr},
+    builders::{DsmKrootBuilder, NmaHeaderBuilder},
+    tesla::{HashFunction, Key, MacFunction, NmaHeader},
+    types::{DsmBlock, DSM_BLOCK_BYTES},
+    Gst, Validated,
+};
+use rand::{CryptoRng, RngCore};
+
+/// Builds an NMA header.
+///
+/// # Panics
+///
+/// Panics if `chain_id` does not fit in 2 bits.
+pub fn nma_header(nma_status: NmaStatus, chain_id: u8, cpks: ChainAndPubkeyStatus) -> u8 {
+    NmaHeaderBuilder {
+        nma_status,
+        chain_id,
+        chain_and_pubkey_status: cpks,
+    }
+    .build()
+    .expect("invalid chain ID")
+    .data()
+}
+
+/// Builds a DSM header.
+pub fn dsm_header(dsm_id: u8, block_id: u8) -> u8 {
+    ((dsm_id & 0xf) << 4) | (block_id & 0xf)
+}
+
+/// Gives the builder of a DSM-KROOT message.
+///
+/// The builder contains the chain parameters `params`, the PKID `pkid`, the
+/// TESLA root key `kroot`, and the GST `kroot_time` given by the WNK and TOWHK
+/// fields, which must be an integer number of hours.
+///
+/// # Panics
+///
+/// Panics if `kroot_time` is not an integer number of hours.
+pub fn dsm_kroot_builder<'a>(
+    params: &ChainParameters,
+    pkid: u8,
+    kroot_time: Gst,
+    kroot: &'a [u8],
+) -> DsmKrootBuilder<'a> {
+    assert_eq!(kroot_time.tow() % 3600, 0);
+    DsmKrootBuilder {
+        public_key_id: pkid,
+        kroot_chain_id: params.chain_id,
+        hash_function: match params.hash_function {
+            HashFunction::Sha256 => bitfields::HashFunction::Sha256,
+            HashFunction::Sha3_256 => bitfields::HashFunction::Sha3_256,
+        },
+        mac_function: match params.mac_function {
+            MacFunction::HmacSha256 => bitfields::MacFunction::HmacSha256,
+            MacFunction::CmacAes => bitfields::MacFunction::CmacAes,
+        },
+        key_size: params.key_size_bits,
+        tag_size: params.tag_size_bits,
+        mac_lookup_table: params.maclt,
+        kroot_wn: kroot_time.wn(),
+        kroot_towh: u8::try_from(kroot_time.tow() / 3600).unwrap(),
+        alpha: params.alpha,
+        kroot,
+    }
+}
+
+/// Builds a DSM-KROOT message.
+///
+/// The message contains the chain parameters `params`, the PKID `pkid`, the
+/// GST `kroot_time` (see [`dsm_kroot_builder`]), the TESLA root key `kroot`,
+/// the ECDSA signature made with `signing_key` and the padding. The signature
+/// covers the NMA header `nma_header`, so the message must be transmitted
+/// with this NMA header.
+///
+/// # Panics
+///
+/// Panics if the chain parameters are not valid (see
+/// [`ChainParameters::check`]), if `pkid` does not fit in 4 bits, or if
+/// `kroot_time` is not an integer number of hours.
+pub fn dsm_kroot<R: RngCore + CryptoRng>(
+    params: &ChainParameters,
+    pkid: u8,
+    kroot_time: Gst,
+    kroot: &Key<Validated>,
+    nma_header: u8,
+    signing_key: &SigningKey,
+    rng: &mut R,
+) -> OwnedDsmKroot {
+    let builder = dsm_kroot_builder(params, pkid, kroot_time, kroot.data());
+    let nma_header = NmaHeader::new(nma_header);
+    let message = builder
+        .signature_message(nma_header)
+        .expect("invalid DSM-KROOT fields");
+    let signature = signing_key.sign(rng, message.as_ref());
+    builder
+        .build(nma_header, &signature)
+        .expect("invalid DSM-KROOT fields")
+}
+
+/// Builds a DSM-PKR message.
+///
+/// The message transmits the leaf with ID `message_id` of the Merkle tree
+/// `tree`. The type of the leaf determines the number of blocks of the
+/// message.
+///
+/// # Panics
+///
+/// Panics if the leaf is not valid for a DSM-PKR.
+pub fn dsm_pkr(tree: &MerkleTree, message_id: u8) -> OwnedDsmPkr {
+    tree.dsm_pkr_builder(message_id)
+        .build(&tree.root())
+        .expect("invalid DSM-PKR fields")
+}
+
+/// Splits a DSM message into DSM blocks.
+///
+/// # Panics
+///
+/// Panics if the size of the message is not a multiple of the block size.
+pub fn blocks(dsm: &[u8]) -> Vec<DsmBlock> {
+    assert_eq!(dsm.len() % DSM_BLOCK_BYTES, 0);
+    dsm.chunks_exact(DSM_BLOCK_BYTES)
+        .map(|block| block.try_into().unwrap())
+        .collect()
+}
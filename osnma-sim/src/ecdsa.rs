@@ -0,0 +1,96 @@
+//! ECDSA signing keys.
+//!
+//! The DSM-KROOT messages are signed with a locally generated ECDSA key, which
+//! can be either a P-256 or a P-521 key.
+
+use galileo_osnma::{bitfields::EcdsaFunction, PublicKey, Validated};
+use p256::ecdsa::signature::RandomizedSigner;
+use rand::{CryptoRng, RngCore};
+
+/// ECDSA signing key.
+#[derive(Clone)]
+pub enum SigningKey {
+    /// ECDSA P-256 key, used with SHA-256.
+    P256(p256::ecdsa::SigningKey),
+    /// ECDSA P-521 key, used with SHA-512.
+    P521(p521::ecdsa::SigningKey),
+}
+
+impl SigningKey {
+    /// Generates a random signing key for the given ECDSA function.
+    pub fn random<R: RngCore + CryptoRng>(function: EcdsaFunction, rng: &mut R) -> SigningKey {
+        match function {
+            EcdsaFunction::P256Sha256 => SigningKey::P256(p256::ecdsa::SigningKey::random(rng)),
+            EcdsaFunction::P521Sha512 => SigningKey::P521(p521::ecdsa::SigningKey::random(rng)),
+        }
+    }
+
+    /// Gives the ECDSA function used by this key.
+    pub fn ecdsa_function(&self) -> EcdsaFunction {
+        match self {
+            SigningKey::P256(_) => EcdsaFunction::P256Sha256,
+            SigningKey::P521(_) => EcdsaFunction::P521Sha512,
+        }
+    }
+
+    /// Gives the size in bytes of the signatures made with this key.
+    pub fn signature_size(&self) -> usize {
+        signature_size(self.ecdsa_function())
+    }
+
+    /// Gives the public key in compressed SEC1 format.
+    ///
+    /// This is the format used in the New Public Key field of the DSM-PKR.
+    pub fn public_key_sec1(&self) -> Vec<u8> {
+        match self {
+            SigningKey::P256(key) => key
+                .verifying_key()
+                .to_encoded_point(true)
+                .as_bytes()
+                .to_vec(),
+            SigningKey::P521(key) => p521::ecdsa::VerifyingKey::from(key)
+                .to_encoded_point(true)
+                .as_bytes()
+                .to_vec(),
+        }
+    }
+
+    /// Gives the public key, as used by galileo-osnma.
+    ///
+    /// The `pkid` parameter indicates the PKID associated with the key.
+    pub fn public_key(&self, pkid: u8) -> PublicKey<Validated> {
+        match self {
+            SigningKey::P256(key) => PublicKey::from_p256(*key.verifying_key(), pkid),
+            SigningKey::P521(key) => {
+                PublicKey::from_p521(p521::ecdsa::VerifyingKey::from(key), pkid)
+            }
+        }
+        .force_valid()
+    }
+
+    /// Signs a message.
+    ///
+    /// The signature is returned as the concatenation of the values r and s,
+    /// which is the format used in the digital signature field of the
+    /// DSM-KROOT.
+    pub fn sign<R: RngCore + CryptoRng>(&self, rng: &mut R, message: &[u8]) -> Vec<u8> {
+        match self {
+            SigningKey::P256(key) => {
+                let signature: p256::ecdsa::Signature = key.sign_with_rng(rng, message);
+                signature.to_bytes().to_vec()
+            }
+            SigningKey::P521(key) => {
+                let signature: p521::ecdsa::Signature = key.sign_with_rng(rng, message);
+                signature.to_bytes().to_vec()
+            }
+        }
+    }
+}
+
+/// Gives the size in bytes of the signatures made with an ECDSA function.
+pub fn signature_size(function: EcdsaFunction) -> usize {
+    match function {
+        EcdsaFunction::P256Sha256 => 64,
+        EcdsaFunction::P521Sha512 => 132,
+    }
+}
@@ -0,0 +1,81 @@
+//! Output in Galmon transport format.
+//!
+//! The pages are written as Galmon `NavMonMessage` protobuf messages of type
+//! Galileo INAV, using the same fields as the `osnma-test-vectors-to-galmon`
+//! converter, so the output can be fed to `galmon-osnma`.
+
+use crate::sim::Page;
+use anyhow::Result;
+use bitvec::prelude::*;
+use galileo_osnma::{
+    galmon::{
+        navmon::{
+            nav_mon_message::{GalileoInav, Type},
+            NavMonMessage,
+        },
+        transport::WriteTransport,
+    },
+    InavBand,
+};
+use std::io::Write;
+
+/// Writer of Galmon transport streams.
+pub struct GalmonWriter<W> {
+    transport: WriteTransport<W>,
+}
+
+impl<W: Write> GalmonWriter<W> {
+    /// Creates a new writer that writes to `write`.
+    pub fn new(write: W) -> GalmonWriter<W> {
+        GalmonWriter {
+            transport: WriteTransport::new(write),
+        }
+    }
+
+    /// Writes a page.
+    ///
+    /// Pages that do not contain an INAV word are not written.
+    pub fn write_page(&mut self, page: &Page) -> Result<()> {
+        let Some(word) = &page.word else {
+            return Ok(());
+        };
+        let sigid = match page.band {
+            InavBand::E1B => 1,
+            InavBand::E5B => 5,
+        };
+        // Galmon gets 24 bits instead of 22 for SAR, and the 2 spare bits
+        // as the LSBs of a byte. These are set to zero, as the SSP.
+        let gi = GalileoInav {
+            gnss_wn: page.gst.wn().into(),
+            gnss_tow: page.gst.tow(),
+            gnss_id: 2,
+            gnss_sv: u8::from(page.svn).into(),
+            contents: word.to_vec(),
+            sigid: Some(sigid),
+            reserved1: Some(page.osnma.to_vec()),
+            reserved2: Some(vec![0]),
+            sar: Some(vec![0; 3]),
+            spare: Some(vec![0]),
+            crc: Some(crc(page)),
+            ssp: Some(0),
+        };
+        let message = NavMonMessage {
+            source_id: 0,
+            r#type: Type::GalileoInavType.into(),
+            local_utc_seconds: 0,
+            local_utc_nanoseconds: 0,
+            gi: Some(gi),
+            ..Default::default()
+        };
+        self.transport.write_packet(&message)?;
+        Ok(())
+    }
+}
+
+// Gives the CRC field of the page, as it would be transmitted in E1-B.
+fn crc(page: &Page) -> Vec<u8> {
+    let data = crate::csv::encode_page(page);
+    let mut crc = BitVec::<u8, Msb0>::from_bitslice(&data.view_bits::<Msb0>()[202..226]);
+    crc.force_align();
+    crc.into_vec()
+}
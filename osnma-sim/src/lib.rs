@@ -0,0 +1,25 @@
+//! Galileo OSNMA signal-in-space message generator.
+//!
+//! This crate generates the INAV words and OSNMA data messages that a set of
+//! Galileo satellites would transmit, using a locally generated ECDSA key
+//! pair, Merkle tree and TESLA chain. The output can be written in the format
+//! of the OSNMA test vectors or in Galmon transport format, and it can be
+//! processed by galileo-osnma using the generated Merkle tree root or public
+//! key. This is intended to test OSNMA receivers in scenarios for which there
+//! are no test vectors.
+//!
+//! The main entry point is the [`Simulator`](sim::Simulator).
+
+#![warn(missing_docs)]
+
+pub mod attacks;
+pub mod csv;
+pub mod dsm;
+pub mod ecdsa;
+#[cfg(feature = "galmon")]
+pub mod galmon;
+pub mod mack;
+pub mod merkle;
+pub mod navdata;
+pub mod sim;
+pub mod tesla;
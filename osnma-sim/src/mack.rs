@@ -0,0 +1,188 @@
+//! MACK message construction.
+//!
+//! This module builds the MACK messages transmitted by each satellite, which
+//! contain the tags that authenticate the navigation data, the MACSEQ and the
+//! TESLA key. The format of the MACK message is described in Section 3.3 of
+//! the
+//! [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+
+use crate::{navdata::NavDataSnapshot, tesla::TeslaChain};
+use bitvec::prelude::*;
+use galileo_osnma::{
+    bitfields::{Adkd, Mack, NmaStatus, Prnd},
+    builders::{self, TagAndInfoBuilder},
+    maclt::{get_maclt_entry, AuthObject, MacLTSlot},
+    tesla::MacKey,
+    types::{BitSlice, MackMessage, MACK_MESSAGE_BYTES},
+    Gst, Svn,
+};
+
+/// Navigation data of the constellation.
+///
+/// This gives the navigation data that the tags of a MACK message
+/// authenticate, indexed by SVN.
+pub trait Constellation {
+    /// Gives the navigation data of a satellite, or `None` if the satellite
+    /// is not transmitting.
+    fn navdata(&self, svn: Svn) -> Option<&NavDataSnapshot>;
+
+    /// Gives the SVN of the satellite whose data is cross-authenticated by
+    /// the tag with index `tag_index` transmitted by `prna`.
+    fn cross_auth_target(&self, prna: Svn, tag_index: usize) -> Svn;
+}
+
+/// MACK message builder.
+///
+/// The builder holds the parameters that are common to all the MACK messages.
+#[derive(Clone)]
+pub struct MackBuilder<'a> {
+    chain: &'a TeslaChain,
+    nma_status: NmaStatus,
+    cop: u8,
+}
+
+impl<'a> MackBuilder<'a> {
+    /// Creates a new MACK message builder.
+    ///
+    /// The MACK messages are built using the keys of the TESLA chain `chain`.
+    /// The NMA status `nma_status` must be the one transmitted in the NMA
+    /// header, since it is used in the computation of the tags. The value
+    /// `cop` is used in the COP fields of the tags that authenticate some
+    /// data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cop` is zero or does not fit in 4 bits.
+    pub fn new(chain: &'a TeslaChain, nma_status: NmaStatus, cop: u8) -> MackBuilder<'a> {
+        assert!((1..16).contains(&cop));
+        MackBuilder {
+            chain,
+            nma_status,
+            cop,
+        }
+    }
+
+    /// Builds the MACK message transmitted by the satellite `prna` in the
+    /// subframe starting at `gst`.
+    ///
+    /// The tags authenticate the navigation data given by `constellation`,
+    /// which must be the data transmitted in the previous subframe. When the
+    /// data for a tag is not available, a dummy tag (with COP = 0) is
+    /// transmitted instead.
+    ///
+    /// `None` is returned if the TESLA chain does not contain the keys needed
+    /// to build the message.
+    pub fn build<C: Constellation>(
+        &self,
+        prna: Svn,
+        gst: Gst,
+        constellation: &C,
+    ) -> Option<MackMessage> {
+        let chain = self.chain.chain();
+        let key = self.chain.key(gst)?;
+        // The MAC context of each key is initialized once and used for all the
+        // tags computed with the key.
+        let tag_key = MacKey::new(*self.chain.key(gst.add_subframes(1))?);
+        let slowmac_key = self
+            .chain
+            .key(gst.add_subframes(11))
+            .copied()
+            .map(MacKey::new);
+        let ts = chain.tag_size_bits();
+        let ks = chain.key_size_bits();
+        let num_tags = (8 * MACK_MESSAGE_BYTES - ks) / (ts + 16);
+        let msg = usize::try_from((gst.tow() / 30) % 2).unwrap();
+        let own_navdata = constellation.navdata(prna);
+
+        // Tag0 and COP. The MACSEQ is filled in at the end.
+        let mut tag0 = BitVec::<u8, Msb0>::repeat(false, ts);
+        let cop = match own_navdata.and_then(|n| n.ced_and_status.as_ref()) {
+            Some(navdata) => {
+                tag_key.compute_tag0(&mut tag0, gst, prna, self.nma_status, navdata);
+                self.cop
+            }
+            None => {
+                let navdata = BitVec::<u8, Msb0>::repeat(false, ADKD0_BITS);
+                tag_key.compute_tag0(&mut tag0, gst, prna, self.nma_status, &navdata);
+                0
+            }
+        };
+
+        let mut tags = Vec::with_capacity(num_tags - 1);
+        for j in 1..num_tags {
+            let (adkd, prnd) = match get_maclt_entry(chain.mac_lookup_table(), msg, j).ok()? {
+                MacLTSlot::Fixed {
+                    adkd,
+                    object: AuthObject::SelfAuth,
+                } => (adkd, prna),
+                MacLTSlot::Fixed {
+                    adkd,
+                    object: AuthObject::CrossAuth,
+                } => (adkd, constellation.cross_auth_target(prna, j)),
+                MacLTSlot::Flex => (Adkd::InavCed, constellation.cross_auth_target(prna, j)),
+            };
+            let navdata = constellation.navdata(prnd).and_then(|n| match adkd {
+                Adkd::InavCed | Adkd::SlowMac => n.ced_and_status.as_ref(),
+                Adkd::InavTiming => n.timing_parameters.as_ref(),
+                Adkd::Reserved => None,
+            });
+            let key = match adkd {
+                Adkd::InavCed | Adkd::InavTiming => &tag_key,
+                Adkd::SlowMac => slowmac_key.as_ref()?,
+                Adkd::Reserved => return None,
+            };
+            let mut tag = BitVec::<u8, Msb0>::repeat(false, ts);
+            let ctr = u8::try_from(j + 1).unwrap();
+            let prnd_u8 = u8::from(prnd);
+            let cop = match navdata {
+                Some(navdata) => {
+                    key.compute_tag(&mut tag, gst, prnd_u8, prna, ctr, self.nma_status, navdata);
+                    self.cop
+                }
+                None => {
+                    let len = match adkd {
+                        Adkd::InavTiming => ADKD4_BITS,
+                        _ => ADKD0_BITS,
+                    };
+                    let navdata = BitVec::<u8, Msb0>::repeat(false, len);
+                    key.compute_tag(&mut tag, gst, prnd_u8, prna, ctr, self.nma_status, &navdata);
+                    0
+                }
+            };
+            tags.push((tag, Prnd::GalileoSvid(prnd_u8), adkd, cop));
+        }
+        let tags: Vec<TagAndInfoBuilder> = tags
+            .iter()
+            .map(|(tag, prnd, adkd, cop)| TagAndInfoBuilder {
+                tag,
+                prnd: *prnd,
+                adkd: *adkd,
+                cop: *cop,
+            })
+            .collect();
+
+        let mut mack = builders::MackBuilder {
+            key_size: ks,
+            tag_size: ts,
+            tag0: &tag0,
+            macseq: 0,
+            cop,
+            tags: &tags,
+            key: &key.data().view_bits::<Msb0>()[..ks],
+        }
+        .build()
+        .expect("invalid MACK fields");
+
+        // The MACSEQ does not cover itself, so it is computed on the message
+        // built with a zero MACSEQ.
+        let macseq = tag_key
+            .compute_macseq(&Mack::new(&mack, ks, ts), prna, gst)
+            .ok()?;
+        BitSlice::from_slice_mut(&mut mack)[ts..ts + 12].store_be(macseq);
+        Some(mack)
+    }
+}
+
+// Sizes of the navigation data for each ADKD, used for dummy tags.
+const ADKD0_BITS: usize = crate::navdata::CED_AND_STATUS_BITS;
+const ADKD4_BITS: usize = crate::navdata::TIMING_PARAMETERS_BITS;
@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use galileo_osnma::{bitfields::EcdsaFunction, Gst, Svn};
+use osnma_sim::{
+    csv::{file_name, CsvWriter},
+    ecdsa::SigningKey,
+    navdata::SyntheticNavData,
+    sim::{SimConfig, Simulator},
+};
+use p256::pkcs8::{EncodePublicKey, LineEnding};
+use std::{fs::File, io::Write, path::PathBuf};
+
+/// Generate Galileo OSNMA signal-in-space messages
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+    /// Output path. The output is written to stdout if this is not given. If
+    /// the path is a directory and the format is CSV, the file is created in
+    /// this directory with the file name used by the OSNMA test vectors.
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Week number at the start of the simulation.
+    #[arg(long, default_value_t = 1300)]
+    wn: u16,
+    /// Time of week at the start of the simulation (must be a multiple of
+    /// 30).
+    #[arg(long, default_value_t = 3600)]
+    tow: u32,
+    /// Number of subframes to generate.
+    #[arg(long, default_value_t = 40)]
+    subframes: u32,
+    /// SVNs of the transmitting satellites (comma-separated).
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "1,2,3,4,5,6,7,8,9,10,11,12"
+    )]
+    svns: Vec<u8>,
+    /// Seed for the generation of the keys and the navigation data.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Use an ECDSA P-521 key instead of a P-256 key.
+    #[arg(long)]
+    p521: bool,
+    /// Public key ID.
+    #[arg(long, default_value_t = 1)]
+    pkid: u8,
+    /// Do not transmit the DSM-PKR.
+    #[arg(long)]
+    no_pkr: bool,
+    /// Path to write the public key to. P-256 keys are written in PEM format
+    /// and P-521 keys are written in hexadecimal format (SEC1 encoding).
+    #[arg(long)]
+    pubkey_out: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Copy, Clone, Eq, PartialEq)]
+enum Format {
+    /// CSV file in the format of the OSNMA test vectors.
+    Csv,
+    /// Galmon transport protocol.
+    #[cfg(feature = "galmon")]
+    Galmon,
+}
+
+fn write_pubkey(signing_key: &SigningKey, path: &PathBuf) -> Result<()> {
+    let contents = match signing_key {
+        SigningKey::P256(key) => key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .context("could not encode public key")?,
+        SigningKey::P521(_) => format!("{}\n", hex::encode(signing_key.public_key_sec1())),
+    };
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+fn output(path: Option<PathBuf>, default_name: impl FnOnce() -> String) -> Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) if path.is_dir() => Box::new(File::create(path.join(default_name()))?),
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(std::io::stdout().lock()),
+    })
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let start = Gst::new(args.wn, args.tow);
+    let svns = args
+        .svns
+        .iter()
+        .map(|&svn| Svn::try_from(svn).map_err(|_| anyhow::anyhow!("invalid SVN {svn}")))
+        .collect::<Result<Vec<_>>>()?;
+    let config = SimConfig {
+        start,
+        subframes: args.subframes,
+        svns,
+        ecdsa_function: if args.p521 {
+            EcdsaFunction::P521Sha512
+        } else {
+            EcdsaFunction::P256Sha256
+        },
+        pkid: args.pkid,
+        transmit_pkr: !args.no_pkr,
+        seed: args.seed,
+        ..SimConfig::default()
+    };
+    let mut sim = Simulator::new(config, SyntheticNavData::new(args.seed, 20))?;
+
+    eprintln!("Merkle tree root: {}", hex::encode(sim.merkle_root()));
+    eprintln!("PKID: {}", args.pkid);
+    if let Some(path) = &args.pubkey_out {
+        write_pubkey(sim.signing_key(), path)?;
+    }
+
+    match args.format {
+        Format::Csv => {
+            let mut writer = CsvWriter::new(start);
+            while let Some(pages) = sim.next_subframe() {
+                for page in &pages {
+                    writer.add_page(page)?;
+                }
+            }
+            writer.finish(output(args.output, || file_name(start))?)?;
+        }
+        #[cfg(feature = "galmon")]
+        Format::Galmon => {
+            let mut writer = osnma_sim::galmon::GalmonWriter::new(output(args.output, || {
+                "osnma-sim.galmon".to_string()
+            })?);
+            while let Some(pages) = sim.next_subframe() {
+                for page in &pages {
+                    writer.write_page(page)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! Merkle tree construction.
+//!
+//! The OSNMA Merkle tree has 16 leaves, each containing a public key or an
+//! OSNMA Alert Message, and a depth of 4. It is described in Section 6.2 of the
+//! [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+
+use galileo_osnma::{
+    bitfields::NewPublicKeyType,
+    builders::{BuildError, DsmPkrBuilder},
+    merkle_tree::{FullMerkleTree, MerkleTreeLeaf, MERKLE_TREE_DEPTH, MERKLE_TREE_LEAVES},
+    MerkleTreeNode,
+};
+
+/// Number of leaves in the Merkle tree.
+pub const NUM_LEAVES: usize = MERKLE_TREE_LEAVES;
+
+/// Leaf of the Merkle tree.
+///
+/// This is an owned version of [`MerkleTreeLeaf`], which contains the NPKT,
+/// NPKID and New Public Key fields of a DSM-PKR.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Leaf {
+    /// New public key type (NPKT field).
+    pub new_public_key_type: NewPublicKeyType,
+    /// New public key ID (NPKID field).
+    pub new_public_key_id: u8,
+    /// New public key (NPK field), in SEC1 compressed format for ECDSA keys,
+    /// or the contents of the OSNMA Alert Message.
+    pub new_public_key: Vec<u8>,
+}
+
+impl Leaf {
+    /// Gives the [`MerkleTreeLeaf`] that corresponds to this leaf.
+    pub fn as_merkle_tree_leaf(&self) -> MerkleTreeLeaf<'_> {
+        MerkleTreeLeaf {
+            new_public_key_type: self.new_public_key_type,
+            new_public_key_id: self.new_public_key_id,
+            new_public_key: &self.new_public_key,
+        }
+    }
+}
+
+/// Merkle tree.
+///
+/// The tree stores its leaves together with a [`FullMerkleTree`] containing
+/// all its nodes, so that the intermediate tree nodes required to
+/// authenticate any of the leaves can be given.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MerkleTree {
+    leaves: Vec<Leaf>,
+    tree: FullMerkleTree,
+}
+
+impl MerkleTree {
+    /// Builds a Merkle tree from its leaves.
+    ///
+    /// The leaf at index `j` of `leaves` is the one with message ID `j`. An
+    /// error is returned if any of the leaves cannot be serialized (see
+    /// [`FullMerkleTree::from_leaves`]).
+    pub fn new(leaves: [Leaf; NUM_LEAVES]) -> Result<MerkleTree, BuildError> {
+        let tree = FullMerkleTree::from_leaves(&core::array::from_fn(|j| {
+            leaves[j].as_merkle_tree_leaf()
+        }))?;
+        Ok(MerkleTree {
+            tree,
+            leaves: leaves.to_vec(),
+        })
+    }
+
+    /// Gives the root of the tree.
+    pub fn root(&self) -> MerkleTreeNode {
+        *self.tree.root()
+    }
+
+    /// Gives a leaf of the tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `message_id` is not smaller than 16.
+    pub fn leaf(&self, message_id: u8) -> &Leaf {
+        &self.leaves[usize::from(message_id)]
+    }
+
+    /// Gives the intermediate tree nodes that authenticate a leaf.
+    ///
+    /// These are the 4 nodes transmitted in the DSM-PKR of the leaf with
+    /// message ID `message_id`, starting with the node closest to the leaf.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `message_id` is not smaller than 16.
+    pub fn intermediate_nodes(&self, message_id: u8) -> [MerkleTreeNode; MERKLE_TREE_DEPTH] {
+        self.tree.intermediate_tree_nodes(message_id)
+    }
+
+    /// Gives a builder for the DSM-PKR that transmits a leaf.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `message_id` is not smaller than 16.
+    pub fn dsm_pkr_builder(&self, message_id: u8) -> DsmPkrBuilder<'_> {
+        self.tree
+            .dsm_pkr_builder(message_id, &self.leaf(message_id).as_merkle_tree_leaf())
+    }
+
+    /// Gives the full Merkle tree.
+    pub fn full_tree(&self) -> &FullMerkleTree {
+        &self.tree
+    }
+}
@@ -0,0 +1,245 @@
+//! Navigation data.
+//!
+//! This module contains the sources of the INAV words transmitted by the
+//! simulated satellites, and the tracking of the navigation data that is
+//! authenticated by the tags.
+
+use bitvec::prelude::*;
+use galileo_osnma::{types::InavWord, Gst, Svn};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+/// Source of INAV words.
+pub trait NavDataSource {
+    /// Gives the E1-B INAV word transmitted by a satellite.
+    ///
+    /// This returns the word transmitted by the satellite with SVN `svn` in
+    /// the page starting at `gst`, or `None` if the satellite does not
+    /// transmit this page.
+    fn word(&mut self, svn: Svn, gst: Gst) -> Option<InavWord>;
+}
+
+/// Word types transmitted in each of the pages of an E1-B nominal subframe.
+///
+/// The alternating words 7 or 9 and 8 or 10 are given as 7 and 8, and are
+/// replaced by 9 and 10 in odd subframes. Word type 0 is used in the pages
+/// that carry the Reed-Solomon parity words and the reduced CED in the signal
+/// in space, since these are not generated.
+pub const E1B_SUBFRAME_WORDS: [u8; 15] = [2, 4, 6, 7, 8, 0, 0, 0, 0, 0, 1, 3, 5, 0, 0];
+
+/// Gives the type of the E1-B word transmitted in the page starting at `gst`.
+pub fn e1b_word_type(gst: Gst) -> u8 {
+    let page = usize::try_from((gst.tow() % 30) / 2).unwrap();
+    let word_type = E1B_SUBFRAME_WORDS[page];
+    let odd_subframe = (gst.tow() / 30) % 2 == 1;
+    match word_type {
+        7 | 8 if odd_subframe => word_type + 2,
+        _ => word_type,
+    }
+}
+
+/// Synthetic navigation data.
+///
+/// The contents of the INAV words are pseudorandom, except for the fields that
+/// galileo-osnma interprets: word types, IODnav, SVID, health and validity
+/// flags (which indicate a healthy satellite), and the GST. The words follow
+/// the E1-B nominal subframe layout in [`E1B_SUBFRAME_WORDS`]. The IODnav of
+/// each satellite changes periodically, and the CED in words 1 to 4 changes
+/// with it.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SyntheticNavData {
+    seed: u64,
+    iodnav_period: u32,
+}
+
+impl SyntheticNavData {
+    /// Creates a new synthetic navigation data source.
+    ///
+    /// The contents of the words are determined by `seed`. The IODnav changes
+    /// every `iodnav_period` subframes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `iodnav_period` is zero.
+    pub fn new(seed: u64, iodnav_period: u32) -> SyntheticNavData {
+        assert!(iodnav_period > 0);
+        SyntheticNavData {
+            seed,
+            iodnav_period,
+        }
+    }
+
+    /// Gives the IODnav transmitted by a satellite at a given GST.
+    pub fn iodnav(&self, svn: Svn, gst: Gst) -> u16 {
+        let subframe = i64::from(gst.wn()) * (604800 / 30) + i64::from(gst.tow() / 30);
+        // Each satellite uses a different offset, so that the IODnav changes
+        // are not simultaneous.
+        let offset = i64::from(u8::from(svn)) * 7;
+        u16::try_from(((subframe + offset) / i64::from(self.iodnav_period)) % 1024).unwrap()
+    }
+
+    // Fills a word with pseudorandom data that depends on the seed and on
+    // `tag`.
+    fn random_word(&self, tag: &[u8]) -> InavWord {
+        let mut hash = Sha256::new();
+        hash.update(self.seed.to_be_bytes());
+        hash.update(tag);
+        let mut rng = ChaCha20Rng::from_seed(hash.finalize().into());
+        let mut word = [0; 16];
+        rng.fill_bytes(&mut word);
+        word
+    }
+}
+
+impl Default for SyntheticNavData {
+    /// Creates a synthetic navigation data source with seed 0 and an IODnav
+    /// period of 20 subframes (10 minutes).
+    fn default() -> SyntheticNavData {
+        SyntheticNavData::new(0, 20)
+    }
+}
+
+impl NavDataSource for SyntheticNavData {
+    fn word(&mut self, svn: Svn, gst: Gst) -> Option<InavWord> {
+        let word_type = e1b_word_type(gst);
+        let svn_u8 = u8::from(svn);
+        let iodnav = self.iodnav(svn, gst);
+        let mut word = match word_type {
+            1..=4 => self.random_word(&[
+                word_type,
+                svn_u8,
+                (iodnav >> 8) as u8,
+                (iodnav & 0xff) as u8,
+            ]),
+            5 => self.random_word(&[word_type, svn_u8]),
+            // Data common to all the satellites
+            6..=10 => self.random_word(&[word_type]),
+            _ => [0; 16],
+        };
+        let bits = word.view_bits_mut::<Msb0>();
+        bits[..6].store_be(word_type);
+        match word_type {
+            0 => {
+                // Time field = 2 indicates that WN and TOW are valid
+                bits[6..8].store_be(2u8);
+                bits[8..96].fill(false);
+                bits[96..108].store_be(gst.wn());
+                bits[108..128].store_be(gst.tow());
+            }
+            1..=4 => {
+                bits[6..16].store_be(iodnav);
+                match word_type {
+                    // SVID
+                    4 => bits[16..22].store_be(svn_u8),
+                    // Spare bits
+                    1 | 2 => bits[126..128].fill(false),
+                    _ => (),
+                }
+                if word_type == 4 {
+                    bits[126..128].fill(false);
+                }
+            }
+            5 => {
+                // Signal health and data validity status
+                bits[67..73].fill(false);
+                bits[73..85].store_be(gst.wn());
+                bits[85..105].store_be(gst.tow());
+                bits[105..128].fill(false);
+            }
+            6 => {
+                bits[105..125].store_be(gst.tow());
+                bits[125..128].fill(false);
+            }
+            _ => (),
+        }
+        Some(word)
+    }
+}
+
+/// Number of bits of CED and health status data (ADKD = 0 and 12).
+pub const CED_AND_STATUS_BITS: usize = 549;
+
+/// Number of bits of timing parameters data (ADKD = 4).
+pub const TIMING_PARAMETERS_BITS: usize = 141;
+
+/// Navigation data authenticated by the tags.
+///
+/// This contains the navigation data that a receiver has collected from a
+/// satellite by the end of a subframe, in the format used to compute the
+/// tags.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct NavDataSnapshot {
+    /// CED and health status data, if all the words 1 to 5 have been
+    /// received.
+    pub ced_and_status: Option<BitVec<u8, Msb0>>,
+    /// Timing parameters data, if the words 6 and 10 have been received.
+    pub timing_parameters: Option<BitVec<u8, Msb0>>,
+}
+
+/// Navigation data tracker.
+///
+/// This keeps the last INAV words 1 to 6 and 10 transmitted by a satellite in
+/// the same way as a receiver would, so that the tags authenticate the data
+/// that the receiver has. In particular, when a word with a new IODnav is
+/// transmitted, the words 1 to 4 with the old IODnav are discarded.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct NavDataTracker {
+    ced: [Option<InavWord>; 5],
+    word6: Option<InavWord>,
+    word10: Option<InavWord>,
+}
+
+impl NavDataTracker {
+    /// Creates a new tracker with no data.
+    pub fn new() -> NavDataTracker {
+        NavDataTracker::default()
+    }
+
+    /// Feeds an E1-B INAV word transmitted by the satellite.
+    pub fn feed(&mut self, word: &InavWord) {
+        let bits = word.view_bits::<Msb0>();
+        let word_type = bits[..6].load_be::<u8>();
+        match word_type {
+            1..=4 => {
+                let iodnav = bits[6..16].load_be::<u16>();
+                for stored in self.ced[..4].iter_mut() {
+                    if stored
+                        .is_some_and(|w| w.view_bits::<Msb0>()[6..16].load_be::<u16>() != iodnav)
+                    {
+                        *stored = None;
+                    }
+                }
+                self.ced[usize::from(word_type) - 1] = Some(*word);
+            }
+            5 => self.ced[4] = Some(*word),
+            6 => self.word6 = Some(*word),
+            10 => self.word10 = Some(*word),
+            _ => (),
+        }
+    }
+
+    /// Gives the navigation data collected so far.
+    pub fn snapshot(&self) -> NavDataSnapshot {
+        let ced_and_status = self.ced.iter().all(Option::is_some).then(|| {
+            let mut data = BitVec::with_capacity(CED_AND_STATUS_BITS);
+            for (word, end) in self.ced.iter().zip([126, 126, 128, 126, 73]) {
+                data.extend_from_bitslice(&word.as_ref().unwrap().view_bits::<Msb0>()[6..end]);
+            }
+            data
+        });
+        let timing_parameters = match (&self.word6, &self.word10) {
+            (Some(word6), Some(word10)) => {
+                let mut data = BitVec::with_capacity(TIMING_PARAMETERS_BITS);
+                data.extend_from_bitslice(&word6.view_bits::<Msb0>()[6..105]);
+                data.extend_from_bitslice(&word10.view_bits::<Msb0>()[86..128]);
+                Some(data)
+            }
+            _ => None,
+        };
+        NavDataSnapshot {
+            ced_and_status,
+            timing_parameters,
+        }
+    }
+}
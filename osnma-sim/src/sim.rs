@@ -0,0 +1,446 @@
+//! OSNMA signal-in-space simulator.
+//!
+//! The [`Simulator`] generates the INAV words and the OSNMA data messages
+//! transmitted by a set of satellites, subframe by subframe.
+
+use crate::{
+    dsm::{blocks, dsm_header, dsm_kroot, dsm_kroot_builder, dsm_pkr, nma_header},
+    ecdsa::SigningKey,
+    mack::{Constellation, MackBuilder},
+    merkle::{Leaf, MerkleTree, NUM_LEAVES},
+    navdata::{NavDataSnapshot, NavDataSource, NavDataTracker},
+    tesla::{ChainParameters, TeslaChain},
+};
+use anyhow::{ensure, Context, Result};
+use galileo_osnma::{
+    bitfields::{ChainAndPubkeyStatus, EcdsaFunction, NewPublicKeyType, NmaStatus},
+    tesla::{Chain, NmaHeader},
+    types::{
+        DsmBlock, HkrootMessage, InavWord, MackMessage, OsnmaDataMessage, HKROOT_MESSAGE_BYTES,
+        HKROOT_SECTION_BYTES, MACK_SECTION_BYTES,
+    },
+    Gst, InavBand, MerkleTreeNode, PublicKey, Svn, Validated,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// Simulation configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SimConfig {
+    /// GST at the start of the simulation.
+    ///
+    /// This must be the start of a subframe.
+    pub start: Gst,
+    /// Number of subframes to simulate.
+    pub subframes: u32,
+    /// SVNs of the satellites that transmit.
+    pub svns: Vec<Svn>,
+    /// ECDSA function used to sign the DSM-KROOT.
+    pub ecdsa_function: EcdsaFunction,
+    /// Public key ID.
+    ///
+    /// This is also used as the message ID of the DSM-PKR that transmits the
+    /// public key.
+    pub pkid: u8,
+    /// TESLA chain parameters.
+    pub chain: ChainParameters,
+    /// NMA status transmitted in the NMA header.
+    pub nma_status: NmaStatus,
+    /// Value of the COP field of the tags.
+    pub cop: u8,
+    /// Transmit the DSM-PKR in addition to the DSM-KROOT.
+    pub transmit_pkr: bool,
+    /// Seed for the generation of the keys.
+    pub seed: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> SimConfig {
+        SimConfig {
+            start: Gst::new(1300, 3600),
+            subframes: 40,
+            svns: (1..=12).map(|n| Svn::try_from(n).unwrap()).collect(),
+            ecdsa_function: EcdsaFunction::P256Sha256,
+            pkid: 1,
+            chain: ChainParameters::default(),
+            nma_status: NmaStatus::Operational,
+            cop: 15,
+            transmit_pkr: true,
+            seed: 0,
+        }
+    }
+}
+
+/// Simulated INAV page.
+///
+/// This contains the data transmitted by a satellite in an E1-B INAV page.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Page {
+    /// SVN of the satellite.
+    pub svn: Svn,
+    /// GST at the start of the page.
+    pub gst: Gst,
+    /// Band in which the page is transmitted.
+    pub band: InavBand,
+    /// INAV word, or `None` if the navigation data source does not give
+    /// a word for this page.
+    pub word: Option<InavWord>,
+    /// OSNMA data message.
+    pub osnma: OsnmaDataMessage,
+}
+
+/// OSNMA signal-in-space simulator.
+///
+/// The simulator generates an ECDSA key pair and a Merkle tree that contains
+/// the public key, a TESLA chain, and the corresponding DSM-KROOT and DSM-PKR.
+/// Then it generates the INAV pages transmitted by each of the satellites
+/// with [`Simulator::next_subframe`]. The INAV words are obtained from a
+/// [`NavDataSource`].
+pub struct Simulator<N> {
+    config: SimConfig,
+    navdata: N,
+    signing_key: SigningKey,
+    merkle_tree: MerkleTree,
+    chain: TeslaChain,
+    nma_header: u8,
+    dsm_kroot: Vec<DsmBlock>,
+    dsm_pkr: Vec<DsmBlock>,
+    trackers: Vec<NavDataTracker>,
+    snapshots: Snapshots,
+    subframe: u32,
+}
+
+// Navigation data of the constellation at the end of a subframe.
+struct Snapshots {
+    svns: Vec<Svn>,
+    snapshots: Vec<NavDataSnapshot>,
+}
+
+impl Constellation for Snapshots {
+    fn navdata(&self, svn: Svn) -> Option<&NavDataSnapshot> {
+        let idx = self.svns.iter().position(|&s| s == svn)?;
+        Some(&self.snapshots[idx])
+    }
+
+    fn cross_auth_target(&self, prna: Svn, tag_index: usize) -> Svn {
+        let n = self.svns.len();
+        let Some(idx) = self.svns.iter().position(|&s| s == prna) else {
+            return prna;
+        };
+        if n == 1 {
+            return prna;
+        }
+        // Round-robin over the other satellites
+        let offset = (tag_index - 1) % (n - 1) + 1;
+        self.svns[(idx + offset) % n]
+    }
+}
+
+impl<N: NavDataSource> Simulator<N> {
+    /// Creates a new simulator.
+    ///
+    /// An error is returned if the configuration is not valid.
+    pub fn new(config: SimConfig, navdata: N) -> Result<Simulator<N>> {
+        config.chain.check()?;
+        ensure!(
+            config.start.is_subframe(),
+            "the start GST must be the start of a subframe"
+        );
+        ensure!(
+            !config.svns.is_empty(),
+            "at least one satellite is required"
+        );
+        ensure!(config.pkid < 16, "the PKID must be smaller than 16");
+        ensure!(
+            (1..16).contains(&config.cop),
+            "the COP must be between 1 and 15"
+        );
+
+        let mut rng = ChaCha20Rng::seed_from_u64(config.seed);
+        let signing_key = SigningKey::random(config.ecdsa_function, &mut rng);
+        let npkt = NewPublicKeyType::EcdsaKey(config.ecdsa_function);
+        let leaves: [Leaf; NUM_LEAVES] = core::array::from_fn(|j| {
+            if j == usize::from(config.pkid) {
+                Leaf {
+                    new_public_key_type: npkt,
+                    new_public_key_id: config.pkid,
+                    new_public_key: signing_key.public_key_sec1(),
+                }
+            } else {
+                // Other leaves contain unrelated P-256 public keys
+                let other = SigningKey::random(EcdsaFunction::P256Sha256, &mut rng);
+                Leaf {
+                    new_public_key_type: NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256),
+                    new_public_key_id: u8::try_from(j).unwrap(),
+                    new_public_key: other.public_key_sec1(),
+                }
+            }
+        });
+        let merkle_tree =
+            MerkleTree::new(leaves).map_err(|e| anyhow::anyhow!("invalid Merkle tree: {e}"))?;
+
+        // The TESLA root key corresponds to the hour in which the
+        // simulation starts.
+        let kroot_time = Gst::new(
+            config.start.wn(),
+            config.start.tow() - config.start.tow() % 3600,
+        );
+        // The chain parameters do not depend on the KROOT, so they are
+        // extracted from a DSM-KROOT with a placeholder KROOT and signature.
+        let placeholder_kroot = vec![0; config.chain.key_size_bits / 8];
+        let placeholder =
+            dsm_kroot_builder(&config.chain, config.pkid, kroot_time, &placeholder_kroot)
+                .build(NmaHeader::new(0), &vec![0; signing_key.signature_size()])
+                .map_err(|e| anyhow::anyhow!("invalid chain parameters: {e}"))?;
+        let chain = Chain::from_dsm_kroot(placeholder.dsm_kroot())
+            .map_err(|e| anyhow::anyhow!("invalid chain parameters: {e}"))?;
+        let end = config
+            .start
+            .add_subframes(i32::try_from(config.subframes).context("too many subframes")? + 12);
+        let chain = TeslaChain::generate(chain, kroot_time.add_seconds(-30), end, &mut rng);
+
+        let nma_header = nma_header(
+            config.nma_status,
+            config.chain.chain_id,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        let dsm_kroot = dsm_kroot(
+            &config.chain,
+            config.pkid,
+            kroot_time,
+            chain.kroot(),
+            nma_header,
+            &signing_key,
+            &mut rng,
+        );
+        let dsm_kroot = blocks(dsm_kroot.dsm_kroot().0);
+        let dsm_pkr = blocks(dsm_pkr(&merkle_tree, config.pkid).dsm_pkr().0);
+
+        let num_svns = config.svns.len();
+        Ok(Simulator {
+            navdata,
+            signing_key,
+            merkle_tree,
+            chain,
+            nma_header,
+            dsm_kroot,
+            dsm_pkr,
+            trackers: vec![NavDataTracker::new(); num_svns],
+            snapshots: Snapshots {
+                svns: config.svns.clone(),
+                snapshots: vec![NavDataSnapshot::default(); num_svns],
+            },
+            subframe: 0,
+            config,
+        })
+    }
+
+    /// Gives the simulation configuration.
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// Gives the root of the Merkle tree.
+    pub fn merkle_root(&self) -> MerkleTreeNode {
+        self.merkle_tree.root()
+    }
+
+    /// Gives the Merkle tree.
+    pub fn merkle_tree(&self) -> &MerkleTree {
+        &self.merkle_tree
+    }
+
+    /// Gives the ECDSA key used to sign the DSM-KROOT.
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.signing_key
+    }
+
+    /// Gives the ECDSA public key, as used by galileo-osnma.
+    pub fn public_key(&self) -> PublicKey<Validated> {
+        self.signing_key.public_key(self.config.pkid)
+    }
+
+    /// Gives the TESLA chain.
+    pub fn tesla_chain(&self) -> &TeslaChain {
+        &self.chain
+    }
+
+    /// Generates the pages of the next subframe.
+    ///
+    /// The pages are returned in chronological order, and the pages with the
+    /// same GST are sorted in the order of the configured SVNs. `None` is
+    /// returned when all the subframes of the simulation have been
+    /// generated.
+    pub fn next_subframe(&mut self) -> Option<Vec<Page>> {
+        if self.subframe >= self.config.subframes {
+            return None;
+        }
+        let gst = self
+            .config
+            .start
+            .add_subframes(i32::try_from(self.subframe).unwrap());
+        let builder = MackBuilder::new(&self.chain, self.config.nma_status, self.config.cop);
+        let macks = self
+            .config
+            .svns
+            .iter()
+            .map(|&svn| builder.build(svn, gst, &self.snapshots))
+            .collect::<Option<Vec<_>>>()
+            .expect("TESLA chain is too short");
+        let hkroots = (0..self.config.svns.len())
+            .map(|j| self.hkroot(j))
+            .collect::<Vec<_>>();
+
+        let mut pages = Vec::with_capacity(15 * self.config.svns.len());
+        for page in 0..15 {
+            let page_gst = gst.add_seconds(2 * page);
+            let page = usize::try_from(page).unwrap();
+            for (j, &svn) in self.config.svns.iter().enumerate() {
+                let word = self.navdata.word(svn, page_gst);
+                if let Some(word) = &word {
+                    self.trackers[j].feed(word);
+                }
+                pages.push(Page {
+                    svn,
+                    gst: page_gst,
+                    band: InavBand::E1B,
+                    word,
+                    osnma: osnma_data_message(&hkroots[j], &macks[j], page),
+                });
+            }
+        }
+
+        for (snapshot, tracker) in self
+            .snapshots
+            .snapshots
+            .iter_mut()
+            .zip(self.trackers.iter())
+        {
+            *snapshot = tracker.snapshot();
+        }
+        self.subframe += 1;
+        Some(pages)
+    }
+
+    // Builds the HKROOT message transmitted by the j-th satellite in the
+    // current subframe.
+    //
+    // The DSM-KROOT and DSM-PKR are transmitted alternately (the DSM-PKR
+    // first). Each DSM is transmitted for the number of subframes needed to
+    // send all its blocks, with the satellites sending different blocks.
+    fn hkroot(&self, j: usize) -> HkrootMessage {
+        let n = self.config.svns.len();
+        let duration = |dsm: &[DsmBlock]| (dsm.len() + n - 1) / n;
+        let kroot_duration = duration(&self.dsm_kroot);
+        let pkr_duration = if self.config.transmit_pkr {
+            duration(&self.dsm_pkr)
+        } else {
+            0
+        };
+        let offset = usize::try_from(self.subframe).unwrap() % (kroot_duration + pkr_duration);
+        let (dsm_id, dsm, offset) = if offset < pkr_duration {
+            (12, &self.dsm_pkr, offset)
+        } else {
+            (0, &self.dsm_kroot, offset - pkr_duration)
+        };
+        let block_id = (offset * n + j) % dsm.len();
+        let mut hkroot = [0; HKROOT_MESSAGE_BYTES];
+        hkroot[0] = self.nma_header;
+        hkroot[1] = dsm_header(dsm_id, u8::try_from(block_id).unwrap());
+        hkroot[2..].copy_from_slice(&dsm[block_id]);
+        hkroot
+    }
+}
+
+// Builds the OSNMA data message transmitted in a page of a subframe.
+fn osnma_data_message(hkroot: &HkrootMessage, mack: &MackMessage, page: usize) -> OsnmaDataMessage {
+    let mut osnma = [0; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+    osnma[..HKROOT_SECTION_BYTES]
+        .copy_from_slice(&hkroot[page * HKROOT_SECTION_BYTES..(page + 1) * HKROOT_SECTION_BYTES]);
+    osnma[HKROOT_SECTION_BYTES..]
+        .copy_from_slice(&mack[page * MACK_SECTION_BYTES..(page + 1) * MACK_SECTION_BYTES]);
+    osnma
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::navdata::SyntheticNavData;
+    use galileo_osnma::{storage::FullStorage, trace::MacTraceHook, Osnma};
+
+    fn run(
+        sim: &mut Simulator<SyntheticNavData>,
+        osnma: &mut Osnma<FullStorage, 8, 8, impl MacTraceHook>,
+    ) {
+        while let Some(pages) = sim.next_subframe() {
+            for page in &pages {
+                if let Some(word) = &page.word {
+                    osnma.feed_inav(word, page.svn, page.gst, page.band);
+                }
+                osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+            }
+        }
+    }
+
+    fn check_authenticated(sim: &Simulator<SyntheticNavData>, osnma: &Osnma<FullStorage>) {
+        for &svn in &sim.config().svns {
+            assert!(osnma.get_ced_and_status(svn).is_some(), "no CED for {svn}");
+            assert!(
+                osnma.get_timing_parameters(svn).is_some(),
+                "no timing parameters for {svn}"
+            );
+        }
+    }
+
+    #[test]
+    fn merkle_tree() {
+        let mut sim = Simulator::new(SimConfig::default(), SyntheticNavData::default()).unwrap();
+        let mut osnma = Osnma::<FullStorage>::from_merkle_tree(sim.merkle_root(), None, false);
+        run(&mut sim, &mut osnma);
+        check_authenticated(&sim, &osnma);
+    }
+
+    #[test]
+    fn p521_pubkey() {
+        let config = SimConfig {
+            ecdsa_function: EcdsaFunction::P521Sha512,
+            svns: (5..=8).map(|n| Svn::try_from(n).unwrap()).collect(),
+            transmit_pkr: false,
+            seed: 1,
+            ..SimConfig::default()
+        };
+        let mut sim = Simulator::new(config, SyntheticNavData::new(1, 20)).unwrap();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(sim.public_key(), false);
+        run(&mut sim, &mut osnma);
+        check_authenticated(&sim, &osnma);
+    }
+
+    #[test]
+    fn mac_trace() {
+        use galileo_osnma::trace::MacTrace;
+
+        #[derive(Default)]
+        struct Count {
+            tags: usize,
+            macseqs: usize,
+        }
+
+        impl MacTraceHook for Count {
+            fn mac_trace(&mut self, trace: &MacTrace<'_>) {
+                // All the MACs generated by the simulator are correct
+                assert!(trace.is_valid(), "{trace}");
+                match trace {
+                    MacTrace::Tag(_) => self.tags += 1,
+                    MacTrace::Macseq(_) => self.macseqs += 1,
+                }
+            }
+        }
+
+        let mut sim = Simulator::new(SimConfig::default(), SyntheticNavData::default()).unwrap();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(sim.public_key(), false)
+            .with_mac_trace(Count::default());
+        run(&mut sim, &mut osnma);
+        assert!(osnma.mac_trace_hook().tags > 0);
+        assert!(osnma.mac_trace_hook().macseqs > 0);
+    }
+}
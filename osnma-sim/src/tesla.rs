@@ -0,0 +1,154 @@
+//! TESLA chain generation.
+//!
+//! A TESLA chain is generated by drawing a random key for the last subframe
+//! of the chain and applying the one-way function repeatedly to obtain the
+//! keys of the previous subframes, down to the TESLA root key.
+
+use crate::dsm::dsm_kroot_builder;
+use anyhow::{anyhow, bail, Result};
+use galileo_osnma::{
+    maclt::{get_maclt_entry, MacLTError},
+    tesla::{Chain, HashFunction, Key, MacFunction, NmaHeader},
+    types::MACK_MESSAGE_BYTES,
+    Gst, Validated,
+};
+use rand::{CryptoRng, RngCore};
+
+/// Parameters of a TESLA chain.
+///
+/// These are the parameters that are transmitted in the DSM-KROOT.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ChainParameters {
+    /// Chain ID (CIDKR).
+    pub chain_id: u8,
+    /// Hash function (HF).
+    pub hash_function: HashFunction,
+    /// MAC function (MF).
+    pub mac_function: MacFunction,
+    /// TESLA key size in bits (KS).
+    pub key_size_bits: usize,
+    /// Tag size in bits (TS).
+    pub tag_size_bits: usize,
+    /// MAC look-up table ID (MACLT).
+    pub maclt: u8,
+    /// Random pattern alpha (48 bits).
+    pub alpha: u64,
+}
+
+impl Default for ChainParameters {
+    /// Gives the parameters currently used by Galileo: SHA-256, HMAC-SHA-256,
+    /// 128-bit keys, 40-bit tags and MAC look-up table 34.
+    fn default() -> ChainParameters {
+        ChainParameters {
+            chain_id: 1,
+            hash_function: HashFunction::Sha256,
+            mac_function: MacFunction::HmacSha256,
+            key_size_bits: 128,
+            tag_size_bits: 40,
+            maclt: 34,
+            alpha: 0x25d3964da3a2,
+        }
+    }
+}
+
+impl ChainParameters {
+    /// Gives the number of tags in each MACK message, including the tag0.
+    pub fn num_tags(&self) -> usize {
+        (8 * MACK_MESSAGE_BYTES - self.key_size_bits) / (self.tag_size_bits + 16)
+    }
+
+    /// Checks that the parameters can be transmitted in the DSM-KROOT and
+    /// that they are consistent with the MAC look-up table.
+    pub fn check(&self) -> Result<()> {
+        // The chain ID, key size, tag size and alpha are checked by
+        // serializing the parameters in a DSM-KROOT with a placeholder KROOT.
+        let placeholder_kroot = vec![0; self.key_size_bits / 8];
+        dsm_kroot_builder(self, 0, Gst::new(0, 0), &placeholder_kroot)
+            .signature_message(NmaHeader::new(0))
+            .map_err(|e| anyhow!("invalid chain parameters: {e}"))?;
+        if self.mac_function == MacFunction::CmacAes && self.key_size_bits != 128 {
+            bail!("CMAC-AES requires a key size of 128 bits");
+        }
+        // The number of tags in the MACK must match the number of tags 'nt'
+        // in the MAC look-up table entry.
+        let num_tags = self.num_tags();
+        for msg in 0..2 {
+            for tag in 1..num_tags {
+                if let Err(e) = get_maclt_entry(self.maclt, msg, tag) {
+                    bail!(
+                        "MACLT {} cannot be used with {num_tags} tags: {e}",
+                        self.maclt
+                    );
+                }
+            }
+            if get_maclt_entry(self.maclt, msg, num_tags) != Err(MacLTError::InvalidTagNumber) {
+                bail!("MACLT {} requires more than {num_tags} tags", self.maclt);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// TESLA chain.
+///
+/// This contains the keys of a TESLA chain from the TESLA root key up to a
+/// certain subframe.
+#[derive(Clone)]
+pub struct TeslaChain {
+    chain: Chain,
+    // Keys in chronological order. The first key is the TESLA root key.
+    keys: Vec<Key<Validated>>,
+}
+
+impl TeslaChain {
+    /// Generates a TESLA chain.
+    ///
+    /// The TESLA root key of the chain corresponds to the subframe starting at
+    /// `kroot_gst`, which is 30 seconds before the GST given by the WNK and
+    /// TOWHK fields of the DSM-KROOT. The chain is generated up to the key of
+    /// the subframe starting at `last_gst`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `kroot_gst` or `last_gst` are not the start of a subframe, or
+    /// if `last_gst` is before `kroot_gst`.
+    pub fn generate<R: RngCore + CryptoRng>(
+        chain: Chain,
+        kroot_gst: Gst,
+        last_gst: Gst,
+        rng: &mut R,
+    ) -> TeslaChain {
+        assert!(kroot_gst.is_subframe() && last_gst.is_subframe());
+        let num_keys = usize::try_from(last_gst.subframes_difference(kroot_gst)).unwrap() + 1;
+        let mut data = vec![0; chain.key_size_bytes()];
+        rng.fill_bytes(&mut data);
+        let mut key = Key::from_slice(&data, last_gst, &chain).force_valid();
+        let mut keys = Vec::with_capacity(num_keys);
+        for _ in 1..num_keys {
+            let previous = key.one_way_function();
+            keys.push(key);
+            key = previous;
+        }
+        keys.push(key);
+        keys.reverse();
+        TeslaChain { chain, keys }
+    }
+
+    /// Gives the chain parameters.
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    /// Gives the TESLA root key.
+    pub fn kroot(&self) -> &Key<Validated> {
+        &self.keys[0]
+    }
+
+    /// Gives the key transmitted in the subframe starting at `gst`.
+    ///
+    /// If `gst` is outside the span of the chain, `None` is returned.
+    pub fn key(&self, gst: Gst) -> Option<&Key<Validated>> {
+        let index = usize::try_from(gst.subframes_difference(self.kroot().gst_subframe())).ok()?;
+        self.keys.get(index)
+    }
+}
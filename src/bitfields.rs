@@ -4,8 +4,15 @@
 //! the messages used by OSNMA. As a general rule, the structures are a wrapper
 //! over a `&[u8]` or `&[u8; N]`.
 
+use crate::dsm::MAX_DSM_BYTES;
+use crate::maclt::MacLTRegistry;
+#[cfg(feature = "serde")]
+use crate::serde_helpers::Bytes;
 pub use crate::tesla::NmaHeader;
-use crate::tesla::{AdkdCheckError, Key, MacseqCheckError};
+use crate::tesla::{AdkdCheckError, Key, MacKey, MacseqCheckError};
+use crate::trace::MacseqTrace;
+#[cfg(feature = "serde")]
+use crate::types::DSM_BLOCK_BYTES;
 use crate::types::{
     BitSlice, MackMessage, MerkleTreeNode, Towh, MACK_MESSAGE_BYTES, MERKLE_TREE_NODE_BYTES,
 };
@@ -16,6 +23,7 @@ use core::fmt;
 use ecdsa::{PrimeCurve, Signature, SignatureSize};
 use sha2::{Digest, Sha256};
 use signature::Verifier;
+use typenum::Unsigned;
 
 /// Status of the NMA chain.
 ///
@@ -23,6 +31,8 @@ use signature::Verifier;
 /// as defined in Section 3.1.1 of the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NmaStatus {
     /// Reserved value (NMAS = 0),
     Reserved,
@@ -40,6 +50,8 @@ pub enum NmaStatus {
 /// as defined in Section 3.1.3 of the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChainAndPubkeyStatus {
     /// Reserved value (CPKS = 0).
     Reserved,
@@ -75,6 +87,8 @@ pub struct DsmHeader<'a>(
 /// This is derived from the DSM ID field according to Section 3.2.1.1 in the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DsmType {
     /// DSM-KROOT.
     ///
@@ -143,6 +157,8 @@ pub struct DsmPkr<'a>(
 /// DSM-PKR message. See Table 5 in the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NewPublicKeyType {
     /// An ECDSA key, as defined by the enum [`EcdsaFunction`].
     EcdsaKey(EcdsaFunction),
@@ -229,18 +245,22 @@ impl<'a> DsmPkr<'a> {
     /// Gives a slice containing the New Public Key field.
     ///
     /// If the size of the New Public Key field cannot be determined because
-    /// some other fields contain reserved values, `None` is returned.
+    /// some other fields contain reserved values, or if the New Public Key
+    /// does not fit in the message, `None` is returned.
     pub fn new_public_key(&self) -> Option<&[u8]> {
-        self.key_size().map(|s| &self.0[1040 / 8..1040 / 8 + s])
+        self.key_size()
+            .and_then(|s| self.0.get(1040 / 8..1040 / 8 + s))
     }
 
     /// Gives a slice containing the padding field.
     ///
     /// If the size of the New Public Key field cannot be determined because
-    /// some other fields contain reserved values, `None` is returned.
+    /// some other fields contain reserved values, or if the New Public Key
+    /// does not fit in the number of blocks of the message, `None` is
+    /// returned.
     pub fn padding(&self) -> Option<&[u8]> {
         if let (Some(ks), Some(nb)) = (self.key_size(), self.number_of_blocks()) {
-            Some(&self.0[1040 / 8 + ks..nb * 104 / 8])
+            self.0.get(1040 / 8 + ks..nb * 104 / 8)
         } else {
             None
         }
@@ -252,9 +272,11 @@ impl<'a> DsmPkr<'a> {
     /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
     ///
     /// If the size of the New Public Key field cannot be determined because
-    /// some other fields contain reserved values, `None` is returned.
+    /// some other fields contain reserved values, or if the New Public Key
+    /// does not fit in the message, `None` is returned.
     pub fn merkle_tree_leaf(&self) -> Option<&[u8]> {
-        self.key_size().map(|s| &self.0[1032 / 8..1040 / 8 + s])
+        self.key_size()
+            .and_then(|s| self.0.get(1032 / 8..1040 / 8 + s))
     }
 
     /// Checks the contents of the padding field.
@@ -273,10 +295,12 @@ impl<'a> DsmPkr<'a> {
             // does not need to be checked.
             return true;
         }
+        let Some(leaf) = self.merkle_tree_leaf() else {
+            return false;
+        };
         let mut hash = Sha256::new();
         hash.update(merkle_tree_root);
-        // merkle_tree_leaf should not panic, because self.padding() is not None
-        hash.update(self.merkle_tree_leaf().unwrap());
+        hash.update(leaf);
         let hash = hash.finalize();
         let truncated = &hash[..padding.len()];
         truncated == padding
@@ -321,6 +345,7 @@ pub struct DsmKroot<'a>(
 /// message. See Table 8 in the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HashFunction {
     /// SHA-256 (HF = 0).
     Sha256,
@@ -336,6 +361,7 @@ pub enum HashFunction {
 /// message. See Table 9 in the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MacFunction {
     /// HMAC-SHA-256 (MF = 0).
     HmacSha256,
@@ -351,6 +377,8 @@ pub enum MacFunction {
 /// in the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EcdsaFunction {
     /// ECDSA P-256/SHA-256.
     P256Sha256,
@@ -485,13 +513,21 @@ impl<'a> DsmKroot<'a> {
     /// # Panics
     ///
     /// Panics if the key size field in the DSM-KROOT message contains a reserved
-    /// value.
+    /// value or if the message is too short to contain the KROOT.
     pub fn kroot(&self) -> &[u8] {
-        let size = self
-            .key_size()
-            .expect("attempted to extract kroot of DSM with reserved key size");
-        let size_bytes = size / 8;
-        &self.0[13..13 + size_bytes]
+        self.try_kroot()
+            .expect("attempted to extract kroot of DSM with reserved key size")
+    }
+
+    /// Returns a slice reference to the KROOT in the DSM-KROOT message, if it
+    /// can be extracted.
+    ///
+    /// This is similar to [`DsmKroot::kroot`], but it returns `None` instead of
+    /// panicking if the KS field contains a reserved value or if the message is
+    /// too short to contain the KROOT.
+    pub fn try_kroot(&self) -> Option<&[u8]> {
+        let size_bytes = self.key_size()? / 8;
+        self.0.get(13..13 + size_bytes)
     }
 
     /// Returns the ECDSA function used by this DSM-KROOT message.
@@ -504,27 +540,41 @@ impl<'a> DsmKroot<'a> {
     /// Panics if the ECDSA function cannot be guessed because the size of
     /// the signature is neither 512 bits (for P-256) nor 1056 bits (for P-521).
     pub fn ecdsa_function(&self) -> EcdsaFunction {
+        self.try_ecdsa_function().unwrap_or_else(|| {
+            panic!(
+                "failed to guess ECDSA function with DSM-KROOT total len = {}",
+                self.0.len()
+            )
+        })
+    }
+
+    /// Returns the ECDSA function used by this DSM-KROOT message, if it can be
+    /// determined.
+    ///
+    /// This is similar to [`DsmKroot::ecdsa_function`], but it returns `None`
+    /// instead of panicking if the KS field contains a reserved value or if
+    /// the size of the message does not match any ECDSA function. The methods
+    /// [`DsmKroot::kroot`], [`DsmKroot::digital_signature`] and
+    /// [`DsmKroot::padding`] do not panic if this returns `Some`, and their
+    /// fallible versions return `Some`.
+    pub fn try_ecdsa_function(&self) -> Option<EcdsaFunction> {
         // Although the ICD is not clear about this, we can guess the
         // ECDSA function in use from the size of the DSM-KROOT
         let total_len = self.0.len();
         let fixed_len = 13;
-        let kroot_len = self.kroot().len();
-        let remaining_len = total_len - fixed_len - kroot_len;
+        let kroot_len = self.key_size()? / 8;
+        let remaining_len = total_len.checked_sub(fixed_len + kroot_len)?;
         let b = 13; // block size
         let p256_bytes = 64; // 512 bits
         let p521_bytes = 132; // 1056 bits
         let p256_padding = (b - (kroot_len + p256_bytes) % b) % b;
         let p521_padding = (b - (kroot_len + p521_bytes) % b) % b;
         if remaining_len == p256_bytes + p256_padding {
-            EcdsaFunction::P256Sha256
+            Some(EcdsaFunction::P256Sha256)
         } else if remaining_len == p521_bytes + p521_padding {
-            EcdsaFunction::P521Sha512
+            Some(EcdsaFunction::P521Sha512)
         } else {
-            panic!(
-                "failed to guess ECDSA function with DSM-KROOT total len = {}\
-                    and kroot len = {}",
-                total_len, kroot_len
-            );
+            None
         }
     }
 
@@ -538,28 +588,62 @@ impl<'a> DsmKroot<'a> {
     /// Panics if the ECDSA function cannot be guessed because the size of
     /// the signature is neither 512 bits (for P-256) nor 1056 bits (for P-521).
     pub fn digital_signature(&self) -> &[u8] {
-        let size = match self.ecdsa_function() {
+        self.try_digital_signature().unwrap_or_else(|| {
+            panic!(
+                "failed to extract ECDSA signature with DSM-KROOT total len = {}",
+                self.0.len()
+            )
+        })
+    }
+
+    /// Returns a slice reference to the ECDSA signature in the DSM-KROOT
+    /// message, if the ECDSA function can be determined.
+    ///
+    /// This is similar to [`DsmKroot::digital_signature`], but it returns
+    /// `None` instead of panicking if [`DsmKroot::try_ecdsa_function`] returns
+    /// `None`.
+    pub fn try_digital_signature(&self) -> Option<&[u8]> {
+        let size = match self.try_ecdsa_function()? {
             EcdsaFunction::P256Sha256 => 64,
             EcdsaFunction::P521Sha512 => 132,
         };
-        let start = 13 + self.kroot().len();
-        &self.0[start..start + size]
+        let start = 13 + self.try_kroot()?.len();
+        self.0.get(start..start + size)
     }
 
     /// Gives the contents of the DSM-KROOT padding (P_DK) field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ECDSA function cannot be guessed because the size of
+    /// the signature is neither 512 bits (for P-256) nor 1056 bits (for P-521).
     pub fn padding(&self) -> &[u8] {
-        let start = 13 + self.kroot().len() + self.digital_signature().len();
-        &self.0[start..]
+        self.try_padding().unwrap_or_else(|| {
+            panic!(
+                "failed to extract padding with DSM-KROOT total len = {}",
+                self.0.len()
+            )
+        })
+    }
+
+    /// Gives the contents of the DSM-KROOT padding (P_DK) field, if the ECDSA
+    /// function can be determined.
+    ///
+    /// This is similar to [`DsmKroot::padding`], but it returns `None` instead
+    /// of panicking if [`DsmKroot::try_ecdsa_function`] returns `None`.
+    pub fn try_padding(&self) -> Option<&[u8]> {
+        let start = 13 + self.try_kroot()?.len() + self.try_digital_signature()?.len();
+        self.0.get(start..)
     }
 
     // message for digital signature verification
-    fn signature_message(&self, nma_header: NmaHeader<NotValidated>) -> ([u8; 209], usize) {
+    fn signature_message(&self, nma_header: NmaHeader<NotValidated>) -> Option<([u8; 209], usize)> {
         let mut m = [0; 209];
         m[0] = nma_header.data();
-        let end = 13 + self.kroot().len();
+        let end = 13 + self.try_kroot()?.len();
         // we skip the NB_DK and PKID fields in self.0
         m[1..end].copy_from_slice(&self.0[1..end]);
-        (m, end)
+        Some((m, end))
     }
 
     /// Checks the contents of the padding field.
@@ -568,15 +652,21 @@ impl<'a> DsmKroot<'a> {
     /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
     ///
     /// If the contents are correct, this returns `true`. Otherwise, this
-    /// returns `false`.
+    /// returns `false`. If the ECDSA function cannot be determined (see
+    /// [`DsmKroot::try_ecdsa_function`]), this returns `false`.
     pub fn check_padding(&self, nma_header: NmaHeader<NotValidated>) -> bool {
-        let (message, size) = self.signature_message(nma_header);
+        let (Some((message, size)), Some(signature), Some(padding)) = (
+            self.signature_message(nma_header),
+            self.try_digital_signature(),
+            self.try_padding(),
+        ) else {
+            return false;
+        };
         let message = &message[..size];
         let mut hash = Sha256::new();
         hash.update(message);
-        hash.update(self.digital_signature());
+        hash.update(signature);
         let hash = hash.finalize();
-        let padding = self.padding();
         let truncated = &hash[..padding.len()];
         truncated == padding
     }
@@ -597,7 +687,7 @@ impl<'a> DsmKroot<'a> {
         nma_header: NmaHeader<NotValidated>,
         pubkey: &p256::ecdsa::VerifyingKey,
     ) -> bool {
-        assert_eq!(self.ecdsa_function(), EcdsaFunction::P256Sha256);
+        assert_eq!(self.try_ecdsa_function(), Some(EcdsaFunction::P256Sha256));
         self.check_signature(nma_header, pubkey)
     }
 
@@ -618,7 +708,7 @@ impl<'a> DsmKroot<'a> {
         nma_header: NmaHeader<NotValidated>,
         pubkey: &p521::ecdsa::VerifyingKey,
     ) -> bool {
-        assert_eq!(self.ecdsa_function(), EcdsaFunction::P521Sha512);
+        assert_eq!(self.try_ecdsa_function(), Some(EcdsaFunction::P521Sha512));
         self.check_signature(nma_header, pubkey)
     }
 
@@ -630,21 +720,28 @@ impl<'a> DsmKroot<'a> {
     // The function can also be called with other type parameters, but it doesn't
     // make sense to do so.
     //
-    // # Panics
-    //
-    // The function panics if the ECDSA signature cannot be serialized, which
-    // can happen if the chosen type parameters do not match the signature
-    // length in the DSM-KROOT message.
+    // The function returns false if the ECDSA signature cannot be serialized,
+    // which happens if it contains invalid scalars or if the chosen type
+    // parameters do not match the signature length in the DSM-KROOT message.
     fn check_signature<VK, C>(&self, nma_header: NmaHeader<NotValidated>, pubkey: &VK) -> bool
     where
         VK: Verifier<Signature<C>>,
         C: PrimeCurve,
         SignatureSize<C>: crypto_common::generic_array::ArrayLength<u8>,
     {
-        let (message, size) = self.signature_message(nma_header);
+        let (Some((message, size)), Some(signature)) = (
+            self.signature_message(nma_header),
+            self.try_digital_signature(),
+        ) else {
+            return false;
+        };
         let message = &message[..size];
-        let signature = Signature::from_bytes(self.digital_signature().into())
-            .expect("error serializing ECDSA signature");
+        if signature.len() != SignatureSize::<C>::USIZE {
+            return false;
+        }
+        let Ok(signature) = Signature::from_bytes(signature.into()) else {
+            return false;
+        };
         pubkey.verify(message, &signature).is_ok()
     }
 }
@@ -663,9 +760,9 @@ impl fmt::Debug for DsmKroot<'_> {
             .field("kroot_wn", &self.kroot_wn())
             .field("kroot_towh", &self.kroot_towh())
             .field("alpha", &self.alpha())
-            .field("kroot", &self.kroot())
-            .field("digital_signature", &self.digital_signature())
-            .field("padding", &self.padding())
+            .field("kroot", &self.try_kroot())
+            .field("digital_signature", &self.try_digital_signature())
+            .field("padding", &self.try_padding())
             .finish()
     }
 }
@@ -700,7 +797,7 @@ impl<'a> Mack<'a, NotValidated> {
     /// the MACK message. The `key_size` in bits and `tag_size` in bits should
     /// be taken from the parameters of the current TESLA chain. The MACK
     /// message is marked as [`NotValidated`].
-    pub fn new(data: &MackMessage, key_size: usize, tag_size: usize) -> Mack<NotValidated> {
+    pub fn new(data: &MackMessage, key_size: usize, tag_size: usize) -> Mack<'_, NotValidated> {
         Mack {
             data: BitSlice::from_slice(data),
             key_size,
@@ -777,6 +874,7 @@ impl<'a, V> Mack<'a, V> {
 /// This enum lists the possible errors that can happen when a MACK message
 /// validation using [`Mack::validate`] is attempted.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MackValidationError {
     /// The MACSEQ field could not be verified.
     ///
@@ -859,24 +957,58 @@ impl<'a, V: Clone> Mack<'a, V> {
     ///
     /// The parameter `prna` should be the SVN of the satellite that transmitted
     /// this MACK message, and `gst_mack` corresponds to the GST at the start of
-    /// the subframe in which the MACK message was transmitted. The `maclt`
-    /// parameter indicates the active MAC Look-up Table id. It is used to
-    /// determine which tags are flexible.
+    /// the subframe in which the MACK message was transmitted.
     ///
     /// If the validation is successful, this returns a copy of `self` with the
     /// validation type parameter `V` set to `Validated`. Otherwise, an error
-    /// indicating which check was not satisfied is returned.
+    /// indicating which check was not satisfied is returned. Only the MAC
+    /// look-up tables built into the crate are used. See
+    /// [`Mack::validate_with_registry`] to use a [`MacLTRegistry`].
     pub fn validate(
         &self,
         key: &'_ Key<Validated>,
         prna: Svn,
         gst_mack: Gst,
     ) -> Result<Mack<'a, Validated>, MackValidationError> {
-        key.validate_macseq(self, prna, gst_mack)?;
+        self.validate_with_registry(key, prna, gst_mack, &MacLTRegistry::<0>::new())
+    }
+
+    /// Try to validate the MACK message using a MAC look-up table registry.
+    ///
+    /// This works as [`Mack::validate`], but the MAC look-up table of the chain
+    /// is looked up in `registry`, so tables added at runtime can be used.
+    pub fn validate_with_registry<const N: usize>(
+        &self,
+        key: &'_ Key<Validated>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+    ) -> Result<Mack<'a, Validated>, MackValidationError> {
+        self.validate_with_trace(&MacKey::new(*key), prna, gst_mack, registry, |_| {})
+    }
+
+    // Validates the MACK message as in validate_with_registry, calling `trace`
+    // with the trace of the MACSEQ computation.
+    pub(crate) fn validate_with_trace<const N: usize>(
+        &self,
+        key: &MacKey,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+        trace: impl FnOnce(&MacseqTrace<'_>),
+    ) -> Result<Mack<'a, Validated>, MackValidationError> {
+        key.validate_macseq_with_trace(self, prna, gst_mack, registry, trace)?;
 
         for j in 1..self.num_tags() {
             let tag = self.tag_and_info(j);
-            if let Err(e) = key.chain().validate_adkd(j, tag, prna, gst_mack) {
+            if let Err(e) = key.key().chain().validate_adkd_with_registry(
+                j,
+                self.num_tags(),
+                tag,
+                prna,
+                gst_mack,
+                registry,
+            ) {
                 return Err(MackValidationError::WrongAdkd {
                     tag_index: j,
                     error: e,
@@ -923,6 +1055,8 @@ pub struct TagAndInfo<'a, V> {
 /// described in Table 12 in the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Prnd {
     /// Galileo SVID (PRND = 1 - 36).
     GalileoSvid(
@@ -952,6 +1086,8 @@ impl TryFrom<Prnd> for u8 {
 /// as defined in Table 14 in the
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Adkd {
     /// Galileo I/NAV ephemeris, clock and status (ADKD = 0).
     InavCed,
@@ -1017,6 +1153,271 @@ impl<V: fmt::Debug> fmt::Debug for TagAndInfo<'_, V> {
     }
 }
 
+/// Error copying a DSM message into an owned message.
+///
+/// The copy can only fail if the message is longer than the maximum DSM size
+/// of 16 blocks.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DsmTooLongError;
+
+impl fmt::Display for DsmTooLongError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "DSM message longer than 16 blocks".fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DsmTooLongError {}
+
+// Copies a DSM message into the buffer of an owned message.
+fn copy_dsm(dsm: &[u8]) -> Result<[u8; MAX_DSM_BYTES], DsmTooLongError> {
+    let mut data = [0; MAX_DSM_BYTES];
+    data.get_mut(..dsm.len())
+        .ok_or(DsmTooLongError)?
+        .copy_from_slice(dsm);
+    Ok(data)
+}
+
+/// Owned DSM-KROOT message.
+///
+/// This is an owned counterpart of [`DsmKroot`], which stores a copy of the
+/// DSM-KROOT message data. The fields of the message can be accessed through
+/// the [`DsmKroot`] returned by [`OwnedDsmKroot::dsm_kroot`].
+///
+/// With the `serde` feature, this is serialized as a byte string containing the
+/// message. Deserialization checks that the length of the message matches its
+/// NB_DK field and that its key size is not reserved.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Bytes<MAX_DSM_BYTES>", into = "Bytes<MAX_DSM_BYTES>")
+)]
+pub struct OwnedDsmKroot {
+    data: [u8; MAX_DSM_BYTES],
+    len: usize,
+}
+
+impl OwnedDsmKroot {
+    /// Returns a [`DsmKroot`] that refers to the data of this message.
+    pub fn dsm_kroot(&self) -> DsmKroot<'_> {
+        DsmKroot(&self.data[..self.len])
+    }
+}
+
+/// Copies a [`DsmKroot`] into an [`OwnedDsmKroot`].
+///
+/// An error is returned if the DSM-KROOT is longer than the maximum DSM size
+/// of 16 blocks.
+impl TryFrom<DsmKroot<'_>> for OwnedDsmKroot {
+    type Error = DsmTooLongError;
+
+    fn try_from(dsm: DsmKroot<'_>) -> Result<OwnedDsmKroot, DsmTooLongError> {
+        Ok(OwnedDsmKroot {
+            data: copy_dsm(dsm.0)?,
+            len: dsm.0.len(),
+        })
+    }
+}
+
+impl fmt::Debug for OwnedDsmKroot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.dsm_kroot().fmt(f)
+    }
+}
+
+/// Owned DSM-PKR message.
+///
+/// This is an owned counterpart of [`DsmPkr`], which stores a copy of the
+/// DSM-PKR message data. The fields of the message can be accessed through the
+/// [`DsmPkr`] returned by [`OwnedDsmPkr::dsm_pkr`].
+///
+/// With the `serde` feature, this is serialized as a byte string containing the
+/// message. Deserialization checks that the length of the message matches its
+/// NB_DP field.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Bytes<MAX_DSM_BYTES>", into = "Bytes<MAX_DSM_BYTES>")
+)]
+pub struct OwnedDsmPkr {
+    data: [u8; MAX_DSM_BYTES],
+    len: usize,
+}
+
+impl OwnedDsmPkr {
+    /// Returns a [`DsmPkr`] that refers to the data of this message.
+    pub fn dsm_pkr(&self) -> DsmPkr<'_> {
+        DsmPkr(&self.data[..self.len])
+    }
+}
+
+/// Copies a [`DsmPkr`] into an [`OwnedDsmPkr`].
+///
+/// An error is returned if the DSM-PKR is longer than the maximum DSM size of
+/// 16 blocks.
+impl TryFrom<DsmPkr<'_>> for OwnedDsmPkr {
+    type Error = DsmTooLongError;
+
+    fn try_from(dsm: DsmPkr<'_>) -> Result<OwnedDsmPkr, DsmTooLongError> {
+        Ok(OwnedDsmPkr {
+            data: copy_dsm(dsm.0)?,
+            len: dsm.0.len(),
+        })
+    }
+}
+
+impl fmt::Debug for OwnedDsmPkr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.dsm_pkr().fmt(f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<OwnedDsmKroot> for Bytes<MAX_DSM_BYTES> {
+    fn from(dsm: OwnedDsmKroot) -> Bytes<MAX_DSM_BYTES> {
+        Bytes::new(&dsm.data[..dsm.len])
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Bytes<MAX_DSM_BYTES>> for OwnedDsmKroot {
+    type Error = &'static str;
+
+    fn try_from(bytes: Bytes<MAX_DSM_BYTES>) -> Result<OwnedDsmKroot, &'static str> {
+        let dsm = DsmKroot(bytes.as_slice());
+        if dsm.0.is_empty() || dsm.number_of_blocks() != Some(dsm.0.len() / DSM_BLOCK_BYTES) {
+            return Err("DSM-KROOT length does not match NB_DK");
+        }
+        if dsm.0.len() % DSM_BLOCK_BYTES != 0 {
+            return Err("DSM-KROOT length is not a multiple of the block size");
+        }
+        if dsm.key_size().is_none() {
+            return Err("DSM-KROOT key size is reserved");
+        }
+        dsm.try_into().map_err(|_| "DSM-KROOT too long")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<OwnedDsmPkr> for Bytes<MAX_DSM_BYTES> {
+    fn from(dsm: OwnedDsmPkr) -> Bytes<MAX_DSM_BYTES> {
+        Bytes::new(&dsm.data[..dsm.len])
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Bytes<MAX_DSM_BYTES>> for OwnedDsmPkr {
+    type Error = &'static str;
+
+    fn try_from(bytes: Bytes<MAX_DSM_BYTES>) -> Result<OwnedDsmPkr, &'static str> {
+        let dsm = DsmPkr(bytes.as_slice());
+        if dsm.0.is_empty() || dsm.number_of_blocks() != Some(dsm.0.len() / DSM_BLOCK_BYTES) {
+            return Err("DSM-PKR length does not match NB_DP");
+        }
+        if dsm.0.len() % DSM_BLOCK_BYTES != 0 {
+            return Err("DSM-PKR length is not a multiple of the block size");
+        }
+        dsm.try_into().map_err(|_| "DSM-PKR too long")
+    }
+}
+
+/// Owned MACK message.
+///
+/// This is an owned counterpart of [`Mack`], which stores a copy of the MACK
+/// message data together with the key and tag sizes. The fields of the message
+/// can be accessed through the [`Mack`] returned by [`OwnedMack::mack`].
+///
+/// The validation status of the `Mack` is not kept, since a `Validated` MACK
+/// message could otherwise be forged by deserialization. The `Mack` obtained
+/// from an `OwnedMack` is always [`NotValidated`].
+///
+/// With the `serde` feature, this is serialized as a struct containing the
+/// MACK message as a byte string and the key and tag sizes. Deserialization
+/// checks that the key and tag sizes are valid.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SerdeMack", into = "SerdeMack")
+)]
+pub struct OwnedMack {
+    data: MackMessage,
+    key_size: usize,
+    tag_size: usize,
+}
+
+impl OwnedMack {
+    /// Returns a [`Mack`] that refers to the data of this message.
+    pub fn mack(&self) -> Mack<'_, NotValidated> {
+        Mack::new(&self.data, self.key_size, self.tag_size)
+    }
+}
+
+impl<V> From<Mack<'_, V>> for OwnedMack {
+    fn from(mack: Mack<'_, V>) -> OwnedMack {
+        let mut data = [0; MACK_MESSAGE_BYTES];
+        BitSlice::from_slice_mut(&mut data).copy_from_bitslice(mack.data);
+        OwnedMack {
+            data,
+            key_size: mack.key_size,
+            tag_size: mack.tag_size,
+        }
+    }
+}
+
+impl fmt::Debug for OwnedMack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.mack().fmt(f)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Mack")]
+struct SerdeMack {
+    data: Bytes<MACK_MESSAGE_BYTES>,
+    key_size: usize,
+    tag_size: usize,
+}
+
+#[cfg(feature = "serde")]
+impl From<OwnedMack> for SerdeMack {
+    fn from(mack: OwnedMack) -> SerdeMack {
+        SerdeMack {
+            data: Bytes::new(&mack.data),
+            key_size: mack.key_size,
+            tag_size: mack.tag_size,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeMack> for OwnedMack {
+    type Error = &'static str;
+
+    fn try_from(mack: SerdeMack) -> Result<OwnedMack, &'static str> {
+        let data = mack
+            .data
+            .as_slice()
+            .try_into()
+            .map_err(|_| "wrong MACK message length")?;
+        if ![96, 104, 112, 120, 128, 160, 192, 224, 256].contains(&mack.key_size) {
+            return Err("invalid TESLA key size");
+        }
+        if ![20, 24, 28, 32, 40].contains(&mack.tag_size) {
+            return Err("invalid tag size");
+        }
+        Ok(OwnedMack {
+            data,
+            key_size: mack.key_size,
+            tag_size: mack.tag_size,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1195,6 +1596,62 @@ mod test {
         assert!(dsm.check_padding(nma_header));
     }
 
+    #[test]
+    fn malformed_dsm_pkr() {
+        // A DSM-PKR with 13 blocks claiming to carry a P-521 key, which does
+        // not fit in the message
+        let mut dsm = [0; 13 * 13];
+        dsm[0] = 7 << 4; // NB_DP = 13 blocks
+        dsm[129] = 3 << 4; // NPKT = P-521
+        let dsm = DsmPkr(&dsm);
+        assert_eq!(
+            dsm.new_public_key_type(),
+            NewPublicKeyType::EcdsaKey(EcdsaFunction::P521Sha512)
+        );
+        assert_eq!(dsm.new_public_key(), None);
+        assert_eq!(dsm.padding(), None);
+        assert_eq!(dsm.merkle_tree_leaf(), None);
+        assert!(!dsm.check_padding(&[0; 32]));
+    }
+
+    #[test]
+    fn malformed_dsm_kroot() {
+        // DSM-KROOT broadcast on 2022-03-07 9:00 UTC
+        let dsm = hex!(
+            "
+            22 50 49 21 04 98 21 25 d3 96 4d a3 a2 84 1e 1d
+            e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3 79 58 de
+            28 51 97 a2 63 53 f1 a4 c6 6d 7e 3d 29 18 53 ba
+            5a 13 c9 c3 48 4a 26 77 70 11 2a 13 38 3e a5 2d
+            3a 01 9d 5b 6e 1d d1 87 b9 45 3c df 06 ca 7f 34
+            ea 14 97 52 5a af 18 f1 f9 f1 fc cb 12 29 89 77
+            35 c0 21 b0 41 73 93 b5"
+        );
+        let nma_header = NmaHeader::new(0x52);
+        // Truncated to 7 blocks
+        let truncated = DsmKroot(&dsm[..7 * 13]);
+        assert_eq!(truncated.try_ecdsa_function(), None);
+        assert!(truncated.try_kroot().is_some());
+        assert_eq!(truncated.try_digital_signature(), None);
+        assert_eq!(truncated.try_padding(), None);
+        assert!(!truncated.check_padding(nma_header));
+        let _ = format!("{truncated:?}");
+        // Truncated to a single block, which does not even hold the KROOT
+        let truncated = DsmKroot(&dsm[..13]);
+        assert_eq!(truncated.try_ecdsa_function(), None);
+        assert_eq!(truncated.try_kroot(), None);
+        assert!(!truncated.check_padding(nma_header));
+        let _ = format!("{truncated:?}");
+        // Reserved key size
+        let mut reserved = dsm;
+        reserved[2] |= 0xf0;
+        let reserved = DsmKroot(&reserved);
+        assert_eq!(reserved.key_size(), None);
+        assert_eq!(reserved.try_kroot(), None);
+        assert_eq!(reserved.try_ecdsa_function(), None);
+        assert!(!reserved.check_padding(nma_header));
+    }
+
     #[test]
     fn mack() {
         // MACK broadcast on 2022-03-07 9:00 UTC
@@ -1248,4 +1705,61 @@ mod test {
             BitSlice::from_slice(&hex!("42 b4 19 da 6a da 1c 0a 3d 6f 56 a5 e5 dc 59 a7"))
         );
     }
+
+    #[test]
+    fn owned_dsm_too_long() {
+        let dsm = [0; MAX_DSM_BYTES + 1];
+        assert_eq!(
+            OwnedDsmKroot::try_from(DsmKroot(&dsm)),
+            Err(DsmTooLongError)
+        );
+        assert_eq!(OwnedDsmPkr::try_from(DsmPkr(&dsm)), Err(DsmTooLongError));
+        let owned = OwnedDsmPkr::try_from(DsmPkr(&dsm[..MAX_DSM_BYTES])).unwrap();
+        assert_eq!(owned.dsm_pkr().0, &dsm[..MAX_DSM_BYTES]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn owned_dsm_kroot_serde() {
+        // DSM-KROOT broadcast on 2022-03-07 9:00 UTC
+        let dsm = hex!(
+            "
+            22 50 49 21 04 98 21 25 d3 96 4d a3 a2 84 1e 1d
+            e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3 79 58 de
+            28 51 97 a2 63 53 f1 a4 c6 6d 7e 3d 29 18 53 ba
+            5a 13 c9 c3 48 4a 26 77 70 11 2a 13 38 3e a5 2d
+            3a 01 9d 5b 6e 1d d1 87 b9 45 3c df 06 ca 7f 34
+            ea 14 97 52 5a af 18 f1 f9 f1 fc cb 12 29 89 77
+            35 c0 21 b0 41 73 93 b5"
+        );
+        let owned = OwnedDsmKroot::try_from(DsmKroot(&dsm)).unwrap();
+        let json = serde_json::to_string(&owned).unwrap();
+        let decoded: OwnedDsmKroot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.dsm_kroot().0, &dsm[..]);
+        // A truncated DSM-KROOT does not match its number of blocks
+        let json = serde_json::to_string(&dsm[..91]).unwrap();
+        assert!(serde_json::from_str::<OwnedDsmKroot>(&json).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn owned_mack_serde() {
+        // MACK broadcast on 2022-03-07 9:00 UTC
+        let mack = hex!(
+            "
+            11 55 d3 71 f2 1f 30 a8 e4 ec e0 c0 1b 07 6d 17
+            7d 64 03 12 05 d4 02 7e 77 13 15 c0 4c ca 1c 16
+            99 1a 05 48 91 07 a7 f7 0e c5 42 b4 19 da 6a da
+            1c 0a 3d 6f 56 a5 e5 dc 59 a7 00 00"
+        );
+        let owned = OwnedMack::from(Mack::new(&mack, 128, 40));
+        let json = serde_json::to_string(&owned).unwrap();
+        let decoded: OwnedMack = serde_json::from_str(&json).unwrap();
+        let decoded = decoded.mack();
+        assert_eq!(decoded.key_size(), 128);
+        assert_eq!(decoded.tag_size(), 40);
+        assert_eq!(decoded.macseq(), 0x1f3);
+        let json = json.replace("128", "100");
+        assert!(serde_json::from_str::<OwnedMack>(&json).is_err());
+    }
 }
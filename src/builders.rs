@@ -0,0 +1,889 @@
+//! Message builders.
+//!
+//! This module contains builders that serialize the messages used by OSNMA
+//! from the values of their fields. They are the counterpart of the parsers in
+//! [bitfields](crate::bitfields), and they are useful to craft messages in
+//! tests and tools, such as signal generators.
+//!
+//! The builders are plain structs whose public fields give the values of the
+//! message fields. The `build` methods check that the values fit in the
+//! message fields and return the message, which can then be accessed with the
+//! corresponding parser.
+
+use crate::bitfields::{
+    Adkd, ChainAndPubkeyStatus, DsmKroot, DsmPkr, EcdsaFunction, HashFunction, MacFunction,
+    NewPublicKeyType, NmaHeader, NmaStatus, OwnedDsmKroot, OwnedDsmPkr, Prnd,
+};
+use crate::dsm::MAX_DSM_BYTES;
+use crate::types::{
+    BitSlice, MackMessage, MerkleTreeNode, Towh, DSM_BLOCK_BYTES, MACK_MESSAGE_BYTES,
+    MERKLE_TREE_NODE_BYTES,
+};
+use crate::validation::NotValidated;
+use crate::Wn;
+use bitvec::prelude::*;
+use core::fmt;
+use sha2::{Digest, Sha256};
+
+/// Errors produced when building a message.
+///
+/// This gives the errors that can happen when the field values given to a
+/// builder cannot be serialized into a message.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BuildError {
+    /// The value of a field does not fit in the number of bits of the field.
+    FieldOutOfRange {
+        /// Name of the field.
+        field: &'static str,
+    },
+    /// A field has been given a reserved value.
+    ReservedValue {
+        /// Name of the field.
+        field: &'static str,
+    },
+    /// The length of a field is not valid.
+    WrongLength {
+        /// Name of the field.
+        field: &'static str,
+        /// Expected length (in bits for MACK fields and in bytes for DSM
+        /// fields).
+        expected: usize,
+        /// Actual length.
+        actual: usize,
+    },
+    /// The TESLA key size is not one of the sizes listed in the ICD.
+    InvalidKeySize,
+    /// The tag size is not one of the sizes listed in the ICD.
+    InvalidTagSize,
+    /// The message does not fit in a number of blocks allowed for its DSM
+    /// type.
+    InvalidNumberOfBlocks,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::FieldOutOfRange { field } => {
+                write!(f, "value of field {field} is out of range")
+            }
+            BuildError::ReservedValue { field } => write!(f, "reserved value in field {field}"),
+            BuildError::WrongLength {
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "wrong length of field {field} (expected {expected}, got {actual})"
+            ),
+            BuildError::InvalidKeySize => "invalid TESLA key size".fmt(f),
+            BuildError::InvalidTagSize => "invalid tag size".fmt(f),
+            BuildError::InvalidNumberOfBlocks => "invalid number of DSM blocks".fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildError {}
+
+// Checks that a value fits in a field of `bits` bits.
+pub(crate) fn check_range(value: u64, bits: usize, field: &'static str) -> Result<(), BuildError> {
+    if value >> bits == 0 {
+        Ok(())
+    } else {
+        Err(BuildError::FieldOutOfRange { field })
+    }
+}
+
+// Checks the length of a field.
+fn check_length(actual: usize, expected: usize, field: &'static str) -> Result<(), BuildError> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(BuildError::WrongLength {
+            field,
+            expected,
+            actual,
+        })
+    }
+}
+
+/// Builder of NMA headers.
+///
+/// The fields correspond to those of the [`NmaHeader`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NmaHeaderBuilder {
+    /// NMA status (NMAS field).
+    pub nma_status: NmaStatus,
+    /// Chain ID (CID field). This is a 2-bit value.
+    pub chain_id: u8,
+    /// Chain and public key status (CPKS field).
+    pub chain_and_pubkey_status: ChainAndPubkeyStatus,
+}
+
+impl NmaHeaderBuilder {
+    /// Builds the NMA header.
+    ///
+    /// The reserved bit of the header is set to zero.
+    pub fn build(&self) -> Result<NmaHeader<NotValidated>, BuildError> {
+        check_range(self.chain_id.into(), 2, "CID")?;
+        let nmas = match self.nma_status {
+            NmaStatus::Reserved => 0,
+            NmaStatus::Test => 1,
+            NmaStatus::Operational => 2,
+            NmaStatus::DontUse => 3,
+        };
+        let cpks = match self.chain_and_pubkey_status {
+            ChainAndPubkeyStatus::Reserved => 0,
+            ChainAndPubkeyStatus::Nominal => 1,
+            ChainAndPubkeyStatus::EndOfChain => 2,
+            ChainAndPubkeyStatus::ChainRevoked => 3,
+            ChainAndPubkeyStatus::NewPublicKey => 4,
+            ChainAndPubkeyStatus::PublicKeyRevoked => 5,
+            ChainAndPubkeyStatus::NewMerkleTree => 6,
+            ChainAndPubkeyStatus::AlertMessage => 7,
+        };
+        Ok(NmaHeader::new(
+            (nmas << 6) | (self.chain_id << 4) | (cpks << 1),
+        ))
+    }
+}
+
+/// Builder of DSM-KROOT messages.
+///
+/// The fields correspond to those of the
+/// [`DsmKroot`], except for the NB_DK field, which
+/// is computed from the sizes of the KROOT and the digital signature, and the
+/// padding, which is computed as indicated in Eq. 7 in the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+/// The reserved fields are set to zero.
+///
+/// Since the digital signature covers the other fields and the NMA header, the
+/// message is built in two steps: the message to sign is obtained with
+/// [`DsmKrootBuilder::signature_message`], and then the message is built with
+/// [`DsmKrootBuilder::build`] by giving the signature.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DsmKrootBuilder<'a> {
+    /// Public key ID (PKID field). This is a 4-bit value.
+    pub public_key_id: u8,
+    /// KROOT chain ID (CIDKR field). This is a 2-bit value.
+    pub kroot_chain_id: u8,
+    /// Hash function (HF field).
+    pub hash_function: HashFunction,
+    /// MAC function (MF field).
+    pub mac_function: MacFunction,
+    /// TESLA key size in bits (KS field).
+    pub key_size: usize,
+    /// Tag size in bits (TS field).
+    pub tag_size: usize,
+    /// MAC look-up table ID (MACLT field).
+    pub mac_lookup_table: u8,
+    /// KROOT week number (WNK field). This is a 12-bit value.
+    pub kroot_wn: Wn,
+    /// KROOT time of week in hours (TOWHK field).
+    pub kroot_towh: Towh,
+    /// Random pattern alpha. This is a 48-bit value.
+    pub alpha: u64,
+    /// TESLA root key (KROOT field). Its length must match the key size.
+    pub kroot: &'a [u8],
+}
+
+/// Message signed by the digital signature of a DSM-KROOT.
+///
+/// This is returned by [`DsmKrootBuilder::signature_message`]. The contents of
+/// the message can be obtained with [`AsRef`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SignatureMessage {
+    data: [u8; Self::MAX_BYTES],
+    len: usize,
+}
+
+impl SignatureMessage {
+    // NMA header, fixed fields and a KROOT of up to 256 bits
+    const MAX_BYTES: usize = 13 + 256 / 8;
+}
+
+impl AsRef<[u8]> for SignatureMessage {
+    fn as_ref(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl DsmKrootBuilder<'_> {
+    // Serializes the fields that precede the digital signature.
+    fn write_fields(&self, dsm: &mut [u8]) -> Result<usize, BuildError> {
+        check_range(self.public_key_id.into(), 4, "PKID")?;
+        check_range(self.kroot_chain_id.into(), 2, "CIDKR")?;
+        check_range(self.kroot_wn.into(), 12, "WNK")?;
+        check_range(self.alpha, 48, "alpha")?;
+        let hf: u8 = match self.hash_function {
+            HashFunction::Sha256 => 0,
+            HashFunction::Sha3_256 => 2,
+            HashFunction::Reserved => return Err(BuildError::ReservedValue { field: "HF" }),
+        };
+        let mf: u8 = match self.mac_function {
+            MacFunction::HmacSha256 => 0,
+            MacFunction::CmacAes => 1,
+            MacFunction::Reserved => return Err(BuildError::ReservedValue { field: "MF" }),
+        };
+        let ks = key_size_field(self.key_size)?;
+        let ts = tag_size_field(self.tag_size)?;
+        check_length(self.kroot.len(), self.key_size / 8, "KROOT")?;
+
+        let bits = dsm[..13].view_bits_mut::<Msb0>();
+        bits[4..8].store_be(self.public_key_id);
+        bits[8..10].store_be(self.kroot_chain_id);
+        bits[10..12].fill(false);
+        bits[12..14].store_be(hf);
+        bits[14..16].store_be(mf);
+        bits[16..20].store_be(ks);
+        bits[20..24].store_be(ts);
+        bits[24..32].store_be(self.mac_lookup_table);
+        bits[32..36].fill(false);
+        bits[36..48].store_be(self.kroot_wn);
+        bits[48..56].store_be(self.kroot_towh);
+        bits[56..104].store_be(self.alpha);
+        let end = 13 + self.kroot.len();
+        dsm[13..end].copy_from_slice(self.kroot);
+        Ok(end)
+    }
+
+    /// Gives the message signed by the digital signature.
+    ///
+    /// This is the message defined in Section 6.3 of the
+    /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf),
+    /// which contains the NMA header `nma_header` transmitted with the
+    /// DSM-KROOT, the fields of the DSM-KROOT except for NB_DK and PKID, and
+    /// the KROOT.
+    pub fn signature_message(
+        &self,
+        nma_header: NmaHeader<NotValidated>,
+    ) -> Result<SignatureMessage, BuildError> {
+        let mut data = [0; SignatureMessage::MAX_BYTES];
+        let len = self.write_fields(&mut data)?;
+        // The NMA header replaces the NB_DK and PKID fields
+        data[0] = nma_header.data();
+        Ok(SignatureMessage { data, len })
+    }
+
+    /// Builds the DSM-KROOT message.
+    ///
+    /// The `signature` is the digital signature of the message given by
+    /// [`DsmKrootBuilder::signature_message`], in the format used in the DS
+    /// field. Its length determines the ECDSA function. The `nma_header` is
+    /// used to compute the padding, so it must be the same that was used to
+    /// compute the signature.
+    pub fn build(
+        &self,
+        nma_header: NmaHeader<NotValidated>,
+        signature: &[u8],
+    ) -> Result<OwnedDsmKroot, BuildError> {
+        // The length of the signature determines the ECDSA function
+        if !matches!(signature.len(), 64 | 132) {
+            return Err(BuildError::WrongLength {
+                field: "DS",
+                expected: 64,
+                actual: signature.len(),
+            });
+        }
+        let mut dsm = [0; MAX_DSM_BYTES];
+        let kroot_end = self.write_fields(&mut dsm)?;
+        let signature_end = kroot_end + signature.len();
+        let num_blocks = num_blocks(signature_end);
+        if !(7..=14).contains(&num_blocks) {
+            return Err(BuildError::InvalidNumberOfBlocks);
+        }
+        dsm[..1].view_bits_mut::<Msb0>()[..4].store_be(u8::try_from(num_blocks - 6).unwrap());
+        dsm[kroot_end..signature_end].copy_from_slice(signature);
+
+        let message = self.signature_message(nma_header)?;
+        let mut hash = Sha256::new();
+        hash.update(message.as_ref());
+        hash.update(signature);
+        let hash = hash.finalize();
+        let len = num_blocks * DSM_BLOCK_BYTES;
+        dsm[signature_end..len].copy_from_slice(&hash[..len - signature_end]);
+        OwnedDsmKroot::try_from(DsmKroot(&dsm[..len]))
+            .map_err(|_| BuildError::InvalidNumberOfBlocks)
+    }
+}
+
+/// Builder of DSM-PKR messages.
+///
+/// The fields correspond to those of the [`DsmPkr`],
+/// except for the NB_DP field, which is computed from the size of the New
+/// Public Key field, and the padding, which is computed as indicated in Eq. 4
+/// in the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DsmPkrBuilder<'a> {
+    /// Message ID (MID field). This is a 4-bit value.
+    pub message_id: u8,
+    /// Intermediate tree nodes (ITN field).
+    pub intermediate_tree_nodes: [MerkleTreeNode; 4],
+    /// New public key type (NPKT field).
+    pub new_public_key_type: NewPublicKeyType,
+    /// New public key ID (NPKID field). This is a 4-bit value.
+    pub new_public_key_id: u8,
+    /// New public key field.
+    ///
+    /// For ECDSA keys, this contains the key in compressed SEC1 format, and
+    /// its length must match the key type. For OSNMA Alert Messages, the
+    /// length must be such that the message fills an integer number of
+    /// blocks, since there is no padding.
+    pub new_public_key: &'a [u8],
+}
+
+impl DsmPkrBuilder<'_> {
+    /// Builds the DSM-PKR message.
+    ///
+    /// The Merkle tree root `merkle_tree_root` is used to compute the padding.
+    pub fn build(&self, merkle_tree_root: &MerkleTreeNode) -> Result<OwnedDsmPkr, BuildError> {
+        check_range(self.message_id.into(), 4, "MID")?;
+        check_range(self.new_public_key_id.into(), 4, "NPKID")?;
+        const FIXED_BYTES: usize = 1 + 4 * MERKLE_TREE_NODE_BYTES + 1;
+        let npkt = new_public_key_type_field(self.new_public_key_type, self.new_public_key)?;
+        if self.new_public_key_type == NewPublicKeyType::OsnmaAlertMessage {
+            let len = FIXED_BYTES + self.new_public_key.len();
+            if len % DSM_BLOCK_BYTES != 0 {
+                return Err(BuildError::WrongLength {
+                    field: "NPK",
+                    expected: num_blocks(len) * DSM_BLOCK_BYTES - FIXED_BYTES,
+                    actual: self.new_public_key.len(),
+                });
+            }
+        }
+        let key_end = FIXED_BYTES + self.new_public_key.len();
+        let num_blocks = num_blocks(key_end);
+        if !(13..=16).contains(&num_blocks) {
+            return Err(BuildError::InvalidNumberOfBlocks);
+        }
+
+        let mut dsm = [0; MAX_DSM_BYTES];
+        dsm[0] = (u8::try_from(num_blocks - 6).unwrap() << 4) | self.message_id;
+        for (j, node) in self.intermediate_tree_nodes.iter().enumerate() {
+            let start = 1 + j * MERKLE_TREE_NODE_BYTES;
+            dsm[start..start + MERKLE_TREE_NODE_BYTES].copy_from_slice(node);
+        }
+        dsm[FIXED_BYTES - 1] = (npkt << 4) | self.new_public_key_id;
+        dsm[FIXED_BYTES..key_end].copy_from_slice(self.new_public_key);
+        let len = num_blocks * DSM_BLOCK_BYTES;
+        if len > key_end {
+            let mut hash = Sha256::new();
+            hash.update(merkle_tree_root);
+            // Merkle tree leaf
+            hash.update(&dsm[FIXED_BYTES - 1..key_end]);
+            let hash = hash.finalize();
+            dsm[key_end..len].copy_from_slice(&hash[..len - key_end]);
+        }
+        OwnedDsmPkr::try_from(DsmPkr(&dsm[..len])).map_err(|_| BuildError::InvalidNumberOfBlocks)
+    }
+}
+
+/// Builder of MACK messages.
+///
+/// The fields correspond to those of the [`Mack`](crate::bitfields::Mack). The
+/// tags, the Tag-Info sections and the key are placed according to the key and
+/// tag sizes, and the bits after the key are set to zero.
+///
+/// The MACSEQ can be computed with
+/// [`Key::compute_macseq`](crate::tesla::Key::compute_macseq) on a message
+/// built with any MACSEQ value, since the MACSEQ does not cover itself.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MackBuilder<'a> {
+    /// TESLA key size in bits.
+    pub key_size: usize,
+    /// Tag size in bits.
+    pub tag_size: usize,
+    /// Tag0. Its length must be equal to the tag size.
+    pub tag0: &'a BitSlice,
+    /// MACSEQ field. This is a 12-bit value.
+    pub macseq: u16,
+    /// COP field of the MACK header. This is a 4-bit value.
+    pub cop: u8,
+    /// Tags and Tag-Info sections, starting with the tag with index 1.
+    ///
+    /// The number of elements must be one less than the number of tags that
+    /// fit in the MACK message, since the tag0 is given separately.
+    pub tags: &'a [TagAndInfoBuilder<'a>],
+    /// TESLA key. Its length must be equal to the key size.
+    pub key: &'a BitSlice,
+}
+
+/// Builder of a tag and its Tag-Info section.
+///
+/// This is used in the [`MackBuilder`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TagAndInfoBuilder<'a> {
+    /// Tag. Its length must be equal to the tag size.
+    pub tag: &'a BitSlice,
+    /// PRND field.
+    pub prnd: Prnd,
+    /// ADKD field.
+    pub adkd: Adkd,
+    /// COP field. This is a 4-bit value.
+    pub cop: u8,
+}
+
+impl MackBuilder<'_> {
+    /// Builds the MACK message.
+    pub fn build(&self) -> Result<MackMessage, BuildError> {
+        key_size_field(self.key_size)?;
+        tag_size_field(self.tag_size)?;
+        check_range(self.macseq.into(), 12, "MACSEQ")?;
+        check_range(self.cop.into(), 4, "COP")?;
+        check_length(self.tag0.len(), self.tag_size, "tag0")?;
+        check_length(self.key.len(), self.key_size, "key")?;
+        let tag_and_info_size = self.tag_size + 16;
+        let num_tags = (8 * MACK_MESSAGE_BYTES - self.key_size) / tag_and_info_size;
+        check_length(self.tags.len(), num_tags - 1, "tags")?;
+
+        let mut mack = [0; MACK_MESSAGE_BYTES];
+        let bits = BitSlice::from_slice_mut(&mut mack);
+        bits[..self.tag_size].copy_from_bitslice(self.tag0);
+        bits[self.tag_size..self.tag_size + 12].store_be(self.macseq);
+        bits[self.tag_size + 12..tag_and_info_size].store_be(self.cop);
+        for (j, tag) in self.tags.iter().enumerate() {
+            check_length(tag.tag.len(), self.tag_size, "tag")?;
+            check_range(tag.cop.into(), 4, "COP")?;
+            let prnd =
+                u8::try_from(tag.prnd).map_err(|_| BuildError::ReservedValue { field: "PRND" })?;
+            let adkd: u8 = match tag.adkd {
+                Adkd::InavCed => 0,
+                Adkd::InavTiming => 4,
+                Adkd::SlowMac => 12,
+                Adkd::Reserved => return Err(BuildError::ReservedValue { field: "ADKD" }),
+            };
+            let dest = &mut bits[(j + 1) * tag_and_info_size..(j + 2) * tag_and_info_size];
+            dest[..self.tag_size].copy_from_bitslice(tag.tag);
+            dest[self.tag_size..self.tag_size + 8].store_be(prnd);
+            dest[self.tag_size + 8..self.tag_size + 12].store_be(adkd);
+            dest[self.tag_size + 12..].store_be(tag.cop);
+        }
+        let key_start = num_tags * tag_and_info_size;
+        bits[key_start..key_start + self.key_size].copy_from_bitslice(self.key);
+        Ok(mack)
+    }
+}
+
+// Value of the NPKT field for a new public key type. The length of the New
+// Public Key field is also checked for ECDSA keys.
+pub(crate) fn new_public_key_type_field(
+    new_public_key_type: NewPublicKeyType,
+    new_public_key: &[u8],
+) -> Result<u8, BuildError> {
+    match new_public_key_type {
+        NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256) => {
+            check_length(new_public_key.len(), 33, "NPK")?;
+            Ok(1)
+        }
+        NewPublicKeyType::EcdsaKey(EcdsaFunction::P521Sha512) => {
+            check_length(new_public_key.len(), 67, "NPK")?;
+            Ok(3)
+        }
+        NewPublicKeyType::OsnmaAlertMessage => Ok(4),
+        NewPublicKeyType::Reserved => Err(BuildError::ReservedValue { field: "NPKT" }),
+    }
+}
+
+// Value of the KS field for a key size.
+fn key_size_field(key_size: usize) -> Result<u8, BuildError> {
+    match key_size {
+        96 => Ok(0),
+        104 => Ok(1),
+        112 => Ok(2),
+        120 => Ok(3),
+        128 => Ok(4),
+        160 => Ok(5),
+        192 => Ok(6),
+        224 => Ok(7),
+        256 => Ok(8),
+        _ => Err(BuildError::InvalidKeySize),
+    }
+}
+
+// Value of the TS field for a tag size.
+fn tag_size_field(tag_size: usize) -> Result<u8, BuildError> {
+    match tag_size {
+        20 => Ok(5),
+        24 => Ok(6),
+        28 => Ok(7),
+        32 => Ok(8),
+        40 => Ok(9),
+        _ => Err(BuildError::InvalidTagSize),
+    }
+}
+
+// Number of DSM blocks needed to hold a number of bytes.
+fn num_blocks(bytes: usize) -> usize {
+    (bytes + DSM_BLOCK_BYTES - 1) / DSM_BLOCK_BYTES
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitfields::{DsmKroot, DsmPkr, Mack};
+    use hex_literal::hex;
+
+    const KEY_SIZES: [usize; 9] = [96, 104, 112, 120, 128, 160, 192, 224, 256];
+    const TAG_SIZES: [usize; 5] = [20, 24, 28, 32, 40];
+
+    // Deterministic pseudorandom bytes for the round-trip tests
+    fn pseudorandom(seed: u32, data: &mut [u8]) {
+        let mut state = seed.wrapping_mul(0x9e37_79b9) | 1;
+        for x in data.iter_mut() {
+            // xorshift32
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            *x = state as u8;
+        }
+    }
+
+    #[test]
+    fn nma_header() {
+        // NMA header broadcast on 2022-03-07
+        let builder = NmaHeaderBuilder {
+            nma_status: NmaStatus::Test,
+            chain_id: 1,
+            chain_and_pubkey_status: ChainAndPubkeyStatus::Nominal,
+        };
+        assert_eq!(builder.build().unwrap().data(), 0x52);
+
+        for data in (0..=u8::MAX).step_by(2) {
+            let header = NmaHeader::new(data);
+            let builder = NmaHeaderBuilder {
+                nma_status: header.nma_status(),
+                chain_id: header.chain_id(),
+                chain_and_pubkey_status: header.chain_and_pubkey_status(),
+            };
+            assert_eq!(builder.build().unwrap().data(), data);
+        }
+
+        let builder = NmaHeaderBuilder {
+            chain_id: 4,
+            ..builder
+        };
+        assert_eq!(
+            builder.build(),
+            Err(BuildError::FieldOutOfRange { field: "CID" })
+        );
+    }
+
+    #[test]
+    fn dsm_kroot() {
+        // DSM-KROOT broadcast on 2022-03-07 9:00 UTC
+        let dsm = hex!(
+            "
+            22 50 49 21 04 98 21 25 d3 96 4d a3 a2 84 1e 1d
+            e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3 79 58 de
+            28 51 97 a2 63 53 f1 a4 c6 6d 7e 3d 29 18 53 ba
+            5a 13 c9 c3 48 4a 26 77 70 11 2a 13 38 3e a5 2d
+            3a 01 9d 5b 6e 1d d1 87 b9 45 3c df 06 ca 7f 34
+            ea 14 97 52 5a af 18 f1 f9 f1 fc cb 12 29 89 77
+            35 c0 21 b0 41 73 93 b5"
+        );
+        let nma_header = NmaHeader::new(0x52);
+        let parsed = DsmKroot(&dsm);
+        let builder = DsmKrootBuilder {
+            public_key_id: parsed.public_key_id(),
+            kroot_chain_id: parsed.kroot_chain_id(),
+            hash_function: parsed.hash_function(),
+            mac_function: parsed.mac_function(),
+            key_size: parsed.key_size().unwrap(),
+            tag_size: parsed.tag_size().unwrap(),
+            mac_lookup_table: parsed.mac_lookup_table(),
+            kroot_wn: parsed.kroot_wn(),
+            kroot_towh: parsed.kroot_towh(),
+            alpha: parsed.alpha(),
+            kroot: parsed.kroot(),
+        };
+        let built = builder
+            .build(nma_header, parsed.digital_signature())
+            .unwrap();
+        // This message was broadcast with a non-zero value in the reserved
+        // field following CIDKR, which the builder always sets to zero. Since
+        // the reserved field is covered by the signature and the padding,
+        // these are the only parts of the message that differ.
+        let built = built.dsm_kroot();
+        assert_eq!(built.0.len(), dsm.len());
+        assert_eq!(built.0[0], dsm[0]);
+        assert_eq!(built.0[1], dsm[1] & !0x30);
+        assert_eq!(built.0[2..93], dsm[2..93]);
+        assert!(built.check_padding(nma_header));
+        let message = builder.signature_message(nma_header).unwrap();
+        assert_eq!(message.as_ref().len(), 29);
+        assert_eq!(message.as_ref()[0], 0x52);
+        assert_eq!(message.as_ref()[2..], dsm[2..29]);
+
+        let builder = DsmKrootBuilder {
+            mac_function: MacFunction::Reserved,
+            ..builder
+        };
+        assert_eq!(
+            builder.build(nma_header, parsed.digital_signature()),
+            Err(BuildError::ReservedValue { field: "MF" })
+        );
+    }
+
+    #[test]
+    fn dsm_kroot_round_trip() {
+        let nma_header = NmaHeader::new(0x52);
+        for (j, &key_size) in KEY_SIZES.iter().enumerate() {
+            for signature_size in [64, 132] {
+                let seed = u32::try_from(j * 1000 + signature_size).unwrap();
+                let mut kroot = [0; 32];
+                pseudorandom(seed, &mut kroot);
+                let mut signature = [0; 132];
+                pseudorandom(seed + 1, &mut signature);
+                let kroot = &kroot[..key_size / 8];
+                let signature = &signature[..signature_size];
+                let builder = DsmKrootBuilder {
+                    public_key_id: 9,
+                    kroot_chain_id: 3,
+                    hash_function: HashFunction::Sha3_256,
+                    mac_function: MacFunction::CmacAes,
+                    key_size,
+                    tag_size: 32,
+                    mac_lookup_table: 0x2a,
+                    kroot_wn: 0xabc,
+                    kroot_towh: 167,
+                    alpha: 0xfedc_ba98_7654,
+                    kroot,
+                };
+                let built = builder.build(nma_header, signature).unwrap();
+                let dsm = built.dsm_kroot();
+                assert_eq!(dsm.number_of_blocks(), Some(dsm.0.len() / DSM_BLOCK_BYTES));
+                assert_eq!(dsm.public_key_id(), 9);
+                assert_eq!(dsm.kroot_chain_id(), 3);
+                assert_eq!(dsm.hash_function(), HashFunction::Sha3_256);
+                assert_eq!(dsm.mac_function(), MacFunction::CmacAes);
+                assert_eq!(dsm.key_size(), Some(key_size));
+                assert_eq!(dsm.tag_size(), Some(32));
+                assert_eq!(dsm.mac_lookup_table(), 0x2a);
+                assert_eq!(dsm.kroot_wn(), 0xabc);
+                assert_eq!(dsm.kroot_towh(), 167);
+                assert_eq!(dsm.alpha(), 0xfedc_ba98_7654);
+                assert_eq!(dsm.kroot(), kroot);
+                let ecdsa_function = if signature_size == 64 {
+                    EcdsaFunction::P256Sha256
+                } else {
+                    EcdsaFunction::P521Sha512
+                };
+                assert_eq!(dsm.ecdsa_function(), ecdsa_function);
+                assert_eq!(dsm.digital_signature(), signature);
+                assert!(dsm.check_padding(nma_header));
+                if !dsm.padding().is_empty() {
+                    assert!(!dsm.check_padding(NmaHeader::new(0x92)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dsm_pkr() {
+        // DSM-PKR broadcast on 2023-12-12 12:00 UTC
+        let dsm = hex!(
+            "
+            70 01 63 1b dc ed 79 d4 31 7b c2 87 0e e3 89 5b
+            d5 9c f2 b6 ea 51 6f ab bf df 1d 73 96 26 14 6f
+            fe 31 6f a9 28 5f 5a 1e 44 04 24 13 bd af 18 aa
+            3c f6 84 72 33 97 d7 b8 32 5a ec a1 eb ca 9f 0f
+            64 99 05 42 4c be 48 2a 1a 32 b0 10 64 f8 5d 0c
+            36 df 03 8e 52 ce 12 8e 7e c5 f3 23 e1 65 b1 82
+            a7 15 37 bd b0 10 97 2e b4 a3 b9 0b aa cd 14 94
+            1e f4 0d a2 cb 2b 82 d3 78 b3 15 c0 08 de ce fd
+            8e 11 03 74 a9 25 cf a0 ff 18 05 e5 c5 a5 8f db
+            a3 1b f0 14 5d 5b 5b e2 f0 62 d3 f8 bb 2e e9 8f
+            0f 6d b0 e8 23 c5 e7 5e 78"
+        );
+        // Obtained from OSNMA_MerkleTree_20231213105954_PKID_1.xml
+        let merkle_tree_root =
+            hex!("0E63F552C8021709043C239032EFFE941BF22C8389032F5F2701E0FBC80148B8");
+        let parsed = DsmPkr(&dsm);
+        let builder = DsmPkrBuilder {
+            message_id: parsed.message_id(),
+            intermediate_tree_nodes: core::array::from_fn(|j| *parsed.intermediate_tree_node(j)),
+            new_public_key_type: parsed.new_public_key_type(),
+            new_public_key_id: parsed.new_public_key_id(),
+            new_public_key: parsed.new_public_key().unwrap(),
+        };
+        let built = builder.build(&merkle_tree_root).unwrap();
+        assert_eq!(built.dsm_pkr().0, &dsm[..]);
+
+        let builder = DsmPkrBuilder {
+            new_public_key: &parsed.new_public_key().unwrap()[1..],
+            ..builder
+        };
+        assert_eq!(
+            builder.build(&merkle_tree_root),
+            Err(BuildError::WrongLength {
+                field: "NPK",
+                expected: 33,
+                actual: 32
+            })
+        );
+    }
+
+    #[test]
+    fn dsm_pkr_round_trip() {
+        let mut merkle_tree_root = [0; 32];
+        pseudorandom(1, &mut merkle_tree_root);
+        let mut nodes = [[0; 32]; 4];
+        for (j, node) in nodes.iter_mut().enumerate() {
+            pseudorandom(u32::try_from(j).unwrap() + 2, node);
+        }
+        let mut key = [0; 78];
+        pseudorandom(10, &mut key);
+        for (npkt, key_len, num_blocks) in [
+            (
+                NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256),
+                33,
+                13,
+            ),
+            (
+                NewPublicKeyType::EcdsaKey(EcdsaFunction::P521Sha512),
+                67,
+                16,
+            ),
+            (NewPublicKeyType::OsnmaAlertMessage, 39, 13),
+            (NewPublicKeyType::OsnmaAlertMessage, 78, 16),
+        ] {
+            let builder = DsmPkrBuilder {
+                message_id: 7,
+                intermediate_tree_nodes: nodes,
+                new_public_key_type: npkt,
+                new_public_key_id: 12,
+                new_public_key: &key[..key_len],
+            };
+            let built = builder.build(&merkle_tree_root).unwrap();
+            let dsm = built.dsm_pkr();
+            assert_eq!(dsm.0.len(), num_blocks * DSM_BLOCK_BYTES);
+            assert_eq!(dsm.number_of_blocks(), Some(num_blocks));
+            assert_eq!(dsm.message_id(), 7);
+            for (j, node) in nodes.iter().enumerate() {
+                assert_eq!(dsm.intermediate_tree_node(j), node);
+            }
+            assert_eq!(dsm.new_public_key_type(), npkt);
+            assert_eq!(dsm.new_public_key_id(), 12);
+            assert_eq!(dsm.new_public_key(), Some(&key[..key_len]));
+            assert!(dsm.check_padding(&merkle_tree_root));
+        }
+
+        // An OSNMA Alert Message must fill an integer number of blocks
+        let builder = DsmPkrBuilder {
+            message_id: 0,
+            intermediate_tree_nodes: nodes,
+            new_public_key_type: NewPublicKeyType::OsnmaAlertMessage,
+            new_public_key_id: 0,
+            new_public_key: &key[..40],
+        };
+        assert_eq!(
+            builder.build(&merkle_tree_root),
+            Err(BuildError::WrongLength {
+                field: "NPK",
+                expected: 52,
+                actual: 40
+            })
+        );
+    }
+
+    #[test]
+    fn mack() {
+        // MACK broadcast on 2022-03-07 9:00 UTC
+        let data = hex!(
+            "
+            11 55 d3 71 f2 1f 30 a8 e4 ec e0 c0 1b 07 6d 17
+            7d 64 03 12 05 d4 02 7e 77 13 15 c0 4c ca 1c 16
+            99 1a 05 48 91 07 a7 f7 0e c5 42 b4 19 da 6a da
+            1c 0a 3d 6f 56 a5 e5 dc 59 a7 00 00"
+        );
+        let parsed = Mack::new(&data, 128, 40);
+        let parsed_tags: [_; 5] = core::array::from_fn(|j| parsed.tag_and_info(j + 1));
+        let tags: [TagAndInfoBuilder; 5] = core::array::from_fn(|j| {
+            let tag = &parsed_tags[j];
+            TagAndInfoBuilder {
+                tag: tag.tag(),
+                prnd: tag.prnd(),
+                adkd: tag.adkd(),
+                cop: tag.cop(),
+            }
+        });
+        let builder = MackBuilder {
+            key_size: 128,
+            tag_size: 40,
+            tag0: parsed.tag0(),
+            macseq: parsed.macseq(),
+            cop: parsed.cop(),
+            tags: &tags,
+            key: parsed.key(),
+        };
+        assert_eq!(builder.build().unwrap(), data);
+
+        let builder = MackBuilder {
+            tags: &tags[..4],
+            ..builder
+        };
+        assert_eq!(
+            builder.build(),
+            Err(BuildError::WrongLength {
+                field: "tags",
+                expected: 5,
+                actual: 4
+            })
+        );
+    }
+
+    #[test]
+    fn mack_round_trip() {
+        for &key_size in &KEY_SIZES {
+            for &tag_size in &TAG_SIZES {
+                let seed = u32::try_from(key_size * 100 + tag_size).unwrap();
+                let mut random = [0; 4 * MACK_MESSAGE_BYTES];
+                pseudorandom(seed, &mut random);
+                let random = BitSlice::from_slice(&random);
+                let num_tags = (8 * MACK_MESSAGE_BYTES - key_size) / (tag_size + 16);
+                let adkds = [Adkd::InavCed, Adkd::InavTiming, Adkd::SlowMac];
+                let tags: [TagAndInfoBuilder; 32] = core::array::from_fn(|j| TagAndInfoBuilder {
+                    tag: &random[j * tag_size..(j + 1) * tag_size],
+                    prnd: if j % 7 == 6 {
+                        Prnd::GalileoConstellation
+                    } else {
+                        Prnd::GalileoSvid(u8::try_from(j % 36).unwrap() + 1)
+                    },
+                    adkd: adkds[j % 3],
+                    cop: u8::try_from(j % 16).unwrap(),
+                });
+                let tags = &tags[..num_tags - 1];
+                let tag0 = &random[1300..1300 + tag_size];
+                let key = &random[1400..1400 + key_size];
+                let builder = MackBuilder {
+                    key_size,
+                    tag_size,
+                    tag0,
+                    macseq: 0xa5c,
+                    cop: 11,
+                    tags,
+                    key,
+                };
+                let data = builder.build().unwrap();
+                let mack = Mack::new(&data, key_size, tag_size);
+                assert_eq!(mack.num_tags(), num_tags);
+                assert_eq!(mack.tag0(), tag0);
+                assert_eq!(mack.macseq(), 0xa5c);
+                assert_eq!(mack.cop(), 11);
+                for (j, tag) in tags.iter().enumerate() {
+                    let parsed = mack.tag_and_info(j + 1);
+                    assert_eq!(parsed.tag(), tag.tag);
+                    assert_eq!(parsed.prnd(), tag.prnd);
+                    assert_eq!(parsed.adkd(), tag.adkd);
+                    assert_eq!(parsed.cop(), tag.cop);
+                }
+                assert_eq!(mack.key(), key);
+                // The bits after the key are zero
+                let key_end = num_tags * (tag_size + 16) + key_size;
+                assert!(BitSlice::from_slice(&data)[key_end..].not_any());
+            }
+        }
+    }
+}
@@ -4,10 +4,11 @@
 //! the DSM blocks of a DSM message and recompose the message.
 
 use crate::bitfields::{DsmHeader, DsmType};
+use crate::logging::Hex;
 use crate::types::{DsmBlock, DSM_BLOCK_BYTES};
 
 const MAX_DSM_BLOCKS: usize = 16;
-const MAX_DSM_BYTES: usize = MAX_DSM_BLOCKS * DSM_BLOCK_BYTES;
+pub(crate) const MAX_DSM_BYTES: usize = MAX_DSM_BLOCKS * DSM_BLOCK_BYTES;
 
 /// DSM message.
 ///
@@ -80,14 +81,15 @@ impl CollectDsm {
     ///
     /// If the block fed corresponds to a new DSM ID, the old data is discarded
     /// and the collection for the new DSM begins.
-    pub fn feed(&mut self, header: DsmHeader, block: &DsmBlock) -> Option<Dsm> {
-        log::trace!("feeding header = {:?}, block = {:02x?}", header, block);
+    pub fn feed(&mut self, header: DsmHeader, block: &DsmBlock) -> Option<Dsm<'_>> {
+        trace!(
+            "feeding DSM block";
+            dsm_id = header.dsm_id(),
+            block_id = header.dsm_block_id(),
+            block = Hex(block),
+        );
         if header.dsm_id() != self.dsm_id || self.dsm_type.is_none() {
-            log::info!(
-                "new DSM id = {} (had id = {}). resetting",
-                header.dsm_id(),
-                self.dsm_id
-            );
+            info!("new DSM id. resetting"; dsm_id = header.dsm_id(), old_dsm_id = self.dsm_id);
             self.reset();
             self.dsm_id = header.dsm_id();
             self.dsm_type = Some(header.dsm_type());
@@ -96,18 +98,14 @@ impl CollectDsm {
         // not None
         let dsm_type = self.dsm_type.unwrap();
         if self.done {
-            log::trace!("current DSM is complete. nothing to do");
+            trace!("current DSM is complete. nothing to do");
             return None;
         }
         self.store_block(header.dsm_block_id(), block);
         if let Some(size) = self.done_and_size(dsm_type) {
-            log::info!(
-                "completed DSM with id = {}, size = {} bytes",
-                self.dsm_id,
-                size
-            );
+            info!("completed DSM"; dsm_id = self.dsm_id, size_bytes = size);
             let dsm = &self.dsm[..size];
-            log::trace!("DSM contents {:02x?}", dsm);
+            trace!("DSM contents"; dsm = Hex(dsm));
             self.done = true;
             Some(Dsm {
                 id: self.dsm_id,
@@ -125,33 +123,32 @@ impl CollectDsm {
         let section = &mut self.dsm[idx..idx + DSM_BLOCK_BYTES];
         if self.block_valid[block_id] {
             if section != block {
-                log::error!(
-                    "block {} already stored, but its contents differ\
-                             stored = {:02x?}, just received = {:02x?}",
-                    block_id,
-                    section,
-                    block
+                error!(
+                    "block already stored, but its contents differ";
+                    block_id = block_id,
+                    stored = Hex(section),
+                    received = Hex(block),
                 );
             } else {
-                log::trace!("block {} already stored", block_id);
+                trace!("block already stored"; block_id = block_id);
             }
         } else {
             section.copy_from_slice(block);
             self.block_valid[block_id] = true;
-            log::trace!("stored block {}", block_id);
+            trace!("stored block"; block_id = block_id);
         }
     }
 
     fn done_and_size(&self, dsm_type: DsmType) -> Option<usize> {
         if !self.block_valid[0] {
-            log::trace!("first block not yet present. DSM size unknown");
+            trace!("first block not yet present. DSM size unknown");
             return None;
         }
         // If first block is present, we can read the NB field
         let nb = self.dsm[0] >> 4;
         if let Some(n) = Self::number_of_blocks(dsm_type, nb) {
             let missing = self.block_valid[..n].iter().filter(|&x| !x).count();
-            log::trace!("DSM size = {} blocks. missing {} blocks", n, missing);
+            trace!("DSM size"; blocks = n, missing = missing);
             if missing == 0 {
                 Some(n * DSM_BLOCK_BYTES)
             } else {
@@ -192,7 +189,7 @@ impl CollectDsm {
             }
         };
         if a.is_none() {
-            log::error!("reserved NB value {} for dsm_type = {:?}", nb, dsm_type);
+            error!("reserved NB value"; nb = nb, dsm_type = dsm_type);
         }
         a
     }
@@ -0,0 +1,283 @@
+//! OSNMA processing events.
+//!
+//! This module contains the [`OsnmaEvent`] enum, which describes the outcome
+//! of the cryptographic verifications done by the [`Osnma`](crate::Osnma)
+//! black box: the verification of public keys in DSM-PKR messages, of TESLA
+//! root keys in DSM-KROOT messages, of TESLA keys in MACK messages and of the
+//! MACK messages themselves, as well as OSNMA Alert Messages, state
+//! transitions and discrepancies in the GST of the INAV pages. These events
+//! are also logged, but an [`EventHook`] gives them to the application in a
+//! structured form (see
+//! [`Osnma::with_event_hook`](crate::Osnma::with_event_hook)).
+//!
+//! The validation of individual tags is reported by the MAC traces in the
+//! [trace](crate::trace) module.
+
+use crate::bitfields::{MackValidationError, NmaStatus};
+use crate::inav_clock::GstDiscrepancy;
+use crate::merkle_tree::PkrError;
+use crate::state::StateTransition;
+use crate::tesla::{KrootValidationError, ValidationError};
+use crate::{Gst, Svn};
+use core::fmt;
+
+/// OSNMA processing event.
+///
+/// The `gst` field of each variant gives the GST of the subframe in which the
+/// event happened.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OsnmaEvent {
+    /// A new public key has been verified using a DSM-PKR and the Merkle tree.
+    PublicKeyVerified {
+        /// ID of the new public key (NPKID field).
+        npkid: u8,
+        /// GST of the subframe.
+        gst: Gst,
+    },
+    /// The verification of a DSM-PKR failed.
+    PublicKeyVerificationFailed {
+        /// Reason for the failure.
+        error: PkrError,
+        /// GST of the subframe.
+        gst: Gst,
+    },
+    /// A DSM-KROOT has been verified using the public key.
+    KrootVerified {
+        /// ID of the public key used to verify the DSM-KROOT.
+        pkid: u8,
+        /// ID of the TESLA chain of the KROOT.
+        chain_id: u8,
+        /// NMA status in the NMA header of the DSM-KROOT.
+        nma_status: NmaStatus,
+        /// GST of the KROOT.
+        kroot_gst: Gst,
+        /// GST of the subframe.
+        gst: Gst,
+    },
+    /// The verification of a DSM-KROOT failed.
+    KrootVerificationFailed {
+        /// ID of the public key used to try to verify the DSM-KROOT.
+        pkid: u8,
+        /// Reason for the failure.
+        error: KrootValidationError,
+        /// GST of the subframe.
+        gst: Gst,
+    },
+    /// A TESLA key transmitted in a MACK message has been verified.
+    TeslaKeyVerified {
+        /// GST of the TESLA key.
+        key_gst: Gst,
+        /// ID of the TESLA chain of the key.
+        chain_id: u8,
+        /// GST of the key that was used to verify the new key.
+        validated_by: Gst,
+        /// GST of the subframe.
+        gst: Gst,
+    },
+    /// The verification of a TESLA key transmitted in a MACK message failed.
+    TeslaKeyVerificationFailed {
+        /// GST of the TESLA key.
+        key_gst: Gst,
+        /// GST of the key that was used to try to verify the new key.
+        validated_by: Gst,
+        /// Reason for the failure.
+        error: ValidationError,
+        /// GST of the subframe.
+        gst: Gst,
+    },
+    /// The verification of a MACK message failed.
+    ///
+    /// The tags of the MACK message are discarded.
+    MackVerificationFailed {
+        /// SVN of the satellite that transmitted the MACK message.
+        svn: Svn,
+        /// Reason for the failure.
+        error: MackValidationError,
+        /// GST of the subframe in which the MACK message was transmitted.
+        gst: Gst,
+    },
+    /// A verified OSNMA Alert Message has been received.
+    AlertMessage {
+        /// GST of the subframe.
+        gst: Gst,
+    },
+    /// The [`OsnmaState`](crate::state::OsnmaState) has changed.
+    StateTransition(StateTransition),
+    /// The GST decoded from an INAV word does not match the expected GST.
+    ///
+    /// This is reported by [`Osnma::feed_inav`](crate::Osnma::feed_inav) when
+    /// the GST check is enabled, and by
+    /// [`Osnma::feed_inav_page`](crate::Osnma::feed_inav_page). The GST of the
+    /// event is the subframe of the expected GST.
+    GstDiscrepancy(GstDiscrepancy),
+}
+
+impl OsnmaEvent {
+    /// Returns the GST of the subframe in which the event happened.
+    pub fn gst(&self) -> Gst {
+        match self {
+            OsnmaEvent::PublicKeyVerified { gst, .. }
+            | OsnmaEvent::PublicKeyVerificationFailed { gst, .. }
+            | OsnmaEvent::KrootVerified { gst, .. }
+            | OsnmaEvent::KrootVerificationFailed { gst, .. }
+            | OsnmaEvent::TeslaKeyVerified { gst, .. }
+            | OsnmaEvent::TeslaKeyVerificationFailed { gst, .. }
+            | OsnmaEvent::MackVerificationFailed { gst, .. }
+            | OsnmaEvent::AlertMessage { gst } => *gst,
+            OsnmaEvent::StateTransition(transition) => transition.gst,
+            OsnmaEvent::GstDiscrepancy(discrepancy) => discrepancy.expected().gst_subframe(),
+        }
+    }
+
+    /// Returns `true` if the event is a failed verification.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            OsnmaEvent::PublicKeyVerificationFailed { .. }
+                | OsnmaEvent::KrootVerificationFailed { .. }
+                | OsnmaEvent::TeslaKeyVerificationFailed { .. }
+                | OsnmaEvent::MackVerificationFailed { .. }
+        )
+    }
+}
+
+impl fmt::Display for OsnmaEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OsnmaEvent::PublicKeyVerified { npkid, .. } => {
+                write!(f, "public key {npkid} verified")
+            }
+            OsnmaEvent::PublicKeyVerificationFailed { error, .. } => {
+                write!(f, "public key verification failed: {error}")
+            }
+            OsnmaEvent::KrootVerified {
+                pkid,
+                chain_id,
+                nma_status,
+                ..
+            } => write!(
+                f,
+                "KROOT of chain {chain_id} verified with public key {pkid} \
+                 (NMA status {nma_status:?})"
+            ),
+            OsnmaEvent::KrootVerificationFailed { pkid, error, .. } => write!(
+                f,
+                "KROOT verification with public key {pkid} failed: {error}"
+            ),
+            OsnmaEvent::TeslaKeyVerified {
+                key_gst, chain_id, ..
+            } => write!(
+                f,
+                "TESLA key of chain {chain_id} at {}:{} verified",
+                key_gst.wn(),
+                key_gst.tow()
+            ),
+            OsnmaEvent::TeslaKeyVerificationFailed { key_gst, error, .. } => write!(
+                f,
+                "TESLA key at {}:{} verification failed: {error}",
+                key_gst.wn(),
+                key_gst.tow()
+            ),
+            OsnmaEvent::MackVerificationFailed { svn, error, .. } => {
+                write!(f, "MACK verification for {svn} failed: {error}")
+            }
+            OsnmaEvent::AlertMessage { .. } => "OSNMA Alert Message received".fmt(f),
+            OsnmaEvent::StateTransition(transition) => {
+                write!(f, "state transition {transition}")
+            }
+            OsnmaEvent::GstDiscrepancy(discrepancy) => write!(
+                f,
+                "GST in INAV word type {} from {} differs from expected GST by {} s",
+                discrepancy.word_type(),
+                discrepancy.svn(),
+                discrepancy.seconds()
+            ),
+        }
+    }
+}
+
+/// Hook that receives OSNMA processing events.
+///
+/// The [`Osnma`](crate::Osnma) black box owns the hook, which is a type
+/// parameter of the black box. Therefore, the hook can hold the context that
+/// the application needs to handle the events, such as a channel or a
+/// counter, and it is possible to access it through
+/// [`Osnma::event_hook`](crate::Osnma::event_hook).
+///
+/// This trait is implemented for closures taking an `&OsnmaEvent`, for
+/// `Option<H>`, which ignores the events when it is `None`, and for `()`, which
+/// ignores all the events and is the hook used by default.
+pub trait EventHook {
+    /// Receives an OSNMA processing event.
+    fn event(&mut self, event: &OsnmaEvent);
+
+    /// Returns `true` if the hook uses the events.
+    ///
+    /// With the `parallel` feature, the events of the MACK messages validated
+    /// concurrently are only recorded if this returns `true`. The default
+    /// implementation returns `true`.
+    fn is_enabled(&self) -> bool {
+        true
+    }
+}
+
+impl<F: FnMut(&OsnmaEvent)> EventHook for F {
+    fn event(&mut self, event: &OsnmaEvent) {
+        self(event)
+    }
+}
+
+impl<H: EventHook> EventHook for Option<H> {
+    fn event(&mut self, event: &OsnmaEvent) {
+        if let Some(hook) = self {
+            hook.event(event);
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.as_ref().is_some_and(|hook| hook.is_enabled())
+    }
+}
+
+impl EventHook for () {
+    fn event(&mut self, _event: &OsnmaEvent) {}
+
+    fn is_enabled(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::{OsnmaState, TransitionReason};
+
+    #[test]
+    fn display() {
+        let gst = Gst::new(1176, 121050);
+        let event = OsnmaEvent::TeslaKeyVerificationFailed {
+            key_gst: Gst::new(1176, 121080),
+            validated_by: gst,
+            error: ValidationError::WrongOneWayFunction,
+            gst,
+        };
+        assert!(event.is_failure());
+        assert_eq!(event.gst(), gst);
+        assert_eq!(
+            event.to_string(),
+            "TESLA key at 1176:121080 verification failed: derived key does not match"
+        );
+        let event = OsnmaEvent::StateTransition(StateTransition {
+            from: OsnmaState::WarmStart,
+            to: OsnmaState::Operational,
+            reason: TransitionReason::TeslaKeyVerified,
+            gst,
+        });
+        assert!(!event.is_failure());
+        assert_eq!(
+            event.to_string(),
+            "state transition warm start -> operational (TESLA key verified)"
+        );
+    }
+}
@@ -4,6 +4,8 @@
 //! [Galmon transport protocol](https://github.com/berthubert/galmon#internals).
 //! The reader can be used to obtain INAV frames and OSNMA data from the
 //! [Galmon](https://github.com/berthubert/galmon) tools, such as `ubxtool`.
+//! The [`pages`] module extracts the INAV pages from the navmon packets, fixing
+//! some problems of Galmon data.
 
 pub mod navmon {
     //! Galmon `navmon` protocol buffers definition.
@@ -17,6 +19,8 @@ pub mod navmon {
     include!(concat!(env!("OUT_DIR"), "/navmon_protobuf.rs"));
 }
 
+pub mod pages;
+
 pub mod transport {
     //! Galmon transport protocol.
     use super::navmon::NavMonMessage;
@@ -0,0 +1,217 @@
+//! Extraction of the Galileo INAV pages from Galmon streams.
+//!
+//! Galmon data contains some wrong TOWs, as well as INAV words that should
+//! not be fed to the OSNMA black box. The [`PageExtractor`] fixes the TOWs and
+//! drops these words, giving the INAV pages that can be fed to
+//! [`Osnma`](crate::Osnma).
+
+use super::navmon::{nav_mon_message::GalileoInav, NavMonMessage};
+use crate::types::{InavWord, OsnmaDataMessage};
+use crate::{Gst, InavBand, Svn, Tow, Wn};
+
+const SECS_IN_WEEK: u32 = 604800;
+
+/// INAV page extracted from a Galmon packet.
+#[derive(Debug, Clone)]
+pub struct Page {
+    /// INAV word of the page.
+    pub inav_word: InavWord,
+    /// OSNMA data of the page, if present in the Galmon packet.
+    pub osnma_data: Option<OsnmaDataMessage>,
+    /// SVN of the satellite that transmitted the page.
+    pub svn: Svn,
+    /// GST at the start of the transmission of the page.
+    pub gst: Gst,
+    /// Band in which the page was transmitted.
+    pub band: InavBand,
+}
+
+/// Extracts the INAV pages from Galmon packets.
+///
+/// This fixes the wrong TOWs that are found in Galmon data, and drops the INAV
+/// words of a previous subframe and the dummy INAV words. The packets of a
+/// Galmon stream should be given in order to the same `PageExtractor`, since
+/// the TOW fixes depend on the previous INAV words.
+#[derive(Debug, Clone, Default)]
+pub struct PageExtractor {
+    current_subframe: Option<Gst>,
+    last_gst: Option<Gst>,
+    last_tow_mod_30: Tow,
+}
+
+impl PageExtractor {
+    /// Constructs a new `PageExtractor`.
+    pub fn new() -> PageExtractor {
+        PageExtractor::default()
+    }
+
+    /// Returns the GST of the last INAV word that was not dropped because it
+    /// belonged to a previous subframe.
+    pub fn last_gst(&self) -> Option<Gst> {
+        self.last_gst
+    }
+
+    /// Computes the GST of an INAV word, fixing the wrong TOWs of Galmon data.
+    ///
+    /// Galmon sometimes gives a TOW of 604800 or greater, which is normalized
+    /// by incrementing the week number. Besides this, the E1B word 16 starting
+    /// at TOW = 29 mod 30 often has the TOW of the previous word 16 in the
+    /// subframe, which starts at TOW = 15 mod 30. This is detected by looking
+    /// at the TOW of the previous INAV word.
+    ///
+    /// This is called by [`PageExtractor::extract`]. It can be used directly to
+    /// obtain the GST of all the INAV words, including those that `extract`
+    /// drops. `None` is returned if the week number is out of range.
+    pub fn gst(&mut self, inav: &GalileoInav) -> Option<Gst> {
+        let mut tow = inav.gnss_tow % SECS_IN_WEEK;
+        let wn = Wn::try_from(inav.gnss_wn)
+            .ok()?
+            .checked_add(Wn::try_from(inav.gnss_tow / SECS_IN_WEEK).ok()?)?;
+        if tow % 30 == 15 && self.last_tow_mod_30 >= 19 {
+            log::debug!(
+                "fixing wrong TOW for SVN {}; tow = {}, last tow mod 30 = {}",
+                inav.gnss_sv,
+                tow,
+                self.last_tow_mod_30
+            );
+            tow += 29 - 15; // wn rollover is not possible by this addition
+        }
+        self.last_tow_mod_30 = tow % 30;
+        Some(Gst::new(wn, tow))
+    }
+
+    /// Extracts the INAV page contained in a packet, if any.
+    ///
+    /// `None` is returned if the packet does not contain a Galileo INAV page,
+    /// or if the page is dropped.
+    pub fn extract(&mut self, packet: &NavMonMessage) -> Option<Page> {
+        let inav @ GalileoInav {
+            contents: inav_word,
+            reserved1: osnma_data,
+            sigid: Some(sigid),
+            ..
+        } = packet.gi.as_ref()?
+        else {
+            return None;
+        };
+
+        let Some(gst) = self.gst(inav) else {
+            log::error!("week number out of range: wn = {}", inav.gnss_wn);
+            return None;
+        };
+        if let Some(current) = self.current_subframe {
+            if current > gst.gst_subframe() {
+                // Avoid processing INAV words that are in a previous subframe
+                log::warn!(
+                    "dropping INAV word from previous subframe (current subframe {:?}, \
+                     this INAV word {:?} SVN {} band {})",
+                    current,
+                    gst,
+                    inav.gnss_sv,
+                    sigid
+                );
+                return None;
+            }
+        }
+        self.current_subframe = Some(gst.gst_subframe());
+        self.last_gst = Some(gst);
+        let Ok(svn) = Svn::try_from(inav.gnss_sv) else {
+            log::error!("INAV word received from invalid SVN {}", inav.gnss_sv);
+            return None;
+        };
+        let band = match sigid {
+            1 => InavBand::E1B,
+            5 => InavBand::E5B,
+            _ => {
+                log::error!("INAV word received on non-INAV band: sigid = {}", sigid);
+                return None;
+            }
+        };
+
+        // The OSNMA SIS ICD says that OSNMA is not provided in INAV Dummy
+        // Messages or Alert Pages. The OSNMA field in these pages may not
+        // contain all zeros, but is invalid and should be discarded.
+        //
+        // Here we drop INAV words that are Dummy Messages. There is no way
+        // for us to filter for Alert Pages in Galmon data (the page type
+        // bit is not present), so hopefully these pages don't make it here.
+        let Ok(inav_word) = InavWord::try_from(&inav_word[..]) else {
+            log::error!("INAV word with wrong size: {} bytes", inav_word.len());
+            return None;
+        };
+        let inav_word_type = inav_word[0] >> 2;
+        if inav_word_type == 63 {
+            log::debug!(
+                "discarding dummy INAV word from {} {:?} at {:?}",
+                svn,
+                band,
+                gst
+            );
+            return None;
+        }
+        let osnma_data = match osnma_data {
+            Some(osnma_data) => match OsnmaDataMessage::try_from(&osnma_data[..]) {
+                Ok(osnma_data) => Some(osnma_data),
+                Err(_) => {
+                    log::error!("OSNMA data with wrong size: {} bytes", osnma_data.len());
+                    return None;
+                }
+            },
+            None => None,
+        };
+
+        Some(Page {
+            inav_word,
+            osnma_data,
+            svn,
+            gst,
+            band,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn packet(wn: u32, tow: u32, word_type: u8) -> NavMonMessage {
+        let mut contents = vec![0; 16];
+        contents[0] = word_type << 2;
+        NavMonMessage {
+            gi: Some(GalileoInav {
+                gnss_wn: wn,
+                gnss_tow: tow,
+                gnss_sv: 11,
+                contents,
+                sigid: Some(1),
+                reserved1: Some(vec![0x5a; 5]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn extract() {
+        let mut extractor = PageExtractor::new();
+        let page = extractor.extract(&packet(1300, 604801, 2)).unwrap();
+        // The TOW is normalized
+        assert_eq!(page.gst, Gst::new(1301, 1));
+        assert_eq!(page.svn, Svn::try_from(11).unwrap());
+        assert_eq!(page.band, InavBand::E1B);
+        assert_eq!(page.osnma_data, Some([0x5a; 5]));
+        // Word 16 with the TOW of the previous word 16 in the subframe
+        let page = extractor.extract(&packet(1301, 23, 16)).unwrap();
+        assert_eq!(page.gst, Gst::new(1301, 23));
+        let page = extractor.extract(&packet(1301, 15, 16)).unwrap();
+        assert_eq!(page.gst, Gst::new(1301, 29));
+        // Dummy words are dropped
+        assert!(extractor.extract(&packet(1301, 31, 63)).is_none());
+        assert_eq!(extractor.last_gst(), Some(Gst::new(1301, 31)));
+        // Words from a previous subframe are dropped
+        assert!(extractor.extract(&packet(1301, 1, 2)).is_none());
+        assert_eq!(extractor.last_gst(), Some(Gst::new(1301, 31)));
+        // Packets without INAV words are ignored
+        assert!(extractor.extract(&NavMonMessage::default()).is_none());
+    }
+}
@@ -5,7 +5,7 @@ pub type Wn = u16;
 /// This represents the time of week in seconds.
 pub type Tow = u32;
 
-const SECS_IN_WEEK: Tow = 24 * 3600 * 7;
+pub(crate) const SECS_IN_WEEK: Tow = 24 * 3600 * 7;
 const SECS_PER_SUBFRAME: Tow = 30;
 
 /// GST (Galileo System Time)
@@ -21,11 +21,39 @@ const SECS_PER_SUBFRAME: Tow = 30;
 /// assert_eq!(gst.tow(), 175767);
 /// ```
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SerdeGst")
+)]
 pub struct Gst {
     wn: Wn,
     tow: Tow,
 }
 
+// Unchecked GST used for deserialization. The TOW range is checked when
+// converting to a Gst.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Gst")]
+struct SerdeGst {
+    wn: Wn,
+    tow: Tow,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeGst> for Gst {
+    type Error = &'static str;
+
+    fn try_from(gst: SerdeGst) -> Result<Gst, &'static str> {
+        if gst.tow >= SECS_IN_WEEK {
+            return Err("TOW out of range 0-604799");
+        }
+        Ok(Gst::new(gst.wn, gst.tow))
+    }
+}
+
 impl Gst {
     /// Constructs a new GST from a week number and TOW.
     ///
@@ -143,6 +171,15 @@ impl Gst {
         self.tow % SECS_PER_SUBFRAME == 0
     }
 
+    /// Returns the difference in seconds between `other` and `self`.
+    ///
+    /// The returned value is equal to the number of GST seconds elapsed between
+    /// `other` and `self`. It is negative if `self` is earlier than `other`.
+    pub fn seconds_difference(&self, other: Gst) -> i32 {
+        (i32::from(self.wn) - i32::from(other.wn)) * i32::try_from(SECS_IN_WEEK).unwrap()
+            + (i32::try_from(self.tow).unwrap() - i32::try_from(other.tow).unwrap())
+    }
+
     /// Returns the difference in subframes between `other` and `self`.
     ///
     /// The returned value is equal to the number of GST seconds elapsed between
@@ -154,3 +191,17 @@ impl Gst {
                 / i32::try_from(SECS_PER_SUBFRAME).unwrap()
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serde() {
+        let gst = Gst::new(1177, 175767);
+        let json = serde_json::to_string(&gst).unwrap();
+        assert_eq!(json, r#"{"wn":1177,"tow":175767}"#);
+        assert_eq!(serde_json::from_str::<Gst>(&json).unwrap(), gst);
+        assert!(serde_json::from_str::<Gst>(r#"{"wn":1177,"tow":604800}"#).is_err());
+    }
+}
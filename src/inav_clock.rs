@@ -0,0 +1,468 @@
+//! GST obtained from the INAV navigation message.
+//!
+//! The INAV words 0, 5 and 6 contain the GST at the start of the page in which
+//! they are transmitted. Words 0 and 5 contain the week number and time of
+//! week, while word 6 only contains the time of week. This module contains
+//! functions to decode this GST, and the [`InavClock`] struct, which uses it
+//! to cross-check the GST supplied by the user and to compute the GST of INAV
+//! pages when only their reception order is known.
+//!
+//! # Security
+//!
+//! The WN and TOW fields of the INAV words 0, 5 and 6 are not part of the
+//! navigation data authenticated by OSNMA. An attacker can forge them, so the
+//! GST decoded from the INAV words, and hence the [`InavClock`], must not be
+//! used as the time reference for the TESLA key freshness checks. These checks
+//! require a time reference that is independent of the signal-in-space, with
+//! the accuracy given in the OSNMA Receiver Guidelines. A coarse bound on the
+//! GST obtained from such a time reference is given by a [`GstBound`].
+
+use crate::gst::SECS_IN_WEEK;
+use crate::types::{band_index, BitSlice, InavBand, InavWord, NUM_SVNS};
+use crate::{Gst, Svn, Tow, Wn};
+use bitvec::prelude::*;
+
+// Duration of an INAV page in seconds. This is the same for E1B and E5b.
+const SECS_PER_PAGE: i32 = 2;
+
+/// Decodes the GST contained in an INAV word.
+///
+/// This returns the GST at the start of the page in which the word is
+/// transmitted, if the word is a word type 0 with valid time (time field equal
+/// to `0b10`) or a word type 5. It returns `None` for any other word, including
+/// word type 6, which only contains the time of week (see [`word_tow`]).
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::inav_clock::word_gst;
+/// use galileo_osnma::Gst;
+///
+/// // Word type 0 with time field 0b10, WN 1177 and TOW 175767
+/// let word = [0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x49, 0x92, 0xae, 0x97];
+/// assert_eq!(word_gst(&word), Some(Gst::new(1177, 175767)));
+/// ```
+pub fn word_gst(word: &InavWord) -> Option<Gst> {
+    let bits = BitSlice::from_slice(word);
+    let (wn, tow) = match word_type(word) {
+        0 if bits[6..8].load_be::<u8>() == 0b10 => (&bits[96..108], &bits[108..128]),
+        5 => (&bits[73..85], &bits[85..105]),
+        _ => return None,
+    };
+    let tow = tow.load_be::<Tow>();
+    (tow < SECS_IN_WEEK).then(|| Gst::new(wn.load_be::<Wn>(), tow))
+}
+
+/// Decodes the time of week contained in an INAV word.
+///
+/// This returns the time of week at the start of the page in which the word
+/// is transmitted, if the word is a word type 0 with valid time, a word type 5
+/// or a word type 6. It returns `None` for any other word.
+pub fn word_tow(word: &InavWord) -> Option<Tow> {
+    if word_type(word) == 6 {
+        let tow = BitSlice::from_slice(word)[105..125].load_be::<Tow>();
+        (tow < SECS_IN_WEEK).then_some(tow)
+    } else {
+        word_gst(word).map(|gst| gst.tow())
+    }
+}
+
+fn word_type(word: &InavWord) -> u8 {
+    word[0] >> 2
+}
+
+// Returns the GST with time of week `tow` that is closest to `reference`.
+fn gst_near(tow: Tow, reference: Gst) -> Gst {
+    let gst = Gst::new(reference.wn(), tow);
+    let diff = gst.seconds_difference(reference);
+    let half_week = i32::try_from(SECS_IN_WEEK / 2).unwrap();
+    if diff > half_week && reference.wn() > 0 {
+        Gst::new(reference.wn() - 1, tow)
+    } else if diff < -half_week {
+        Gst::new(reference.wn() + 1, tow)
+    } else {
+        gst
+    }
+}
+
+// Decodes the GST of an INAV word using a reference GST. This is like
+// word_gst, but for word type 6, which does not contain the week number, the
+// week number is chosen so that the decoded GST is as close as possible to
+// the reference.
+fn word_gst_near(word: &InavWord, reference: Gst) -> Option<Gst> {
+    word_gst(word).or_else(|| Some(gst_near(word_tow(word)?, reference)))
+}
+
+/// Discrepancy between an expected GST and the GST decoded from an INAV word.
+///
+/// This is produced by an [`InavClock`] when the GST decoded from an INAV word
+/// does not match the GST that was expected for the page that contains the
+/// word. The [`Osnma`](crate::Osnma) black box reports it as an
+/// [`OsnmaEvent::GstDiscrepancy`](crate::event::OsnmaEvent::GstDiscrepancy).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GstDiscrepancy {
+    svn: Svn,
+    band: InavBand,
+    word_type: u8,
+    expected: Gst,
+    decoded: Gst,
+}
+
+impl GstDiscrepancy {
+    /// Returns the SVN of the satellite that transmitted the INAV word.
+    pub fn svn(&self) -> Svn {
+        self.svn
+    }
+
+    /// Returns the band in which the INAV word was received.
+    pub fn band(&self) -> InavBand {
+        self.band
+    }
+
+    /// Returns the type of the INAV word (0, 5 or 6).
+    pub fn word_type(&self) -> u8 {
+        self.word_type
+    }
+
+    /// Returns the expected GST of the page.
+    ///
+    /// This is either the GST supplied by the user or the GST predicted from
+    /// the reception order of the pages.
+    pub fn expected(&self) -> Gst {
+        self.expected
+    }
+
+    /// Returns the GST of the page decoded from the INAV word.
+    pub fn decoded(&self) -> Gst {
+        self.decoded
+    }
+
+    /// Returns the difference in seconds between the decoded and the expected
+    /// GST.
+    pub fn seconds(&self) -> i32 {
+        self.decoded.seconds_difference(self.expected)
+    }
+}
+
+/// Coarse bound on the GST.
+///
+/// This gives a GST obtained from a time reference that is independent of the
+/// signal-in-space, such as a real-time clock, together with the maximum error
+/// of this GST. It is used to reject INAV pages whose GST, as decoded from the
+/// unauthenticated WN and TOW fields, is not compatible with the time
+/// reference (see [`Osnma::feed_inav_page`](crate::Osnma::feed_inav_page)).
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::inav_clock::GstBound;
+/// use galileo_osnma::Gst;
+///
+/// let bound = GstBound::new(Gst::new(1177, 175770), 30);
+/// assert!(bound.contains(Gst::new(1177, 175740)));
+/// assert!(!bound.contains(Gst::new(1177, 175800 + 2)));
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GstBound {
+    gst: Gst,
+    max_error: u32,
+}
+
+impl GstBound {
+    /// Constructs a new GST bound.
+    ///
+    /// The `gst` parameter gives the current GST according to the time
+    /// reference, and `max_error` gives the maximum error of `gst` in seconds.
+    pub fn new(gst: Gst, max_error: u32) -> GstBound {
+        GstBound { gst, max_error }
+    }
+
+    /// Returns the GST of the time reference.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Returns the maximum error of the GST in seconds.
+    pub fn max_error(&self) -> u32 {
+        self.max_error
+    }
+
+    /// Returns `true` if `gst` is within the bound.
+    ///
+    /// This is the case if the difference between `gst` and the GST of the
+    /// time reference is at most the maximum error.
+    pub fn contains(&self, gst: Gst) -> bool {
+        gst.seconds_difference(self.gst).unsigned_abs() <= self.max_error
+    }
+}
+
+/// Clock derived from the INAV navigation message.
+///
+/// This struct uses the GST decoded from the INAV words 0, 5 and 6 to
+/// cross-check the GST of the INAV pages supplied by the user
+/// ([`InavClock::check`]), and to compute the GST of INAV pages when only
+/// their reception order is known ([`InavClock::next_page`]).
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::inav_clock::InavClock;
+/// use galileo_osnma::{Gst, InavBand, Svn};
+///
+/// let mut clock = InavClock::new();
+/// let svn = Svn::try_from(12).unwrap();
+/// // Word type 0 with time field 0b10, WN 1177 and TOW 175767
+/// let word0 = [0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x49, 0x92, 0xae, 0x97];
+/// let word1 = [0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+///
+/// // The GST of a word without time is not known until a word with time
+/// // is received.
+/// assert_eq!(clock.next_page(&word1, svn, InavBand::E1B), (None, None));
+/// assert_eq!(
+///     clock.next_page(&word0, svn, InavBand::E1B),
+///     (Some(Gst::new(1177, 175767)), None)
+/// );
+/// assert_eq!(
+///     clock.next_page(&word1, svn, InavBand::E1B),
+///     (Some(Gst::new(1177, 175769)), None)
+/// );
+///
+/// // Cross-check of a GST supplied by the user
+/// let discrepancy = clock
+///     .check(&word0, svn, InavBand::E1B, Gst::new(1177, 175769))
+///     .unwrap();
+/// assert_eq!(discrepancy.seconds(), -2);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct InavClock {
+    // GST of the next page of each SVN and band (E1B, E5b).
+    next_page: [[Option<Gst>; 2]; NUM_SVNS],
+}
+
+impl InavClock {
+    /// Constructs a new clock.
+    ///
+    /// The clock does not know the GST of the pages of any satellite until
+    /// INAV words containing the GST are fed to [`InavClock::next_page`].
+    pub fn new() -> InavClock {
+        InavClock {
+            next_page: [[None; 2]; NUM_SVNS],
+        }
+    }
+
+    /// Checks the GST of an INAV page supplied by the user.
+    ///
+    /// The `gst` parameter gives the GST at the start of the page that
+    /// contains the INAV word `word`. If the word contains the GST (see
+    /// [`word_gst`] and [`word_tow`]) and this is different from `gst`, the
+    /// discrepancy is logged and returned. Otherwise, this returns `None`.
+    pub fn check(
+        &self,
+        word: &InavWord,
+        svn: Svn,
+        band: InavBand,
+        gst: Gst,
+    ) -> Option<GstDiscrepancy> {
+        let decoded = word_gst_near(word, gst)?;
+        self.compare(word, svn, band, gst, decoded)
+    }
+
+    /// Computes the GST of the next INAV page of a satellite.
+    ///
+    /// This function should be called for every INAV page received from the
+    /// satellite with SVN `svn` in `band`, in reception order, with the INAV
+    /// word `word` contained in the page. Pages should not be skipped, since
+    /// the GST is computed by assuming that each page starts 2 seconds after
+    /// the previous one. If a page is missed,
+    /// [`InavClock::notify_loss_of_lock`] should be called.
+    ///
+    /// The GST at the start of the page is returned, or `None` if it is not
+    /// known yet. The GST becomes known when an INAV word that contains it is
+    /// received. If the GST in the word does not match the GST predicted from
+    /// the reception order, the discrepancy is logged and returned together
+    /// with the GST, and the clock is re-synchronized to the GST in the word.
+    ///
+    /// # Security
+    ///
+    /// The GST returned by this function is derived from INAV fields that are
+    /// not authenticated (see the [module documentation](self)). It must not
+    /// be used as the time reference for the TESLA key freshness checks.
+    pub fn next_page(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        band: InavBand,
+    ) -> (Option<Gst>, Option<GstDiscrepancy>) {
+        let predicted = self.next_page[usize::from(svn) - 1][band_index(band)];
+        let decoded = match predicted {
+            Some(predicted) => word_gst_near(word, predicted),
+            None => word_gst(word),
+        };
+        let (gst, discrepancy) = match (predicted, decoded) {
+            (Some(predicted), Some(decoded)) => (
+                Some(decoded),
+                self.compare(word, svn, band, predicted, decoded),
+            ),
+            (None, Some(decoded)) => {
+                debug!("INAV clock synchronized"; svn = %svn, band = band, gst = decoded);
+                (Some(decoded), None)
+            }
+            (predicted, None) => (predicted, None),
+        };
+        self.next_page[usize::from(svn) - 1][band_index(band)] =
+            gst.map(|gst| gst.add_seconds(SECS_PER_PAGE));
+        (gst, discrepancy)
+    }
+
+    /// Notify a loss of lock of a satellite.
+    ///
+    /// The GST of the pages of the satellite with SVN `svn` in `band` will not
+    /// be known again until an INAV word containing the GST is received.
+    pub fn notify_loss_of_lock(&mut self, svn: Svn, band: InavBand) {
+        self.next_page[usize::from(svn) - 1][band_index(band)] = None;
+    }
+
+    fn compare(
+        &self,
+        word: &InavWord,
+        svn: Svn,
+        band: InavBand,
+        expected: Gst,
+        decoded: Gst,
+    ) -> Option<GstDiscrepancy> {
+        if expected == decoded {
+            return None;
+        }
+        let discrepancy = GstDiscrepancy {
+            svn,
+            band,
+            word_type: word_type(word),
+            expected,
+            decoded,
+        };
+        warn!(
+            "GST in INAV word does not match expected GST";
+            svn = %svn,
+            band = band,
+            word_type = discrepancy.word_type,
+            expected = expected,
+            decoded = decoded,
+        );
+        Some(discrepancy)
+    }
+}
+
+impl Default for InavClock {
+    fn default() -> InavClock {
+        InavClock::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn word(word_type: u8, gst: Gst) -> InavWord {
+        let mut word = [0; 16];
+        let bits = BitSlice::from_slice_mut(&mut word);
+        bits[..6].store_be(word_type);
+        match word_type {
+            0 => {
+                bits[6..8].store_be(0b10u8);
+                bits[96..108].store_be(gst.wn());
+                bits[108..128].store_be(gst.tow());
+            }
+            5 => {
+                bits[73..85].store_be(gst.wn());
+                bits[85..105].store_be(gst.tow());
+            }
+            6 => bits[105..125].store_be(gst.tow()),
+            _ => (),
+        }
+        word
+    }
+
+    #[test]
+    fn decode() {
+        let gst = Gst::new(1177, 175767);
+        assert_eq!(word_gst(&word(0, gst)), Some(gst));
+        assert_eq!(word_gst(&word(5, gst)), Some(gst));
+        assert_eq!(word_gst(&word(6, gst)), None);
+        assert_eq!(word_tow(&word(6, gst)), Some(gst.tow()));
+        assert_eq!(word_tow(&word(1, gst)), None);
+        // Word type 0 without valid time
+        let mut w = word(0, gst);
+        w[0] = 0;
+        assert_eq!(word_gst(&w), None);
+    }
+
+    #[test]
+    fn week_rollover() {
+        let reference = Gst::new(1177, 604798);
+        let gst = Gst::new(1178, 0);
+        assert_eq!(word_gst_near(&word(6, gst), reference), Some(gst));
+        assert_eq!(word_gst_near(&word(6, reference), gst), Some(reference));
+    }
+
+    #[test]
+    fn next_page() {
+        let svn = Svn::try_from(12).unwrap();
+        let gst = Gst::new(1177, 175760);
+        let mut clock = InavClock::new();
+        assert_eq!(
+            clock.next_page(&word(6, gst), svn, InavBand::E1B),
+            (None, None)
+        );
+        assert_eq!(
+            clock.next_page(&word(5, gst), svn, InavBand::E1B),
+            (Some(gst), None)
+        );
+        assert_eq!(
+            clock.next_page(&word(2, gst), svn, InavBand::E1B),
+            (Some(gst.add_seconds(2)), None)
+        );
+        // The other band is independent
+        assert_eq!(
+            clock.next_page(&word(2, gst), svn, InavBand::E5B),
+            (None, None)
+        );
+
+        // A missed page is detected when a word with time is received, and
+        // the clock is re-synchronized.
+        let gst = gst.add_seconds(6);
+        let (page_gst, discrepancy) = clock.next_page(&word(6, gst), svn, InavBand::E1B);
+        assert_eq!(page_gst, Some(gst));
+        let discrepancy = discrepancy.unwrap();
+        assert_eq!(discrepancy.word_type(), 6);
+        assert_eq!(discrepancy.seconds(), 2);
+        assert_eq!(
+            clock.next_page(&word(0, gst.add_seconds(2)), svn, InavBand::E1B),
+            (Some(gst.add_seconds(2)), None)
+        );
+
+        clock.notify_loss_of_lock(svn, InavBand::E1B);
+        assert_eq!(
+            clock.next_page(&word(2, gst), svn, InavBand::E1B),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn check() {
+        let svn = Svn::try_from(12).unwrap();
+        let gst = Gst::new(1177, 175760);
+        let clock = InavClock::new();
+        assert_eq!(clock.check(&word(0, gst), svn, InavBand::E5B, gst), None);
+        assert_eq!(clock.check(&word(3, gst), svn, InavBand::E5B, gst), None);
+        let discrepancy = clock
+            .check(&word(6, gst), svn, InavBand::E5B, gst.add_seconds(-30))
+            .unwrap();
+        assert_eq!(discrepancy.band(), InavBand::E5B);
+        assert_eq!(discrepancy.decoded(), gst);
+        assert_eq!(discrepancy.seconds(), 30);
+    }
+}
@@ -22,9 +22,13 @@
 //!
 //! ## Logging
 //!
-//! The galileo-osnma crate makes extensive use of the
-//! [log](https://docs.rs/log/latest/log/) crate to log events related to the
-//! processing of the messages and the cryptographic functions.
+//! The galileo-osnma crate logs events related to the processing of the
+//! messages and the cryptographic functions. Each event has a message and a
+//! list of structured fields, which are formatted as `name=value`. By default,
+//! the events are logged using the [log](https://docs.rs/log/latest/log/)
+//! crate. Embedded targets can use [defmt](https://docs.rs/defmt/latest/defmt/)
+//! instead, which avoids the formatting overhead on the target, or disable
+//! logging entirely to save code size (see the features below).
 //!
 //! ## Galmon integration
 //!
@@ -46,21 +50,48 @@
 //!
 //! When built with the default features, the crate does not require
 //! `std`. Additionally, the crate supports the following features:
+//! * `log`. This is enabled by default, and logs events using the
+//!   [log](https://docs.rs/log/latest/log/) crate. It needs to be enabled
+//!   explicitly when building with `default-features = false`.
+//! * `defmt`. This logs events using
+//!   [defmt](https://docs.rs/defmt/latest/defmt/). It can be enabled together
+//!   with or instead of `log`. If neither `log` nor `defmt` are enabled,
+//!   logging is compiled out.
 //! * `galmon`. This enables support for reading the Galmon transport protocol
-//!    and requires `std`.
+//!   and requires `std`.
+//! * `parallel`. This validates the MACK messages and tags of different
+//!   satellites in parallel using [rayon](https://docs.rs/rayon), and
+//!   requires `std`. The results are the same as when the validation is done
+//!   sequentially.
+//! * `previous-ced-batch`. This is enabled by default. During an ephemeris
+//!   update, it keeps the previous complete batch of CED and health status
+//!   data so that it can still be authenticated, at the cost of doubling the
+//!   storage used for this data (see
+//!   [`CollectNavMessage`](navmessage::CollectNavMessage)).
 //! * `p521`. This enables support for ECDSA P-521 public keys. These public keys
-//!    defined in the OSNMA ICD, but currently only ECDSA P-256 keys are used in
-//!    the signal-in-space.
+//!   defined in the OSNMA ICD, but currently only ECDSA P-256 keys are used in
+//!   the signal-in-space.
+//! * `serde`. This implements the [serde](https://docs.rs/serde) `Serialize`
+//!   and `Deserialize` traits for the public data types, such as [`Gst`],
+//!   [`Svn`], the enums of the message fields, [`tesla::Chain`], and the owned
+//!   counterparts of the message and navigation data types (for instance,
+//!   [`bitfields::OwnedDsmKroot`]). It does not require `std`.
 
 #![warn(missing_docs)]
 #![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
 
+#[macro_use]
+mod logging;
+
 pub mod bitfields;
+pub mod builders;
 pub mod dsm;
+pub mod event;
 #[cfg(feature = "galmon")]
 pub mod galmon;
 mod gst;
 pub use gst::{Gst, Tow, Wn};
+pub mod inav_clock;
 pub mod mack;
 pub mod maclt;
 pub mod merkle_tree;
@@ -68,11 +99,17 @@ pub use merkle_tree::PublicKey;
 pub mod navmessage;
 mod osnma;
 pub use osnma::Osnma;
+mod reed_solomon;
+#[cfg(feature = "serde")]
+mod serde_helpers;
+pub mod snapshot;
+pub mod state;
 pub mod storage;
 pub mod subframe;
 mod svn;
 pub use svn::{Svn, SvnError};
 pub mod tesla;
+pub mod trace;
 pub mod types;
 pub use types::{InavBand, MerkleTreeNode};
 pub mod validation;
@@ -0,0 +1,113 @@
+//! Logging facade.
+//!
+//! The rest of the crate logs through the macros defined here instead of
+//! calling the [log](https://docs.rs/log/latest/log/) crate directly. The
+//! events can be routed to `log` (feature `log`), to
+//! [defmt](https://docs.rs/defmt/latest/defmt/) (feature `defmt`), to both, or
+//! compiled out entirely if neither feature is enabled.
+//!
+//! Each event consists of a constant message and an optional list of
+//! structured fields, separated from the message by a semicolon:
+//!
+//! ```ignore
+//! info!("new TESLA key validated"; gst = key.gst_subframe(), hashes = hashes);
+//! warn!("received OSNMA Alert Message");
+//! ```
+//!
+//! Fields are formatted with [`Debug`](core::fmt::Debug) by default, or with
+//! [`Display`](core::fmt::Display) if the value is prefixed by `%`. When
+//! `defmt` is used, all the fields are formatted with
+//! [`defmt::Format`](https://docs.rs/defmt/latest/defmt/trait.Format.html).
+//! Byte slices can be wrapped in [`Hex`] to format them in hexadecimal.
+
+use core::fmt;
+
+macro_rules! error {
+    ($($args:tt)+) => { event!(error, $($args)+) };
+}
+
+macro_rules! warn {
+    ($($args:tt)+) => { event!(warn, $($args)+) };
+}
+
+macro_rules! info {
+    ($($args:tt)+) => { event!(info, $($args)+) };
+}
+
+macro_rules! debug {
+    ($($args:tt)+) => { event!(debug, $($args)+) };
+}
+
+macro_rules! trace {
+    ($($args:tt)+) => { event!(trace, $($args)+) };
+}
+
+macro_rules! event {
+    // Entry point
+    ($level:ident, $msg:literal $(; $($fields:tt)*)?) => {
+        event!(@munch $level, $msg, [], $($($fields)*)?)
+    };
+    // Normalize the fields to a list of (name, log format spec, value)
+    (@munch $level:ident, $msg:tt, [$($done:tt)*], $(,)?) => {
+        event!(@emit $level, $msg, [$($done)*])
+    };
+    (@munch $level:ident, $msg:tt, [$($done:tt)*],
+     $name:ident = %$value:expr $(, $($rest:tt)*)?) => {
+        event!(@munch $level, $msg, [$($done)* ($name, "={}", $value)], $($($rest)*)?)
+    };
+    (@munch $level:ident, $msg:tt, [$($done:tt)*],
+     $name:ident = $value:expr $(, $($rest:tt)*)?) => {
+        event!(@munch $level, $msg, [$($done)* ($name, "={:?}", $value)], $($($rest)*)?)
+    };
+    // Dispatch to the enabled backends
+    (@emit $level:ident, $msg:tt, [$(($name:ident, $spec:tt, $value:expr))*]) => {{
+        #[cfg(feature = "log")]
+        log::$level!(concat!($msg $(, " ", stringify!($name), $spec)*) $(, $value)*);
+        #[cfg(feature = "defmt")]
+        event!(@defmt $level, $msg, [$(($name, $value))*]);
+        #[cfg(not(any(feature = "log", feature = "defmt")))]
+        {
+            $(let _ = &$value;)*
+        }
+    }};
+    (@defmt $level:ident, $msg:tt, []) => {
+        defmt::$level!($msg)
+    };
+    (@defmt $level:ident, $msg:tt, [$(($name:ident, $value:expr))+]) => {
+        defmt::$level!(
+            "{=istr} {}",
+            defmt::intern!($msg),
+            ($($crate::logging::Field(stringify!($name), &$value),)+)
+        )
+    };
+}
+
+/// Structured field of a `defmt` event.
+#[cfg(feature = "defmt")]
+pub(crate) struct Field<'a, T: ?Sized>(pub(crate) &'static str, pub(crate) &'a T);
+
+#[cfg(feature = "defmt")]
+impl<T: defmt::Format + ?Sized> defmt::Format for Field<'_, T> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}={}", self.0, self.1)
+    }
+}
+
+/// Byte slice formatted in hexadecimal.
+///
+/// This is used to log the contents of messages.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct Hex<'a>(pub(crate) &'a [u8]);
+
+impl fmt::Debug for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x?}", self.0)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Hex<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=[u8]:02x}", self.0)
+    }
+}
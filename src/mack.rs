@@ -5,7 +5,8 @@
 
 use crate::bitfields::NmaStatus;
 use crate::gst::Gst;
-use crate::storage::StaticStorage;
+use crate::logging::Hex;
+use crate::storage::{find_gst_row, StaticStorage, SvnIndex};
 use crate::types::MackMessage;
 use crate::Svn;
 use generic_array::GenericArray;
@@ -17,10 +18,15 @@ use typenum::Unsigned;
 /// they can be used when the TESLA keys corresponding to their tags become
 /// available. The storage size is statically allocated, and as new messages are
 /// stored, the older ones are deleted.
+///
+/// An index giving the location of the MACK message of each SVN is maintained
+/// for each stored GST, so that retrieving a MACK message does not require
+/// searching through the storage.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct MackStorage<S: StaticStorage> {
     macks: GenericArray<Option<Mack>, S::MackDepthSats>,
     gsts: GenericArray<Option<Gst>, S::MackDepth>,
+    index: GenericArray<SvnIndex, S::MackDepth>,
     write_pointer: usize,
 }
 
@@ -39,6 +45,7 @@ impl<S: StaticStorage> MackStorage<S> {
         MackStorage {
             macks: GenericArray::default(),
             gsts: GenericArray::default(),
+            index: GenericArray::default(),
             write_pointer: 0,
         }
     }
@@ -59,23 +66,26 @@ impl<S: StaticStorage> MackStorage<S> {
     /// message was transmitted.
     pub fn store(&mut self, mack: &MackMessage, svn: Svn, gst: Gst, nma_status: NmaStatus) {
         self.adjust_write_pointer(gst);
-        for location in self.current_macks_as_mut().iter_mut() {
-            if location.is_none() {
-                log::trace!("storing MACK {:02x?} for {} and GST {:?}", mack, svn, gst);
-                *location = Some(Mack {
-                    message: *mack,
-                    svn,
-                    nma_status,
-                });
-                return;
-            }
+        if self.index[self.write_pointer].get(svn).is_some() {
+            trace!(
+                "MACK already stored; ignoring";
+                svn = %svn,
+                gst = gst,
+                mack = Hex(mack),
+            );
+            return;
         }
-        log::warn!(
-            "no room to store MACK {:02x?} for {} and GST {:?}",
-            mack,
+        let Some(slot) = self.current_macks_as_mut().iter().position(Option::is_none) else {
+            warn!("no room to store MACK"; svn = %svn, gst = gst, mack = Hex(mack));
+            return;
+        };
+        trace!("storing MACK"; svn = %svn, gst = gst, mack = Hex(mack));
+        self.current_macks_as_mut()[slot] = Some(Mack {
+            message: *mack,
             svn,
-            gst
-        );
+            nma_status,
+        });
+        self.index[self.write_pointer].set(svn, slot);
     }
 
     fn current_macks_as_mut(&mut self) -> &mut [Option<Mack>] {
@@ -88,14 +98,10 @@ impl<S: StaticStorage> MackStorage<S> {
         // everything at the new write pointer location.
         if let Some(g) = self.gsts[self.write_pointer] {
             if g != gst {
-                log::trace!(
-                    "got a new GST {:?} (current GST is {:?}); \
-                             advancing write pointer",
-                    gst,
-                    g
-                );
+                trace!("got a new GST; advancing write pointer"; gst = gst, current_gst = g);
                 self.write_pointer = (self.write_pointer + 1) % S::MackDepth::USIZE;
                 self.current_macks_as_mut().fill(None);
+                self.index[self.write_pointer] = SvnIndex::default();
             }
         }
         self.gsts[self.write_pointer] = Some(gst);
@@ -114,20 +120,13 @@ impl<S: StaticStorage> MackStorage<S> {
     /// The `gst` parameter refers to the GST at the start of the subframe when the
     /// MACK message was transmitted.
     pub fn get(&self, svn: Svn, gst: Gst) -> Option<(&MackMessage, NmaStatus)> {
-        let gst_idx =
-            self.gsts
-                .iter()
-                .enumerate()
-                .find_map(|(j, &g)| if g == Some(gst) { Some(j) } else { None })?;
-        self.macks[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS]
-            .iter()
-            .find_map(|x| match x {
-                Some(Mack {
-                    svn: s,
-                    message,
-                    nma_status,
-                }) if *s == svn => Some((message, *nma_status)),
-                _ => None,
+        let gst_idx = find_gst_row(&self.gsts, self.write_pointer, gst)?;
+        let slot = self.index[gst_idx].get(svn)?;
+        self.macks[gst_idx * S::NUM_SATS + slot]
+            .as_ref()
+            .map(|mack| {
+                debug_assert_eq!(mack.svn, svn);
+                (&mack.message, mack.nma_status)
             })
     }
 }
@@ -137,3 +136,52 @@ impl<S: StaticStorage> Default for MackStorage<S> {
         MackStorage::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::SmallStorage;
+
+    fn svn(n: u8) -> Svn {
+        Svn::try_from(n).unwrap()
+    }
+
+    #[test]
+    fn store_and_get() {
+        let mut storage = MackStorage::<SmallStorage>::new();
+        let gst = Gst::new(1176, 120930);
+        let mack_a = [0xaa; 60];
+        let mack_b = [0xbb; 60];
+        storage.store(&mack_a, svn(5), gst, NmaStatus::Test);
+        storage.store(&mack_b, svn(30), gst, NmaStatus::Operational);
+        assert_eq!(storage.get(svn(5), gst), Some((&mack_a, NmaStatus::Test)));
+        assert_eq!(
+            storage.get(svn(30), gst),
+            Some((&mack_b, NmaStatus::Operational))
+        );
+        assert!(storage.get(svn(6), gst).is_none());
+        assert!(storage.get(svn(5), gst.add_seconds(30)).is_none());
+
+        // A second message for the same SVN and GST is ignored
+        storage.store(&mack_b, svn(5), gst, NmaStatus::Operational);
+        assert_eq!(storage.get(svn(5), gst), Some((&mack_a, NmaStatus::Test)));
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let mut storage = MackStorage::<SmallStorage>::new();
+        let gst0 = Gst::new(1176, 120930);
+        let gst1 = gst0.add_seconds(30);
+        let gst2 = gst1.add_seconds(30);
+        storage.store(&[0; 60], svn(1), gst0, NmaStatus::Test);
+        storage.store(&[1; 60], svn(1), gst1, NmaStatus::Test);
+        assert_eq!(storage.get(svn(1), gst0).unwrap().0, &[0; 60]);
+        assert_eq!(storage.get(svn(1), gst1).unwrap().0, &[1; 60]);
+        // SmallStorage keeps only two subframes, so gst0 is overwritten
+        storage.store(&[2; 60], svn(2), gst2, NmaStatus::Test);
+        assert!(storage.get(svn(1), gst0).is_none());
+        assert!(storage.get(svn(1), gst2).is_none());
+        assert_eq!(storage.get(svn(1), gst1).unwrap().0, &[1; 60]);
+        assert_eq!(storage.get(svn(2), gst2).unwrap().0, &[2; 60]);
+    }
+}
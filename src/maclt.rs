@@ -3,9 +3,19 @@
 //! This module contains the MAC Look-up Table defined in ANNEX C of the
 //! [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 //! and the supporting code required to use it.
+//!
+//! The functions [`get_maclt_entry`] and [`get_flx_indices`] only use the
+//! tables that are built into the crate. Tables published in later ICD
+//! updates can be added at runtime to a [`MacLTRegistry`], either as
+//! [`MacLTEntry`] values or by loading a text description with
+//! [`MacLTRegistry::load`]. With the `maclt-json` feature, a JSON description
+//! can also be loaded with `MacLTRegistry::load_json`.
 
 use crate::bitfields::Adkd;
+#[cfg(feature = "serde")]
+use crate::serde_helpers::Seq;
 use core::fmt;
+use core::str::FromStr;
 
 const MSG: usize = 2;
 
@@ -43,7 +53,26 @@ const F12E: MacLTSlot = MacLTSlot::Fixed {
 };
 const FLX: MacLTSlot = MacLTSlot::Flex;
 
-struct MacLTEntry {
+/// MAC Look-up Table entry.
+///
+/// This represents the entry of the MAC Look-up Table for a particular MACLT
+/// ID, which contains the sequences of slots for the two messages (Msg = 2) or
+/// the single message (Msg = 1) of the entry. Entries are validated for
+/// consistency when they are constructed with [`MacLTEntry::new`] or parsed
+/// from a text description with [`FromStr`].
+///
+/// With the `serde` feature, entries can be deserialized from a structure with
+/// an `id` field containing the MACLT ID and a `sequences` field containing
+/// one or two sequences of slots, which are given as strings. See
+/// `MacLTRegistry::load_json` for an example. Deserialized entries are also
+/// validated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(try_from = "MacLTEntryDescription")
+)]
+pub struct MacLTEntry {
     id: u8,
     nt: u8,
     // The first entry in the sequence is omitted, since it is always 00S and is
@@ -156,57 +185,409 @@ static MACLT: [MacLTEntry; MAC_LT_ENTRIES] = [
     },
 ];
 
+impl MacLTEntry {
+    /// Constructs a new MAC Look-up Table entry.
+    ///
+    /// The `sequences` contain the slots for each of the two messages of the
+    /// entry, including the first slot, which corresponds to tag0 and must be
+    /// '00S'. For entries with Msg = 1 the same sequence should be given
+    /// twice. The number of tags 'nt' of the entry is the length of the
+    /// sequences.
+    ///
+    /// An error is returned if the sequences do not have the same length, if
+    /// their length is not between 1 and 10, if the first slot is not '00S',
+    /// if a slot is '04E', which is not allowed by the ICD, or if a sequence
+    /// has more than [`MAX_FLX_ENTRIES`] FLX slots.
+    pub fn new(id: u8, sequences: [&[MacLTSlot]; MSG]) -> Result<MacLTEntry, MacLTRegistryError> {
+        let nt = sequences[0].len();
+        if sequences[1].len() != nt {
+            return Err(MacLTRegistryError::MismatchedSequences);
+        }
+        if !(1..=MAX_NT).contains(&nt) {
+            return Err(MacLTRegistryError::WrongNumberOfTags);
+        }
+        let mut sequence = [[FLX; MAX_NT - 1]; MSG];
+        for (dest, source) in sequence.iter_mut().zip(sequences) {
+            if source[0] != F00S {
+                return Err(MacLTRegistryError::WrongFirstSlot);
+            }
+            if source.contains(&MacLTSlot::Fixed {
+                adkd: Adkd::InavTiming,
+                object: AuthObject::CrossAuth,
+            }) {
+                return Err(MacLTRegistryError::InvalidSlot);
+            }
+            if source[1..].iter().filter(|&&x| x == FLX).count() > MAX_FLX_ENTRIES {
+                return Err(MacLTRegistryError::TooManyFlx);
+            }
+            dest[..nt - 1].copy_from_slice(&source[1..]);
+        }
+        Ok(MacLTEntry {
+            id,
+            nt: nt.try_into().unwrap(),
+            sequence,
+        })
+    }
+
+    /// Gives the MACLT ID of the entry.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Gives the number of tags 'nt' of the entry, including tag0.
+    pub fn nt(&self) -> usize {
+        self.nt.into()
+    }
+
+    /// Gives the slot corresponding to a message number `msg` (either zero or
+    /// one) and tag number `num_tag`.
+    ///
+    /// If `num_tag` is not smaller than the number of tags of the entry, an
+    /// error is returned.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `msg` is not zero or one, or if `num_tag` is zero.
+    pub fn slot(&self, msg: usize, num_tag: usize) -> Result<MacLTSlot, MacLTError> {
+        assert!((msg == 0) || (msg == 1));
+        assert!(num_tag >= 1);
+        if num_tag >= self.nt() {
+            return Err(MacLTError::InvalidTagNumber);
+        }
+        let slot = self.sequence[msg][num_tag - 1];
+        // Enforce that InavTiming must use SelfAuth as AuthObject
+        if let MacLTSlot::Fixed { adkd, object } = slot {
+            assert!(adkd != Adkd::InavTiming || object == AuthObject::SelfAuth);
+        }
+        Ok(slot)
+    }
+
+    /// Returns an iterator over the indices corresponding to FLX slots for a
+    /// message number `msg` (either zero or one).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `msg` is not zero or one.
+    pub fn flx_indices(&self, msg: usize) -> impl Iterator<Item = usize> + '_ {
+        assert!((msg == 0) || (msg == 1));
+        self.sequence[msg]
+            .iter()
+            .take(self.nt() - 1)
+            .enumerate()
+            .filter_map(|(j, &x)| if x == FLX { Some(j + 1) } else { None })
+    }
+}
+
+/// Parses a MAC Look-up Table entry from its text description.
+///
+/// The description contains the MACLT ID followed by the slots of the sequence
+/// of the first message, as they appear in ANNEX C of the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf)
+/// ('00S', '00E', '04S', '12S', '12E' or 'FLX'), separated by whitespace. For
+/// entries with Msg = 2, the sequence of the second message follows after a
+/// '/'. For example, the entry for MACLT 34 is described as
+/// ```text
+/// 34 00S FLX 04S FLX 12S 00E / 00S FLX 00E 12S 00E 12E
+/// ```
+/// The entry is validated as in [`MacLTEntry::new`].
+impl FromStr for MacLTEntry {
+    type Err = MacLTRegistryError;
+
+    fn from_str(s: &str) -> Result<MacLTEntry, MacLTRegistryError> {
+        let mut tokens = s.split_whitespace();
+        let id = tokens
+            .next()
+            .and_then(|t| t.parse::<u8>().ok())
+            .ok_or(MacLTRegistryError::Syntax)?;
+        let mut sequences = [[F00S; MAX_NT]; MSG];
+        let mut lens = [0; MSG];
+        let mut msg = 0;
+        for token in tokens {
+            if token == "/" {
+                if msg == 1 {
+                    return Err(MacLTRegistryError::Syntax);
+                }
+                msg = 1;
+                continue;
+            }
+            if lens[msg] == MAX_NT {
+                return Err(MacLTRegistryError::WrongNumberOfTags);
+            }
+            sequences[msg][lens[msg]] = parse_slot(token)?;
+            lens[msg] += 1;
+        }
+        if msg == 0 {
+            // Msg = 1 entry
+            sequences[1] = sequences[0];
+            lens[1] = lens[0];
+        }
+        MacLTEntry::new(id, [&sequences[0][..lens[0]], &sequences[1][..lens[1]]])
+    }
+}
+
+fn parse_slot(token: &str) -> Result<MacLTSlot, MacLTRegistryError> {
+    if token == "FLX" {
+        return Ok(FLX);
+    }
+    let (Some(adkd), Some(object)) = (token.get(..2), token.get(2..)) else {
+        return Err(MacLTRegistryError::Syntax);
+    };
+    let adkd = match adkd {
+        "00" => Adkd::InavCed,
+        "04" => Adkd::InavTiming,
+        "12" => Adkd::SlowMac,
+        _ => return Err(MacLTRegistryError::Syntax),
+    };
+    let object = match object {
+        "S" => AuthObject::SelfAuth,
+        "E" => AuthObject::CrossAuth,
+        _ => return Err(MacLTRegistryError::Syntax),
+    };
+    Ok(MacLTSlot::Fixed { adkd, object })
+}
+
+// Structure from which a MacLTEntry is deserialized.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct MacLTEntryDescription {
+    id: u8,
+    sequences: Seq<Seq<MacLTSlot, MAX_NT>, MSG>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<MacLTEntryDescription> for MacLTEntry {
+    type Error = MacLTRegistryError;
+
+    fn try_from(description: MacLTEntryDescription) -> Result<MacLTEntry, MacLTRegistryError> {
+        if description.sequences.len() == 0 {
+            return Err(MacLTRegistryError::Syntax);
+        }
+        let mut sequences = [[F00S; MAX_NT]; MSG];
+        let mut lens = [0; MSG];
+        for (msg, sequence) in description.sequences.iter().enumerate() {
+            for (slot, source) in sequences[msg].iter_mut().zip(sequence.iter()) {
+                *slot = source;
+            }
+            lens[msg] = sequence.len();
+        }
+        if description.sequences.len() == 1 {
+            // Msg = 1 entry
+            sequences[1] = sequences[0];
+            lens[1] = lens[0];
+        }
+        MacLTEntry::new(
+            description.id,
+            [&sequences[0][..lens[0]], &sequences[1][..lens[1]]],
+        )
+    }
+}
+
+fn builtin_entry(maclt: u8) -> Result<&'static MacLTEntry, MacLTError> {
+    MACLT
+        .iter()
+        .find(|&x| x.id == maclt)
+        .ok_or(MacLTError::InvalidMaclt)
+}
+
 /// Looks up an entry in the MAC Look-up Table.
 ///
 /// This function looks up and returns the entry of the MAC Look-up Table
 /// corresponding to a `maclt` ID, message number `msg` (either zero or one) and
 /// tag number `num_tag`. If the entry does not exist in the table, an error is
-/// returned.
+/// returned. Only the tables built into the crate are used. See
+/// [`MacLTRegistry::get_maclt_entry`] for a lookup that also uses tables added
+/// at runtime.
 ///
 /// # Panics
 ///
 /// This function panics if `msg` is not zero or one, or if `num_tag` is zero.
 pub fn get_maclt_entry(maclt: u8, msg: usize, num_tag: usize) -> Result<MacLTSlot, MacLTError> {
-    assert!((msg == 0) || (msg == 1));
-    assert!(num_tag >= 1);
-    let Some(entry) = MACLT.iter().find(|&x| x.id == maclt) else {
-        return Err(MacLTError::InvalidMaclt);
-    };
-    if num_tag >= entry.nt.into() {
-        return Err(MacLTError::InvalidTagNumber);
-    }
-    let entry = entry.sequence[msg][num_tag - 1];
-    // Enforce that InavTiming must use SelfAuth as AuthObject
-    if let MacLTSlot::Fixed { adkd, object } = entry {
-        assert!(adkd != Adkd::InavTiming || object == AuthObject::SelfAuth);
-    }
-    Ok(entry)
+    builtin_entry(maclt)?.slot(msg, num_tag)
 }
 
 /// Returns an iterator over the indices corresponding to FLX entries.
 ///
 /// This function returns an iterator over the indices corresponding to FLX
 /// entries for a particular `maclt` ID and message number `msg` (either zero or
-/// one). If the ID does not exist in the table, an error is returned.
+/// one). If the ID does not exist in the table, an error is returned. Only the
+/// tables built into the crate are used. See
+/// [`MacLTRegistry::get_flx_indices`] for a lookup that also uses tables added
+/// at runtime.
 ///
 /// # Panics
 ///
 /// This function panics if `msg` is not zero or one.
 pub fn get_flx_indices(maclt: u8, msg: usize) -> Result<impl Iterator<Item = usize>, MacLTError> {
-    assert!((msg == 0) || (msg == 1));
-    let Some(entry) = MACLT.iter().find(|&x| x.id == maclt) else {
-        return Err(MacLTError::InvalidMaclt);
-    };
-    Ok(entry.sequence[msg]
-        .iter()
-        .take(usize::from(entry.nt) - 1)
-        .enumerate()
-        .filter_map(|(j, &x)| if x == FLX { Some(j + 1) } else { None }))
+    Ok(builtin_entry(maclt)?.flx_indices(msg))
+}
+
+/// Registry of MAC Look-up Tables.
+///
+/// The registry holds the MAC Look-up Table entries built into the crate, and
+/// up to `N` additional entries that can be added at runtime, for instance
+/// to use a MACLT ID published in an ICD update. The additional entries are
+/// statically allocated.
+#[derive(Debug, Clone)]
+pub struct MacLTRegistry<const N: usize> {
+    additional: [Option<MacLTEntry>; N],
+}
+
+impl<const N: usize> MacLTRegistry<N> {
+    /// Constructs a new registry containing only the built-in entries.
+    pub fn new() -> MacLTRegistry<N> {
+        MacLTRegistry {
+            additional: [None; N],
+        }
+    }
+
+    /// Returns an iterator over all the entries in the registry.
+    ///
+    /// The built-in entries are given first, followed by the entries that have
+    /// been added at runtime.
+    pub fn entries(&self) -> impl Iterator<Item = &MacLTEntry> {
+        MACLT.iter().chain(self.additional.iter().flatten())
+    }
+
+    /// Gives the entry for a `maclt` ID, if it exists in the registry.
+    pub fn get(&self, maclt: u8) -> Option<&MacLTEntry> {
+        self.entries().find(|&x| x.id == maclt)
+    }
+
+    /// Adds an entry to the registry.
+    ///
+    /// An error is returned if there is already an entry with the same MACLT
+    /// ID in the registry, or if the registry is full.
+    pub fn add(&mut self, entry: MacLTEntry) -> Result<(), MacLTRegistryError> {
+        if self.get(entry.id).is_some() {
+            return Err(MacLTRegistryError::DuplicateId);
+        }
+        let slot = self
+            .additional
+            .iter_mut()
+            .find(|x| x.is_none())
+            .ok_or(MacLTRegistryError::Full)?;
+        *slot = Some(entry);
+        Ok(())
+    }
+
+    /// Adds the entries in a text description to the registry.
+    ///
+    /// The description contains one entry per line, in the format described
+    /// in the [`FromStr`] implementation for [`MacLTEntry`]. Empty lines and
+    /// lines starting by '#' are ignored.
+    ///
+    /// Either all the entries in the description are added, or none is added
+    /// if any of them cannot be added. In this case, the error gives the line
+    /// number of the first entry that could not be added. On success, the
+    /// number of entries added is returned.
+    pub fn load(&mut self, description: &str) -> Result<usize, MacLTLoadError> {
+        let mut registry = self.clone();
+        let mut count = 0;
+        for (j, line) in description.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            line.parse()
+                .and_then(|entry| registry.add(entry))
+                .map_err(|error| MacLTLoadError { line: j + 1, error })?;
+            count += 1;
+        }
+        *self = registry;
+        Ok(count)
+    }
+
+    /// Adds the entries in a JSON description to the registry.
+    ///
+    /// The description is a JSON array with one object per entry. Each object
+    /// has an `id` field with the MACLT ID and a `sequences` field with the
+    /// sequences of slots of the messages of the entry, given as strings
+    /// ('00S', '00E', '04S', '12S', '12E' or 'FLX'). Entries with Msg = 1
+    /// contain a single sequence. For example, the entry for MACLT 34 is
+    /// described as
+    /// ```text
+    /// [
+    ///   {
+    ///     "id": 34,
+    ///     "sequences": [
+    ///       ["00S", "FLX", "04S", "FLX", "12S", "00E"],
+    ///       ["00S", "FLX", "00E", "12S", "00E", "12E"]
+    ///     ]
+    ///   }
+    /// ]
+    /// ```
+    /// The entries are deserialized as [`MacLTEntry`] values, so they are
+    /// validated as in [`MacLTEntry::new`].
+    ///
+    /// As in [`MacLTRegistry::load`], either all the entries in the description
+    /// are added, or none is added if any of them cannot be added. On success,
+    /// the number of entries added is returned.
+    #[cfg(feature = "maclt-json")]
+    pub fn load_json(&mut self, description: &str) -> Result<usize, MacLTJsonError> {
+        let entries: Vec<MacLTEntry> =
+            serde_json::from_str(description).map_err(MacLTJsonError::Json)?;
+        let mut registry = self.clone();
+        for (index, &entry) in entries.iter().enumerate() {
+            registry
+                .add(entry)
+                .map_err(|error| MacLTJsonError::Entry { index, error })?;
+        }
+        *self = registry;
+        Ok(entries.len())
+    }
+
+    /// Looks up an entry in the MAC Look-up Table.
+    ///
+    /// This works as [`get_maclt_entry`], but it also uses the entries added to
+    /// the registry at runtime.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `msg` is not zero or one, or if `num_tag` is zero.
+    pub fn get_maclt_entry(
+        &self,
+        maclt: u8,
+        msg: usize,
+        num_tag: usize,
+    ) -> Result<MacLTSlot, MacLTError> {
+        self.get(maclt)
+            .ok_or(MacLTError::InvalidMaclt)?
+            .slot(msg, num_tag)
+    }
+
+    /// Returns an iterator over the indices corresponding to FLX entries.
+    ///
+    /// This works as [`get_flx_indices`], but it also uses the entries added to
+    /// the registry at runtime.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `msg` is not zero or one.
+    pub fn get_flx_indices(
+        &self,
+        maclt: u8,
+        msg: usize,
+    ) -> Result<impl Iterator<Item = usize> + '_, MacLTError> {
+        Ok(self
+            .get(maclt)
+            .ok_or(MacLTError::InvalidMaclt)?
+            .flx_indices(msg))
+    }
+}
+
+impl<const N: usize> Default for MacLTRegistry<N> {
+    fn default() -> MacLTRegistry<N> {
+        MacLTRegistry::new()
+    }
 }
 
 /// MAC Look-up Table slot.
 ///
 /// This enum represents a slot in the MAC Look-up Table.
+///
+/// With the `serde` feature, slots are deserialized from strings containing
+/// their representation in the MAC Look-up Table ('00S', '00E', '04S', '12S',
+/// '12E' or 'FLX').
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum MacLTSlot {
     /// Fixed slot.
@@ -243,8 +624,32 @@ pub enum AuthObject {
     CrossAuth,
 }
 
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MacLTSlot {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<MacLTSlot, D::Error> {
+        deserializer.deserialize_str(MacLTSlotVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct MacLTSlotVisitor;
+
+#[cfg(feature = "serde")]
+impl serde::de::Visitor<'_> for MacLTSlotVisitor {
+    type Value = MacLTSlot;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a MAC look-up table slot ('00S', '00E', '04S', '12S', '12E' or 'FLX')")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<MacLTSlot, E> {
+        parse_slot(v).map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+    }
+}
+
 /// Errors produced during MAC Table look-up.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MacLTError {
     /// The value of the MACLT does not appear as an ID in the MAC Look-up
     /// Table.
@@ -252,6 +657,9 @@ pub enum MacLTError {
     /// The tag number is greater than the number of tags 'nt' in the MAC
     /// Look-up Table entry.
     InvalidTagNumber,
+    /// The number of tags 'nt' in the MAC Look-up Table entry does not match
+    /// the number of tags in the MACK message.
+    WrongNumberOfTags,
 }
 
 impl fmt::Display for MacLTError {
@@ -259,6 +667,9 @@ impl fmt::Display for MacLTError {
         match self {
             MacLTError::InvalidMaclt => "invalid MAC look-up table ID".fmt(f),
             MacLTError::InvalidTagNumber => "invalid tag number".fmt(f),
+            MacLTError::WrongNumberOfTags => {
+                "number of tags does not match MAC look-up table entry".fmt(f)
+            }
         }
     }
 }
@@ -266,6 +677,113 @@ impl fmt::Display for MacLTError {
 #[cfg(feature = "std")]
 impl std::error::Error for MacLTError {}
 
+/// Errors produced when adding entries to a [`MacLTRegistry`].
+///
+/// These errors can also be produced when constructing a [`MacLTEntry`], since
+/// entries are validated for consistency before they are added to a registry.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MacLTRegistryError {
+    /// The text description of the entry could not be parsed.
+    Syntax,
+    /// The number of tags is not between 1 and 10.
+    WrongNumberOfTags,
+    /// The sequences of the two messages have different numbers of tags.
+    MismatchedSequences,
+    /// The first slot of a sequence is not '00S'.
+    WrongFirstSlot,
+    /// A sequence contains a '04E' slot, which is not allowed.
+    InvalidSlot,
+    /// A sequence contains more than [`MAX_FLX_ENTRIES`] FLX slots.
+    TooManyFlx,
+    /// An entry with the same MACLT ID already exists in the registry.
+    DuplicateId,
+    /// There is no room for additional entries in the registry.
+    Full,
+}
+
+impl fmt::Display for MacLTRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacLTRegistryError::Syntax => "syntax error in MAC look-up table entry".fmt(f),
+            MacLTRegistryError::WrongNumberOfTags => "wrong number of tags".fmt(f),
+            MacLTRegistryError::MismatchedSequences => {
+                "sequences have different number of tags".fmt(f)
+            }
+            MacLTRegistryError::WrongFirstSlot => "first slot is not 00S".fmt(f),
+            MacLTRegistryError::InvalidSlot => "04E slot is not allowed".fmt(f),
+            MacLTRegistryError::TooManyFlx => "too many FLX slots".fmt(f),
+            MacLTRegistryError::DuplicateId => "duplicate MAC look-up table ID".fmt(f),
+            MacLTRegistryError::Full => "MAC look-up table registry is full".fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MacLTRegistryError {}
+
+/// Error produced when loading a text description into a [`MacLTRegistry`].
+///
+/// This is returned by [`MacLTRegistry::load`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MacLTLoadError {
+    /// Line number (starting by 1) of the entry that could not be added.
+    pub line: usize,
+    /// Reason why the entry could not be added.
+    pub error: MacLTRegistryError,
+}
+
+impl fmt::Display for MacLTLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MacLTLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Error produced when loading a JSON description into a [`MacLTRegistry`].
+///
+/// This is returned by [`MacLTRegistry::load_json`].
+#[cfg(feature = "maclt-json")]
+#[derive(Debug)]
+pub enum MacLTJsonError {
+    /// The description is not a JSON array of valid MAC look-up table entries.
+    Json(serde_json::Error),
+    /// An entry could not be added to the registry.
+    Entry {
+        /// Index (starting by 0) of the entry in the JSON array.
+        index: usize,
+        /// Reason why the entry could not be added.
+        error: MacLTRegistryError,
+    },
+}
+
+#[cfg(feature = "maclt-json")]
+impl fmt::Display for MacLTJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacLTJsonError::Json(error) => write!(f, "invalid JSON: {error}"),
+            MacLTJsonError::Entry { index, error } => write!(f, "entry {index}: {error}"),
+        }
+    }
+}
+
+#[cfg(feature = "maclt-json")]
+impl std::error::Error for MacLTJsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MacLTJsonError::Json(error) => Some(error),
+            MacLTJsonError::Entry { error, .. } => Some(error),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -314,6 +832,204 @@ mod test {
         assert_eq!(max, MAX_FLX_ENTRIES);
     }
 
+    #[test]
+    fn builtin_entries_are_consistent() {
+        for entry in MACLT.iter() {
+            let sequences = entry.sequence.map(|s| {
+                let mut full = [F00S; MAX_NT];
+                full[1..].copy_from_slice(&s);
+                full
+            });
+            let nt = entry.nt();
+            assert_eq!(
+                MacLTEntry::new(entry.id, [&sequences[0][..nt], &sequences[1][..nt]]),
+                Ok(*entry)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_entry() {
+        let entry: MacLTEntry = "34 00S FLX 04S FLX 12S 00E / 00S FLX 00E 12S 00E 12E"
+            .parse()
+            .unwrap();
+        assert_eq!(Some(&entry), MACLT.iter().find(|x| x.id == 34));
+        // Msg = 1 entry
+        let entry: MacLTEntry = "100 00S 00E 12S FLX".parse().unwrap();
+        assert_eq!(entry.id(), 100);
+        assert_eq!(entry.nt(), 4);
+        for msg in 0..2 {
+            assert_eq!(entry.slot(msg, 2), Ok(F12S));
+            assert_eq!(entry.flx_indices(msg).collect::<Vec<_>>(), [3]);
+        }
+
+        for (description, error) in [
+            ("", MacLTRegistryError::Syntax),
+            ("256 00S", MacLTRegistryError::Syntax),
+            ("100 00S 00X", MacLTRegistryError::Syntax),
+            ("100 00S 05S", MacLTRegistryError::Syntax),
+            ("100 00S / 00S / 00S", MacLTRegistryError::Syntax),
+            ("100 00S 00E / 00S", MacLTRegistryError::MismatchedSequences),
+            ("100", MacLTRegistryError::WrongNumberOfTags),
+            (
+                "100 00S FLX FLX FLX FLX FLX FLX FLX FLX FLX FLX",
+                MacLTRegistryError::WrongNumberOfTags,
+            ),
+            ("100 00E 00E", MacLTRegistryError::WrongFirstSlot),
+            ("100 00S 04E", MacLTRegistryError::InvalidSlot),
+            (
+                "100 00S FLX FLX FLX FLX FLX",
+                MacLTRegistryError::TooManyFlx,
+            ),
+        ] {
+            assert_eq!(description.parse::<MacLTEntry>(), Err(error));
+        }
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = MacLTRegistry::<2>::new();
+        assert_eq!(registry.entries().count(), MAC_LT_ENTRIES);
+        assert_eq!(
+            registry.get_maclt_entry(34, 1, 5),
+            get_maclt_entry(34, 1, 5)
+        );
+        assert_eq!(
+            registry.get_maclt_entry(100, 0, 1),
+            Err(MacLTError::InvalidMaclt)
+        );
+        assert_eq!(
+            registry.add("34 00S 00E".parse().unwrap()),
+            Err(MacLTRegistryError::DuplicateId)
+        );
+
+        let description = "
+            # Additional entries
+            100 00S 00E 12S FLX
+
+            101 00S FLX / 00S 04S
+        ";
+        assert_eq!(registry.load(description), Ok(2));
+        assert_eq!(registry.get_maclt_entry(100, 1, 2), Ok(F12S));
+        assert_eq!(
+            registry
+                .get_flx_indices(101, 0)
+                .unwrap()
+                .collect::<Vec<_>>(),
+            [1]
+        );
+        assert_eq!(registry.get_maclt_entry(101, 1, 1), Ok(F04S));
+        // The built-in lookup functions do not see the additional entries
+        assert_eq!(get_maclt_entry(100, 0, 1), Err(MacLTError::InvalidMaclt));
+        assert_eq!(
+            registry.add("102 00S".parse().unwrap()),
+            Err(MacLTRegistryError::Full)
+        );
+
+        // Loading is atomic
+        let mut registry = MacLTRegistry::<2>::new();
+        assert_eq!(
+            registry.load("100 00S\n101 00S\n102 00S"),
+            Err(MacLTLoadError {
+                line: 3,
+                error: MacLTRegistryError::Full
+            })
+        );
+        assert_eq!(registry.entries().count(), MAC_LT_ENTRIES);
+    }
+
+    #[cfg(feature = "maclt-json")]
+    #[test]
+    fn registry_json() {
+        let mut registry = MacLTRegistry::<2>::new();
+        let description = r#"[
+            {"id": 100, "sequences": [["00S", "00E", "12S", "FLX"]]},
+            {"id": 101, "sequences": [["00S", "FLX"], ["00S", "04S"]]}
+        ]"#;
+        assert_eq!(registry.load_json(description).unwrap(), 2);
+        assert_eq!(
+            registry.get(100),
+            Some(&"100 00S 00E 12S FLX".parse().unwrap())
+        );
+        assert_eq!(
+            registry.get(101),
+            Some(&"101 00S FLX / 00S 04S".parse().unwrap())
+        );
+
+        // Entries that cannot be deserialized
+        let mut registry = MacLTRegistry::<2>::new();
+        for (description, message) in [
+            (r#"{"id": 100}"#, "invalid type"),
+            (r#"[{"sequences": [["00S"]]}]"#, "missing field `id`"),
+            (r#"[{"id": 256, "sequences": [["00S"]]}]"#, "invalid value"),
+            (
+                r#"[{"id": 100, "sequences": []}]"#,
+                "syntax error in MAC look-up table entry",
+            ),
+            (
+                r#"[{"id": 100, "sequences": [["00S", 0]]}]"#,
+                "invalid type",
+            ),
+            (
+                r#"[{"id": 100, "sequences": [["00S", "04X"]]}]"#,
+                "invalid value",
+            ),
+            (
+                r#"[{"id": 100, "sequences": [["00S"]]}, {"id": 101, "sequences": [["00E"]]}]"#,
+                "first slot is not 00S",
+            ),
+            (
+                r#"[{"id": 100, "sequences": [["00S", "00E"], ["00S"]]}]"#,
+                "sequences have different number of tags",
+            ),
+            (
+                r#"[{"id": 100, "sequences": [["00S", "00E", "00E", "00E", "00E", "00E", "00E", "00E", "00E", "00E", "00E"]]}]"#,
+                "invalid length 11",
+            ),
+        ] {
+            match registry.load_json(description) {
+                Err(MacLTJsonError::Json(error)) => {
+                    assert!(
+                        error.to_string().contains(message),
+                        "unexpected error {error}"
+                    )
+                }
+                result => panic!("unexpected result {result:?}"),
+            }
+            // Loading is atomic
+            assert_eq!(registry.entries().count(), MAC_LT_ENTRIES);
+        }
+
+        // Entries that cannot be added to the registry
+        for (description, index, expected) in [
+            (
+                r#"[{"id": 100, "sequences": [["00S"]]}, {"id": 34, "sequences": [["00S"]]}]"#,
+                1,
+                MacLTRegistryError::DuplicateId,
+            ),
+            (
+                r#"[{"id": 100, "sequences": [["00S"]]}, {"id": 100, "sequences": [["00S"]]}]"#,
+                1,
+                MacLTRegistryError::DuplicateId,
+            ),
+            (
+                r#"[{"id": 100, "sequences": [["00S"]]}, {"id": 101, "sequences": [["00S"]]},
+                   {"id": 102, "sequences": [["00S"]]}]"#,
+                2,
+                MacLTRegistryError::Full,
+            ),
+        ] {
+            match registry.load_json(description) {
+                Err(MacLTJsonError::Entry { index: i, error }) => {
+                    assert_eq!((i, error), (index, expected))
+                }
+                result => panic!("unexpected result {result:?}"),
+            }
+            // Loading is atomic
+            assert_eq!(registry.entries().count(), MAC_LT_ENTRIES);
+        }
+    }
+
     #[test]
     fn flx_indices() {
         let indices = get_flx_indices(34, 0).unwrap().collect::<Vec<_>>();
@@ -1,14 +1,23 @@
 //! Merkle tree.
 //!
 //! This module contains code used to authenticate public keys against the OSNMA
-//! Merkle tree.
+//! Merkle tree, and to construct a full Merkle tree from its leaves.
 
 use crate::bitfields::{DsmPkr, EcdsaFunction, NewPublicKeyType};
-use crate::types::{MerkleTreeNode, VerifyingKey};
+use crate::builders::{check_range, new_public_key_type_field, BuildError, DsmPkrBuilder};
+use crate::types::{MerkleTreeNode, VerifyingKey, MERKLE_TREE_NODE_BYTES};
 use crate::validation::{NotValidated, Validated};
 use core::fmt;
 use sha2::{Digest, Sha256};
 
+/// Number of leaves of the OSNMA Merkle tree.
+pub const MERKLE_TREE_LEAVES: usize = 16;
+
+/// Depth of the OSNMA Merkle tree.
+///
+/// This is the number of intermediate tree nodes transmitted in a DSM-PKR.
+pub const MERKLE_TREE_DEPTH: usize = 4;
+
 /// Merkle tree.
 ///
 /// This struct represents the OSNMA Merkle tree.
@@ -72,7 +81,6 @@ impl MerkleTree {
         };
         let mut id = dsm_pkr.message_id();
         let mut node = Self::hash_leaf(leaf);
-        const MERKLE_TREE_DEPTH: usize = 4;
         for j in 0..MERKLE_TREE_DEPTH {
             let is_left = id & 1 == 0;
             let itn = dsm_pkr.intermediate_tree_node(j);
@@ -104,25 +112,26 @@ impl MerkleTree {
     }
 
     fn pubkey_from_pkr(dsm_pkr: DsmPkr) -> Result<PublicKey<Validated>, PkrError> {
-        let key = dsm_pkr.new_public_key().unwrap();
+        let key = dsm_pkr.new_public_key().ok_or(PkrError::ReservedField)?;
         let key = match dsm_pkr.new_public_key_type() {
             NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256) => {
                 p256::ecdsa::VerifyingKey::from_sec1_bytes(key)
-                    .unwrap()
+                    .map_err(|_| PkrError::InvalidPublicKey)?
                     .into()
             }
             #[cfg(feature = "p521")]
             NewPublicKeyType::EcdsaKey(EcdsaFunction::P521Sha512) => {
                 p521::ecdsa::VerifyingKey::from_sec1_bytes(key)
-                    .unwrap()
+                    .map_err(|_| PkrError::InvalidPublicKey)?
                     .into()
             }
             #[cfg(not(feature = "p521"))]
             NewPublicKeyType::EcdsaKey(EcdsaFunction::P521Sha512) => {
                 return Err(PkrError::P521NotSupported)
             }
-            // if this function has been called, the PKR contains a public key
-            _ => unreachable!(),
+            NewPublicKeyType::OsnmaAlertMessage | NewPublicKeyType::Reserved => {
+                return Err(PkrError::NoPublicKey)
+            }
         };
         Ok(PublicKey {
             key,
@@ -132,8 +141,164 @@ impl MerkleTree {
     }
 }
 
+/// Merkle tree leaf.
+///
+/// A leaf of the OSNMA Merkle tree contains a public key or an OSNMA Alert
+/// Message. It is formed by the NPKT, NPKID and NPK fields of the DSM-PKR that
+/// transmits it, as described in Section 6.2 of the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MerkleTreeLeaf<'a> {
+    /// New public key type (NPKT field).
+    pub new_public_key_type: NewPublicKeyType,
+    /// New public key ID (NPKID field). This is a 4-bit value.
+    pub new_public_key_id: u8,
+    /// New public key (NPK field), in SEC1 compressed format for ECDSA keys,
+    /// or the contents of the OSNMA Alert Message.
+    pub new_public_key: &'a [u8],
+}
+
+impl MerkleTreeLeaf<'_> {
+    /// Computes the hash of the leaf.
+    ///
+    /// This is the value of the node of the tree at level 0 that corresponds
+    /// to this leaf. An error is returned if the fields of the leaf cannot be
+    /// serialized.
+    pub fn hash(&self) -> Result<MerkleTreeNode, BuildError> {
+        check_range(self.new_public_key_id.into(), 4, "NPKID")?;
+        let npkt = new_public_key_type_field(self.new_public_key_type, self.new_public_key)?;
+        let mut hash = Sha256::new();
+        hash.update([(npkt << 4) | self.new_public_key_id]);
+        hash.update(self.new_public_key);
+        Ok(hash.finalize().into())
+    }
+}
+
+/// Full Merkle tree.
+///
+/// This struct contains all the nodes of an OSNMA Merkle tree, in contrast
+/// to [`MerkleTree`], which only contains the root. It can be built from the
+/// leaves of the tree, and it gives the intermediate tree nodes that are
+/// transmitted in the DSM-PKR of each leaf. It can be used to construct DSM-PKR
+/// messages, and to check the Merkle trees published by the GSC.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FullMerkleTree {
+    // Nodes ordered by level, starting with the 16 nodes of level 0 and
+    // ending with the root.
+    nodes: [MerkleTreeNode; 2 * MERKLE_TREE_LEAVES - 1],
+}
+
+impl FullMerkleTree {
+    /// Builds a Merkle tree from its leaves.
+    ///
+    /// The leaf at index `j` of `leaves` is the one with message ID `j`. An
+    /// error is returned if any of the leaves cannot be serialized.
+    pub fn from_leaves(
+        leaves: &[MerkleTreeLeaf; MERKLE_TREE_LEAVES],
+    ) -> Result<FullMerkleTree, BuildError> {
+        let mut hashes = [[0; MERKLE_TREE_NODE_BYTES]; MERKLE_TREE_LEAVES];
+        for (hash, leaf) in hashes.iter_mut().zip(leaves.iter()) {
+            *hash = leaf.hash()?;
+        }
+        Ok(Self::from_leaf_hashes(&hashes))
+    }
+
+    /// Builds a Merkle tree from the hashes of its leaves.
+    ///
+    /// The hashes are the nodes of level 0 of the tree, ordered by message ID.
+    pub fn from_leaf_hashes(hashes: &[MerkleTreeNode; MERKLE_TREE_LEAVES]) -> FullMerkleTree {
+        let mut nodes = [[0; MERKLE_TREE_NODE_BYTES]; 2 * MERKLE_TREE_LEAVES - 1];
+        nodes[..MERKLE_TREE_LEAVES].copy_from_slice(hashes);
+        for level in 1..=MERKLE_TREE_DEPTH {
+            let start = Self::level_start(level);
+            let prev_start = Self::level_start(level - 1);
+            for i in 0..(MERKLE_TREE_LEAVES >> level) {
+                nodes[start + i] = MerkleTree::calc_node(
+                    &nodes[prev_start + 2 * i],
+                    &nodes[prev_start + 2 * i + 1],
+                );
+            }
+        }
+        FullMerkleTree { nodes }
+    }
+
+    // Index in self.nodes of the first node of a level
+    fn level_start(level: usize) -> usize {
+        2 * MERKLE_TREE_LEAVES - ((2 * MERKLE_TREE_LEAVES) >> level)
+    }
+
+    /// Gives the root of the tree.
+    pub fn root(&self) -> &MerkleTreeNode {
+        &self.nodes[2 * MERKLE_TREE_LEAVES - 2]
+    }
+
+    /// Gives a node of the tree.
+    ///
+    /// The nodes are indexed by their `level`, which is 0 for the hashes of
+    /// the leaves and 4 for the root, and their `index` within the level,
+    /// counting from the left. This matches the `j` and `i` indices used in
+    /// the Merkle tree files published by the GSC.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is larger than 4 or `index` is out of range for
+    /// `level`.
+    pub fn node(&self, level: usize, index: usize) -> &MerkleTreeNode {
+        assert!(level <= MERKLE_TREE_DEPTH);
+        assert!(index < MERKLE_TREE_LEAVES >> level);
+        &self.nodes[Self::level_start(level) + index]
+    }
+
+    /// Gives the intermediate tree nodes that authenticate a leaf.
+    ///
+    /// These are the nodes transmitted in the ITN field of the DSM-PKR of the
+    /// leaf with message ID `message_id`, in the same order as in
+    /// [`DsmPkr::intermediate_tree_node`], starting with the node at level 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `message_id` is not smaller than 16.
+    pub fn intermediate_tree_nodes(&self, message_id: u8) -> [MerkleTreeNode; MERKLE_TREE_DEPTH] {
+        let id = usize::from(message_id);
+        assert!(id < MERKLE_TREE_LEAVES);
+        core::array::from_fn(|level| *self.node(level, (id >> level) ^ 1))
+    }
+
+    /// Gives a builder for the DSM-PKR that transmits a leaf.
+    ///
+    /// The `leaf` must be the leaf with message ID `message_id` that was used
+    /// to build the tree. The DSM-PKR message can be obtained by calling
+    /// [`DsmPkrBuilder::build`] with the root of the tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `message_id` is not smaller than 16.
+    pub fn dsm_pkr_builder<'a>(
+        &self,
+        message_id: u8,
+        leaf: &MerkleTreeLeaf<'a>,
+    ) -> DsmPkrBuilder<'a> {
+        DsmPkrBuilder {
+            message_id,
+            intermediate_tree_nodes: self.intermediate_tree_nodes(message_id),
+            new_public_key_type: leaf.new_public_key_type,
+            new_public_key_id: leaf.new_public_key_id,
+            new_public_key: leaf.new_public_key,
+        }
+    }
+
+    /// Gives the [`MerkleTree`] corresponding to this tree.
+    ///
+    /// The returned value only contains the root of the tree, and can be used
+    /// to validate DSM-PKR messages.
+    pub fn merkle_tree(&self) -> MerkleTree {
+        MerkleTree::new(*self.root())
+    }
+}
+
 /// Errors produced during validation of the DSM-PKR using the Merkle tree.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PkrError {
     /// One of the fields in the DSM-PKR needed to interpret it has a reserved
     /// value.
@@ -145,6 +310,8 @@ pub enum PkrError {
     NoPublicKey,
     /// The DSM-PKR is not an Alert Message.
     NotAlert,
+    /// The New Public Key field does not contain a valid ECDSA public key.
+    InvalidPublicKey,
     /// The DSM-PRK key is P-521, but P-521 support has not been enabled.
     #[cfg(not(feature = "p521"))]
     P521NotSupported,
@@ -157,6 +324,7 @@ impl fmt::Display for PkrError {
             PkrError::Invalid => "wrong calculated Merkle tree root".fmt(f),
             PkrError::NoPublicKey => "no public key in DSM-PKR".fmt(f),
             PkrError::NotAlert => "the DSM-PKR is not an alert message".fmt(f),
+            PkrError::InvalidPublicKey => "invalid public key in DSM-PKR".fmt(f),
             #[cfg(not(feature = "p521"))]
             PkrError::P521NotSupported => "P-521 support disabled".fmt(f),
         }
@@ -310,4 +478,134 @@ mod test {
         let mtree = merkle_tree();
         assert_eq!(mtree.validate_pkr(dsm).unwrap_err(), PkrError::Invalid);
     }
+
+    #[test]
+    fn leaf_hashes() {
+        // The DSM-PKRs of messages 0 and 1 broadcast on 2023-12-12 and
+        // 2023-12-15 contain the hash of the other leaf as first intermediate
+        // tree node
+        let dsm_0 = hex!(
+            "
+            70 01 63 1b dc ed 79 d4 31 7b c2 87 0e e3 89 5b
+            d5 9c f2 b6 ea 51 6f ab bf df 1d 73 96 26 14 6f
+            fe 31 6f a9 28 5f 5a 1e 44 04 24 13 bd af 18 aa
+            3c f6 84 72 33 97 d7 b8 32 5a ec a1 eb ca 9f 0f
+            64 99 05 42 4c be 48 2a 1a 32 b0 10 64 f8 5d 0c
+            36 df 03 8e 52 ce 12 8e 7e c5 f3 23 e1 65 b1 82
+            a7 15 37 bd b0 10 97 2e b4 a3 b9 0b aa cd 14 94
+            1e f4 0d a2 cb 2b 82 d3 78 b3 15 c0 08 de ce fd
+            8e 11 03 74 a9 25 cf a0 ff 18 05 e5 c5 a5 8f db
+            a3 1b f0 14 5d 5b 5b e2 f0 62 d3 f8 bb 2e e9 8f
+            0f 6d b0 e8 23 c5 e7 5e 78"
+        );
+        let dsm_1 = hex!(
+            "
+            71 e5 53 0a 33 d5 cb 60 c9 50 16 b8 ae c7 45 93
+            db cd f2 71 1d 39 9e a2 48 69 17 3c a2 29 37 9a
+            15 31 6f a9 28 5f 5a 1e 44 04 24 13 bd af 18 aa
+            3c f6 84 72 33 97 d7 b8 32 5a ec a1 eb ca 9f 0f
+            64 99 05 42 4c be 48 2a 1a 32 b0 10 64 f8 5d 0c
+            36 df 03 8e 52 ce 12 8e 7e c5 f3 23 e1 65 b1 82
+            a7 15 37 bd b0 10 97 2e b4 a3 b9 0b aa cd 14 94
+            1e f4 0d a2 cb 2b 82 d3 78 b3 15 c0 08 de ce fd
+            8e 12 03 35 78 e5 c7 11 a9 c3 bd dd 1c a4 ee 85
+            f7 c5 1b 36 78 97 cb 40 b8 85 68 a0 c8 97 da 30
+            ef b7 c3 24 e0 22 2c 90 80"
+        );
+        let leaf = |dsm: &[u8; 169]| {
+            let dsm = DsmPkr(dsm);
+            MerkleTreeLeaf {
+                new_public_key_type: dsm.new_public_key_type(),
+                new_public_key_id: dsm.new_public_key_id(),
+                new_public_key: dsm.new_public_key().unwrap(),
+            }
+            .hash()
+            .unwrap()
+        };
+        assert_eq!(&leaf(&dsm_0), DsmPkr(&dsm_1).intermediate_tree_node(0));
+        assert_eq!(&leaf(&dsm_1), DsmPkr(&dsm_0).intermediate_tree_node(0));
+    }
+
+    #[test]
+    fn full_merkle_tree() {
+        // P-256 public key broadcast in the DSM-PKR of 2023-12-12
+        let p256_key = hex!(
+            "
+            03 74 a9 25 cf a0 ff 18 05 e5 c5 a5 8f db a3 1b
+            f0 14 5d 5b 5b e2 f0 62 d3 f8 bb 2e e9 8f 0f 6d
+            b0"
+        );
+        // The contents of the OSNMA Alert Message must fill the DSM-PKR
+        let alert_message = [0xa5; 39];
+        let mut leaves = [MerkleTreeLeaf {
+            new_public_key_type: NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256),
+            new_public_key_id: 0,
+            new_public_key: &p256_key,
+        }; MERKLE_TREE_LEAVES];
+        for (j, leaf) in leaves.iter_mut().enumerate() {
+            leaf.new_public_key_id = u8::try_from(j).unwrap();
+        }
+        leaves[15] = MerkleTreeLeaf {
+            new_public_key_type: NewPublicKeyType::OsnmaAlertMessage,
+            new_public_key_id: 0,
+            new_public_key: &alert_message,
+        };
+        let tree = FullMerkleTree::from_leaves(&leaves).unwrap();
+        assert_eq!(tree.node(4, 0), tree.root());
+        assert_eq!(tree.node(0, 3), &leaves[3].hash().unwrap());
+        assert_eq!(
+            tree.node(1, 2),
+            &MerkleTree::calc_node(tree.node(0, 4), tree.node(0, 5))
+        );
+
+        let mtree = tree.merkle_tree();
+        for (j, leaf) in leaves.iter().enumerate() {
+            let message_id = u8::try_from(j).unwrap();
+            let dsm = tree
+                .dsm_pkr_builder(message_id, leaf)
+                .build(tree.root())
+                .unwrap();
+            let dsm = dsm.dsm_pkr();
+            assert!(dsm.check_padding(tree.root()));
+            if j == 15 {
+                assert!(mtree.validate_alert_message(dsm).is_ok());
+            } else {
+                let pubkey = mtree.validate_pkr(dsm).unwrap();
+                assert_eq!(pubkey.public_key_id(), message_id);
+            }
+            // The DSM-PKR does not validate against a different leaf position
+            let dsm = tree
+                .dsm_pkr_builder(message_id ^ 1, leaf)
+                .build(tree.root())
+                .unwrap();
+            assert!(mtree.validate(dsm.dsm_pkr()).is_err());
+        }
+
+        leaves[3].new_public_key_id = 16;
+        assert_eq!(
+            FullMerkleTree::from_leaves(&leaves),
+            Err(BuildError::FieldOutOfRange { field: "NPKID" })
+        );
+    }
+
+    #[test]
+    fn invalid_public_key() {
+        // A correctly authenticated DSM-PKR whose public key is not a valid
+        // point in SEC1 format
+        let invalid_key = [0xff; 33];
+        let leaves = [MerkleTreeLeaf {
+            new_public_key_type: NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256),
+            new_public_key_id: 0,
+            new_public_key: &invalid_key,
+        }; MERKLE_TREE_LEAVES];
+        let tree = FullMerkleTree::from_leaves(&leaves).unwrap();
+        let dsm = tree
+            .dsm_pkr_builder(0, &leaves[0])
+            .build(tree.root())
+            .unwrap();
+        assert_eq!(
+            tree.merkle_tree().validate_pkr(dsm.dsm_pkr()).err(),
+            Some(PkrError::InvalidPublicKey)
+        );
+    }
 }
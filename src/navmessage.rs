@@ -6,9 +6,14 @@
 //! if finer control is needed.
 
 use crate::bitfields::{Adkd, Mack, NmaStatus};
-use crate::storage::StaticStorage;
-use crate::tesla::Key;
-use crate::types::{BitSlice, InavBand, InavWord};
+use crate::logging::Hex;
+use crate::reed_solomon;
+#[cfg(feature = "serde")]
+use crate::serde_helpers::Bytes;
+use crate::storage::{find_gst_row, StaticStorage, SvnIndex};
+use crate::tesla::{Key, MacKey};
+use crate::trace::{MacTrace, TagTrace};
+use crate::types::{band_index, BitSlice, InavBand, InavWord, MACK_MESSAGE_BYTES, NUM_SVNS};
 use crate::validation::Validated;
 use crate::{Gst, Svn};
 use bitvec::prelude::*;
@@ -20,16 +25,57 @@ use typenum::Unsigned;
 // https://www.gsc-europa.eu/news/updated-documentation-and-cryptographic-material-in-preparation-for-the-galileo-osnma-initial
 const MIN_AUTHBITS: u16 = 40;
 
+// Maximum GST difference between consecutive INAV words of the same SVN and
+// band before assuming that there has been a loss of lock. Each word type is
+// transmitted once per subframe, so a larger gap means that at least one
+// subframe has been missed completely.
+const MAX_WORD_GAP_SECS: i32 = 30;
+
 /// Navigation message store.
 ///
 /// This struct is used to store and classify the navigation message data, and
 /// to authenticate it using MAC tags and their corresponding TESLA keys.
+///
+/// For each stored GST, an index giving the location of the data of each SVN
+/// is maintained, so that retrieving the data for a particular SVN does not
+/// require searching through the storage.
+///
+/// The CED and health status data is assembled taking into account the IODnav
+/// of the INAV words. When a word with a new IODnav is received, the words
+/// with the old IODnav are discarded, and the last complete batch with the old
+/// IODnav is kept alongside the new batch until the new batch is complete.
+/// Each batch is authenticated separately, so that an ephemeris update does
+/// not mix words from two batches. Keeping the previous batch doubles the
+/// storage used for the CED and health status, so it is only done with the
+/// `previous-ced-batch` feature, which is enabled by default. Without this
+/// feature, the old batch is discarded when a word with a new IODnav is
+/// received.
+///
+/// Missing words 1 to 4 of the CED are reconstructed using the Reed-Solomon
+/// outer FEC transmitted in the INAV words 17 to 20, when enough words of the
+/// same IODnav have been received. Reconstructed words are authenticated in
+/// the same way as received words, and the data that contains them is marked
+/// (see [`NavMessageData::has_reconstructed_words`]).
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct CollectNavMessage<S: StaticStorage> {
     ced_and_status: GenericArray<CedAndStatus, S::NavMessageDepthSats>,
+    // Last complete CED and health status batch with an IODnav different from
+    // the batch in ced_and_status. It uses the same slots as ced_and_status.
+    // Without the previous-ced-batch feature this is empty.
+    #[cfg(feature = "previous-ced-batch")]
+    ced_and_status_previous: GenericArray<CedAndStatus, S::NavMessageDepthSats>,
+    #[cfg(not(feature = "previous-ced-batch"))]
+    ced_and_status_previous: [CedAndStatus; 0],
     timing_parameters: GenericArray<TimingParameters, S::NavMessageDepthSats>,
+    ced_and_status_index: GenericArray<SvnIndex, S::NavMessageDepth>,
+    timing_parameters_index: GenericArray<SvnIndex, S::NavMessageDepth>,
     gsts: GenericArray<Option<Gst>, S::NavMessageDepth>,
     write_pointer: usize,
+    // GST of the last INAV word fed for each SVN and band (E1B, E5b), used
+    // to detect gaps in the data.
+    last_word_gst: [[Option<Gst>; 2]; NUM_SVNS],
+    // Reed-Solomon parity words of the CED for each SVN.
+    ced_parity: [CedParity; NUM_SVNS],
 }
 
 /// Authenticated navigation message data.
@@ -42,6 +88,7 @@ pub struct NavMessageData<'a> {
     data: &'a BitSlice,
     authbits: u16,
     gst: Gst,
+    reconstructed: bool,
 }
 
 impl<'a> NavMessageData<'a> {
@@ -65,6 +112,247 @@ impl<'a> NavMessageData<'a> {
     pub fn gst(&self) -> Gst {
         self.gst
     }
+
+    /// Returns the IODnav of the navigation data.
+    ///
+    /// All the INAV words with an IODnav in authenticated CED and health
+    /// status data have the same IODnav. This returns `None` for timing
+    /// parameters data, which does not have an IODnav.
+    pub fn iodnav(&self) -> Option<u16> {
+        iodnav(self.data)
+    }
+
+    /// Returns `true` if some of the INAV words in the navigation data were
+    /// reconstructed using the Reed-Solomon outer FEC instead of being
+    /// received.
+    ///
+    /// Reconstructed words are authenticated in the same way as received
+    /// words. This is always `false` for timing parameters data.
+    pub fn has_reconstructed_words(&self) -> bool {
+        self.reconstructed
+    }
+
+    /// Returns `true` if the INAV word `word` is part of the navigation data.
+    ///
+    /// The INAV words 1 to 5 are part of the CED and health status data, and
+    /// the INAV words 6 and 10 are part of the timing parameters data. Only the
+    /// fields of the word that are included in the navigation data, and hence
+    /// authenticated by OSNMA, are compared. For instance, the WN and TOW
+    /// fields of the INAV word 5 are ignored. This returns `false` for the
+    /// words that are not part of this kind of navigation data.
+    pub fn contains_inav_word(&self, word: &InavWord) -> bool {
+        let word = BitSlice::from_slice(word);
+        let word_type = word[..6].load_be::<u8>();
+        let position = if self.data.len() == CED_AND_STATUS_BITS {
+            ced_and_status_word_position(word_type)
+        } else {
+            timing_parameters_word_position(word_type)
+        };
+        position.is_some_and(|p| self.data[p.data_bits] == word[p.word_bits])
+    }
+}
+
+/// Position of an INAV word in some navigation data.
+///
+/// This gives the location of the fields of an INAV word that are included
+/// in the CED and health status data or in the timing parameters data, and
+/// hence authenticated by OSNMA. See [`ced_and_status_word_position`] and
+/// [`timing_parameters_word_position`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct InavWordPosition {
+    /// Index of the word in the navigation data.
+    pub index: usize,
+    /// Range of the navigation data bits that contains the word.
+    pub data_bits: core::ops::Range<usize>,
+    /// Range of the word bits that is contained in the navigation data.
+    pub word_bits: core::ops::Range<usize>,
+}
+
+impl InavWordPosition {
+    fn new(
+        index: usize,
+        data_bits: core::ops::Range<usize>,
+        word_bits: core::ops::Range<usize>,
+    ) -> InavWordPosition {
+        InavWordPosition {
+            index,
+            data_bits,
+            word_bits,
+        }
+    }
+}
+
+/// Returns the position of an INAV word in the CED and health status data.
+///
+/// The CED and health status data contains the INAV words 1 to 5. This
+/// returns `None` for other word types.
+pub fn ced_and_status_word_position(word_type: u8) -> Option<InavWordPosition> {
+    match word_type {
+        1 => Some(InavWordPosition::new(0, 0..120, 6..126)),
+        2 => Some(InavWordPosition::new(1, 120..240, 6..126)),
+        3 => Some(InavWordPosition::new(2, 240..362, 6..128)),
+        4 => Some(InavWordPosition::new(3, 362..482, 6..126)),
+        5 => Some(InavWordPosition::new(4, 482..549, 6..73)),
+        _ => None,
+    }
+}
+
+/// Returns the position of an INAV word in the timing parameters data.
+///
+/// The timing parameters data contains the INAV words 6 and 10. This returns
+/// `None` for other word types.
+pub fn timing_parameters_word_position(word_type: u8) -> Option<InavWordPosition> {
+    match word_type {
+        6 => Some(InavWordPosition::new(0, 0..99, 6..105)),
+        10 => Some(InavWordPosition::new(1, 99..141, 86..128)),
+        _ => None,
+    }
+}
+
+fn iodnav(data: &BitSlice) -> Option<u16> {
+    (data.len() == CED_AND_STATUS_BITS).then(|| data[..IODNAV_BITS].load_be())
+}
+
+/// Owned authenticated navigation message data.
+///
+/// This is an owned counterpart of [`NavMessageData`], which stores a copy of
+/// the navigation data together with its number of authentication bits and
+/// GST. It can hold either CED and health status data or timing parameters
+/// data.
+///
+/// With the `serde` feature, this is serialized as a struct containing the
+/// data as a byte string (packed MSB-first and padded with zeros), the length
+/// of the data in bits, the authentication bits, the GST and whether the data
+/// contains reconstructed words.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SerdeNavMessageData", into = "SerdeNavMessageData")
+)]
+pub struct OwnedNavMessageData {
+    data: [u8; CED_AND_STATUS_BYTES],
+    num_bits: usize,
+    authbits: u16,
+    gst: Gst,
+    reconstructed: bool,
+}
+
+impl OwnedNavMessageData {
+    /// Returns the navigation data as a `BitSlice`.
+    pub fn data(&self) -> &BitSlice {
+        &BitSlice::from_slice(&self.data)[..self.num_bits]
+    }
+
+    /// Returns the navigation data as bytes.
+    ///
+    /// The data is packed MSB-first, and the last byte is padded with zeros.
+    pub fn data_bytes(&self) -> &[u8] {
+        &self.data[..(self.num_bits + 7) / 8]
+    }
+
+    /// Returns the number of authentication bits corresponding to this data.
+    ///
+    /// See [`NavMessageData::authbits`].
+    pub fn authbits(&self) -> u16 {
+        self.authbits
+    }
+
+    /// Returns the GST that corresponds to this navigation data.
+    ///
+    /// See [`NavMessageData::gst`].
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Returns the IODnav of the navigation data.
+    ///
+    /// See [`NavMessageData::iodnav`].
+    pub fn iodnav(&self) -> Option<u16> {
+        iodnav(self.data())
+    }
+
+    /// Returns `true` if some of the INAV words in the navigation data were
+    /// reconstructed using the Reed-Solomon outer FEC.
+    ///
+    /// See [`NavMessageData::has_reconstructed_words`].
+    pub fn has_reconstructed_words(&self) -> bool {
+        self.reconstructed
+    }
+}
+
+impl From<NavMessageData<'_>> for OwnedNavMessageData {
+    fn from(data: NavMessageData<'_>) -> OwnedNavMessageData {
+        let mut bytes = [0; CED_AND_STATUS_BYTES];
+        BitSlice::from_slice_mut(&mut bytes)[..data.data.len()].copy_from_bitslice(data.data);
+        OwnedNavMessageData {
+            data: bytes,
+            num_bits: data.data.len(),
+            authbits: data.authbits,
+            gst: data.gst,
+            reconstructed: data.reconstructed,
+        }
+    }
+}
+
+impl<'a> From<&'a OwnedNavMessageData> for NavMessageData<'a> {
+    fn from(data: &'a OwnedNavMessageData) -> NavMessageData<'a> {
+        NavMessageData {
+            data: data.data(),
+            authbits: data.authbits,
+            gst: data.gst,
+            reconstructed: data.reconstructed,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "NavMessageData")]
+struct SerdeNavMessageData {
+    data: Bytes<CED_AND_STATUS_BYTES>,
+    num_bits: usize,
+    authbits: u16,
+    gst: Gst,
+    #[serde(default)]
+    reconstructed: bool,
+}
+
+#[cfg(feature = "serde")]
+impl From<OwnedNavMessageData> for SerdeNavMessageData {
+    fn from(data: OwnedNavMessageData) -> SerdeNavMessageData {
+        SerdeNavMessageData {
+            data: Bytes::new(data.data_bytes()),
+            num_bits: data.num_bits,
+            authbits: data.authbits,
+            gst: data.gst,
+            reconstructed: data.reconstructed,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeNavMessageData> for OwnedNavMessageData {
+    type Error = &'static str;
+
+    fn try_from(data: SerdeNavMessageData) -> Result<OwnedNavMessageData, &'static str> {
+        let bytes = data.data.as_slice();
+        if data.num_bits > CED_AND_STATUS_BITS || bytes.len() != (data.num_bits + 7) / 8 {
+            return Err("navigation data length does not match number of bits");
+        }
+        let mut owned = OwnedNavMessageData {
+            data: [0; CED_AND_STATUS_BYTES],
+            num_bits: data.num_bits,
+            authbits: data.authbits,
+            gst: data.gst,
+            reconstructed: data.reconstructed,
+        };
+        // the padding bits in the last byte are not copied, so that they are
+        // always zero
+        BitSlice::from_slice_mut(&mut owned.data)[..data.num_bits]
+            .copy_from_bitslice(&BitSlice::from_slice(bytes)[..data.num_bits]);
+        Ok(owned)
+    }
 }
 
 impl<S: StaticStorage> CollectNavMessage<S> {
@@ -72,9 +360,17 @@ impl<S: StaticStorage> CollectNavMessage<S> {
     pub fn new() -> CollectNavMessage<S> {
         CollectNavMessage {
             ced_and_status: GenericArray::default(),
+            #[cfg(feature = "previous-ced-batch")]
+            ced_and_status_previous: GenericArray::default(),
+            #[cfg(not(feature = "previous-ced-batch"))]
+            ced_and_status_previous: [],
             timing_parameters: GenericArray::default(),
+            ced_and_status_index: GenericArray::default(),
+            timing_parameters_index: GenericArray::default(),
             gsts: GenericArray::default(),
             write_pointer: 0,
+            last_word_gst: [[None; 2]; NUM_SVNS],
+            ced_parity: [CedParity::new(); NUM_SVNS],
         }
     }
 
@@ -86,53 +382,132 @@ impl<S: StaticStorage> CollectNavMessage<S> {
     /// The `gst` parameter gives the GST at the start of the INAV page transmission.
     ///
     /// The `band` parameter indicates the band in which the INAV word was received.
+    ///
+    /// If the GST of this word is more than one subframe later than the GST of
+    /// the previous word fed for the same SVN and band, this is treated as a
+    /// loss of lock (see [`CollectNavMessage::notify_loss_of_lock`]), so that
+    /// words received before and after the gap are not combined.
+    ///
+    /// When a word that belongs to the CED or its Reed-Solomon parity is fed,
+    /// the missing CED words are reconstructed if possible.
     pub fn feed(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
-        log::trace!(
-            "feeding INAV word = {:02x?} for {} GST {:?}",
-            word,
-            svn,
-            gst
-        );
+        trace!("feeding INAV word"; word = Hex(word), svn = %svn, gst = gst);
+        let last_word_gst = &mut self.last_word_gst[usize::from(svn) - 1][band_index(band)];
+        let gap =
+            last_word_gst.is_some_and(|last| gst.seconds_difference(last) > MAX_WORD_GAP_SECS);
+        *last_word_gst = Some(gst);
         let gst = gst.gst_subframe();
         self.adjust_write_pointer(gst);
+        if gap {
+            warn!("gap in INAV words; assuming loss of lock"; svn = %svn, band = band, gst = gst);
+            self.discard_current(svn, band);
+        }
 
         // CED
-        //
-        // Search for best location to place this SVN
-        let ced = self
-            .current_ced_as_mut()
-            .iter_mut()
-            .max_by_key(|x| match x.svn {
-                Some(s) if s == svn => u16::from(u8::MAX) + 2,
-                None => u16::from(u8::MAX) + 1,
-                _ => u16::from(x.max_age()),
-            })
-            .unwrap();
-        log::trace!(
-            "selected CED store with SVN {:?} and age {}",
-            ced.svn,
-            ced.max_age()
-        );
-        ced.feed(word, svn);
+        let slot = match self.ced_and_status_index[self.write_pointer].get(svn) {
+            Some(slot) => slot,
+            None => {
+                // Search for best location to place this SVN
+                let (slot, ced) = self
+                    .current_ced_as_mut()
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, x)| match x.svn {
+                        None => u16::from(u8::MAX) + 1,
+                        _ => u16::from(x.max_age()),
+                    })
+                    .unwrap();
+                trace!("selected CED store"; svn = ced.svn, age = ced.max_age());
+                let evicted = ced.svn;
+                let index = &mut self.ced_and_status_index[self.write_pointer];
+                if let Some(evicted) = evicted {
+                    index.clear(evicted);
+                }
+                index.set(svn, slot);
+                if let Some(previous) = self.current_ced_previous_as_mut().get_mut(slot) {
+                    previous.reset();
+                }
+                slot
+            }
+        };
+        if let Some(previous) = self.current_ced_as_mut()[slot].feed(word, svn) {
+            if let Some(slot) = self.current_ced_previous_as_mut().get_mut(slot) {
+                *slot = previous;
+            }
+        }
+        let ced_parity = &mut self.ced_parity[usize::from(svn) - 1];
+        if ced_parity.feed(word) {
+            self.ced_and_status[self.write_pointer * S::NUM_SATS + slot].reconstruct(ced_parity);
+        }
 
         // Timing parameters
-        //
-        // Search for best location to place this SVN
-        let timing_parameters = self
-            .current_timing_parameters_as_mut()
-            .iter_mut()
-            .max_by_key(|x| match x.svn {
-                Some(s) if s == svn => u16::from(u8::MAX) + 2,
-                None => u16::from(u8::MAX) + 1,
-                _ => u16::from(x.max_age()),
-            })
-            .unwrap();
-        log::trace!(
-            "selected timing parameters store with SVN {:?} and age {}",
-            timing_parameters.svn,
-            timing_parameters.max_age(),
-        );
-        timing_parameters.feed(word, svn, band);
+        let slot = match self.timing_parameters_index[self.write_pointer].get(svn) {
+            Some(slot) => slot,
+            None => {
+                // Search for best location to place this SVN
+                let (slot, timing_parameters) = self
+                    .current_timing_parameters_as_mut()
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, x)| match x.svn {
+                        None => u16::from(u8::MAX) + 1,
+                        _ => u16::from(x.max_age()),
+                    })
+                    .unwrap();
+                trace!(
+                    "selected timing parameters store";
+                    svn = timing_parameters.svn,
+                    age = timing_parameters.max_age(),
+                );
+                let evicted = timing_parameters.svn;
+                let index = &mut self.timing_parameters_index[self.write_pointer];
+                if let Some(evicted) = evicted {
+                    index.clear(evicted);
+                }
+                index.set(svn, slot);
+                slot
+            }
+        };
+        self.current_timing_parameters_as_mut()[slot].feed(word, svn, band);
+    }
+
+    /// Notify a loss of lock of a satellite.
+    ///
+    /// This should be called when the receiver loses lock of the signal of
+    /// the satellite with SVN `svn` in `band`, or when it has any other
+    /// reason to believe that the INAV words received from now on might not
+    /// belong to the same transmission as the words received before.
+    ///
+    /// The navigation data that is being collected for this satellite is
+    /// discarded, so that INAV words received before and after the loss of
+    /// lock are never combined. CED and health status data is discarded for
+    /// a loss of lock in either band, since it is collected from both bands,
+    /// while timing parameters are only discarded for a loss of lock in E1B.
+    /// The data stored for previous subframes is kept, so authenticated data
+    /// remains available and the tags that refer to it can still be
+    /// validated.
+    pub fn notify_loss_of_lock(&mut self, svn: Svn, band: InavBand) {
+        info!("loss of lock"; svn = %svn, band = band);
+        self.last_word_gst[usize::from(svn) - 1][band_index(band)] = None;
+        self.discard_current(svn, band);
+    }
+
+    // Discards the data of an SVN in the current row of the storage.
+    fn discard_current(&mut self, svn: Svn, band: InavBand) {
+        self.ced_parity[usize::from(svn) - 1].reset();
+        if let Some(slot) = self.ced_and_status_index[self.write_pointer].get(svn) {
+            self.current_ced_as_mut()[slot].reset();
+            if let Some(previous) = self.current_ced_previous_as_mut().get_mut(slot) {
+                previous.reset();
+            }
+            self.ced_and_status_index[self.write_pointer].clear(svn);
+        }
+        if band == InavBand::E1B {
+            if let Some(slot) = self.timing_parameters_index[self.write_pointer].get(svn) {
+                self.current_timing_parameters_as_mut()[slot].reset();
+                self.timing_parameters_index[self.write_pointer].clear(svn);
+            }
+        }
     }
 
     fn adjust_write_pointer(&mut self, gst: Gst) {
@@ -142,49 +517,49 @@ impl<S: StaticStorage> CollectNavMessage<S> {
         // the stale counter of the copy.
         if let Some(g) = self.gsts[self.write_pointer] {
             if g != gst {
-                log::trace!(
-                    "got a new GST {:?} (current GST is {:?}); \
-                     advancing write pointer",
-                    gst,
-                    g
-                );
+                trace!("got a new GST; advancing write pointer"; gst = gst, current_gst = g);
                 let new_pointer = (self.write_pointer + 1) % S::NavMessageDepth::USIZE;
                 self.ced_and_status.copy_within(
                     self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS,
                     new_pointer * S::NUM_SATS,
                 );
+                #[cfg(feature = "previous-ced-batch")]
+                self.ced_and_status_previous.copy_within(
+                    self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS,
+                    new_pointer * S::NUM_SATS,
+                );
                 self.timing_parameters.copy_within(
                     self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS,
                     new_pointer * S::NUM_SATS,
                 );
+                self.ced_and_status_index[new_pointer] =
+                    self.ced_and_status_index[self.write_pointer];
+                self.timing_parameters_index[new_pointer] =
+                    self.timing_parameters_index[self.write_pointer];
                 self.write_pointer = new_pointer;
                 self.increase_age();
-                if log::log_enabled!(log::Level::Debug) {
-                    log::debug!("advanced write pointer to {:?}", gst);
-                    log::debug!("CedAndStatus contents:");
-                    for elem in self.ced_and_status
-                        [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
-                        .iter()
-                    {
-                        log::debug!(
-                            "SVN {:?}: age {:?} authbits {}",
-                            elem.svn,
-                            elem.age,
-                            elem.authbits
-                        );
-                    }
-                    log::debug!("TimingParameters contents:");
-                    for elem in self.timing_parameters
-                        [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
-                        .iter()
-                    {
-                        log::debug!(
-                            "SVN {:?}: age {:?} authbits {}",
-                            elem.svn,
-                            elem.age,
-                            elem.authbits
-                        );
-                    }
+                debug!("advanced write pointer"; gst = gst);
+                for elem in self.ced_and_status
+                    [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
+                    .iter()
+                {
+                    debug!(
+                        "CedAndStatus contents";
+                        svn = elem.svn,
+                        age = elem.age,
+                        authbits = elem.authbits,
+                    );
+                }
+                for elem in self.timing_parameters
+                    [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
+                    .iter()
+                {
+                    debug!(
+                        "TimingParameters contents";
+                        svn = elem.svn,
+                        age = elem.age,
+                        authbits = elem.authbits,
+                    );
                 }
             }
         }
@@ -196,6 +571,17 @@ impl<S: StaticStorage> CollectNavMessage<S> {
             [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
     }
 
+    #[cfg(feature = "previous-ced-batch")]
+    fn current_ced_previous_as_mut(&mut self) -> &mut [CedAndStatus] {
+        &mut self.ced_and_status_previous
+            [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
+    }
+
+    #[cfg(not(feature = "previous-ced-batch"))]
+    fn current_ced_previous_as_mut(&mut self) -> &mut [CedAndStatus] {
+        &mut self.ced_and_status_previous
+    }
+
     fn current_timing_parameters_as_mut(&mut self) -> &mut [TimingParameters] {
         &mut self.timing_parameters
             [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
@@ -207,11 +593,21 @@ impl<S: StaticStorage> CollectNavMessage<S> {
                 *age = age.saturating_add(1);
             }
         }
+        for ced in self.current_ced_previous_as_mut().iter_mut() {
+            for age in ced.age.iter_mut() {
+                *age = age.saturating_add(1);
+            }
+        }
         for timing_parameters in self.current_timing_parameters_as_mut().iter_mut() {
             for age in timing_parameters.age.iter_mut() {
                 *age = age.saturating_add(1);
             }
         }
+        for ced_parity in self.ced_parity.iter_mut() {
+            for age in ced_parity.age.iter_mut() {
+                *age = age.saturating_add(1);
+            }
+        }
     }
 
     /// Try to get authenticated CED and health status data for a satellite.
@@ -221,26 +617,8 @@ impl<S: StaticStorage> CollectNavMessage<S> {
     /// available in the OSNMA storage. If the storage does not contain any
     /// authenticated CED and health status data for this SVN, this returns
     /// `None`.
-    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData> {
-        // Search in order of decreasing Gst
-        for j in 0..S::NavMessageDepth::USIZE {
-            let gst_idx =
-                (S::NavMessageDepth::USIZE + self.write_pointer - j) % S::NavMessageDepth::USIZE;
-            for item in
-                self.ced_and_status[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS].iter()
-            {
-                if item.svn == Some(svn) && item.authbits >= MIN_AUTHBITS {
-                    let age: i32 = item.min_age().into();
-                    let gst = self.gsts[gst_idx].unwrap().add_subframes(-age);
-                    return Some(NavMessageData {
-                        data: item.message_bits(),
-                        authbits: item.authbits,
-                        gst,
-                    });
-                }
-            }
-        }
-        None
+    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        self.ced_and_status_history(svn).next()
     }
 
     /// Try to get authenticated timing parameters for a satellite.
@@ -249,47 +627,159 @@ impl<S: StaticStorage> CollectNavMessage<S> {
     /// (ADKD=4) for the satellite with SNV`svn` that is available in the OSNMA
     /// storage. If the storage does not contain any authenticated timing
     /// parameters data for this SVN, this returns `None`.
-    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData> {
-        // Search in order of decreasing Gst
-        for j in 0..S::NavMessageDepth::USIZE {
+    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        self.timing_parameters_history(svn).next()
+    }
+
+    /// Try to get the authenticated CED and health status data for a satellite
+    /// that was valid at a given GST.
+    ///
+    /// This returns the most recent authenticated CED and health status data
+    /// for the satellite with SVN `svn` among the data that had been received
+    /// by the subframe containing `gst`. The data may have been authenticated
+    /// after `gst`. If the storage does not contain any such data (for
+    /// instance, because `gst` is older than the retained history), this
+    /// returns `None`.
+    pub fn get_ced_and_status_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        let gst = gst.gst_subframe();
+        self.ced_and_status_history_with_row(svn)
+            .find(|&(row_gst, _)| row_gst <= gst)
+            .map(|(_, data)| data)
+    }
+
+    /// Try to get the authenticated timing parameters for a satellite that
+    /// were valid at a given GST.
+    ///
+    /// This is similar to [`CollectNavMessage::get_ced_and_status_at`], but
+    /// for the timing parameters data (ADKD=4).
+    pub fn get_timing_parameters_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        let gst = gst.gst_subframe();
+        self.history(
+            [&self.timing_parameters[..]],
+            &self.timing_parameters_index,
+            svn,
+        )
+        .find(|&(row_gst, _)| row_gst <= gst)
+        .map(|(_, data)| data)
+    }
+
+    /// Returns an iterator over the history of authenticated CED and health
+    /// status data for a satellite.
+    ///
+    /// The storage keeps a copy of the navigation data of each satellite as it
+    /// was at the end of each of the most recent subframes (up to
+    /// `S::NavMessageDepth` subframes). This iterator gives the copies that are
+    /// authenticated for the satellite with SVN `svn`, in order of decreasing
+    /// GST. The same navigation data can appear several times, with different
+    /// GSTs, if it was broadcast in several subframes.
+    pub fn ced_and_status_history(&self, svn: Svn) -> impl Iterator<Item = NavMessageData<'_>> {
+        self.ced_and_status_history_with_row(svn)
+            .map(|(_, data)| data)
+    }
+
+    /// Returns an iterator over the history of authenticated timing parameters
+    /// for a satellite.
+    ///
+    /// This is similar to [`CollectNavMessage::ced_and_status_history`], but
+    /// for the timing parameters data (ADKD=4).
+    pub fn timing_parameters_history(&self, svn: Svn) -> impl Iterator<Item = NavMessageData<'_>> {
+        self.history(
+            [&self.timing_parameters[..]],
+            &self.timing_parameters_index,
+            svn,
+        )
+        .map(|(_, data)| data)
+    }
+
+    fn ced_and_status_history_with_row(
+        &self,
+        svn: Svn,
+    ) -> impl Iterator<Item = (Gst, NavMessageData<'_>)> {
+        self.history(
+            [&self.ced_and_status[..], &self.ced_and_status_previous[..]],
+            &self.ced_and_status_index,
+            svn,
+        )
+    }
+
+    // Iterates over the authenticated navigation data of an SVN in order of
+    // decreasing GST. The GST of the storage row is returned together with
+    // the data. Several arrays of items that share the same index can be
+    // given. Within each row, their items are returned in the order of the
+    // arrays.
+    fn history<'a, T: AuthBits, const N: usize>(
+        &'a self,
+        items: [&'a [T]; N],
+        index: &'a [SvnIndex],
+        svn: Svn,
+    ) -> impl Iterator<Item = (Gst, NavMessageData<'a>)> + 'a {
+        (0..S::NavMessageDepth::USIZE).flat_map(move |j| {
             let gst_idx =
                 (S::NavMessageDepth::USIZE + self.write_pointer - j) % S::NavMessageDepth::USIZE;
-            for item in
-                self.timing_parameters[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS].iter()
-            {
-                if item.svn == Some(svn) && item.authbits >= MIN_AUTHBITS {
-                    let age: i32 = item.min_age().into();
-                    let gst = self.gsts[gst_idx].unwrap().add_subframes(-age);
-                    return Some(NavMessageData {
-                        data: item.message_bits(),
-                        authbits: item.authbits,
-                        gst,
-                    });
-                }
-            }
+            items
+                .into_iter()
+                .filter_map(move |items| self.history_item(items, index, svn, gst_idx))
+        })
+    }
+
+    // Returns the item of an SVN in a row of the storage, if it is
+    // authenticated.
+    fn history_item<'a, T: AuthBits>(
+        &'a self,
+        items: &'a [T],
+        index: &'a [SvnIndex],
+        svn: Svn,
+        gst_idx: usize,
+    ) -> Option<(Gst, NavMessageData<'a>)> {
+        let slot = index[gst_idx].get(svn)?;
+        let row_gst = self.gsts[gst_idx]?;
+        let item = items.get(gst_idx * S::NUM_SATS + slot)?;
+        if item.svn() != Some(svn) || item.authbits() < MIN_AUTHBITS {
+            return None;
         }
-        None
+        let age: i32 = item.min_age().into();
+        Some((
+            row_gst,
+            NavMessageData {
+                data: item.message_bits(),
+                authbits: item.authbits(),
+                gst: row_gst.add_subframes(-age),
+                reconstructed: item.reconstructed(),
+            },
+        ))
     }
 
-    fn find_ced_and_status(&mut self, svn: Svn, gst: Gst) -> Option<&CedAndStatus> {
-        let gst_idx = self.find_gst(gst)?;
-        self.ced_and_status[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS]
-            .iter()
-            .find(|item| item.svn == Some(svn))
+    // Returns the CED and health status batches that a tag for an SVN and GST
+    // can authenticate: the batch being collected, and the previous complete
+    // batch with a different IODnav, if there is one.
+    fn find_ced_and_status_batches(
+        &self,
+        svn: Svn,
+        gst: Gst,
+    ) -> impl Iterator<Item = &CedAndStatus> {
+        let idx = self.find_gst(gst).and_then(|gst_idx| {
+            Some(gst_idx * S::NUM_SATS + self.ced_and_status_index[gst_idx].get(svn)?)
+        });
+        [&self.ced_and_status[..], &self.ced_and_status_previous[..]]
+            .into_iter()
+            .filter_map(move |items| items.get(idx?))
+            .filter(move |item| item.svn == Some(svn))
     }
 
-    fn find_timing_parameters(&mut self, svn: Svn, gst: Gst) -> Option<&TimingParameters> {
+    fn find_timing_parameters(&self, svn: Svn, gst: Gst) -> Option<&TimingParameters> {
         let gst_idx = self.find_gst(gst)?;
-        self.timing_parameters[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS]
-            .iter()
-            .find(|item| item.svn == Some(svn))
+        let slot = self.timing_parameters_index[gst_idx].get(svn)?;
+        Some(&self.timing_parameters[gst_idx * S::NUM_SATS + slot])
     }
 
     fn ced_and_status_iter_authbits_mut(&mut self) -> impl Iterator<Item = &mut dyn AuthBits> {
-        self.ced_and_status.iter_mut().map(|x| {
-            let y: &mut dyn AuthBits = x;
-            y
-        })
+        self.ced_and_status
+            .iter_mut()
+            .chain(self.ced_and_status_previous.iter_mut())
+            .map(|x| {
+                let y: &mut dyn AuthBits = x;
+                y
+            })
     }
 
     fn timing_parameters_iter_authbits_mut(&mut self) -> impl Iterator<Item = &mut dyn AuthBits> {
@@ -301,10 +791,7 @@ impl<S: StaticStorage> CollectNavMessage<S> {
 
     fn find_gst(&self, gst: Gst) -> Option<usize> {
         assert!(gst.is_subframe());
-        self.gsts
-            .iter()
-            .enumerate()
-            .find_map(|(j, &g)| if g == Some(gst) { Some(j) } else { None })
+        find_gst_row(&self.gsts, self.write_pointer, gst)
     }
 
     /// Process a MACK message.
@@ -331,7 +818,32 @@ impl<S: StaticStorage> CollectNavMessage<S> {
         gst_mack: Gst,
         nma_status: NmaStatus,
     ) {
-        log::info!("{} tag0 at {:?} COP = {}", prna, gst_mack, mack.cop());
+        let updates = self.validate_mack_tags(
+            mack,
+            &MacKey::new(*key),
+            prna,
+            gst_mack,
+            nma_status,
+            &mut |_| {},
+        );
+        self.apply_authbits_updates(&updates);
+    }
+
+    // Validates the tags of a MACK message as described in process_mack,
+    // returning the authentication bits that should be added to the stored
+    // navigation data. The MAC trace of each tag validation is given to
+    // `trace`.
+    pub(crate) fn validate_mack_tags(
+        &self,
+        mack: Mack<Validated>,
+        key: &MacKey,
+        prna: Svn,
+        gst_mack: Gst,
+        nma_status: NmaStatus,
+        trace: &mut dyn FnMut(&MacTrace<'_>),
+    ) -> AuthbitsUpdates {
+        let mut updates = AuthbitsUpdates::new();
+        info!("tag0"; prna = %prna, gst = gst_mack, cop = mack.cop());
         let gst_navmessage = gst_mack.add_seconds(-30);
         if mack.cop() == 0 {
             Self::validate_dummy_tag(
@@ -345,40 +857,49 @@ impl<S: StaticStorage> CollectNavMessage<S> {
                 nma_status,
                 CED_AND_STATUS_BITS,
             );
-        } else if let Some(&navdata) = self.find_ced_and_status(prna, gst_navmessage) {
-            if navdata.max_age().saturating_add(1) <= mack.cop() {
-                // Try to validate tag0
-                Self::validate_tag(
-                    key,
-                    mack.tag0(),
-                    Adkd::InavCed,
-                    gst_mack,
-                    u8::from(prna),
-                    prna,
-                    0,
-                    nma_status,
-                    &navdata,
-                    self.ced_and_status_iter_authbits_mut(),
-                );
+        } else {
+            // Try to validate tag0
+            for &navdata in self.find_ced_and_status_batches(prna, gst_navmessage) {
+                if navdata.max_age().saturating_add(1) <= mack.cop()
+                    && Self::validate_tag(
+                        key,
+                        mack.tag0(),
+                        Adkd::InavCed,
+                        gst_mack,
+                        u8::from(prna),
+                        prna,
+                        0,
+                        nma_status,
+                        &navdata,
+                        trace,
+                    )
+                {
+                    updates.add(
+                        AuthbitsUpdate::CedAndStatus(navdata),
+                        mack.tag0(),
+                        nma_status,
+                    );
+                    break;
+                }
             }
         }
 
         // Try to validate InavCed and InavTiming tags
         for j in 1..mack.num_tags() {
             let tag = mack.tag_and_info(j);
-            log::info!(
-                "{} tag{} {:?} at {:?} COP = {} PRND = {:?}",
-                prna,
-                j,
-                tag.adkd(),
-                gst_mack,
-                tag.cop(),
-                tag.prnd()
+            info!(
+                "tag";
+                prna = %prna,
+                tag_idx = j,
+                adkd = tag.adkd(),
+                gst = gst_mack,
+                cop = tag.cop(),
+                prnd = tag.prnd(),
             );
             let prnd = match u8::try_from(tag.prnd()) {
                 Ok(p) => p,
                 Err(_) => {
-                    log::error!("could not obtain PRND from tag {:?}", tag);
+                    error!("could not obtain PRND from tag"; prnd = tag.prnd(), adkd = tag.adkd());
                     continue;
                 }
             };
@@ -397,27 +918,36 @@ impl<S: StaticStorage> CollectNavMessage<S> {
                                 nma_status,
                                 CED_AND_STATUS_BITS,
                             );
-                        } else if let Some(&navdata) =
-                            self.find_ced_and_status(prnd_svn, gst_navmessage)
-                        {
-                            if navdata.max_age().saturating_add(1) <= tag.cop() {
-                                Self::validate_tag(
-                                    key,
-                                    tag.tag(),
-                                    tag.adkd(),
-                                    gst_mack,
-                                    prnd,
-                                    prna,
-                                    j,
-                                    nma_status,
-                                    &navdata,
-                                    self.ced_and_status_iter_authbits_mut(),
-                                );
+                        } else {
+                            for &navdata in
+                                self.find_ced_and_status_batches(prnd_svn, gst_navmessage)
+                            {
+                                if navdata.max_age().saturating_add(1) <= tag.cop()
+                                    && Self::validate_tag(
+                                        key,
+                                        tag.tag(),
+                                        tag.adkd(),
+                                        gst_mack,
+                                        prnd,
+                                        prna,
+                                        j,
+                                        nma_status,
+                                        &navdata,
+                                        trace,
+                                    )
+                                {
+                                    updates.add(
+                                        AuthbitsUpdate::CedAndStatus(navdata),
+                                        tag.tag(),
+                                        nma_status,
+                                    );
+                                    break;
+                                }
                             }
                         }
                     }
                     Err(_) => {
-                        log::error!("invalid PRND {:?} for ADKD {:?}", tag.prnd(), tag.adkd());
+                        error!("invalid PRND for ADKD"; prnd = tag.prnd(), adkd = tag.adkd());
                     }
                 },
                 Adkd::InavTiming => match Svn::try_from(prnd) {
@@ -437,8 +967,8 @@ impl<S: StaticStorage> CollectNavMessage<S> {
                         } else if let Some(&navdata) =
                             self.find_timing_parameters(prnd_svn, gst_navmessage)
                         {
-                            if navdata.max_age().saturating_add(1) <= tag.cop() {
-                                Self::validate_tag(
+                            if navdata.max_age().saturating_add(1) <= tag.cop()
+                                && Self::validate_tag(
                                     key,
                                     tag.tag(),
                                     tag.adkd(),
@@ -448,13 +978,19 @@ impl<S: StaticStorage> CollectNavMessage<S> {
                                     j,
                                     nma_status,
                                     &navdata,
-                                    self.timing_parameters_iter_authbits_mut(),
+                                    trace,
+                                )
+                            {
+                                updates.add(
+                                    AuthbitsUpdate::TimingParameters(navdata),
+                                    tag.tag(),
+                                    nma_status,
                                 );
                             }
                         }
                     }
                     Err(_) => {
-                        log::error!("invalid PRND {:?} for ADKD {:?}", tag.prnd(), tag.adkd());
+                        error!("invalid PRND for ADKD"; prnd = tag.prnd(), adkd = tag.adkd());
                     }
                 },
                 Adkd::SlowMac => {
@@ -462,10 +998,11 @@ impl<S: StaticStorage> CollectNavMessage<S> {
                     // have the appropriate extra delay
                 }
                 Adkd::Reserved => {
-                    log::error!("reserved ADKD in tag {:?}", tag);
+                    error!("reserved ADKD in tag"; prnd = tag.prnd(), cop = tag.cop());
                 }
             }
         }
+        updates
     }
 
     /// Process the Slow MAC (ADKD=12) tags in a MACK message.
@@ -492,6 +1029,30 @@ impl<S: StaticStorage> CollectNavMessage<S> {
         gst_mack: Gst,
         nma_status: NmaStatus,
     ) {
+        let updates = self.validate_mack_slowmac_tags(
+            mack,
+            &MacKey::new(*key),
+            prna,
+            gst_mack,
+            nma_status,
+            &mut |_| {},
+        );
+        self.apply_authbits_updates(&updates);
+    }
+
+    // Validates the Slow MAC tags of a MACK message as described in
+    // process_mack_slowmac, returning the authentication bits that should be
+    // added to the stored navigation data.
+    pub(crate) fn validate_mack_slowmac_tags(
+        &self,
+        mack: Mack<Validated>,
+        key: &MacKey,
+        prna: Svn,
+        gst_mack: Gst,
+        nma_status: NmaStatus,
+        trace: &mut dyn FnMut(&MacTrace<'_>),
+    ) -> AuthbitsUpdates {
+        let mut updates = AuthbitsUpdates::new();
         let gst_navmessage = gst_mack.add_seconds(-30);
         for j in 1..mack.num_tags() {
             let tag = mack.tag_and_info(j);
@@ -501,14 +1062,14 @@ impl<S: StaticStorage> CollectNavMessage<S> {
             let prnd = match u8::try_from(tag.prnd()) {
                 Ok(p) => p,
                 Err(_) => {
-                    log::error!("could not obtain PRND from tag {:?}", tag);
+                    error!("could not obtain PRND from tag"; prnd = tag.prnd(), adkd = tag.adkd());
                     continue;
                 }
             };
             let prnd_svn = match Svn::try_from(prnd) {
                 Ok(s) => s,
                 Err(_) => {
-                    log::error!("invalid PRND {:?} for Slow MAC tag {:?}", tag.prnd(), tag);
+                    error!("invalid PRND for Slow MAC tag"; prnd = tag.prnd(), cop = tag.cop());
                     continue;
                 }
             };
@@ -524,28 +1085,34 @@ impl<S: StaticStorage> CollectNavMessage<S> {
                     nma_status,
                     CED_AND_STATUS_BITS,
                 );
-            } else if let Some(&navdata) = self.find_ced_and_status(prnd_svn, gst_navmessage) {
-                if navdata.max_age().saturating_add(1) <= tag.cop() {
-                    Self::validate_tag(
-                        key,
-                        tag.tag(),
-                        tag.adkd(),
-                        gst_mack,
-                        prnd,
-                        prna,
-                        j,
-                        nma_status,
-                        &navdata,
-                        self.ced_and_status_iter_authbits_mut(),
-                    );
+            } else {
+                for &navdata in self.find_ced_and_status_batches(prnd_svn, gst_navmessage) {
+                    if navdata.max_age().saturating_add(1) <= tag.cop()
+                        && Self::validate_tag(
+                            key,
+                            tag.tag(),
+                            tag.adkd(),
+                            gst_mack,
+                            prnd,
+                            prna,
+                            j,
+                            nma_status,
+                            &navdata,
+                            trace,
+                        )
+                    {
+                        updates.add(AuthbitsUpdate::CedAndStatus(navdata), tag.tag(), nma_status);
+                        break;
+                    }
                 }
             }
         }
+        updates
     }
 
     #[allow(clippy::too_many_arguments)]
-    fn validate_tag<'a>(
-        key: &Key<Validated>,
+    fn validate_tag(
+        key: &MacKey,
         tag: &BitSlice,
         adkd: Adkd,
         gst_tag: Gst,
@@ -554,12 +1121,20 @@ impl<S: StaticStorage> CollectNavMessage<S> {
         tag_idx: usize,
         nma_status: NmaStatus,
         navdata: &dyn AuthBits,
-        to_add_authbits: impl Iterator<Item = &'a mut dyn AuthBits>,
+        trace: &mut dyn FnMut(&MacTrace<'_>),
     ) -> bool {
         let ctr = (tag_idx + 1).try_into().unwrap();
+        let trace = |t: &TagTrace<'_>| trace(&MacTrace::Tag(*t));
         let ret = match tag_idx {
-            0 => key.validate_tag0(tag, gst_tag, prna, nma_status, navdata.message_bits()),
-            _ => key.validate_tag(
+            0 => key.validate_tag0_with_trace(
+                tag,
+                gst_tag,
+                prna,
+                nma_status,
+                navdata.message_bits(),
+                trace,
+            ),
+            _ => key.validate_tag_with_trace(
                 tag,
                 gst_tag,
                 prnd,
@@ -567,37 +1142,26 @@ impl<S: StaticStorage> CollectNavMessage<S> {
                 ctr,
                 nma_status,
                 navdata.message_bits(),
+                trace,
             ),
         };
         if ret {
-            log::info!(
-                "E{:02} {:?} at {:?} tag{} correct (auth by {})",
-                prnd,
-                adkd,
-                gst_tag,
-                tag_idx,
-                prna
+            info!(
+                "tag correct";
+                prnd = prnd,
+                adkd = adkd,
+                gst = gst_tag,
+                tag_idx = tag_idx,
+                prna = %prna,
             );
-            // This nma_status is known good because it has been used in the tag
-            // validation, so we can act on it to decide if we can add
-            // authentication bits.
-            if matches!(nma_status, NmaStatus::Operational | NmaStatus::Test) {
-                for to_add in to_add_authbits {
-                    if navdata.svn() == to_add.svn()
-                        && navdata.message_bits() == to_add.message_bits()
-                    {
-                        to_add.add_authbits(tag);
-                    }
-                }
-            }
         } else {
-            log::error!(
-                "E{:02} {:?} at {:?} tag{} wrong (auth by {})",
-                prnd,
-                adkd,
-                gst_tag,
-                tag_idx,
-                prna
+            error!(
+                "tag wrong";
+                prnd = prnd,
+                adkd = adkd,
+                gst = gst_tag,
+                tag_idx = tag_idx,
+                prna = %prna,
             );
         }
         ret
@@ -605,7 +1169,7 @@ impl<S: StaticStorage> CollectNavMessage<S> {
 
     #[allow(clippy::too_many_arguments)]
     fn validate_dummy_tag(
-        key: &Key<Validated>,
+        key: &MacKey,
         tag: &BitSlice,
         adkd: Adkd,
         gst_tag: Gst,
@@ -623,22 +1187,22 @@ impl<S: StaticStorage> CollectNavMessage<S> {
             }
         };
         if ret {
-            log::info!(
-                "E{:02} {:?} at {:?} dummy tag{} correct (auth by {})",
-                prnd,
-                adkd,
-                gst_tag,
-                tag_idx,
-                prna
+            info!(
+                "dummy tag correct";
+                prnd = prnd,
+                adkd = adkd,
+                gst = gst_tag,
+                tag_idx = tag_idx,
+                prna = %prna,
             );
         } else {
-            log::error!(
-                "E{:02} {:?} at {:?} dummy tag{} wrong (auth by {})",
-                prnd,
-                adkd,
-                gst_tag,
-                tag_idx,
-                prna
+            error!(
+                "dummy tag wrong";
+                prnd = prnd,
+                adkd = adkd,
+                gst = gst_tag,
+                tag_idx = tag_idx,
+                prna = %prna,
             );
         }
         ret
@@ -649,13 +1213,93 @@ impl<S: StaticStorage> CollectNavMessage<S> {
     /// This function can be called when the NMA status is set to don't use in
     /// order to discard all the previously generated authentication bits.
     pub fn reset_authbits(&mut self) {
-        for ced in self.ced_and_status.iter_mut() {
+        for ced in self
+            .ced_and_status
+            .iter_mut()
+            .chain(self.ced_and_status_previous.iter_mut())
+        {
             ced.reset_authbits();
         }
         for timing in self.timing_parameters.iter_mut() {
             timing.reset_authbits();
         }
     }
+
+    // Adds the authentication bits obtained by validating the tags of a MACK
+    // message to all the stored copies of the corresponding navigation data.
+    pub(crate) fn apply_authbits_updates(&mut self, updates: &AuthbitsUpdates) {
+        for &(update, authbits) in updates.updates.iter().flatten() {
+            match update {
+                AuthbitsUpdate::CedAndStatus(navdata) => {
+                    Self::add_authbits(&navdata, authbits, self.ced_and_status_iter_authbits_mut())
+                }
+                AuthbitsUpdate::TimingParameters(navdata) => Self::add_authbits(
+                    &navdata,
+                    authbits,
+                    self.timing_parameters_iter_authbits_mut(),
+                ),
+            }
+        }
+    }
+
+    fn add_authbits<'a>(
+        navdata: &dyn AuthBits,
+        authbits: u16,
+        to_add_authbits: impl Iterator<Item = &'a mut dyn AuthBits>,
+    ) {
+        for to_add in to_add_authbits {
+            if navdata.svn() == to_add.svn() && navdata.message_bits() == to_add.message_bits() {
+                to_add.add_authbits(authbits);
+            }
+        }
+    }
+}
+
+// Maximum number of tags in a MACK message (including tag0). This is attained
+// with the minimum key size (96 bits) and tag size (20 bits).
+const MAX_TAGS: usize = (8 * MACK_MESSAGE_BYTES - 96) / (20 + 16);
+
+// Authentication bits to be added to the stored navigation data as a result of
+// validating the tags in a MACK message.
+//
+// Tag validation only needs a shared reference to the CollectNavMessage. The
+// authentication bits are added afterwards by
+// CollectNavMessage::apply_authbits_updates. This allows validating the MACK
+// messages of different satellites concurrently.
+#[derive(Debug, Clone)]
+pub(crate) struct AuthbitsUpdates {
+    // Each update contains the navigation data that has been authenticated
+    // and the number of authentication bits to add.
+    updates: [Option<(AuthbitsUpdate, u16)>; MAX_TAGS],
+}
+
+#[derive(Debug, Copy, Clone)]
+enum AuthbitsUpdate {
+    CedAndStatus(CedAndStatus),
+    TimingParameters(TimingParameters),
+}
+
+impl AuthbitsUpdates {
+    fn new() -> AuthbitsUpdates {
+        AuthbitsUpdates {
+            updates: [None; MAX_TAGS],
+        }
+    }
+
+    // Records the update for a tag that has been validated successfully.
+    fn add(&mut self, update: AuthbitsUpdate, tag: &BitSlice, nma_status: NmaStatus) {
+        // This nma_status is known good because it has been used in the tag
+        // validation, so we can act on it to decide if we can add
+        // authentication bits.
+        if !matches!(nma_status, NmaStatus::Operational | NmaStatus::Test) {
+            return;
+        }
+        let authbits = tag.len().try_into().unwrap();
+        match self.updates.iter_mut().find(|u| u.is_none()) {
+            Some(slot) => *slot = Some((update, authbits)),
+            None => error!("too many tags in MACK; discarding update"; authbits = authbits),
+        }
+    }
 }
 
 impl<S: StaticStorage> Default for CollectNavMessage<S> {
@@ -666,7 +1310,7 @@ impl<S: StaticStorage> Default for CollectNavMessage<S> {
 
 const CED_AND_STATUS_WORDS: usize = 5;
 const CED_AND_STATUS_BITS: usize = 549;
-const CED_AND_STATUS_BYTES: usize = (CED_AND_STATUS_BITS + 7) / 8;
+pub(crate) const CED_AND_STATUS_BYTES: usize = (CED_AND_STATUS_BITS + 7) / 8;
 
 #[doc(hidden)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -676,11 +1320,14 @@ pub struct CedAndStatus {
     age: [u8; CED_AND_STATUS_WORDS],
     svn: Option<Svn>,
     authbits: u16,
+    // Bitmask of the words 1 to 4 that have been reconstructed with the
+    // Reed-Solomon outer FEC.
+    reconstructed: u8,
 }
 
 const TIMING_PARAMETERS_WORDS: usize = 2;
 const TIMING_PARAMETERS_BITS: usize = 141;
-const TIMING_PARAMETERS_BYTES: usize = (TIMING_PARAMETERS_BITS + 7) / 8;
+pub(crate) const TIMING_PARAMETERS_BYTES: usize = (TIMING_PARAMETERS_BITS + 7) / 8;
 
 #[doc(hidden)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -690,12 +1337,18 @@ pub struct TimingParameters {
     age: [u8; TIMING_PARAMETERS_WORDS],
     svn: Option<Svn>,
     authbits: u16,
+    // Always zero. This is only present so that the struct can share the
+    // common implementation with CedAndStatus.
+    reconstructed: u8,
 }
 
 trait AuthBits {
     fn svn(&self) -> Option<Svn>;
     fn message_bits(&self) -> &BitSlice;
-    fn add_authbits(&mut self, tag: &BitSlice);
+    fn authbits(&self) -> u16;
+    fn min_age(&self) -> u8;
+    fn reconstructed(&self) -> bool;
+    fn add_authbits(&mut self, authbits: u16);
     fn reset_authbits(&mut self);
 }
 
@@ -708,6 +1361,7 @@ macro_rules! impl_common {
                     age: [u8::MAX; $num_words],
                     authbits: 0,
                     svn: None,
+                    reconstructed: 0,
                 }
             }
 
@@ -715,6 +1369,7 @@ macro_rules! impl_common {
                 self.age.fill(u8::MAX);
                 self.authbits = 0;
                 self.svn = None;
+                self.reconstructed = 0;
             }
 
             fn bits(&self) -> &BitSlice {
@@ -729,10 +1384,6 @@ macro_rules! impl_common {
                 self.age.iter().copied().max().unwrap()
             }
 
-            fn min_age(&self) -> u8 {
-                self.age.iter().copied().min().unwrap()
-            }
-
             fn copy_word(
                 &mut self,
                 dest_range: core::ops::Range<usize>,
@@ -748,18 +1399,20 @@ macro_rules! impl_common {
             }
 
             fn log_word(&self, word_type: u8) {
-                log::trace!(
-                    concat!(stringify!($s), " storing INAV word type {} for {}"),
-                    self.svn.unwrap(),
-                    word_type
+                trace!(
+                    "storing INAV word";
+                    store = %stringify!($s),
+                    word_type = word_type,
+                    svn = %self.svn.unwrap(),
                 );
             }
 
             fn log_age(&self) {
-                log::trace!(
-                    concat!(stringify!($s), " for {} age: {:?}"),
-                    self.svn.unwrap(),
-                    &self.age
+                trace!(
+                    "INAV word age";
+                    store = %stringify!($s),
+                    svn = %self.svn.unwrap(),
+                    age = self.age,
                 );
             }
         }
@@ -773,8 +1426,20 @@ macro_rules! impl_common {
                 &self.bits()[..$num_bits]
             }
 
-            fn add_authbits(&mut self, tag: &BitSlice) {
-                self.authbits = self.authbits.saturating_add(tag.len().try_into().unwrap());
+            fn authbits(&self) -> u16 {
+                self.authbits
+            }
+
+            fn min_age(&self) -> u8 {
+                self.age.iter().copied().min().unwrap()
+            }
+
+            fn reconstructed(&self) -> bool {
+                self.reconstructed != 0
+            }
+
+            fn add_authbits(&mut self, authbits: u16) {
+                self.authbits = self.authbits.saturating_add(authbits);
             }
 
             fn reset_authbits(&mut self) {
@@ -803,8 +1468,16 @@ impl_common!(
     TIMING_PARAMETERS_BITS
 );
 
+// Offsets of the INAV words 1 to 4, which contain the IODnav, in the CED and
+// health status data.
+const IODNAV_WORD_OFFSETS: [usize; 4] = [0, 120, 240, 362];
+const IODNAV_BITS: usize = 10;
+
 impl CedAndStatus {
-    fn feed(&mut self, word: &InavWord, svn: Svn) {
+    // Feeds an INAV word. If the word has an IODnav different from the words
+    // stored so far and these form a complete batch, the complete batch is
+    // returned, so that it can be kept as the previous batch.
+    fn feed(&mut self, word: &InavWord, svn: Svn) -> Option<CedAndStatus> {
         match self.svn {
             Some(s) if s == svn => (),
             None => self.svn = Some(svn),
@@ -816,21 +1489,227 @@ impl CedAndStatus {
 
         let word = BitSlice::from_slice(word);
         let word_type = word[..6].load_be::<u8>();
+        let mut previous = None;
+        if (1..=4).contains(&word_type) {
+            let iodnav = word[6..6 + IODNAV_BITS].load_be::<u16>();
+            if let Some(current) = self.iodnav().filter(|&current| current != iodnav) {
+                debug!("new IODnav"; svn = %svn, iodnav = iodnav, previous_iodnav = current);
+                if self.max_age() != u8::MAX {
+                    previous = Some(*self);
+                }
+                // Discard the words with the old IODnav. Word 5 does not have
+                // an IODnav, so it is kept.
+                self.age[..IODNAV_WORD_OFFSETS.len()].fill(u8::MAX);
+                self.authbits = 0;
+                self.reconstructed = 0;
+            }
+            // A received word replaces a reconstructed word
+            self.reconstructed &= !(1 << (word_type - 1));
+        }
         if (1..=5).contains(&word_type) {
             self.log_word(word_type);
         }
-        match word_type {
-            1 => self.copy_word(0..120, &word[6..126], 0),
-            2 => self.copy_word(120..240, &word[6..126], 1),
-            3 => self.copy_word(240..362, &word[6..128], 2),
-            4 => self.copy_word(362..482, &word[6..126], 3),
-            5 => self.copy_word(482..549, &word[6..73], 4),
-            _ => (),
+        if let Some(position) = ced_and_status_word_position(word_type) {
+            self.copy_word(
+                position.data_bits,
+                &word[position.word_bits],
+                position.index,
+            );
+        }
+        self.log_age();
+        previous
+    }
+
+    // Returns the IODnav of the words 1 to 4 that have been received. All of
+    // them have the same IODnav.
+    fn iodnav(&self) -> Option<u16> {
+        let offset = IODNAV_WORD_OFFSETS
+            .iter()
+            .zip(self.age.iter())
+            .find_map(|(&offset, &age)| (age != u8::MAX).then_some(offset))?;
+        Some(self.bits()[offset..offset + IODNAV_BITS].load_be())
+    }
+}
+
+// Number of bits of the INAV words 1 to 4 stored in the CED and health status
+// data, starting with the IODnav. The last 2 bits of words 1, 2 and 4 are
+// spare and are not stored.
+const CED_WORD_BITS: [usize; 4] = [120, 120, 122, 120];
+
+// Information symbols of the Reed-Solomon outer FEC that correspond to the
+// bits of the INAV words 1 to 4 that follow the IODnav. The first 2 symbols
+// contain the word type and IODnav of word 1.
+const FEC_WORD_SYMBOLS: [core::ops::Range<usize>; 4] = [2..16, 16..30, 30..44, 44..58];
+// Number of parity symbols in each of the INAV words 17 to 20.
+const FEC_PARITY_WORD_SYMBOLS: usize = 15;
+
+impl CedAndStatus {
+    // Tries to reconstruct the missing words 1 to 4 using the Reed-Solomon
+    // outer FEC. This requires that the received words 1 to 4 and parity
+    // words 17 to 20 with the same IODnav amount to at least 4 words.
+    //
+    // The age of the reconstructed words is the maximum age of the words used
+    // to reconstruct them.
+    fn reconstruct(&mut self, parity: &CedParity) {
+        let missing = (0..IODNAV_WORD_OFFSETS.len())
+            .filter(|&j| self.age[j] == u8::MAX)
+            .fold(0u8, |mask, j| mask | (1 << j));
+        let Some(svn) = self.svn else {
+            return;
         };
+        if missing == 0 {
+            return;
+        }
+        let iodnav = self.iodnav();
+        // If no words with an IODnav have been received, the parity words
+        // with the same IODnav LSBs as the most recent parity word are used.
+        let iodnav_lsbs = match iodnav {
+            Some(iodnav) => (iodnav & 0x3) as u8,
+            None => {
+                let (j, _) = parity
+                    .age
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, &age)| age)
+                    .unwrap();
+                parity.iodnav_lsbs[j]
+            }
+        };
+
+        let mut codeword = [0; reed_solomon::CODEWORD_SYMBOLS];
+        let mut erased = [false; reed_solomon::CODEWORD_SYMBOLS];
+        let mut age = 0;
+        let codeword_bits = BitSlice::from_slice_mut(&mut codeword);
+        codeword_bits[..6].store_be(1u8);
+        match iodnav {
+            Some(iodnav) => codeword_bits[6..6 + IODNAV_BITS].store_be(iodnav),
+            None => erased[..FEC_WORD_SYMBOLS[0].start].fill(true),
+        }
+        for (j, symbols) in FEC_WORD_SYMBOLS.iter().enumerate() {
+            if missing & (1 << j) != 0 {
+                erased[symbols.clone()].fill(true);
+                continue;
+            }
+            age = age.max(self.age[j]);
+            let offset = IODNAV_WORD_OFFSETS[j];
+            let stored = CED_WORD_BITS[j] - IODNAV_BITS;
+            let dest = &mut codeword_bits[8 * symbols.start..8 * symbols.end];
+            dest[..stored]
+                .copy_from_bitslice(&self.bits()[offset + IODNAV_BITS..offset + CED_WORD_BITS[j]]);
+            if stored < dest.len() {
+                dest[stored..].store_be(parity.spare[j]);
+            }
+        }
+        for j in 0..parity.age.len() {
+            let symbols = FEC_PARITY_WORD_SYMBOLS * j..FEC_PARITY_WORD_SYMBOLS * (j + 1);
+            let codeword_symbols = reed_solomon::INFO_SYMBOLS + symbols.start
+                ..reed_solomon::INFO_SYMBOLS + symbols.end;
+            if parity.age[j] != u8::MAX && parity.iodnav_lsbs[j] == iodnav_lsbs {
+                age = age.max(parity.age[j]);
+                codeword[codeword_symbols].copy_from_slice(&parity.data[symbols]);
+            } else {
+                erased[codeword_symbols].fill(true);
+            }
+        }
+        if erased.iter().filter(|&&e| e).count() > reed_solomon::PARITY_SYMBOLS {
+            return;
+        }
+
+        if reed_solomon::decode_erasures(&mut codeword, &erased).is_err() {
+            debug!("Reed-Solomon decoding of CED failed"; svn = %svn);
+            return;
+        }
+        let codeword_bits = BitSlice::from_slice(&codeword);
+        let iodnav = codeword_bits[6..6 + IODNAV_BITS].load_be::<u16>();
+        if codeword_bits[..6].load_be::<u8>() != 1 || (iodnav & 0x3) as u8 != iodnav_lsbs {
+            debug!("Reed-Solomon decoded CED is inconsistent"; svn = %svn);
+            return;
+        }
+        for (j, symbols) in FEC_WORD_SYMBOLS.iter().enumerate() {
+            if missing & (1 << j) == 0 {
+                continue;
+            }
+            let mut word = [0; 16];
+            let word_bits = BitSlice::from_slice_mut(&mut word);
+            word_bits[..IODNAV_BITS].store_be(iodnav);
+            let source = &codeword_bits[8 * symbols.start..8 * symbols.end];
+            word_bits[IODNAV_BITS..IODNAV_BITS + source.len()].copy_from_bitslice(source);
+            let offset = IODNAV_WORD_OFFSETS[j];
+            self.copy_word(
+                offset..offset + CED_WORD_BITS[j],
+                &word_bits[..CED_WORD_BITS[j]],
+                j,
+            );
+            self.age[j] = age;
+            self.reconstructed |= 1 << j;
+        }
+        info!(
+            "reconstructed CED words with Reed-Solomon FEC";
+            svn = %svn,
+            words = missing,
+            iodnav = iodnav,
+        );
         self.log_age();
     }
 }
 
+// Reed-Solomon parity of the CED of a satellite.
+//
+// This stores the most recently received parity words 17 to 20, together with
+// the 2 LSBs of the IODnav of the CED that each of them protects. It also
+// stores the spare bits at the end of words 1, 2 and 4, which are part of the
+// information symbols of the code but are not stored in CedAndStatus.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct CedParity {
+    data: [u8; reed_solomon::PARITY_SYMBOLS],
+    age: [u8; 4],
+    iodnav_lsbs: [u8; 4],
+    spare: [u8; 4],
+}
+
+impl CedParity {
+    const fn new() -> CedParity {
+        CedParity {
+            data: [0; reed_solomon::PARITY_SYMBOLS],
+            age: [u8::MAX; 4],
+            iodnav_lsbs: [0; 4],
+            spare: [0; 4],
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = CedParity::new();
+    }
+
+    // Feeds an INAV word. Returns true if the word is one of the words 1 to 4
+    // or 17 to 20, which take part in the Reed-Solomon outer FEC.
+    fn feed(&mut self, word: &InavWord) -> bool {
+        let word = BitSlice::from_slice(word);
+        let word_type = word[..6].load_be::<u8>();
+        match word_type {
+            1..=4 => {
+                let j = usize::from(word_type - 1);
+                if CED_WORD_BITS[j] < 122 {
+                    self.spare[j] = word[126..128].load_be();
+                }
+                true
+            }
+            17..=20 => {
+                trace!("storing Reed-Solomon parity word"; word_type = word_type);
+                let j = usize::from(word_type - 17);
+                let symbols =
+                    &mut self.data[FEC_PARITY_WORD_SYMBOLS * j..FEC_PARITY_WORD_SYMBOLS * (j + 1)];
+                symbols[0] = word[6..14].load_be();
+                BitSlice::from_slice_mut(&mut symbols[1..]).copy_from_bitslice(&word[16..128]);
+                self.iodnav_lsbs[j] = word[14..16].load_be();
+                self.age[j] = 0;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
 impl TimingParameters {
     fn feed(&mut self, word: &InavWord, svn: Svn, band: InavBand) {
         match self.svn {
@@ -844,17 +1723,398 @@ impl TimingParameters {
 
         let word = BitSlice::from_slice(word);
         let word_type = word[..6].load_be::<u8>();
-        match (word_type, band) {
-            (6, InavBand::E1B) => {
-                self.log_word(word_type);
-                self.copy_word(0..99, &word[6..105], 0);
-            }
-            (10, InavBand::E1B) => {
+        if band == InavBand::E1B {
+            if let Some(position) = timing_parameters_word_position(word_type) {
                 self.log_word(word_type);
-                self.copy_word(99..141, &word[86..128], 1);
+                self.copy_word(
+                    position.data_bits,
+                    &word[position.word_bits],
+                    position.index,
+                );
             }
-            _ => (),
         }
         self.log_age();
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::storage::FullStorage;
+
+    pub(crate) fn inav_word(word_type: u8, iodnav: u16, fill: u8) -> InavWord {
+        let mut word = [fill; 16];
+        let bits = BitSlice::from_slice_mut(&mut word);
+        bits[..6].store_be(word_type);
+        if (1..=4).contains(&word_type) {
+            bits[6..16].store_be(iodnav);
+        }
+        word
+    }
+
+    // Computes the Reed-Solomon parity words 17 to 20 for the words 1 to 4.
+    fn parity_words(words: &[InavWord; 4]) -> [InavWord; 4] {
+        let mut info = [0; reed_solomon::INFO_SYMBOLS];
+        info[..16].copy_from_slice(&words[0]);
+        for j in 1..4 {
+            info[FEC_WORD_SYMBOLS[j].clone()].copy_from_slice(&words[j][2..]);
+        }
+        let parity = reed_solomon::encode(&info);
+        let iodnav = BitSlice::from_slice(&words[0])[6..16].load_be::<u16>();
+        core::array::from_fn(|j| {
+            let symbols = &parity[FEC_PARITY_WORD_SYMBOLS * j..FEC_PARITY_WORD_SYMBOLS * (j + 1)];
+            let mut word = [0; 16];
+            let bits = BitSlice::from_slice_mut(&mut word);
+            bits[..6].store_be(17 + j as u8);
+            bits[6..14].store_be(symbols[0]);
+            bits[14..16].store_be(iodnav & 0x3);
+            word[2..].copy_from_slice(&symbols[1..]);
+            word
+        })
+    }
+
+    pub(crate) fn authenticate_all(navmessage: &mut CollectNavMessage<FullStorage>) {
+        for ced in navmessage.ced_and_status_iter_authbits_mut() {
+            if ced.svn().is_some() {
+                ced.add_authbits(MIN_AUTHBITS);
+            }
+        }
+    }
+
+    #[test]
+    fn contains_inav_word() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1177, 175740);
+        let mut navmessage = CollectNavMessage::<FullStorage>::new();
+        for word_type in [1, 2, 3, 4, 5, 6, 10] {
+            navmessage.feed(&inav_word(word_type, 1, 0xaa), svn, gst, InavBand::E1B);
+        }
+        authenticate_all(&mut navmessage);
+        for timing in navmessage.timing_parameters_iter_authbits_mut() {
+            if timing.svn().is_some() {
+                timing.add_authbits(MIN_AUTHBITS);
+            }
+        }
+        let ced = navmessage.get_ced_and_status(svn).unwrap();
+        let timing = navmessage.get_timing_parameters(svn).unwrap();
+        for word_type in 1..=5 {
+            assert!(ced.contains_inav_word(&inav_word(word_type, 1, 0xaa)));
+            assert!(!ced.contains_inav_word(&inav_word(word_type, 1, 0x55)));
+            assert!(!timing.contains_inav_word(&inav_word(word_type, 1, 0xaa)));
+        }
+        // Different IODnav
+        assert!(!ced.contains_inav_word(&inav_word(1, 2, 0xaa)));
+        // The WN and TOW fields of word 5 are not part of the data
+        let mut word = inav_word(5, 1, 0xaa);
+        word[15] = 0;
+        assert!(ced.contains_inav_word(&word));
+        for word_type in [6, 10] {
+            assert!(timing.contains_inav_word(&inav_word(word_type, 1, 0xaa)));
+            assert!(!timing.contains_inav_word(&inav_word(word_type, 1, 0x55)));
+            assert!(!ced.contains_inav_word(&inav_word(word_type, 1, 0xaa)));
+        }
+        // Words that are not authenticated by OSNMA
+        assert!(!ced.contains_inav_word(&inav_word(0, 1, 0xaa)));
+        assert!(!timing.contains_inav_word(&inav_word(7, 1, 0xaa)));
+    }
+
+    #[test]
+    fn history() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst0 = Gst::new(1177, 175740);
+        let gst1 = gst0.add_seconds(30);
+        let gst2 = gst0.add_seconds(60);
+        let mut navmessage = CollectNavMessage::<FullStorage>::new();
+        for word_type in 1..=5 {
+            navmessage.feed(&inav_word(word_type, 1, 0xaa), svn, gst0, InavBand::E1B);
+        }
+        assert!(navmessage.get_ced_and_status(svn).is_none());
+        authenticate_all(&mut navmessage);
+        let first = navmessage.get_ced_and_status(svn).unwrap();
+        assert_eq!(first.gst(), gst0);
+        let first = OwnedNavMessageData::from(first);
+
+        // New data in the next subframe is not authenticated yet, so the
+        // previous data is returned.
+        navmessage.feed(&inav_word(1, 1, 0x55), svn, gst1, InavBand::E1B);
+        assert_eq!(
+            navmessage.get_ced_and_status(svn).unwrap().data(),
+            first.data()
+        );
+        assert_eq!(navmessage.ced_and_status_history(svn).count(), 1);
+
+        authenticate_all(&mut navmessage);
+        navmessage.feed(&inav_word(2, 1, 0x55), svn, gst2, InavBand::E1B);
+        let second = navmessage.get_ced_and_status(svn).unwrap();
+        assert_ne!(second.data(), first.data());
+        assert_eq!(second.gst(), gst1);
+        let history = navmessage
+            .ced_and_status_history(svn)
+            .map(|data| data.gst())
+            .collect::<Vec<_>>();
+        assert_eq!(history, [gst1, gst0]);
+
+        assert_eq!(
+            navmessage
+                .get_ced_and_status_at(svn, gst0.add_seconds(29))
+                .unwrap()
+                .data(),
+            first.data()
+        );
+        assert_eq!(
+            navmessage.get_ced_and_status_at(svn, gst2).unwrap().data(),
+            second.data()
+        );
+        assert!(navmessage
+            .get_ced_and_status_at(svn, gst0.add_seconds(-30))
+            .is_none());
+        assert!(navmessage.get_timing_parameters(svn).is_none());
+    }
+
+    #[test]
+    fn loss_of_lock() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst0 = Gst::new(1177, 175740);
+        let gst1 = gst0.add_seconds(30);
+        let mut navmessage = CollectNavMessage::<FullStorage>::new();
+        for word_type in 1..=5 {
+            navmessage.feed(&inav_word(word_type, 1, 0xaa), svn, gst0, InavBand::E1B);
+        }
+        navmessage.feed(&inav_word(1, 1, 0xaa), svn, gst1, InavBand::E1B);
+        let max_age = |n: &CollectNavMessage<FullStorage>, gst| {
+            n.find_ced_and_status_batches(svn, gst)
+                .next()
+                .map(|ced| ced.max_age())
+        };
+        assert_eq!(max_age(&navmessage, gst1), Some(1));
+
+        // The words received before the loss of lock are discarded in the
+        // current subframe, but kept in the previous subframe.
+        navmessage.notify_loss_of_lock(svn, InavBand::E1B);
+        assert_eq!(max_age(&navmessage, gst1), None);
+        navmessage.feed(&inav_word(2, 1, 0xaa), svn, gst1, InavBand::E1B);
+        assert_eq!(max_age(&navmessage, gst1), Some(u8::MAX));
+        assert_eq!(max_age(&navmessage, gst0), Some(0));
+
+        // A gap of more than one subframe is detected as a loss of lock
+        let gst2 = gst1.add_seconds(60);
+        for word_type in 1..=5 {
+            navmessage.feed(&inav_word(word_type, 1, 0xaa), svn, gst2, InavBand::E5B);
+        }
+        let gst3 = gst2.add_seconds(90);
+        navmessage.feed(&inav_word(1, 1, 0xaa), svn, gst3, InavBand::E5B);
+        assert_eq!(max_age(&navmessage, gst3), Some(u8::MAX));
+    }
+
+    #[test]
+    fn iodnav_batches() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst0 = Gst::new(1177, 175740);
+        let gst1 = gst0.add_seconds(30);
+        let mut navmessage = CollectNavMessage::<FullStorage>::new();
+        for word_type in 1..=5 {
+            navmessage.feed(&inav_word(word_type, 1, 0xaa), svn, gst0, InavBand::E1B);
+        }
+        authenticate_all(&mut navmessage);
+
+        // The first word with a new IODnav starts a new batch, and the
+        // complete batch with the old IODnav is kept if the previous-ced-batch
+        // feature is enabled.
+        navmessage.feed(&inav_word(1, 2, 0x55), svn, gst1, InavBand::E1B);
+        let batches = navmessage
+            .find_ced_and_status_batches(svn, gst1)
+            .map(|ced| (ced.iodnav(), ced.max_age()))
+            .collect::<Vec<_>>();
+        #[cfg(feature = "previous-ced-batch")]
+        assert_eq!(batches, [(Some(2), u8::MAX), (Some(1), 1)]);
+        #[cfg(not(feature = "previous-ced-batch"))]
+        assert_eq!(batches, [(Some(2), u8::MAX)]);
+        assert_eq!(
+            navmessage.get_ced_and_status(svn).unwrap().iodnav(),
+            Some(1)
+        );
+
+        for word_type in 2..=4 {
+            navmessage.feed(&inav_word(word_type, 2, 0x55), svn, gst1, InavBand::E1B);
+        }
+        let &current = navmessage
+            .find_ced_and_status_batches(svn, gst1)
+            .next()
+            .unwrap();
+        assert_eq!(current.max_age(), 1);
+        let mut updates = AuthbitsUpdates::new();
+        updates.add(
+            AuthbitsUpdate::CedAndStatus(current),
+            &BitSlice::from_slice(&[0; 5])[..usize::from(MIN_AUTHBITS)],
+            NmaStatus::Operational,
+        );
+        navmessage.apply_authbits_updates(&updates);
+        let history = navmessage
+            .ced_and_status_history(svn)
+            .map(|data| data.iodnav())
+            .collect::<Vec<_>>();
+        #[cfg(feature = "previous-ced-batch")]
+        assert_eq!(history, [Some(2), Some(1), Some(1)]);
+        #[cfg(not(feature = "previous-ced-batch"))]
+        assert_eq!(history, [Some(2), Some(1)]);
+        assert_eq!(
+            navmessage
+                .get_ced_and_status_at(svn, gst0)
+                .unwrap()
+                .iodnav(),
+            Some(1)
+        );
+        assert_eq!(navmessage.get_timing_parameters(svn), None);
+    }
+
+    #[test]
+    fn reed_solomon_reconstruction() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst0 = Gst::new(1177, 175740);
+        let words: [InavWord; 4] =
+            core::array::from_fn(|j| inav_word(j as u8 + 1, 7, 0x13 * (j as u8 + 1)));
+        let parity = parity_words(&words);
+        let word5 = inav_word(5, 0, 0x77);
+        let mut expected = CollectNavMessage::<FullStorage>::new();
+        for word in words.iter().chain([&word5]) {
+            expected.feed(word, svn, gst0, InavBand::E1B);
+        }
+        authenticate_all(&mut expected);
+        let expected = expected.get_ced_and_status(svn).unwrap();
+        assert!(!expected.has_reconstructed_words());
+
+        // Any 4 of the 8 words are enough to reconstruct the others
+        for received in [
+            [&words[0], &words[2], &parity[1], &parity[3]],
+            [&words[3], &parity[0], &parity[1], &parity[2]],
+            [&parity[0], &parity[1], &parity[2], &parity[3]],
+        ] {
+            let mut navmessage = CollectNavMessage::<FullStorage>::new();
+            for word in received.into_iter().chain([&word5]) {
+                navmessage.feed(word, svn, gst0, InavBand::E1B);
+            }
+            authenticate_all(&mut navmessage);
+            let data = navmessage.get_ced_and_status(svn).unwrap();
+            assert!(data.has_reconstructed_words());
+            assert_eq!(data.data(), expected.data());
+            assert_eq!(data.iodnav(), Some(7));
+
+            // Receiving the reconstructed words clears the mark
+            for word in words.iter() {
+                navmessage.feed(word, svn, gst0, InavBand::E1B);
+            }
+            let data = navmessage.get_ced_and_status(svn).unwrap();
+            assert!(!data.has_reconstructed_words());
+            assert_eq!(data.data(), expected.data());
+        }
+
+        // Parity words for a different IODnav are not used
+        let other_parity = parity_words(&core::array::from_fn(|j| {
+            inav_word(j as u8 + 1, 6, 0x13 * (j as u8 + 1))
+        }));
+        let mut navmessage = CollectNavMessage::<FullStorage>::new();
+        for word in [&words[0], &words[2], &other_parity[1], &other_parity[3]] {
+            navmessage.feed(word, svn, gst0, InavBand::E1B);
+        }
+        let ced = navmessage
+            .find_ced_and_status_batches(svn, gst0)
+            .next()
+            .unwrap();
+        assert_eq!(ced.max_age(), u8::MAX);
+        assert!(!ced.reconstructed());
+    }
+    // Checks the Reed-Solomon outer FEC against the words 1 to 4 and 17 to 20
+    // broadcast in the signal-in-space. The path of a CSV file of the OSNMA
+    // test vectors published by the European GNSS Service Centre is given in
+    // the OSNMA_TEST_VECTOR environment variable. These contain the E1-B INAV
+    // pages of each satellite.
+    #[test]
+    #[ignore = "requires the OSNMA_TEST_VECTOR environment variable"]
+    fn reed_solomon_test_vector() {
+        const PAGE_BYTES: usize = 30;
+        let path = std::env::var("OSNMA_TEST_VECTOR").unwrap();
+        let csv = std::fs::read_to_string(path).unwrap();
+        let gst = Gst::new(1177, 175740);
+        let mut checked = 0;
+        // Skip CSV header
+        for line in csv.lines().skip(1) {
+            let mut fields = line.split(',');
+            let svn = Svn::try_from(fields.next().unwrap().parse::<u8>().unwrap()).unwrap();
+            let hex = fields.nth(1).unwrap().trim();
+            let data = (0..hex.len())
+                .step_by(2)
+                .map(|j| u8::from_str_radix(&hex[j..j + 2], 16).unwrap())
+                .collect::<Vec<u8>>();
+            // Last words 1 to 4, 5 and 17 to 20 received. They are cleared
+            // when the IODnav changes.
+            let mut words: [Option<InavWord>; 4] = [None; 4];
+            let mut word5 = None;
+            let mut parity: [Option<InavWord>; 4] = [None; 4];
+            let mut checked_iodnav = None;
+            for page in data.chunks_exact(PAGE_BYTES) {
+                let page = BitSlice::from_slice(page);
+                // Skip alert pages
+                if page[1] {
+                    continue;
+                }
+                let mut word = [0; 16];
+                let bits = BitSlice::from_slice_mut(&mut word);
+                bits[..112].copy_from_bitslice(&page[2..114]);
+                bits[112..].copy_from_bitslice(&page[122..138]);
+                let word_type = bits[..6].load_be::<u8>();
+                let iodnav = bits[6..16].load_be::<u16>();
+                match word_type {
+                    1..=4 => {
+                        let current = words
+                            .iter()
+                            .flatten()
+                            .map(|w| BitSlice::from_slice(w)[6..16].load_be::<u16>())
+                            .next();
+                        if current.is_some_and(|current| current != iodnav) {
+                            words = [None; 4];
+                            parity = [None; 4];
+                        }
+                        words[usize::from(word_type - 1)] = Some(word);
+                    }
+                    5 => word5 = Some(word),
+                    17..=20 => parity[usize::from(word_type - 17)] = Some(word),
+                    _ => continue,
+                }
+                let (Some(words), Some(parity), Some(word5)) = (
+                    words.iter().copied().collect::<Option<Vec<_>>>(),
+                    parity.iter().copied().collect::<Option<Vec<_>>>(),
+                    word5,
+                ) else {
+                    continue;
+                };
+                let words: [InavWord; 4] = words.try_into().unwrap();
+                let iodnav = BitSlice::from_slice(&words[0])[6..16].load_be::<u16>();
+                if checked_iodnav == Some(iodnav)
+                    || parity
+                        .iter()
+                        .any(|w| BitSlice::from_slice(w)[14..16].load_be::<u16>() != iodnav & 0x3)
+                {
+                    continue;
+                }
+
+                // Encoding
+                assert_eq!(&parity_words(&words)[..], &parity[..]);
+
+                // Reconstruction of the words 1 to 4 from the words 17 to 20
+                let mut navmessage = CollectNavMessage::<FullStorage>::new();
+                for word in parity.iter().chain([&word5]) {
+                    navmessage.feed(word, svn, gst, InavBand::E1B);
+                }
+                authenticate_all(&mut navmessage);
+                let ced = navmessage.get_ced_and_status(svn).unwrap();
+                assert!(ced.has_reconstructed_words());
+                for word in words.iter() {
+                    assert!(ced.contains_inav_word(word));
+                }
+                checked_iodnav = Some(iodnav);
+                checked += 1;
+            }
+        }
+        assert!(checked > 0, "test vector does not contain complete batches");
+    }
+}
@@ -3,14 +3,22 @@ use crate::bitfields::{
     NmaStatus,
 };
 use crate::dsm::{CollectDsm, Dsm};
+use crate::event::{EventHook, OsnmaEvent};
+use crate::inav_clock::{GstBound, InavClock};
 use crate::mack::MackStorage;
+use crate::maclt::MacLTRegistry;
 use crate::merkle_tree::MerkleTree;
-use crate::navmessage::{CollectNavMessage, NavMessageData};
+use crate::navmessage::{AuthbitsUpdates, CollectNavMessage, NavMessageData};
+use crate::snapshot::{AuthenticatedSnapshot, Generations};
+use crate::state::{OsnmaState, StateTransition, TransitionReason};
 use crate::storage::StaticStorage;
 use crate::subframe::CollectSubframe;
-use crate::tesla::Key;
+use crate::tesla::{Checkpoints, Key, KeyCache, MacKey, ValidationError};
+use crate::trace::{MacTrace, MacTraceHook, MacseqTrace};
 use crate::types::{HkrootMessage, InavBand, InavWord, MackMessage, OsnmaDataMessage};
 use crate::validation::{NotValidated, Validated};
+#[cfg(feature = "parallel")]
+use crate::{trace::TagTrace, types::BitSlice};
 use crate::{Gst, MerkleTreeNode, PublicKey, Svn};
 
 use core::cmp::Ordering;
@@ -70,29 +78,69 @@ use core::cmp::Ordering;
 /// messages is defined by the [`StaticStorage`] type parameter `S`. See the
 /// [storage](crate::storage) module for a description of how the storage size
 /// is defined.
+///
+/// The const parameter `KEY_CHECKPOINTS` gives the number of TESLA key
+/// checkpoints that are stored (see [`Checkpoints`]). It defaults to 8, and it
+/// can be set to 0 to disable the checkpoints. The const parameter
+/// `MACLT_ENTRIES` gives the number of MAC Look-up Table entries that can be
+/// added at runtime to the registry (see [`MacLTRegistry`]). It defaults to 8,
+/// and it can be set to 0 to use only the entries built into the crate.
+///
+/// # Hooks
+///
+/// The type parameter `T` is the [`MacTraceHook`] that receives the traces of
+/// the MAC computations done to validate the tags and MACSEQ fields. It
+/// defaults to `()`, which ignores the traces. See [`Osnma::with_mac_trace`].
+///
+/// The type parameter `E` is the [`EventHook`] that receives the outcome of
+/// the verifications done by the black box and the state transitions. It
+/// defaults to `()`, which ignores the events. See [`Osnma::with_event_hook`].
+///
+/// # Self-timing
+///
+/// The GST of the INAV pages is normally supplied by the user. The INAV words
+/// 0, 5 and 6 also contain the GST, which `Osnma` can use to cross-check the
+/// GST supplied by the user (see [`Osnma::set_gst_check`]), or to compute the
+/// GST of the pages when the user only knows their reception order and a
+/// coarse bound on the GST (see [`Osnma::feed_inav_page`]). This uses an
+/// [`InavClock`].
 #[derive(Debug, Clone)]
-pub struct Osnma<S: StaticStorage> {
+pub struct Osnma<
+    S: StaticStorage,
+    const KEY_CHECKPOINTS: usize = 8,
+    const MACLT_ENTRIES: usize = 8,
+    T = (),
+    E = (),
+> {
     subframe: CollectSubframe,
-    data: OsnmaDsm<S>,
+    data: OsnmaDsm<S, KEY_CHECKPOINTS, MACLT_ENTRIES, T, E>,
+    generations: Generations,
+    clock: InavClock,
+    gst_check: bool,
 }
 
 // These structures exist only in order to avoid double mutable
 // borrows of Osnma because we take references from CollectSubframe
 // and CollectDsm
 #[derive(Debug, Clone)]
-struct OsnmaDsm<S: StaticStorage> {
+struct OsnmaDsm<S: StaticStorage, const C: usize, const M: usize, T, E> {
     dsm: CollectDsm,
-    data: OsnmaData<S>,
+    data: OsnmaData<S, C, M, T, E>,
 }
 
 #[derive(Debug, Clone)]
-struct OsnmaData<S: StaticStorage> {
+struct OsnmaData<S: StaticStorage, const C: usize, const M: usize, T, E> {
     navmessage: CollectNavMessage<S>,
     mack: MackStorage<S>,
     merkle_tree: Option<MerkleTree>,
     pubkey: PubkeyStore,
-    key: KeyStore,
+    key: KeyStore<C>,
+    maclt: MacLTRegistry<M>,
     only_slowmac: bool,
+    state: OsnmaState,
+    last_transition: Option<StateTransition>,
+    mac_trace: T,
+    event_hook: E,
 }
 
 #[derive(Debug, Clone)]
@@ -105,9 +153,24 @@ struct PubkeyStore {
 // force, and an additional KROOT for a chain that will become in force in the
 // future.
 #[derive(Debug, Clone)]
-struct KeyStore {
+struct KeyStore<const C: usize> {
     keys: [Option<Key<Validated>>; 2],
+    // KROOTs of the chains of the keys in each slot. These are used to detect
+    // a new chain that reuses the chain ID of a stored chain. They are None
+    // for a key loaded from a previous session.
+    kroots: [Option<Key<Validated>>; 2],
     chain_in_force: Option<ChainInForce>,
+    // MAC context of the last key used to validate tags, so that the MAC key
+    // setup is only done once per key.
+    mac_key: Option<MacKey>,
+    // Recent keys, used to re-generate the keys needed for Slow MAC.
+    cache: KeyCache,
+    // Intermediate keys computed during key validations, used to shorten
+    // later validations.
+    checkpoints: Checkpoints<C>,
+    // Number of one-way function computations done in the last key
+    // validation.
+    last_validation_hashes: Option<usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -118,12 +181,19 @@ struct ChainInForce {
     start_applicability: Option<Gst>,
 }
 
-impl<S: StaticStorage> Osnma<S> {
+impl<S: StaticStorage, const KEY_CHECKPOINTS: usize, const MACLT_ENTRIES: usize>
+    Osnma<S, KEY_CHECKPOINTS, MACLT_ENTRIES>
+{
     fn new(
         merkle_tree_root: Option<MerkleTreeNode>,
         pubkey: Option<PublicKey<Validated>>,
         only_slowmac: bool,
-    ) -> Osnma<S> {
+    ) -> Osnma<S, KEY_CHECKPOINTS, MACLT_ENTRIES> {
+        let state = if pubkey.is_some() {
+            OsnmaState::WarmStart
+        } else {
+            OsnmaState::ColdStart
+        };
         Osnma {
             subframe: CollectSubframe::new(),
             data: OsnmaDsm {
@@ -135,9 +205,17 @@ impl<S: StaticStorage> Osnma<S> {
                     pubkey: pubkey
                         .map_or_else(PubkeyStore::empty, PubkeyStore::from_current_pubkey),
                     key: KeyStore::empty(),
+                    maclt: MacLTRegistry::new(),
                     only_slowmac,
+                    state,
+                    last_transition: None,
+                    mac_trace: (),
+                    event_hook: (),
                 },
             },
+            generations: Generations::new(),
+            clock: InavClock::new(),
+            gst_check: false,
         }
     }
 
@@ -156,7 +234,7 @@ impl<S: StaticStorage> Osnma<S> {
         merkle_tree_root: MerkleTreeNode,
         pubkey: Option<PublicKey<Validated>>,
         only_slowmac: bool,
-    ) -> Osnma<S> {
+    ) -> Osnma<S, KEY_CHECKPOINTS, MACLT_ENTRIES> {
         Osnma::new(Some(merkle_tree_root), pubkey, only_slowmac)
     }
 
@@ -174,9 +252,95 @@ impl<S: StaticStorage> Osnma<S> {
     /// This should be used by receivers which have a larger time uncertainty.
     /// (See Annex 3 in the
     /// [OSNMA Receiver Guidelines](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_Receiver_Guidelines_for_Test_Phase_v1.0.pdf)).
-    pub fn from_pubkey(pubkey: PublicKey<Validated>, only_slowmac: bool) -> Osnma<S> {
+    pub fn from_pubkey(
+        pubkey: PublicKey<Validated>,
+        only_slowmac: bool,
+    ) -> Osnma<S, KEY_CHECKPOINTS, MACLT_ENTRIES> {
         Osnma::new(None, Some(pubkey), only_slowmac)
     }
+}
+
+impl<
+        S: StaticStorage,
+        const KEY_CHECKPOINTS: usize,
+        const MACLT_ENTRIES: usize,
+        T: MacTraceHook,
+        E: EventHook,
+    > Osnma<S, KEY_CHECKPOINTS, MACLT_ENTRIES, T, E>
+{
+    /// Sets the hook that receives the MAC computation traces.
+    ///
+    /// The hook is called with a [`MacTrace`] for each tag and MACSEQ field
+    /// that the black box tries to validate, regardless of whether the
+    /// validation is successful. The trace gives the exact MAC input, the key,
+    /// and the computed and received values, which is useful to compare the
+    /// processing with that of other OSNMA implementations. Dummy tags are not
+    /// traced. This replaces the hook that was set previously, which is `()`
+    /// by default.
+    ///
+    /// The hook is called from the thread that feeds the data to the black
+    /// box. With the `parallel` feature, the traces of the tags validated
+    /// concurrently are given to the hook afterwards, in the same order as
+    /// without the `parallel` feature.
+    pub fn with_mac_trace<H: MacTraceHook>(
+        self,
+        hook: H,
+    ) -> Osnma<S, KEY_CHECKPOINTS, MACLT_ENTRIES, H, E> {
+        Osnma {
+            subframe: self.subframe,
+            data: self.data.with_hooks(|_, event_hook| (hook, event_hook)),
+            generations: self.generations,
+            clock: self.clock,
+            gst_check: self.gst_check,
+        }
+    }
+
+    /// Returns a reference to the hook that receives the MAC computation
+    /// traces.
+    pub fn mac_trace_hook(&self) -> &T {
+        &self.data.data.mac_trace
+    }
+
+    /// Returns a mutable reference to the hook that receives the MAC
+    /// computation traces.
+    pub fn mac_trace_hook_mut(&mut self) -> &mut T {
+        &mut self.data.data.mac_trace
+    }
+
+    /// Sets the hook that receives the OSNMA processing events.
+    ///
+    /// The hook is called with an [`OsnmaEvent`] for each verification of a
+    /// DSM-PKR, DSM-KROOT, TESLA key or MACK message that the black box does,
+    /// for each OSNMA Alert Message, for each state transition, and for each
+    /// GST discrepancy. This replaces the hook that was set previously, which
+    /// is `()` by default.
+    ///
+    /// As for [`Osnma::with_mac_trace`], the hook is called from the thread
+    /// that feeds the data to the black box, also with the `parallel` feature.
+    pub fn with_event_hook<H: EventHook>(
+        self,
+        hook: H,
+    ) -> Osnma<S, KEY_CHECKPOINTS, MACLT_ENTRIES, T, H> {
+        Osnma {
+            subframe: self.subframe,
+            data: self.data.with_hooks(|mac_trace, _| (mac_trace, hook)),
+            generations: self.generations,
+            clock: self.clock,
+            gst_check: self.gst_check,
+        }
+    }
+
+    /// Returns a reference to the hook that receives the OSNMA processing
+    /// events.
+    pub fn event_hook(&self) -> &E {
+        &self.data.data.event_hook
+    }
+
+    /// Returns a mutable reference to the hook that receives the OSNMA
+    /// processing events.
+    pub fn event_hook_mut(&mut self) -> &mut E {
+        &mut self.data.data.event_hook
+    }
 
     /// Feed an INAV word into the OSNMA black box.
     ///
@@ -189,8 +353,114 @@ impl<S: StaticStorage> Osnma<S> {
     /// The `gst` parameter gives the GST at the start of the INAV page transmission.
     ///
     /// The `band` parameter indicates the band in which the INAV word was received.
+    ///
+    /// If the GST check is enabled (see [`Osnma::set_gst_check`]) and the
+    /// INAV word contains the GST, the GST in the word is compared with `gst`.
     pub fn feed_inav(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
+        if self.gst_check {
+            if let Some(discrepancy) = self.clock.check(word, svn, band, gst) {
+                self.data.data.emit(OsnmaEvent::GstDiscrepancy(discrepancy));
+            }
+        }
+        self.data.data.navmessage.feed(word, svn, gst, band);
+    }
+
+    /// Feed an INAV page whose GST is not known into the OSNMA black box.
+    ///
+    /// This is an alternative to [`Osnma::feed_inav`] and
+    /// [`Osnma::feed_osnma`] for users that do not know the GST of the INAV
+    /// pages they receive. The GST of the page is computed from the GST
+    /// contained in the INAV words 0, 5 and 6 and the reception order of the
+    /// pages (see [`InavClock::next_page`]). This function must be called for
+    /// every page received from the satellite with SVN `svn` in `band`, in
+    /// order, and [`Osnma::notify_loss_of_lock`] must be called if a page is
+    /// missed.
+    ///
+    /// The INAV word `word` and, if present, the OSNMA data message `osnma` of
+    /// the page are fed into the black box. The GST of the page is returned.
+    /// If the GST is not known yet, because no INAV words containing the GST
+    /// have been received from this satellite and band, or if the GST is not
+    /// within `bound`, the page is discarded and this returns `None`.
+    /// Discrepancies between the GST in the INAV words and the GST computed
+    /// from the reception order are reported as [`OsnmaEvent::GstDiscrepancy`]
+    /// events.
+    ///
+    /// # Security
+    ///
+    /// The GST computed by this function is derived from the WN and TOW fields
+    /// of the INAV words, which are not authenticated by OSNMA and can be
+    /// forged by an attacker. To prevent an attacker from choosing the time
+    /// reference of the TESLA key processing, the page is only processed if
+    /// its GST is within `bound`, which must be obtained from a time reference
+    /// that is independent of the signal-in-space, as required by the OSNMA
+    /// Receiver Guidelines. The maximum error of the bound should be chosen
+    /// according to the guidelines (see also the `only_slowmac` parameter of
+    /// [`Osnma::from_merkle_tree`]). See the [`inav_clock`](crate::inav_clock)
+    /// module.
+    pub fn feed_inav_page(
+        &mut self,
+        word: &InavWord,
+        osnma: Option<&OsnmaDataMessage>,
+        svn: Svn,
+        band: InavBand,
+        bound: GstBound,
+    ) -> Option<Gst> {
+        let (gst, discrepancy) = self.clock.next_page(word, svn, band);
+        if let Some(discrepancy) = discrepancy {
+            self.data.data.emit(OsnmaEvent::GstDiscrepancy(discrepancy));
+        }
+        let Some(gst) = gst else {
+            trace!("discarding INAV page with unknown GST"; svn = %svn, band = band);
+            return None;
+        };
+        if !bound.contains(gst) {
+            error!(
+                "discarding INAV page with GST outside of bound";
+                svn = %svn,
+                band = band,
+                gst = gst,
+                bound = bound.gst(),
+                max_error = bound.max_error(),
+            );
+            return None;
+        }
         self.data.data.navmessage.feed(word, svn, gst, band);
+        if let Some(osnma) = osnma {
+            self.feed_osnma(osnma, svn, gst);
+        }
+        Some(gst)
+    }
+
+    /// Enables or disables the GST check.
+    ///
+    /// When the GST check is enabled, the GST supplied to
+    /// [`Osnma::feed_inav`] is compared with the GST contained in the INAV
+    /// words 0, 5 and 6. Discrepancies are logged as warnings and reported as
+    /// [`OsnmaEvent::GstDiscrepancy`] events (see [`Osnma::with_event_hook`]).
+    /// The GST check is disabled by default.
+    pub fn set_gst_check(&mut self, enabled: bool) {
+        self.gst_check = enabled;
+    }
+
+    /// Notify a loss of lock of a satellite.
+    ///
+    /// This should be called when the receiver loses lock of the signal of
+    /// the satellite with SVN `svn` in `band`. The navigation data that is
+    /// being collected for this satellite is discarded, so that INAV words
+    /// received before and after the loss of lock are never combined. See
+    /// [`CollectNavMessage::notify_loss_of_lock`] for the details.
+    ///
+    /// Gaps of more than one subframe in the INAV words fed with
+    /// [`Osnma::feed_inav`] are detected automatically and treated in the
+    /// same way, but shorter losses of lock can only be handled by calling
+    /// this function.
+    ///
+    /// The GST of the pages of this satellite and band is no longer known by
+    /// [`Osnma::feed_inav_page`] until an INAV word containing the GST is
+    /// received.
+    pub fn notify_loss_of_lock(&mut self, svn: Svn, band: InavBand) {
+        self.clock.notify_loss_of_lock(svn, band);
+        self.data.data.navmessage.notify_loss_of_lock(svn, band);
     }
 
     /// Feed the OSNMA data message from an INAV page into the OSNMA black box.
@@ -217,10 +487,11 @@ impl<S: StaticStorage> Osnma<S> {
     /// This will try to retrieve the most recent authenticated CED and health
     /// status data (ADKD=0 and 12) for the satellite with SVN `svn` that is
     /// available in the OSNMA storage. If the storage does not contain any
-    /// authenticated CED and health status data for this SVN, this returns
-    /// `None`.
-    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData> {
-        self.data.data.navmessage.get_ced_and_status(svn)
+    /// authenticated CED and health status data for this SVN, or if the
+    /// navigation data cannot be used in the current [`OsnmaState`], this
+    /// returns `None`.
+    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        self.usable_navmessage()?.get_ced_and_status(svn)
     }
 
     /// Try to get authenticated timing parameters for a satellite.
@@ -228,13 +499,167 @@ impl<S: StaticStorage> Osnma<S> {
     /// This will try to retrieve the most recent authenticated timing
     /// parameters data (ADKD=4) for the satellite with SVN `svn` that is
     /// available in the OSNMA storage. If the storage does not contain any
-    /// authenticated timing parameters data for this SVN, this returns `None`.
-    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData> {
-        self.data.data.navmessage.get_timing_parameters(svn)
+    /// authenticated timing parameters data for this SVN, or if the navigation
+    /// data cannot be used in the current [`OsnmaState`], this returns `None`.
+    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        self.usable_navmessage()?.get_timing_parameters(svn)
+    }
+
+    /// Try to get the authenticated CED and health status data for a satellite
+    /// that was valid at a given GST.
+    ///
+    /// This returns the most recent authenticated CED and health status data
+    /// for the satellite with SVN `svn` among the data that had been received
+    /// by the subframe containing `gst`. See
+    /// [`CollectNavMessage::get_ced_and_status_at`].
+    pub fn get_ced_and_status_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        self.usable_navmessage()?.get_ced_and_status_at(svn, gst)
+    }
+
+    /// Try to get the authenticated timing parameters for a satellite that
+    /// were valid at a given GST.
+    ///
+    /// See [`CollectNavMessage::get_timing_parameters_at`].
+    pub fn get_timing_parameters_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        self.usable_navmessage()?.get_timing_parameters_at(svn, gst)
+    }
+
+    /// Returns an iterator over the history of authenticated CED and health
+    /// status data for a satellite.
+    ///
+    /// The data is given in order of decreasing GST. The length of the history
+    /// is determined by the storage size `S`. See
+    /// [`CollectNavMessage::ced_and_status_history`]. The history is empty if
+    /// the navigation data cannot be used in the current [`OsnmaState`].
+    pub fn ced_and_status_history(&self, svn: Svn) -> impl Iterator<Item = NavMessageData<'_>> {
+        self.usable_navmessage()
+            .into_iter()
+            .flat_map(move |n| n.ced_and_status_history(svn))
+    }
+
+    /// Returns an iterator over the history of authenticated timing parameters
+    /// for a satellite.
+    ///
+    /// See [`CollectNavMessage::timing_parameters_history`]. The history is
+    /// empty if the navigation data cannot be used in the current
+    /// [`OsnmaState`].
+    pub fn timing_parameters_history(&self, svn: Svn) -> impl Iterator<Item = NavMessageData<'_>> {
+        self.usable_navmessage()
+            .into_iter()
+            .flat_map(move |n| n.timing_parameters_history(svn))
+    }
+
+    /// Takes a snapshot of all the authenticated navigation data.
+    ///
+    /// The snapshot contains an owned copy of the data that
+    /// [`Osnma::get_ced_and_status`] and [`Osnma::get_timing_parameters`]
+    /// return for each SVN, together with a generation counter that can be
+    /// used to find which data has changed since a previous snapshot (see
+    /// [`AuthenticatedSnapshot`]). This function takes `&mut self` because
+    /// the generation counters are updated when the snapshot is taken. The
+    /// snapshot is empty if the navigation data cannot be used in the current
+    /// [`OsnmaState`].
+    pub fn snapshot(&mut self) -> AuthenticatedSnapshot {
+        if self.data.data.state.navigation_data_usable() {
+            self.generations.snapshot(&self.data.data.navmessage)
+        } else {
+            AuthenticatedSnapshot::empty()
+        }
+    }
+
+    /// Gives the computational cost of the last TESLA key validation.
+    ///
+    /// This returns the number of one-way function computations that were
+    /// done in the last attempt to validate a TESLA key, regardless of whether
+    /// the validation was successful. Thanks to the checkpoints of the TESLA
+    /// chain (see [`Checkpoints`]), this is usually small, except for the
+    /// first validation after a long outage. If no key validation has been
+    /// attempted yet, this returns `None`.
+    pub fn last_key_validation_hashes(&self) -> Option<usize> {
+        self.data.data.key.last_validation_hashes
+    }
+
+    /// Gives the MAC Look-up Table registry.
+    ///
+    /// The registry contains the MAC Look-up Tables that are used to validate
+    /// the MACK messages.
+    pub fn maclt_registry(&self) -> &MacLTRegistry<MACLT_ENTRIES> {
+        &self.data.data.maclt
+    }
+
+    /// Gives mutable access to the MAC Look-up Table registry.
+    ///
+    /// This can be used to add MAC Look-up Table entries that are not built
+    /// into the crate, so that MACK messages using a MACLT ID published in an
+    /// ICD update can be validated. See [`MacLTRegistry`].
+    pub fn maclt_registry_mut(&mut self) -> &mut MacLTRegistry<MACLT_ENTRIES> {
+        &mut self.data.data.maclt
+    }
+
+    /// Gives the current state of the OSNMA black box.
+    ///
+    /// The state follows the start-up and operation procedures described in
+    /// the
+    /// [OSNMA Receiver Guidelines v1.3](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_Receiver_Guidelines_v1.3.pdf).
+    /// Authenticated navigation data is not returned in the states in which it
+    /// cannot be used (see [`OsnmaState::navigation_data_usable`]).
+    pub fn state(&self) -> OsnmaState {
+        self.data.data.state
+    }
+
+    /// Gives the last state transition of the OSNMA black box.
+    ///
+    /// This returns `None` if the state has not changed since the construction
+    /// of the black box.
+    pub fn last_state_transition(&self) -> Option<StateTransition> {
+        self.data.data.last_transition
+    }
+
+    /// Loads a TESLA key from a previous session to do a hot start.
+    ///
+    /// The key `key` should have been obtained with [`Osnma::tesla_key`] in a
+    /// previous session, and stored in a trustworthy way. The TESLA keys in the
+    /// MACK messages are verified against this key, and the black box becomes
+    /// operational once one of them is verified. If the verification fails, the
+    /// black box falls back to a warm start (or a cold start if no public key
+    /// is available).
+    ///
+    /// The key is only loaded if the black box is in a start-up state (see
+    /// [`OsnmaState::is_start_up`]) other than a hot start. Otherwise, this
+    /// returns `false` and the key is ignored.
+    pub fn load_tesla_key(&mut self, key: Key<Validated>) -> bool {
+        self.data.data.load_tesla_key(key)
+    }
+
+    /// Gives the most recent verified TESLA key of the chain in force.
+    ///
+    /// This key can be stored and loaded with [`Osnma::load_tesla_key`] in a
+    /// later session to do a hot start.
+    pub fn tesla_key(&self) -> Option<&Key<Validated>> {
+        self.data.data.key.current_key()
+    }
+
+    fn usable_navmessage(&self) -> Option<&CollectNavMessage<S>> {
+        if self.data.data.state.navigation_data_usable() {
+            Some(&self.data.data.navmessage)
+        } else {
+            None
+        }
     }
 }
 
-impl<S: StaticStorage> OsnmaDsm<S> {
+impl<S: StaticStorage, const C: usize, const M: usize, T: MacTraceHook, E: EventHook>
+    OsnmaDsm<S, C, M, T, E>
+{
+    // Replaces the hooks by the ones returned by `hooks`, which is given the
+    // current hooks.
+    fn with_hooks<T2, E2>(self, hooks: impl FnOnce(T, E) -> (T2, E2)) -> OsnmaDsm<S, C, M, T2, E2> {
+        OsnmaDsm {
+            dsm: self.dsm,
+            data: self.data.with_hooks(hooks),
+        }
+    }
+
     fn process_subframe(&mut self, hkroot: &HkrootMessage, mack: &MackMessage, svn: Svn, gst: Gst) {
         let nma_header = NmaHeader::new(hkroot[0]);
         // Note that the NMA status obtained below is retrieved from a NMA
@@ -255,11 +680,33 @@ impl<S: StaticStorage> OsnmaDsm<S> {
     }
 }
 
-impl<S: StaticStorage> OsnmaData<S> {
+impl<S: StaticStorage, const C: usize, const M: usize, T: MacTraceHook, E: EventHook>
+    OsnmaData<S, C, M, T, E>
+{
+    fn with_hooks<T2, E2>(
+        self,
+        hooks: impl FnOnce(T, E) -> (T2, E2),
+    ) -> OsnmaData<S, C, M, T2, E2> {
+        let (mac_trace, event_hook) = hooks(self.mac_trace, self.event_hook);
+        OsnmaData {
+            navmessage: self.navmessage,
+            mack: self.mack,
+            merkle_tree: self.merkle_tree,
+            pubkey: self.pubkey,
+            key: self.key,
+            maclt: self.maclt,
+            only_slowmac: self.only_slowmac,
+            state: self.state,
+            last_transition: self.last_transition,
+            mac_trace,
+            event_hook,
+        }
+    }
+
     fn process_dsm(&mut self, dsm: Dsm, nma_header: NmaHeader<NotValidated>, gst: Gst) {
         match dsm.dsm_type() {
             DsmType::Kroot => self.process_dsm_kroot(DsmKroot(dsm.data()), nma_header, gst),
-            DsmType::Pkr => self.process_dsm_pkr(DsmPkr(dsm.data())),
+            DsmType::Pkr => self.process_dsm_pkr(DsmPkr(dsm.data()), gst),
         }
     }
 
@@ -275,27 +722,66 @@ impl<S: StaticStorage> OsnmaData<S> {
         };
         match Key::from_dsm_kroot(nma_header, dsm_kroot, pubkey) {
             Ok((key, nma_header)) => {
-                log::info!("verified KROOT with public key id {pkid}");
-                log::info!("current NMA header: {nma_header:?}");
+                info!("verified KROOT"; pkid = pkid);
+                info!(
+                    "current NMA header";
+                    nma_status = nma_header.nma_status(),
+                    chain_id = nma_header.chain_id(),
+                    cpks = nma_header.chain_and_pubkey_status(),
+                );
+                self.emit(OsnmaEvent::KrootVerified {
+                    pkid,
+                    chain_id: key.chain().chain_id(),
+                    nma_status: nma_header.nma_status(),
+                    kroot_gst: key.gst_subframe(),
+                    gst,
+                });
                 self.pubkey.make_pkid_current(pkid);
                 self.key.store_kroot(key, nma_header, gst);
-                self.process_nma_header(nma_header, pkid);
+                self.process_nma_header(nma_header, pkid, gst);
+            }
+            Err(e) => {
+                error!("could not verify KROOT"; error = e);
+                self.emit(OsnmaEvent::KrootVerificationFailed {
+                    pkid,
+                    error: e,
+                    gst,
+                });
+                if self.state == OsnmaState::WarmStart {
+                    self.transition(
+                        OsnmaState::StartFailed,
+                        TransitionReason::KrootVerificationFailed,
+                        gst,
+                    );
+                }
             }
-            Err(e) => log::error!("could not verify KROOT: {:?}", e),
         }
     }
 
-    fn process_nma_header(&mut self, nma_header: NmaHeader<Validated>, pkid: u8) {
+    fn process_nma_header(&mut self, nma_header: NmaHeader<Validated>, pkid: u8, gst: Gst) {
         match nma_header.nma_status() {
-            NmaStatus::Operational => {}
-            NmaStatus::Test => {
-                log::info!("NMA status is test");
+            NmaStatus::Operational | NmaStatus::Test => {
+                if nma_header.nma_status() == NmaStatus::Test {
+                    info!("NMA status is test");
+                }
+                self.transition(
+                    OsnmaState::Operational,
+                    TransitionReason::KrootVerified,
+                    gst,
+                );
             }
             NmaStatus::Reserved => {
-                log::error!("NMA status has a reserved value; assuming don't use");
+                error!("NMA status has a reserved value; assuming don't use");
+                self.transition(OsnmaState::DontUse, TransitionReason::NmaStatusDontUse, gst);
             }
             NmaStatus::DontUse => {
-                log::warn!("NMA status is don't use");
+                warn!("NMA status is don't use");
+                let reason = match nma_header.chain_and_pubkey_status() {
+                    ChainAndPubkeyStatus::ChainRevoked => TransitionReason::ChainRevoked,
+                    ChainAndPubkeyStatus::PublicKeyRevoked => TransitionReason::PublicKeyRevoked,
+                    _ => TransitionReason::NmaStatusDontUse,
+                };
+                self.transition(OsnmaState::DontUse, reason, gst);
                 match nma_header.chain_and_pubkey_status() {
                     ChainAndPubkeyStatus::ChainRevoked => {
                         // current chain is revoked
@@ -322,85 +808,167 @@ impl<S: StaticStorage> OsnmaData<S> {
         );
         match nma_header.chain_and_pubkey_status() {
             ChainAndPubkeyStatus::Reserved => {
-                log::error!("CPKS has a reserved value");
+                error!("CPKS has a reserved value");
             }
             ChainAndPubkeyStatus::Nominal => (),
             ChainAndPubkeyStatus::EndOfChain => {
-                log::info!("CPKS is end-of-chain");
+                info!("CPKS is end-of-chain");
             }
             ChainAndPubkeyStatus::ChainRevoked => {
-                log::warn!(
-                    "CPKS is chain revoked: {} chain has been revoked",
-                    if this_one { "current" } else { "previous" }
+                warn!(
+                    "CPKS is chain revoked";
+                    revoked = %if this_one { "current" } else { "previous" },
                 );
             }
             ChainAndPubkeyStatus::NewPublicKey => {
-                log::info!("CPKS is new public key");
+                info!("CPKS is new public key");
             }
             ChainAndPubkeyStatus::PublicKeyRevoked => {
-                log::warn!(
-                    "CPKS is public key revoked: {} key has been revoked",
-                    if this_one { "current" } else { "past" }
+                warn!(
+                    "CPKS is public key revoked";
+                    revoked = %if this_one { "current" } else { "past" },
                 );
             }
             ChainAndPubkeyStatus::NewMerkleTree => {
-                log::warn!("CPKS is new Merkle tree");
+                warn!("CPKS is new Merkle tree");
             }
             ChainAndPubkeyStatus::AlertMessage => {
-                log::warn!("CPKS is alert message");
-                self.alert_message_received();
+                warn!("CPKS is alert message");
+                self.alert_message_received(gst);
             }
         }
     }
 
-    fn alert_message_received(&mut self) {
-        log::warn!("received OSNMA Alert Message; deleting all cryptographic material");
+    fn alert_message_received(&mut self, gst: Gst) {
+        warn!("received OSNMA Alert Message; deleting all cryptographic material");
         self.merkle_tree = None;
         self.pubkey = PubkeyStore::empty();
         self.key = KeyStore::empty();
+        self.emit(OsnmaEvent::AlertMessage { gst });
+        self.transition(OsnmaState::Alert, TransitionReason::AlertMessage, gst);
+    }
+
+    fn transition(&mut self, to: OsnmaState, reason: TransitionReason, gst: Gst) {
+        let from = self.state;
+        if from == to {
+            return;
+        }
+        // The cryptographic material has been deleted, so there is no way out
+        // of the alert state.
+        if from == OsnmaState::Alert {
+            return;
+        }
+        info!("OSNMA state transition"; from = %from, to = %to, reason = %reason, gst = gst);
+        self.state = to;
+        let transition = StateTransition {
+            from,
+            to,
+            reason,
+            gst,
+        };
+        self.last_transition = Some(transition);
+        self.emit(OsnmaEvent::StateTransition(transition));
     }
 
-    fn process_dsm_pkr(&mut self, dsm_pkr: DsmPkr) {
+    fn emit(&mut self, event: OsnmaEvent) {
+        self.event_hook.event(&event);
+    }
+
+    fn load_tesla_key(&mut self, key: Key<Validated>) -> bool {
+        if !self.state.is_start_up() || self.state == OsnmaState::HotStart {
+            warn!("not loading TESLA key because OSNMA is not starting up"; state = %self.state);
+            return false;
+        }
+        let gst = key.gst_subframe();
+        self.key.load_key(key);
+        self.transition(OsnmaState::HotStart, TransitionReason::TeslaKeyLoaded, gst);
+        true
+    }
+
+    // State to fall back to when the start-up with the current cryptographic
+    // material fails. The TESLA key store must be emptied before falling back
+    // to this state.
+    fn fallback_start_state(&self) -> OsnmaState {
+        if self.pubkey.current.is_some() {
+            OsnmaState::WarmStart
+        } else if self.merkle_tree.is_some() {
+            OsnmaState::ColdStart
+        } else {
+            OsnmaState::StartFailed
+        }
+    }
+
+    fn process_dsm_pkr(&mut self, dsm_pkr: DsmPkr, gst: Gst) {
         match dsm_pkr.new_public_key_type() {
-            NewPublicKeyType::EcdsaKey(_) => self.process_dsm_pkr_npk(dsm_pkr),
-            NewPublicKeyType::OsnmaAlertMessage => self.process_dsm_pkr_alert_message(dsm_pkr),
+            NewPublicKeyType::EcdsaKey(_) => self.process_dsm_pkr_npk(dsm_pkr, gst),
+            NewPublicKeyType::OsnmaAlertMessage => self.process_dsm_pkr_alert_message(dsm_pkr, gst),
             NewPublicKeyType::Reserved => {
-                log::error!("reserved NPKT in DSM-PKR: {:?}", dsm_pkr);
+                error!("reserved NPKT in DSM-PKR"; message_id = dsm_pkr.message_id());
             }
         }
     }
 
-    fn process_dsm_pkr_npk(&mut self, dsm_pkr: DsmPkr) {
+    fn process_dsm_pkr_npk(&mut self, dsm_pkr: DsmPkr, gst: Gst) {
         let Some(merkle_tree) = &self.merkle_tree else {
-            log::error!("could not verify public key because Merkle tree is not loaded");
+            error!("could not verify public key because Merkle tree is not loaded");
             return;
         };
         match merkle_tree.validate_pkr(dsm_pkr) {
             Ok(pubkey) => {
-                log::info!("verified public key in DSM-PKR: {dsm_pkr:?}");
+                info!(
+                    "verified public key in DSM-PKR";
+                    npkid = dsm_pkr.new_public_key_id(),
+                    npkt = dsm_pkr.new_public_key_type(),
+                );
+                self.emit(OsnmaEvent::PublicKeyVerified {
+                    npkid: dsm_pkr.new_public_key_id(),
+                    gst,
+                });
                 self.pubkey.store_new_pubkey(pubkey);
+                if matches!(self.state, OsnmaState::ColdStart | OsnmaState::StartFailed)
+                    && self.pubkey.current.is_some()
+                {
+                    self.transition(
+                        OsnmaState::WarmStart,
+                        TransitionReason::PublicKeyVerified,
+                        gst,
+                    );
+                }
+            }
+            Err(e) => {
+                error!("could not verify public key"; error = e);
+                self.emit(OsnmaEvent::PublicKeyVerificationFailed { error: e, gst });
+                if self.state == OsnmaState::ColdStart {
+                    self.transition(
+                        OsnmaState::StartFailed,
+                        TransitionReason::PublicKeyVerificationFailed,
+                        gst,
+                    );
+                }
             }
-            Err(e) => log::error!("could not verify public key: {e:?}"),
         }
     }
 
-    fn process_dsm_pkr_alert_message(&mut self, dsm_pkr: DsmPkr) {
+    fn process_dsm_pkr_alert_message(&mut self, dsm_pkr: DsmPkr, gst: Gst) {
         let Some(merkle_tree) = &self.merkle_tree else {
-            log::error!("could not verify OSNMA Alert Message because Merkle tree is not loaded");
+            error!("could not verify OSNMA Alert Message because Merkle tree is not loaded");
             return;
         };
         match merkle_tree.validate_alert_message(dsm_pkr) {
             Ok(()) => {
-                log::warn!("received valid OSNMA Alert Message in DSM-PKR: {dsm_pkr:?}");
-                self.alert_message_received();
+                warn!(
+                    "received valid OSNMA Alert Message in DSM-PKR";
+                    message_id = dsm_pkr.message_id(),
+                );
+                self.alert_message_received(gst);
             }
-            Err(e) => log::error!("could not verify OSNMA Alert Message: {e:?}"),
+            Err(e) => error!("could not verify OSNMA Alert Message"; error = e),
         }
     }
 
     fn validate_key(&mut self, mack: &MackMessage, gst: Gst) {
-        let Some(current_key) = self.key.current_key() else {
-            log::info!("no valid TESLA key for the chain in force. unable to validate MACK key");
+        let Some(current_key) = self.key.current_key().copied() else {
+            info!("no valid TESLA key for the chain in force. unable to validate MACK key");
             return;
         };
         let mack = Mack::new(
@@ -414,31 +982,63 @@ impl<S: StaticStorage> OsnmaData<S> {
                 // we already have this key; nothing to do
             }
             Ordering::Greater => {
-                log::warn!(
-                    "got a key in MACK which is older than our current valid key\
-                            MACK key = {:?}, current valid key = {:?}",
-                    new_key,
-                    current_key
+                warn!(
+                    "got a key in MACK which is older than our current valid key";
+                    mack_key_gst = new_key.gst_subframe(),
+                    current_key_gst = current_key.gst_subframe(),
                 );
             }
             Ordering::Less => {
                 // attempt to validate the new key
-                match current_key.validate_key(&new_key) {
+                match self.key.validate_key(&current_key, &new_key) {
                     Ok(new_valid_key) => {
-                        log::info!(
-                            "new TESLA key {:?} successfully validated by {:?}",
-                            new_valid_key,
-                            current_key
+                        info!(
+                            "new TESLA key successfully validated";
+                            gst = new_valid_key.gst_subframe(),
+                            chain_id = new_valid_key.chain().chain_id(),
+                            validated_by = current_key.gst_subframe(),
                         );
+                        self.emit(OsnmaEvent::TeslaKeyVerified {
+                            key_gst: new_valid_key.gst_subframe(),
+                            chain_id: new_valid_key.chain().chain_id(),
+                            validated_by: current_key.gst_subframe(),
+                            gst,
+                        });
                         self.key.store_key(new_valid_key);
+                        if self.state.is_start_up() {
+                            self.transition(
+                                OsnmaState::Operational,
+                                TransitionReason::TeslaKeyVerified,
+                                gst,
+                            );
+                        }
                         self.process_tags(&new_valid_key);
                     }
-                    Err(e) => log::error!(
-                        "could not validate TESLA key {:?} using {:?}: {:?}",
-                        new_key,
-                        current_key,
-                        e
-                    ),
+                    Err(e) => {
+                        error!(
+                            "could not validate TESLA key";
+                            gst = new_key.gst_subframe(),
+                            using = current_key.gst_subframe(),
+                            error = e,
+                        );
+                        self.emit(OsnmaEvent::TeslaKeyVerificationFailed {
+                            key_gst: new_key.gst_subframe(),
+                            validated_by: current_key.gst_subframe(),
+                            error: e,
+                            gst,
+                        });
+                        if self.state == OsnmaState::HotStart {
+                            // The loaded key cannot be trusted, so it is
+                            // discarded and the start-up continues from a
+                            // DSM-KROOT.
+                            self.key = KeyStore::empty();
+                            self.transition(
+                                self.fallback_start_state(),
+                                TransitionReason::TeslaKeyVerificationFailed,
+                                gst,
+                            );
+                        }
+                    }
                 }
             }
         }
@@ -446,75 +1046,191 @@ impl<S: StaticStorage> OsnmaData<S> {
 
     fn process_tags(&mut self, current_key: &Key<Validated>) {
         let gst_mack = current_key.gst_subframe().add_seconds(-30);
-        let gst_slowmac = gst_mack.add_seconds(-300);
         // Try to re-generate the key that was used for the MACSEQ of the
         // Slow MAC MACK. This key might be from a previous chain.
         let gst_k_slowmac = current_key.gst_subframe().add_seconds(-300);
-        let slowmac_chain_key = self.key.key_past_chain(gst_k_slowmac);
-        let slowmac_key = slowmac_chain_key.and_then(|k| {
-            let derivations = k.gst_subframe().subframes_difference(gst_k_slowmac);
-            if derivations >= 0 {
-                Some(k.derive(derivations.try_into().unwrap()))
-            } else {
-                None
-            }
-        });
+        let slowmac_key = self.key.slowmac_key(gst_k_slowmac).map(MacKey::new);
+        let current_key = &self.key.mac_key(current_key);
+
+        #[cfg(not(feature = "parallel"))]
+        self.process_tags_sequential(current_key, slowmac_key.as_ref(), gst_mack);
+        #[cfg(feature = "parallel")]
+        self.process_tags_parallel(current_key, slowmac_key.as_ref(), gst_mack);
+    }
+
+    // Validates the MACK messages and tags of each satellite in SVN order,
+    // adding the authentication bits after each satellite.
+    #[cfg_attr(all(feature = "parallel", not(test)), allow(dead_code))]
+    fn process_tags_sequential(
+        &mut self,
+        current_key: &MacKey,
+        slowmac_key: Option<&MacKey>,
+        gst_mack: Gst,
+    ) {
         for svn in Svn::iter() {
-            if !self.only_slowmac {
-                if let Some((mack, nma_status)) = self.mack.get(svn, gst_mack) {
-                    let mack = Mack::new(
+            let (validator, mut hooks) = self.tag_validator_and_hooks();
+            let updates =
+                validator.validate_tags(svn, current_key, slowmac_key, gst_mack, &mut hooks);
+            self.apply_authbits_updates(updates);
+        }
+    }
+
+    // The MACKs of each satellite are validated concurrently. The events and
+    // MAC traces of each satellite are recorded, and then they are given to
+    // the hooks and the authentication bits are added in SVN order, as in
+    // process_tags_sequential, so that the results are exactly the same and
+    // the hooks are only called from this thread.
+    #[cfg(feature = "parallel")]
+    fn process_tags_parallel(
+        &mut self,
+        current_key: &MacKey,
+        slowmac_key: Option<&MacKey>,
+        gst_mack: Gst,
+    ) {
+        use rayon::prelude::*;
+        let record_events = self.event_hook.is_enabled();
+        let record_mac_traces = self.mac_trace.is_enabled();
+        // The hooks are not used during the validation. They are borrowed
+        // again to replay the recorded events and traces once the validator
+        // is no longer used.
+        let (validator, _) = self.tag_validator_and_hooks();
+        let svns: Vec<Svn> = Svn::iter().collect();
+        let results: Vec<_> = svns
+            .into_par_iter()
+            .map(|svn| {
+                let mut hooks = RecordHooks::new(record_events, record_mac_traces);
+                let updates =
+                    validator.validate_tags(svn, current_key, slowmac_key, gst_mack, &mut hooks);
+                (updates, hooks)
+            })
+            .collect();
+        for (updates, hooks) in results {
+            hooks.replay(&mut self.event_hook, &mut self.mac_trace);
+            self.apply_authbits_updates(updates);
+        }
+    }
+
+    // Borrows the data used to validate the tags separately from the hooks,
+    // so that the hooks can be called during the validation. This is the only
+    // place where the TagValidator is built, so that the sequential and
+    // parallel validations use the same data.
+    fn tag_validator_and_hooks(&mut self) -> (TagValidator<'_, S, M>, CallHooks<'_, T, E>) {
+        (
+            TagValidator {
+                navmessage: &self.navmessage,
+                mack: &self.mack,
+                maclt: &self.maclt,
+                only_slowmac: self.only_slowmac,
+            },
+            CallHooks {
+                event_hook: &mut self.event_hook,
+                mac_trace: &mut self.mac_trace,
+            },
+        )
+    }
+
+    fn apply_authbits_updates(&mut self, updates: [Option<AuthbitsUpdates>; 2]) {
+        for u in updates.iter().flatten() {
+            self.navmessage.apply_authbits_updates(u);
+        }
+    }
+}
+
+// Data used to validate the MACK messages and tags.
+struct TagValidator<'a, S: StaticStorage, const M: usize> {
+    navmessage: &'a CollectNavMessage<S>,
+    mack: &'a MackStorage<S>,
+    maclt: &'a MacLTRegistry<M>,
+    only_slowmac: bool,
+}
+
+impl<S: StaticStorage, const M: usize> TagValidator<'_, S, M> {
+    // Validates the MACK message transmitted by a satellite in the subframe
+    // `gst_mack`, and its tags, as well as the Slow MAC tags of the MACK
+    // message transmitted 10 subframes before, if `slowmac_key` is
+    // available. This returns the authentication bits updates for the tags
+    // and for the Slow MAC tags. The events and MAC traces are given to
+    // `hooks`.
+    fn validate_tags(
+        &self,
+        svn: Svn,
+        current_key: &MacKey,
+        slowmac_key: Option<&MacKey>,
+        gst_mack: Gst,
+        hooks: &mut impl TagHooks,
+    ) -> [Option<AuthbitsUpdates>; 2] {
+        let gst_slowmac = gst_mack.add_seconds(-300);
+        let mut updates = [None, None];
+        if !self.only_slowmac {
+            if let Some((mack, nma_status)) = self.mack.get(svn, gst_mack) {
+                let mack = Mack::new(
+                    mack,
+                    current_key.key().chain().key_size_bits(),
+                    current_key.key().chain().tag_size_bits(),
+                );
+                if let Some(mack) = self.validate_mack(mack, current_key, svn, gst_mack, hooks) {
+                    updates[0] = Some(self.navmessage.validate_mack_tags(
                         mack,
-                        current_key.chain().key_size_bits(),
-                        current_key.chain().tag_size_bits(),
-                    );
-                    if let Some(mack) = Self::validate_mack(mack, current_key, svn, gst_mack) {
-                        self.navmessage
-                            .process_mack(mack, current_key, svn, gst_mack, nma_status);
-                    };
-                }
+                        current_key,
+                        svn,
+                        gst_mack,
+                        nma_status,
+                        &mut |t| hooks.mac_trace(t),
+                    ));
+                };
             }
+        }
 
-            // Try to validate Slow MAC
-            // This needs fetching a tag which is 300 seconds older than for
-            // the other ADKDs
-            if let Some(slowmac_key) = &slowmac_key {
-                if let Some((mack, nma_status)) = self.mack.get(svn, gst_slowmac) {
-                    let mack = Mack::new(
+        // Try to validate Slow MAC
+        // This needs fetching a tag which is 300 seconds older than for
+        // the other ADKDs
+        if let Some(slowmac_key) = slowmac_key {
+            if let Some((mack, nma_status)) = self.mack.get(svn, gst_slowmac) {
+                let mack = Mack::new(
+                    mack,
+                    current_key.key().chain().key_size_bits(),
+                    current_key.key().chain().tag_size_bits(),
+                );
+                // Note that slowmac_key is used for validation of the MACK, while
+                // current_key is used for validation of the Slow MAC tags it contains.
+                if let Some(mack) = self.validate_mack(mack, slowmac_key, svn, gst_slowmac, hooks) {
+                    updates[1] = Some(self.navmessage.validate_mack_slowmac_tags(
                         mack,
-                        current_key.chain().key_size_bits(),
-                        current_key.chain().tag_size_bits(),
-                    );
-                    // Note that slowmac_key is used for validation of the MACK, while
-                    // current_key is used for validation of the Slow MAC tags it contains.
-                    if let Some(mack) = Self::validate_mack(mack, slowmac_key, svn, gst_slowmac) {
-                        self.navmessage.process_mack_slowmac(
-                            mack,
-                            current_key,
-                            svn,
-                            gst_slowmac,
-                            nma_status,
-                        );
-                    }
+                        current_key,
+                        svn,
+                        gst_slowmac,
+                        nma_status,
+                        &mut |t| hooks.mac_trace(t),
+                    ));
                 }
             }
         }
+        updates
     }
 
     fn validate_mack<'a>(
+        &self,
         mack: Mack<'a, NotValidated>,
-        key: &Key<Validated>,
+        key: &MacKey,
         prna: Svn,
         gst_mack: Gst,
+        hooks: &mut impl TagHooks,
     ) -> Option<Mack<'a, Validated>> {
-        match mack.validate(key, prna, gst_mack) {
+        let trace = |t: &MacseqTrace<'_>| hooks.mac_trace(&MacTrace::Macseq(*t));
+        match mack.validate_with_trace(key, prna, gst_mack, self.maclt, trace) {
             Err(e) => {
-                log::error!(
-                    "error validating {} {:?} MACK {:?}: {:?}",
-                    prna,
-                    gst_mack,
-                    mack,
-                    e
+                error!(
+                    "error validating MACK";
+                    svn = %prna,
+                    gst = gst_mack,
+                    macseq = mack.macseq(),
+                    error = e,
                 );
+                hooks.event(OsnmaEvent::MackVerificationFailed {
+                    svn: prna,
+                    error: e,
+                    gst: gst_mack,
+                });
                 None
             }
             Ok(m) => Some(m),
@@ -522,6 +1238,172 @@ impl<S: StaticStorage> OsnmaData<S> {
     }
 }
 
+// Receiver of the events and MAC traces produced by the validation of the
+// MACK messages and tags of a satellite.
+trait TagHooks {
+    fn event(&mut self, event: OsnmaEvent);
+    fn mac_trace(&mut self, trace: &MacTrace<'_>);
+}
+
+// Gives the events and MAC traces to the hooks immediately.
+struct CallHooks<'a, T, E> {
+    event_hook: &'a mut E,
+    mac_trace: &'a mut T,
+}
+
+impl<T: MacTraceHook, E: EventHook> TagHooks for CallHooks<'_, T, E> {
+    fn event(&mut self, event: OsnmaEvent) {
+        self.event_hook.event(&event);
+    }
+
+    fn mac_trace(&mut self, trace: &MacTrace<'_>) {
+        self.mac_trace.mac_trace(trace);
+    }
+}
+
+// Records the events and MAC traces, so that they can be given to the hooks
+// later. Only the events and traces for which a hook is set are recorded.
+#[cfg(feature = "parallel")]
+#[derive(Debug)]
+struct RecordHooks {
+    events: bool,
+    mac_traces: bool,
+    records: Vec<HookRecord>,
+}
+
+#[cfg(feature = "parallel")]
+#[derive(Debug)]
+enum HookRecord {
+    Event(OsnmaEvent),
+    // The slices in the traces are empty, and their contents are stored
+    // separately.
+    Tag {
+        trace: TagTrace<'static>,
+        navdata: OwnedBits,
+        mac_input: Vec<u8>,
+        key: Vec<u8>,
+        computed_tag: OwnedBits,
+        received_tag: OwnedBits,
+    },
+    Macseq {
+        trace: MacseqTrace<'static>,
+        mac_input: Vec<u8>,
+        key: Vec<u8>,
+    },
+}
+
+// Owned copy of a BitSlice.
+#[cfg(feature = "parallel")]
+#[derive(Debug)]
+struct OwnedBits {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+#[cfg(feature = "parallel")]
+impl OwnedBits {
+    fn new(bits: &BitSlice) -> OwnedBits {
+        let mut bytes = vec![0; (bits.len() + 7) / 8];
+        BitSlice::from_slice_mut(&mut bytes)[..bits.len()].copy_from_bitslice(bits);
+        OwnedBits {
+            bytes,
+            len: bits.len(),
+        }
+    }
+
+    fn bits(&self) -> &BitSlice {
+        &BitSlice::from_slice(&self.bytes)[..self.len]
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl RecordHooks {
+    fn new(events: bool, mac_traces: bool) -> RecordHooks {
+        RecordHooks {
+            events,
+            mac_traces,
+            records: Vec::new(),
+        }
+    }
+
+    fn replay(&self, event_hook: &mut impl EventHook, mac_trace: &mut impl MacTraceHook) {
+        for record in &self.records {
+            match record {
+                HookRecord::Event(event) => event_hook.event(event),
+                HookRecord::Tag {
+                    trace,
+                    navdata,
+                    mac_input,
+                    key,
+                    computed_tag,
+                    received_tag,
+                } => {
+                    mac_trace.mac_trace(&MacTrace::Tag(TagTrace {
+                        navdata: navdata.bits(),
+                        mac_input,
+                        key,
+                        computed_tag: computed_tag.bits(),
+                        received_tag: received_tag.bits(),
+                        ..*trace
+                    }));
+                }
+                HookRecord::Macseq {
+                    trace,
+                    mac_input,
+                    key,
+                } => {
+                    mac_trace.mac_trace(&MacTrace::Macseq(MacseqTrace {
+                        mac_input,
+                        key,
+                        ..*trace
+                    }));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl TagHooks for RecordHooks {
+    fn event(&mut self, event: OsnmaEvent) {
+        if self.events {
+            self.records.push(HookRecord::Event(event));
+        }
+    }
+
+    fn mac_trace(&mut self, trace: &MacTrace<'_>) {
+        if !self.mac_traces {
+            return;
+        }
+        self.records.push(match trace {
+            MacTrace::Tag(t) => HookRecord::Tag {
+                trace: TagTrace {
+                    navdata: BitSlice::empty(),
+                    mac_input: &[],
+                    key: &[],
+                    computed_tag: BitSlice::empty(),
+                    received_tag: BitSlice::empty(),
+                    ..*t
+                },
+                navdata: OwnedBits::new(t.navdata),
+                mac_input: t.mac_input.to_vec(),
+                key: t.key.to_vec(),
+                computed_tag: OwnedBits::new(t.computed_tag),
+                received_tag: OwnedBits::new(t.received_tag),
+            },
+            MacTrace::Macseq(t) => HookRecord::Macseq {
+                trace: MacseqTrace {
+                    mac_input: &[],
+                    key: &[],
+                    ..*t
+                },
+                mac_input: t.mac_input.to_vec(),
+                key: t.key.to_vec(),
+            },
+        });
+    }
+}
+
 impl PubkeyStore {
     fn empty() -> PubkeyStore {
         PubkeyStore {
@@ -547,17 +1429,15 @@ impl PubkeyStore {
         match (&self.current, &self.next) {
             (Some(k), _) if k.public_key_id() == pkid => Some(k),
             (_, Some(k)) if k.public_key_id() == pkid => {
-                log::info!("selecting next public key to authenticate KROOT");
+                info!("selecting next public key to authenticate KROOT");
                 Some(k)
             }
             (Some(_), _) => {
-                log::error!(
-                    "could not verify KROOT because public key with id {pkid} is not available"
-                );
+                error!("could not verify KROOT because public key is not available"; pkid = pkid);
                 None
             }
             (None, _) => {
-                log::error!("could not verify KROOT because no public key is available");
+                error!("could not verify KROOT because no public key is available");
                 None
             }
         }
@@ -587,7 +1467,11 @@ impl PubkeyStore {
         if let Some(current) = &self.current {
             let curr_pkid = current.public_key_id();
             if new_pkid < curr_pkid {
-                log::error!("received public key with id {new_pkid} smaller than current id {curr_pkid}; discarding");
+                error!(
+                    "received public key with id smaller than current id; discarding";
+                    pkid = new_pkid,
+                    current_pkid = curr_pkid,
+                );
                 return;
             }
             if new_pkid == curr_pkid {
@@ -597,14 +1481,16 @@ impl PubkeyStore {
             if let Some(next) = &self.next {
                 let next_pkid = next.public_key_id();
                 match new_pkid.cmp(&next_pkid) {
-                    Ordering::Less => log::error!(
-                        "received public key with id {new_pkid} smaller than \
-                         the next id {next_pkid}; discarding"
+                    Ordering::Less => error!(
+                        "received public key with id smaller than the next id; discarding";
+                        pkid = new_pkid,
+                        next_pkid = next_pkid,
                     ),
                     Ordering::Greater => {
-                        log::warn!(
-                            "received public key with id {new_pkid} greater than \
-                             the next id {next_pkid}; overwriting"
+                        warn!(
+                            "received public key with id greater than the next id; overwriting";
+                            pkid = new_pkid,
+                            next_pkid = next_pkid,
                         );
                         self.next = Some(pubkey);
                     }
@@ -623,64 +1509,98 @@ impl PubkeyStore {
 
     fn revoke(&mut self, new_pkid: u8) {
         let matches = |k: &PublicKey<Validated>| k.public_key_id() < new_pkid;
-        if self.current.as_ref().map_or(false, matches) {
-            log::warn!(
-                "revoking pubkeys earlier than pkid {new_pkid}: \
-                        revoking current pubkey {:?}",
-                self.current
+        if self.current.as_ref().is_some_and(matches) {
+            warn!(
+                "revoking current pubkey";
+                pkid = self.current.as_ref().map(|k| k.public_key_id()),
+                earlier_than = new_pkid,
             );
             self.current = None;
         }
-        if self.next.as_ref().map_or(false, matches) {
-            log::warn!(
-                "revoking pubkeys earlier than pkid {new_pkid}: \
-                        next pubkey {:?}",
-                self.next
+        if self.next.as_ref().is_some_and(matches) {
+            warn!(
+                "revoking next pubkey";
+                pkid = self.next.as_ref().map(|k| k.public_key_id()),
+                earlier_than = new_pkid,
             );
             self.current = None;
         }
     }
 }
 
-impl KeyStore {
-    fn empty() -> KeyStore {
+impl<const C: usize> KeyStore<C> {
+    fn empty() -> KeyStore<C> {
         KeyStore {
-            keys: [None; 2],
+            keys: [None, None],
+            kroots: [None, None],
             chain_in_force: None,
+            mac_key: None,
+            cache: KeyCache::default(),
+            checkpoints: Checkpoints::new(),
+            last_validation_hashes: None,
         }
     }
 
     fn store_kroot(&mut self, key: Key<Validated>, nma_header: NmaHeader<Validated>, gst: Gst) {
         let kid = key.chain().chain_id();
         let cid = nma_header.chain_id();
-        match (&self.keys[0], &self.keys[1]) {
-            (Some(k), _) if k.chain().chain_id() == kid => {
-                // do nothing; we already have a key for the same chain
-            }
-            (_, Some(k)) if k.chain().chain_id() == kid => {
-                // do nothing; we already have a key for the same chain
-            }
+        let same_chain_slot = self
+            .keys
+            .iter()
+            .position(|k| k.is_some_and(|k| k.chain().chain_id() == kid));
+        let slot = match (same_chain_slot, &self.keys[0], &self.keys[1]) {
+            (Some(slot), _, _) => match self.kroots[slot] {
+                Some(kroot) if kroot != key => {
+                    // a new chain with the same chain ID replaces the stored
+                    // chain
+                    info!("overwriting slot with KROOT of renewed chain"; slot = slot, gst = key.gst_subframe(), chain_id = kid);
+                    Some(slot)
+                }
+                Some(_) => {
+                    // do nothing; we already have a key for the same chain
+                    None
+                }
+                None => {
+                    // the key was loaded from a previous session; assume that
+                    // it belongs to the chain of this KROOT
+                    self.kroots[slot] = Some(key);
+                    None
+                }
+            },
             // there is one slot vacant to place the key
-            (None, _) => {
-                log::info!("storing KROOT {key:?} in slot 0 (vacant)");
-                self.keys[0] = Some(key);
+            (None, None, _) => {
+                info!("storing KROOT in slot 0 (vacant)"; gst = key.gst_subframe(), chain_id = kid);
+                Some(0)
             }
-            (_, None) => {
-                log::info!("storing KROOT {key:?} in slot 1 (vacant)");
-                self.keys[1] = Some(key);
+            (None, _, None) => {
+                info!("storing KROOT in slot 1 (vacant)"; gst = key.gst_subframe(), chain_id = kid);
+                Some(1)
             }
-            (Some(k0), Some(_)) => {
+            (None, Some(k0), Some(_)) => {
                 // both slots are occupied; do not overwrite the slot for the
                 // current chain
                 if k0.chain().chain_id() == cid {
-                    log::info!("overwriting slot 1 with KROOT {key:?}");
-                    self.keys[1] = Some(key);
+                    info!("overwriting slot 1 with KROOT"; gst = key.gst_subframe(), chain_id = kid);
+                    Some(1)
                 } else {
-                    log::info!("overwriting slot 0 with KROOT {key:?}");
-                    self.keys[0] = Some(key);
+                    info!("overwriting slot 0 with KROOT"; gst = key.gst_subframe(), chain_id = kid);
+                    Some(0)
                 }
             }
+        };
+        if let Some(slot) = slot {
+            // The cached keys and checkpoints are looked up by the chain
+            // parameters, which a new chain can share with the chain that it
+            // replaces, so the ones of the replaced chain and of any earlier
+            // chain with the same ID are removed.
+            if let Some(k) = self.keys[slot] {
+                self.forget_chain(k.chain().chain_id());
+            }
+            self.forget_chain(kid);
+            self.keys[slot] = Some(key);
+            self.kroots[slot] = Some(key);
         }
+        self.cache.store(&key);
         // update chain in force
         self.chain_in_force = Some(ChainInForce {
             cid,
@@ -694,8 +1614,23 @@ impl KeyStore {
         });
     }
 
+    // Stores a TESLA key from a previous session and makes its chain the
+    // chain in force.
+    fn load_key(&mut self, key: Key<Validated>) {
+        let cid = key.chain().chain_id();
+        self.cache.store(&key);
+        info!("loading TESLA key"; gst = key.gst_subframe(), chain_id = cid);
+        self.keys = [Some(key), None];
+        self.kroots = [None, None];
+        self.chain_in_force = Some(ChainInForce {
+            cid,
+            start_applicability: None,
+        });
+    }
+
     fn store_key(&mut self, key: Key<Validated>) {
         let id = key.chain().chain_id();
+        self.cache.store(&key);
         match (&self.keys[0], &self.keys[1]) {
             (Some(k), _) if k.chain().chain_id() == id => self.keys[0] = Some(key),
             (_, Some(k)) if k.chain().chain_id() == id => self.keys[1] = Some(key),
@@ -713,10 +1648,23 @@ impl KeyStore {
             self.keys
                 .iter()
                 .flatten()
-                .find(|&&k| k.chain().chain_id() == cif.cid)
+                .find(|k| k.chain().chain_id() == cif.cid)
         })
     }
 
+    // Returns `key` together with its MAC context, reusing the MAC context
+    // computed in the previous call if the key is the same.
+    fn mac_key(&mut self, key: &Key<Validated>) -> MacKey {
+        match &self.mac_key {
+            Some(mac_key) if mac_key.key() == key => mac_key.clone(),
+            _ => {
+                let mac_key = MacKey::new(*key);
+                self.mac_key = Some(mac_key.clone());
+                mac_key
+            }
+        }
+    }
+
     // Similar to current_key but returns a key from the other chain if the
     // requested GST is before the start of applicability of the current
     // chain. This is used to get the key for MACK validation for Slow MAC.
@@ -730,20 +1678,945 @@ impl KeyStore {
                     self.keys
                         .iter()
                         .flatten()
-                        .find(|&&k| k.chain().chain_id() != cif.cid)
+                        .find(|k| k.chain().chain_id() != cif.cid)
                 }
                 _ => self.current_key(),
             })
     }
 
+    // Obtains the key with GST `gst` from the chain that was in force at that
+    // time, using the cache of recent keys when possible.
+    fn slowmac_key(&mut self, gst: Gst) -> Option<Key<Validated>> {
+        let k = *self.key_past_chain(gst)?;
+        let derivations = k.gst_subframe().subframes_difference(gst);
+        if derivations >= 0 {
+            Some(
+                self.cache
+                    .derive(&k, derivations.try_into().unwrap(), &self.checkpoints),
+            )
+        } else {
+            None
+        }
+    }
+
+    // Validates `key` using the key `current_key` (which should be the
+    // current key of the store) and the checkpoints.
+    fn validate_key(
+        &mut self,
+        current_key: &Key<Validated>,
+        key: &Key<NotValidated>,
+    ) -> Result<Key<Validated>, ValidationError> {
+        let (result, hashes) =
+            current_key.validate_key_with_checkpoints(key, &mut self.checkpoints);
+        debug!(
+            "TESLA key validation one-way function computations";
+            gst = key.gst_subframe(),
+            hashes = hashes,
+        );
+        self.last_validation_hashes = Some(hashes);
+        result
+    }
+
     fn revoke(&mut self, cid: u8) {
-        for k in &mut self.keys {
+        self.forget_chain(cid);
+        for (k, kroot) in self.keys.iter_mut().zip(self.kroots.iter_mut()) {
             if let Some(key) = k {
                 if key.chain().chain_id() == cid {
-                    log::warn!("revoking TESLA key {:?}", key);
+                    warn!("revoking TESLA key"; gst = key.gst_subframe(), chain_id = cid);
                     *k = None;
+                    *kroot = None;
                 }
             }
         }
     }
+
+    // Removes the cached keys and checkpoints of the chain with ID `cid`.
+    fn forget_chain(&mut self, cid: u8) {
+        self.cache.revoke(cid);
+        self.checkpoints.revoke(cid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitfields::{
+        ChainAndPubkeyStatus, EcdsaFunction, HashFunction, MacFunction, NewPublicKeyType,
+        NmaStatus, OwnedDsmKroot, OwnedDsmPkr,
+    };
+    use crate::builders::{DsmKrootBuilder, NmaHeaderBuilder};
+    use crate::merkle_tree::{FullMerkleTree, MerkleTreeLeaf, MERKLE_TREE_LEAVES};
+    use crate::navmessage::test::{authenticate_all, inav_word};
+    use crate::storage::FullStorage;
+    use crate::types::MACK_MESSAGE_BYTES;
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+    const KROOT_WN: u16 = 1176;
+    const KROOT_TOWH: u8 = 0x21;
+    // Number of TESLA keys after the KROOT used in the tests.
+    const NUM_KEYS: usize = 10;
+
+    // Cryptographic material of a simulated OSNMA system.
+    struct Fixture {
+        signing_key: SigningKey,
+        pubkey: PublicKey<Validated>,
+        merkle_tree_root: MerkleTreeNode,
+        dsm_pkr: OwnedDsmPkr,
+        dsm_pkr_alert: OwnedDsmPkr,
+        // DSM-PKR authenticated by a different Merkle tree
+        dsm_pkr_wrong: OwnedDsmPkr,
+        // TESLA keys, starting with the KROOT
+        keys: Vec<Key<Validated>>,
+    }
+
+    impl Fixture {
+        fn new() -> Fixture {
+            let signing_key = SigningKey::from_bytes(&[0x5a; 32].into()).unwrap();
+            let pubkey = PublicKey::from_p256(*signing_key.verifying_key(), 1).force_valid();
+            let encoded_pubkey = signing_key.verifying_key().to_encoded_point(true);
+            let alert_message = [0xa5; 39];
+            let mut leaves = [MerkleTreeLeaf {
+                new_public_key_type: NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256),
+                new_public_key_id: 1,
+                new_public_key: encoded_pubkey.as_bytes(),
+            }; MERKLE_TREE_LEAVES];
+            leaves[15] = MerkleTreeLeaf {
+                new_public_key_type: NewPublicKeyType::OsnmaAlertMessage,
+                new_public_key_id: 0,
+                new_public_key: &alert_message,
+            };
+            let tree = FullMerkleTree::from_leaves(&leaves).unwrap();
+            let dsm_pkr = tree
+                .dsm_pkr_builder(0, &leaves[0])
+                .build(tree.root())
+                .unwrap();
+            let dsm_pkr_alert = tree
+                .dsm_pkr_builder(15, &leaves[15])
+                .build(tree.root())
+                .unwrap();
+            leaves[15] = leaves[0];
+            let wrong_tree = FullMerkleTree::from_leaves(&leaves).unwrap();
+            let dsm_pkr_wrong = wrong_tree
+                .dsm_pkr_builder(0, &leaves[0])
+                .build(wrong_tree.root())
+                .unwrap();
+
+            let mut fixture = Fixture {
+                signing_key,
+                pubkey,
+                merkle_tree_root: *tree.root(),
+                dsm_pkr,
+                dsm_pkr_alert,
+                dsm_pkr_wrong,
+                keys: Vec::new(),
+            };
+            // The TESLA chain parameters are obtained from a DSM-KROOT with
+            // an arbitrary KROOT. Then the KROOT is derived from the last key.
+            let nma_header =
+                fixture.nma_header(NmaStatus::Operational, ChainAndPubkeyStatus::Nominal);
+            let dsm = fixture.dsm_kroot(nma_header, &[0; 16]);
+            let (kroot, _) =
+                Key::from_dsm_kroot(nma_header, dsm.dsm_kroot(), &fixture.pubkey).unwrap();
+            let last_key = Key::from_slice(
+                &[0x3c; 16],
+                kroot.gst_subframe().add_seconds(30 * NUM_KEYS as i32),
+                kroot.chain(),
+            )
+            .force_valid();
+            fixture.keys = (0..=NUM_KEYS)
+                .map(|j| last_key.derive(NUM_KEYS - j))
+                .collect();
+            fixture
+        }
+
+        fn nma_header(
+            &self,
+            nma_status: NmaStatus,
+            chain_and_pubkey_status: ChainAndPubkeyStatus,
+        ) -> NmaHeader<NotValidated> {
+            NmaHeaderBuilder {
+                nma_status,
+                chain_id: 1,
+                chain_and_pubkey_status,
+            }
+            .build()
+            .unwrap()
+        }
+
+        fn dsm_kroot(&self, nma_header: NmaHeader<NotValidated>, kroot: &[u8]) -> OwnedDsmKroot {
+            self.dsm_kroot_signed_by(nma_header, kroot, &self.signing_key)
+        }
+
+        fn dsm_kroot_signed_by(
+            &self,
+            nma_header: NmaHeader<NotValidated>,
+            kroot: &[u8],
+            signing_key: &SigningKey,
+        ) -> OwnedDsmKroot {
+            let builder = DsmKrootBuilder {
+                public_key_id: 1,
+                kroot_chain_id: 1,
+                hash_function: HashFunction::Sha256,
+                mac_function: MacFunction::HmacSha256,
+                key_size: 128,
+                tag_size: 40,
+                mac_lookup_table: 0x21,
+                kroot_wn: KROOT_WN,
+                kroot_towh: KROOT_TOWH,
+                alpha: 0x25d3964da3a2,
+                kroot,
+            };
+            let message = builder.signature_message(nma_header).unwrap();
+            let signature: Signature = signing_key.sign(message.as_ref());
+            builder.build(nma_header, &signature.to_bytes()).unwrap()
+        }
+
+        // Feeds a DSM-KROOT with the correct KROOT and the given NMA header.
+        fn feed_kroot(
+            &self,
+            osnma: &mut Osnma<FullStorage, 8, 8, impl MacTraceHook, impl EventHook>,
+            nma_status: NmaStatus,
+            chain_and_pubkey_status: ChainAndPubkeyStatus,
+        ) {
+            let nma_header = self.nma_header(nma_status, chain_and_pubkey_status);
+            let dsm = self.dsm_kroot(nma_header, self.keys[0].data());
+            osnma
+                .data
+                .data
+                .process_dsm_kroot(dsm.dsm_kroot(), nma_header, self.gst());
+        }
+
+        // Feeds a MACK message containing the TESLA key `key`.
+        fn feed_mack(
+            &self,
+            osnma: &mut Osnma<FullStorage, 8, 8, impl MacTraceHook, impl EventHook>,
+            key: &[u8],
+            gst: Gst,
+        ) {
+            let mut mack = [0; MACK_MESSAGE_BYTES];
+            // With a 128-bit key and 40-bit tags, the MACK contains 6 tags.
+            mack[42..58].copy_from_slice(key);
+            osnma.data.data.validate_key(&mack, gst);
+        }
+
+        fn gst(&self) -> Gst {
+            self.keys[0].gst_subframe().add_seconds(30)
+        }
+    }
+
+    // Event hook that stores the events.
+    #[derive(Debug, Default)]
+    struct EventRecorder(Vec<OsnmaEvent>);
+
+    impl EventHook for EventRecorder {
+        fn event(&mut self, event: &OsnmaEvent) {
+            self.0.push(*event);
+        }
+    }
+
+    fn assert_transition(
+        osnma: &Osnma<FullStorage>,
+        from: OsnmaState,
+        to: OsnmaState,
+        reason: TransitionReason,
+    ) {
+        assert_eq!(osnma.state(), to);
+        let transition = osnma.last_state_transition().unwrap();
+        assert_eq!(transition.from, from);
+        assert_eq!(transition.to, to);
+        assert_eq!(transition.reason, reason);
+    }
+
+    #[test]
+    fn cold_start() {
+        let fixture = Fixture::new();
+        let mut osnma =
+            Osnma::<FullStorage>::from_merkle_tree(fixture.merkle_tree_root, None, false);
+        assert_eq!(osnma.state(), OsnmaState::ColdStart);
+        assert!(osnma.last_state_transition().is_none());
+
+        osnma
+            .data
+            .data
+            .process_dsm_pkr(fixture.dsm_pkr_wrong.dsm_pkr(), fixture.gst());
+        assert_transition(
+            &osnma,
+            OsnmaState::ColdStart,
+            OsnmaState::StartFailed,
+            TransitionReason::PublicKeyVerificationFailed,
+        );
+
+        osnma
+            .data
+            .data
+            .process_dsm_pkr(fixture.dsm_pkr.dsm_pkr(), fixture.gst());
+        assert_transition(
+            &osnma,
+            OsnmaState::StartFailed,
+            OsnmaState::WarmStart,
+            TransitionReason::PublicKeyVerified,
+        );
+    }
+
+    #[test]
+    fn warm_start() {
+        let fixture = Fixture::new();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false);
+        assert_eq!(osnma.state(), OsnmaState::WarmStart);
+
+        let nma_header = fixture.nma_header(NmaStatus::Operational, ChainAndPubkeyStatus::Nominal);
+        let wrong_signing_key = SigningKey::from_bytes(&[0xa5; 32].into()).unwrap();
+        let dsm =
+            fixture.dsm_kroot_signed_by(nma_header, fixture.keys[0].data(), &wrong_signing_key);
+        osnma
+            .data
+            .data
+            .process_dsm_kroot(dsm.dsm_kroot(), nma_header, fixture.gst());
+        assert_transition(
+            &osnma,
+            OsnmaState::WarmStart,
+            OsnmaState::StartFailed,
+            TransitionReason::KrootVerificationFailed,
+        );
+
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        assert_transition(
+            &osnma,
+            OsnmaState::StartFailed,
+            OsnmaState::Operational,
+            TransitionReason::KrootVerified,
+        );
+        assert_eq!(osnma.last_state_transition().unwrap().gst, fixture.gst());
+    }
+
+    #[test]
+    fn events() {
+        let fixture = Fixture::new();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false)
+            .with_event_hook(EventRecorder::default());
+
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        let kroot_gst = fixture.keys[0].gst_subframe();
+        let key = &fixture.keys[3];
+        fixture.feed_mack(&mut osnma, key.data(), key.gst_subframe());
+        let wrong_key = [0x55; 16];
+        let wrong_key_gst = fixture.keys[4].gst_subframe();
+        fixture.feed_mack(&mut osnma, &wrong_key, wrong_key_gst);
+
+        assert_eq!(
+            osnma.event_hook().0[..],
+            [
+                OsnmaEvent::KrootVerified {
+                    pkid: 1,
+                    chain_id: 1,
+                    nma_status: NmaStatus::Operational,
+                    kroot_gst,
+                    gst: fixture.gst(),
+                },
+                OsnmaEvent::StateTransition(StateTransition {
+                    from: OsnmaState::WarmStart,
+                    to: OsnmaState::Operational,
+                    reason: TransitionReason::KrootVerified,
+                    gst: fixture.gst(),
+                }),
+                OsnmaEvent::TeslaKeyVerified {
+                    key_gst: key.gst_subframe(),
+                    chain_id: 1,
+                    validated_by: kroot_gst,
+                    gst: key.gst_subframe(),
+                },
+                OsnmaEvent::TeslaKeyVerificationFailed {
+                    key_gst: wrong_key_gst,
+                    validated_by: key.gst_subframe(),
+                    error: ValidationError::WrongOneWayFunction,
+                    gst: wrong_key_gst,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn gst_discrepancy_events() {
+        use crate::types::BitSlice;
+        use bitvec::prelude::*;
+
+        // INAV word 5 containing a GST
+        fn word5(gst: Gst) -> InavWord {
+            let mut word = inav_word(5, 0, 0);
+            let bits = BitSlice::from_slice_mut(&mut word);
+            bits[73..85].store_be(gst.wn());
+            bits[85..105].store_be(gst.tow());
+            word
+        }
+
+        let fixture = Fixture::new();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false)
+            .with_event_hook(EventRecorder::default());
+        let gst = Gst::new(1177, 175770);
+        let svn = Svn::try_from(11).unwrap();
+        let other_svn = Svn::try_from(12).unwrap();
+
+        // Discrepancies are not checked by default
+        osnma.feed_inav(&word5(gst), svn, gst.add_seconds(2), InavBand::E1B);
+        assert!(osnma.event_hook().0.is_empty());
+
+        // Each discrepancy gives an event
+        osnma.set_gst_check(true);
+        osnma.feed_inav(&word5(gst), svn, gst.add_seconds(2), InavBand::E1B);
+        osnma.feed_inav(&word5(gst), other_svn, gst.add_seconds(-4), InavBand::E5B);
+        osnma.feed_inav(&word5(gst), svn, gst, InavBand::E1B);
+
+        // Pages fed without GST
+        let bound = GstBound::new(gst, 30);
+        assert_eq!(
+            osnma.feed_inav_page(&word5(gst), None, svn, InavBand::E1B, bound),
+            Some(gst)
+        );
+        let page_gst = gst.add_seconds(2);
+        assert_eq!(
+            osnma.feed_inav_page(
+                &word5(page_gst.add_seconds(6)),
+                None,
+                svn,
+                InavBand::E1B,
+                bound
+            ),
+            Some(page_gst.add_seconds(6))
+        );
+        // Pages with a GST outside of the bound are discarded
+        let forged_gst = gst.add_seconds(3600);
+        assert_eq!(
+            osnma.feed_inav_page(&word5(forged_gst), None, other_svn, InavBand::E1B, bound),
+            None
+        );
+
+        let discrepancies = osnma
+            .event_hook()
+            .0
+            .iter()
+            .map(|event| match event {
+                OsnmaEvent::GstDiscrepancy(discrepancy) => {
+                    assert_eq!(event.gst(), discrepancy.expected().gst_subframe());
+                    assert!(!event.is_failure());
+                    (
+                        discrepancy.svn(),
+                        discrepancy.band(),
+                        discrepancy.expected(),
+                        discrepancy.seconds(),
+                    )
+                }
+                _ => panic!("unexpected event {event:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            discrepancies,
+            [
+                (svn, InavBand::E1B, gst.add_seconds(2), -2),
+                (other_svn, InavBand::E5B, gst.add_seconds(-4), 4),
+                (svn, InavBand::E1B, page_gst, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn hot_start() {
+        let fixture = Fixture::new();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false);
+        assert!(osnma.load_tesla_key(fixture.keys[2]));
+        assert_transition(
+            &osnma,
+            OsnmaState::WarmStart,
+            OsnmaState::HotStart,
+            TransitionReason::TeslaKeyLoaded,
+        );
+        // Only one key can be loaded
+        assert!(!osnma.load_tesla_key(fixture.keys[1]));
+
+        let key = &fixture.keys[5];
+        fixture.feed_mack(&mut osnma, key.data(), key.gst_subframe());
+        assert_transition(
+            &osnma,
+            OsnmaState::HotStart,
+            OsnmaState::Operational,
+            TransitionReason::TeslaKeyVerified,
+        );
+        assert_eq!(osnma.tesla_key().unwrap().data(), key.data());
+        assert_eq!(
+            osnma.tesla_key().unwrap().gst_subframe(),
+            key.gst_subframe()
+        );
+        // Keys cannot be loaded once operational
+        assert!(!osnma.load_tesla_key(fixture.keys[1]));
+    }
+
+    #[test]
+    fn hot_start_failed() {
+        let fixture = Fixture::new();
+        let gst = fixture.keys[5].gst_subframe();
+
+        // The fallback is a warm start if a public key is available
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false);
+        assert!(osnma.load_tesla_key(fixture.keys[2]));
+        fixture.feed_mack(&mut osnma, &[0xff; 16], gst);
+        assert_transition(
+            &osnma,
+            OsnmaState::HotStart,
+            OsnmaState::WarmStart,
+            TransitionReason::TeslaKeyVerificationFailed,
+        );
+        assert!(osnma.tesla_key().is_none());
+        // The loaded key has been discarded, so a valid MACK does not make
+        // the black box operational until a DSM-KROOT is verified
+        let key = &fixture.keys[6];
+        fixture.feed_mack(&mut osnma, key.data(), key.gst_subframe());
+        assert_eq!(osnma.state(), OsnmaState::WarmStart);
+        assert!(osnma.tesla_key().is_none());
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        assert_transition(
+            &osnma,
+            OsnmaState::WarmStart,
+            OsnmaState::Operational,
+            TransitionReason::KrootVerified,
+        );
+
+        // The fallback is a cold start if only the Merkle tree is available
+        let mut osnma =
+            Osnma::<FullStorage>::from_merkle_tree(fixture.merkle_tree_root, None, false);
+        assert!(osnma.load_tesla_key(fixture.keys[2]));
+        fixture.feed_mack(&mut osnma, &[0xff; 16], gst);
+        assert_transition(
+            &osnma,
+            OsnmaState::HotStart,
+            OsnmaState::ColdStart,
+            TransitionReason::TeslaKeyVerificationFailed,
+        );
+        assert!(osnma.tesla_key().is_none());
+    }
+
+    #[test]
+    fn chain_renewal_same_parameters() {
+        let fixture = Fixture::new();
+        let nma_header = fixture.nma_header(NmaStatus::Operational, ChainAndPubkeyStatus::Nominal);
+        let dsm = fixture.dsm_kroot(nma_header, fixture.keys[0].data());
+        let (kroot, nma_header) =
+            Key::from_dsm_kroot(nma_header, dsm.dsm_kroot(), &fixture.pubkey).unwrap();
+        let chain = kroot.chain();
+        let not_validated =
+            |key: &Key<Validated>| Key::from_slice(key.data(), key.gst_subframe(), key.chain());
+        // Renewed chain with the same chain ID and parameters, but different
+        // keys
+        let last_key = Key::from_slice(&[0xc3; 16], fixture.keys[NUM_KEYS].gst_subframe(), chain)
+            .force_valid();
+        let renewed_keys: Vec<_> = (0..=NUM_KEYS)
+            .map(|j| last_key.derive(NUM_KEYS - j))
+            .collect();
+        assert_eq!(renewed_keys[0].chain(), chain);
+
+        let mut store = KeyStore::<8>::empty();
+        store.store_kroot(kroot, nma_header, fixture.gst());
+        let key = store
+            .validate_key(&kroot, &not_validated(&fixture.keys[NUM_KEYS]))
+            .unwrap();
+        store.store_key(key);
+        assert!(!store.checkpoints.is_empty());
+
+        store.store_kroot(renewed_keys[0], nma_header, fixture.gst());
+        assert_eq!(store.current_key(), Some(&renewed_keys[0]));
+        assert!(store.checkpoints.is_empty());
+        // The keys of the previous chain are not used as shortcuts
+        assert_eq!(
+            store
+                .cache
+                .derive(&renewed_keys[NUM_KEYS], NUM_KEYS, &store.checkpoints),
+            renewed_keys[0]
+        );
+        let key = &renewed_keys[NUM_KEYS / 2];
+        assert_eq!(
+            store.validate_key(&renewed_keys[0], &not_validated(key)),
+            Ok(*key)
+        );
+        let key = &fixture.keys[NUM_KEYS / 2 + 1];
+        assert_eq!(
+            store.validate_key(&renewed_keys[0], &not_validated(key)),
+            Err(ValidationError::WrongOneWayFunction)
+        );
+
+        // Receiving the KROOT again does not discard the checkpoints
+        assert!(!store.checkpoints.is_empty());
+        store.store_kroot(renewed_keys[0], nma_header, fixture.gst());
+        assert!(!store.checkpoints.is_empty());
+    }
+
+    #[test]
+    fn dont_use() {
+        let fixture = Fixture::new();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false);
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        assert_eq!(osnma.state(), OsnmaState::Operational);
+
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::DontUse,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        assert_transition(
+            &osnma,
+            OsnmaState::Operational,
+            OsnmaState::DontUse,
+            TransitionReason::NmaStatusDontUse,
+        );
+
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        assert_transition(
+            &osnma,
+            OsnmaState::DontUse,
+            OsnmaState::Operational,
+            TransitionReason::KrootVerified,
+        );
+
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::DontUse,
+            ChainAndPubkeyStatus::ChainRevoked,
+        );
+        assert_transition(
+            &osnma,
+            OsnmaState::Operational,
+            OsnmaState::DontUse,
+            TransitionReason::ChainRevoked,
+        );
+
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false);
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::DontUse,
+            ChainAndPubkeyStatus::PublicKeyRevoked,
+        );
+        assert_transition(
+            &osnma,
+            OsnmaState::WarmStart,
+            OsnmaState::DontUse,
+            TransitionReason::PublicKeyRevoked,
+        );
+    }
+
+    #[test]
+    fn alert() {
+        let fixture = Fixture::new();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false);
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::DontUse,
+            ChainAndPubkeyStatus::AlertMessage,
+        );
+        assert_transition(
+            &osnma,
+            OsnmaState::DontUse,
+            OsnmaState::Alert,
+            TransitionReason::AlertMessage,
+        );
+        // The cryptographic material has been deleted
+        assert!(osnma.tesla_key().is_none());
+        assert!(!osnma.load_tesla_key(fixture.keys[1]));
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        assert_eq!(osnma.state(), OsnmaState::Alert);
+
+        let mut osnma = Osnma::<FullStorage>::from_merkle_tree(
+            fixture.merkle_tree_root,
+            Some(fixture.pubkey.clone()),
+            false,
+        );
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        osnma
+            .data
+            .data
+            .process_dsm_pkr(fixture.dsm_pkr_alert.dsm_pkr(), fixture.gst());
+        assert_transition(
+            &osnma,
+            OsnmaState::Operational,
+            OsnmaState::Alert,
+            TransitionReason::AlertMessage,
+        );
+    }
+
+    #[test]
+    fn navigation_data_not_usable() {
+        let fixture = Fixture::new();
+        let svn = Svn::try_from(11).unwrap();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false);
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        for word_type in 1..=5 {
+            osnma.feed_inav(
+                &inav_word(word_type, 1, 0xaa),
+                svn,
+                fixture.gst(),
+                InavBand::E1B,
+            );
+        }
+        authenticate_all(&mut osnma.data.data.navmessage);
+        assert!(osnma.get_ced_and_status(svn).is_some());
+        assert_eq!(osnma.ced_and_status_history(svn).count(), 1);
+        assert!(osnma.snapshot().ced_and_status(svn).is_some());
+
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::DontUse,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        assert!(osnma.get_ced_and_status(svn).is_none());
+        assert!(osnma.get_ced_and_status_at(svn, fixture.gst()).is_none());
+        assert_eq!(osnma.ced_and_status_history(svn).count(), 0);
+        assert!(osnma.snapshot().ced_and_status(svn).is_none());
+
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        assert!(osnma.get_ced_and_status(svn).is_some());
+
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::DontUse,
+            ChainAndPubkeyStatus::AlertMessage,
+        );
+        assert_eq!(osnma.state(), OsnmaState::Alert);
+        assert!(osnma.get_ced_and_status(svn).is_none());
+        assert!(osnma.snapshot().ced_and_status(svn).is_none());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_sequential() {
+        use crate::bitfields::{Adkd, Mack, Prnd};
+        use crate::builders::{MackBuilder, TagAndInfoBuilder};
+        use crate::types::{BitSlice, InavWord};
+
+        // Builds the CED and health status and the timing parameters
+        // navigation data from the INAV words 1 to 6 and 10.
+        fn navdata_bits(words: &[InavWord; 7]) -> ([u8; 69], [u8; 18]) {
+            let mut ced = [0; 69];
+            let mut timing = [0; 18];
+            let bits = |j: usize| BitSlice::from_slice(&words[j]);
+            let ced_bits = BitSlice::from_slice_mut(&mut ced);
+            ced_bits[0..120].copy_from_bitslice(&bits(0)[6..126]);
+            ced_bits[120..240].copy_from_bitslice(&bits(1)[6..126]);
+            ced_bits[240..362].copy_from_bitslice(&bits(2)[6..128]);
+            ced_bits[362..482].copy_from_bitslice(&bits(3)[6..126]);
+            ced_bits[482..549].copy_from_bitslice(&bits(4)[6..73]);
+            let timing_bits = BitSlice::from_slice_mut(&mut timing);
+            timing_bits[0..99].copy_from_bitslice(&bits(5)[6..105]);
+            timing_bits[99..141].copy_from_bitslice(&bits(6)[86..128]);
+            (ced, timing)
+        }
+
+        const NUM_SATS: u8 = 12;
+        const TAG_SIZE: usize = 40;
+        let fixture = Fixture::new();
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(fixture.pubkey.clone(), false);
+        fixture.feed_kroot(
+            &mut osnma,
+            NmaStatus::Operational,
+            ChainAndPubkeyStatus::Nominal,
+        );
+        let gst_navdata = fixture.gst().add_seconds(-30);
+        let gst_mack = fixture.gst();
+        let key = fixture.keys[2];
+        let nma_status = NmaStatus::Operational;
+        let svns = || (1..=NUM_SATS).map(|n| Svn::try_from(n).unwrap());
+
+        let mut navdata = Vec::new();
+        for svn in svns() {
+            let words = [1, 2, 3, 4, 5, 6, 10].map(|word_type| {
+                inav_word(word_type, 1, u8::from(svn).wrapping_mul(37) ^ word_type)
+            });
+            for word in &words {
+                osnma.feed_inav(word, svn, gst_navdata, InavBand::E1B);
+            }
+            navdata.push(navdata_bits(&words));
+        }
+        let ced = |svn: Svn| &BitSlice::from_slice(&navdata[usize::from(svn) - 1].0)[..549];
+        let timing = |svn: Svn| &BitSlice::from_slice(&navdata[usize::from(svn) - 1].1)[..141];
+
+        // The MACK messages follow the MAC look-up table 0x21 in the first half
+        // of the minute. The tags authenticate the CED and health status of the
+        // transmitting satellite and the next 3 satellites, and the timing
+        // parameters of the transmitting satellite. The Slow MAC tag is not
+        // valid.
+        for prna in svns() {
+            let other = |n: u8| Svn::try_from((u8::from(prna) + n - 1) % NUM_SATS + 1).unwrap();
+            let mut tags = [[0u8; 5]; 6];
+            key.compute_tag0(
+                BitSlice::from_slice_mut(&mut tags[0]),
+                gst_mack,
+                prna,
+                nma_status,
+                ced(prna),
+            );
+            let slots = [
+                (Adkd::InavCed, other(1)),
+                (Adkd::InavTiming, prna),
+                (Adkd::InavCed, other(2)),
+                (Adkd::SlowMac, prna),
+                (Adkd::InavCed, other(3)),
+            ];
+            for (j, &(adkd, prnd)) in slots.iter().enumerate() {
+                let data = match adkd {
+                    Adkd::InavTiming => timing(prnd),
+                    Adkd::InavCed => ced(prnd),
+                    _ => continue,
+                };
+                key.compute_tag(
+                    BitSlice::from_slice_mut(&mut tags[j + 1]),
+                    gst_mack,
+                    u8::from(prnd),
+                    prna,
+                    u8::try_from(j + 2).unwrap(),
+                    nma_status,
+                    data,
+                );
+            }
+            let tag_bits: Vec<&BitSlice> = tags
+                .iter()
+                .map(|t| &BitSlice::from_slice(t)[..TAG_SIZE])
+                .collect();
+            let tags_and_info: Vec<TagAndInfoBuilder> = slots
+                .iter()
+                .enumerate()
+                .map(|(j, &(adkd, prnd))| TagAndInfoBuilder {
+                    tag: tag_bits[j + 1],
+                    prnd: Prnd::GalileoSvid(u8::from(prnd)),
+                    adkd,
+                    cop: 15,
+                })
+                .collect();
+            let mut builder = MackBuilder {
+                key_size: 128,
+                tag_size: TAG_SIZE,
+                tag0: tag_bits[0],
+                macseq: 0,
+                cop: 15,
+                tags: &tags_and_info,
+                key: &BitSlice::from_slice(fixture.keys[1].data())[..128],
+            };
+            let mack = builder.build().unwrap();
+            builder.macseq = key
+                .compute_macseq(&Mack::new(&mack, 128, TAG_SIZE), prna, gst_mack)
+                .unwrap();
+            let mack = builder.build().unwrap();
+            osnma
+                .data
+                .data
+                .mack
+                .store(&mack, prna, gst_mack, nma_status);
+            // The same MACK message transmitted by other satellites fails the
+            // MACSEQ verification.
+            if u8::from(prna) <= 4 {
+                let other = Svn::try_from(u8::from(prna) + NUM_SATS).unwrap();
+                osnma
+                    .data
+                    .data
+                    .mack
+                    .store(&mack, other, gst_mack, nma_status);
+            }
+        }
+
+        // The events and MAC traces are recorded in the order in which the
+        // hooks are called, together with the thread that calls the hooks.
+        // The hooks share the record, which is not Send.
+        #[derive(Debug, Default, Clone)]
+        struct HookCalls(std::rc::Rc<core::cell::RefCell<Vec<String>>>);
+
+        impl HookCalls {
+            fn record(&self, call: String) {
+                let thread = std::thread::current().id();
+                self.0.borrow_mut().push(format!("{thread:?} {call}"));
+            }
+        }
+
+        impl EventHook for HookCalls {
+            fn event(&mut self, event: &OsnmaEvent) {
+                self.record(format!("event {event:?}"));
+            }
+        }
+
+        impl MacTraceHook for HookCalls {
+            fn mac_trace(&mut self, trace: &MacTrace<'_>) {
+                self.record(format!("trace {trace}"));
+            }
+        }
+
+        let hook_calls = HookCalls::default();
+        let osnma = osnma
+            .with_event_hook(hook_calls.clone())
+            .with_mac_trace(hook_calls.clone());
+
+        let mac_key = MacKey::new(key);
+        let mut sequential = osnma.clone();
+        sequential
+            .data
+            .data
+            .process_tags_sequential(&mac_key, None, gst_mack);
+        let sequential_calls = hook_calls.0.take();
+        let mut parallel = osnma;
+        parallel
+            .data
+            .data
+            .process_tags_parallel(&mac_key, None, gst_mack);
+        let parallel_calls = hook_calls.0.take();
+        for svn in svns() {
+            let data = sequential.data.data.navmessage.get_ced_and_status(svn);
+            assert_eq!(data.unwrap().authbits(), 4 * TAG_SIZE as u16);
+        }
+        assert_eq!(
+            sequential.data.data.navmessage,
+            parallel.data.data.navmessage
+        );
+        let failures = sequential_calls
+            .iter()
+            .filter(|call| call.contains("event MackVerificationFailed"))
+            .count();
+        assert_eq!(failures, 4);
+        assert!(sequential_calls
+            .iter()
+            .any(|call| call.contains("trace tag")));
+        assert_eq!(sequential_calls, parallel_calls);
+    }
 }
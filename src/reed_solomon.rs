@@ -0,0 +1,279 @@
+// Reed-Solomon outer FEC of the I/NAV CED.
+//
+// Galileo I/NAV protects the CED in words 1 to 4 with a Reed-Solomon code
+// whose parity is transmitted in words 17 to 20 (see the Galileo OS SIS ICD
+// v2.1, Annex F). The code is RS(255, 195) over GF(2^8), with field generator
+// polynomial p(x) = x^8 + x^4 + x^3 + x^2 + 1 and code generator polynomial
+// g(x) = (x - a^195) (x - a^196) ... (x - a^254), shortened to 118 symbols:
+// 58 information symbols followed by 60 parity symbols. The codeword symbols
+// are the coefficients of the codeword polynomial in order of decreasing
+// degree.
+//
+// Since the receiver knows which words it has not received, only erasure
+// decoding is implemented.
+
+pub(crate) const INFO_SYMBOLS: usize = 58;
+pub(crate) const PARITY_SYMBOLS: usize = 60;
+pub(crate) const CODEWORD_SYMBOLS: usize = INFO_SYMBOLS + PARITY_SYMBOLS;
+
+const FIRST_ROOT: usize = 195;
+const FIELD_POLY: u16 = 0x11d;
+
+struct Tables {
+    // The exponentials are repeated twice to avoid reducing sums of logarithms
+    // modulo 255.
+    exp: [u8; 510],
+    log: [u8; 256],
+}
+
+const TABLES: Tables = Tables::new();
+
+impl Tables {
+    const fn new() -> Tables {
+        let mut exp = [0; 510];
+        let mut log = [0; 256];
+        let mut x: u16 = 1;
+        let mut j = 0;
+        while j < 255 {
+            exp[j] = x as u8;
+            exp[j + 255] = x as u8;
+            log[x as usize] = j as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= FIELD_POLY;
+            }
+            j += 1;
+        }
+        Tables { exp, log }
+    }
+}
+
+const fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        TABLES.exp[TABLES.log[a as usize] as usize + TABLES.log[b as usize] as usize]
+    }
+}
+
+// Panics if a is zero.
+fn inv(a: u8) -> u8 {
+    assert_ne!(a, 0);
+    TABLES.exp[255 - usize::from(TABLES.log[usize::from(a)])]
+}
+
+const fn alpha_pow(n: usize) -> u8 {
+    TABLES.exp[n % 255]
+}
+
+// Coefficients of g(x) in order of decreasing degree. The encoder is only
+// needed to test the decoder.
+#[cfg(test)]
+const GENERATOR: [u8; PARITY_SYMBOLS + 1] = generator();
+
+#[cfg(test)]
+const fn generator() -> [u8; PARITY_SYMBOLS + 1] {
+    let mut g = [0; PARITY_SYMBOLS + 1];
+    g[0] = 1;
+    let mut j = 0;
+    while j < PARITY_SYMBOLS {
+        // Multiply by (x + a^(FIRST_ROOT + j)). The polynomial currently has
+        // degree j.
+        let root = alpha_pow(FIRST_ROOT + j);
+        let mut k = j + 1;
+        while k > 0 {
+            g[k] ^= mul(g[k - 1], root);
+            k -= 1;
+        }
+        j += 1;
+    }
+    g
+}
+
+// Computes the parity symbols for the information symbols.
+#[cfg(test)]
+pub(crate) fn encode(info: &[u8; INFO_SYMBOLS]) -> [u8; PARITY_SYMBOLS] {
+    let mut parity = [0; PARITY_SYMBOLS];
+    for &symbol in info.iter() {
+        let feedback = symbol ^ parity[0];
+        for j in 0..PARITY_SYMBOLS - 1 {
+            parity[j] = parity[j + 1] ^ mul(feedback, GENERATOR[j + 1]);
+        }
+        parity[PARITY_SYMBOLS - 1] = mul(feedback, GENERATOR[PARITY_SYMBOLS]);
+    }
+    parity
+}
+
+fn syndromes(codeword: &[u8; CODEWORD_SYMBOLS]) -> [u8; PARITY_SYMBOLS] {
+    let mut syndromes = [0; PARITY_SYMBOLS];
+    for (j, syndrome) in syndromes.iter_mut().enumerate() {
+        let root = alpha_pow(FIRST_ROOT + j);
+        *syndrome = codeword.iter().fold(0, |acc, &c| mul(acc, root) ^ c);
+    }
+    syndromes
+}
+
+// Error returned when the erasures cannot be decoded.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct DecodeError;
+
+// Fills the erased symbols of a codeword.
+//
+// The erased symbols are those for which `erased` is true. The decoding fails
+// if there are more erasures than parity symbols, or if the corrected codeword
+// is not a valid codeword (which can only be detected if there are less
+// erasures than parity symbols).
+pub(crate) fn decode_erasures(
+    codeword: &mut [u8; CODEWORD_SYMBOLS],
+    erased: &[bool; CODEWORD_SYMBOLS],
+) -> Result<(), DecodeError> {
+    let num_erasures = erased.iter().filter(|&&e| e).count();
+    if num_erasures > PARITY_SYMBOLS {
+        return Err(DecodeError);
+    }
+    for (c, &e) in codeword.iter_mut().zip(erased.iter()) {
+        if e {
+            *c = 0;
+        }
+    }
+    let syn = syndromes(codeword);
+    if num_erasures == 0 {
+        return if syn.iter().all(|&s| s == 0) {
+            Ok(())
+        } else {
+            Err(DecodeError)
+        };
+    }
+
+    // The symbol at position j corresponds to the power of x equal to
+    // CODEWORD_SYMBOLS - 1 - j, so its locator is a^(CODEWORD_SYMBOLS - 1 - j).
+    let locator_of = |j: usize| alpha_pow(CODEWORD_SYMBOLS - 1 - j);
+
+    // Erasure locator polynomial, in order of increasing degree.
+    let mut lambda = [0; PARITY_SYMBOLS + 1];
+    lambda[0] = 1;
+    let mut degree = 0;
+    for j in (0..CODEWORD_SYMBOLS).filter(|&j| erased[j]) {
+        let x = locator_of(j);
+        degree += 1;
+        for k in (1..=degree).rev() {
+            lambda[k] ^= mul(lambda[k - 1], x);
+        }
+    }
+
+    // Error evaluator polynomial, in order of increasing degree.
+    let mut omega = [0; PARITY_SYMBOLS];
+    for (k, omega_k) in omega.iter_mut().enumerate() {
+        for l in 0..=k.min(degree) {
+            *omega_k ^= mul(lambda[l], syn[k - l]);
+        }
+    }
+
+    // Forney algorithm
+    for j in (0..CODEWORD_SYMBOLS).filter(|&j| erased[j]) {
+        let x = locator_of(j);
+        let x_inv = inv(x);
+        let omega_value = omega.iter().rev().fold(0, |acc, &c| mul(acc, x_inv) ^ c);
+        // Formal derivative of lambda, which only has the odd degree terms.
+        let mut lambda_derivative = 0;
+        let mut x_inv_pow = 1;
+        for k in (1..=degree).step_by(2) {
+            lambda_derivative ^= mul(lambda[k], x_inv_pow);
+            x_inv_pow = mul(x_inv_pow, mul(x_inv, x_inv));
+        }
+        if lambda_derivative == 0 {
+            return Err(DecodeError);
+        }
+        // x^(1 - FIRST_ROOT)
+        let scale = alpha_pow(
+            (255 - (usize::from(TABLES.log[usize::from(x)]) * (FIRST_ROOT - 1)) % 255) % 255,
+        );
+        codeword[j] = mul(mul(scale, omega_value), inv(lambda_derivative));
+    }
+
+    if syndromes(codeword).iter().all(|&s| s == 0) {
+        Ok(())
+    } else {
+        Err(DecodeError)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn codeword(seed: u32) -> [u8; CODEWORD_SYMBOLS] {
+        let mut state = seed;
+        let mut info = [0; INFO_SYMBOLS];
+        for x in info.iter_mut() {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            *x = (state >> 16) as u8;
+        }
+        let mut codeword = [0; CODEWORD_SYMBOLS];
+        codeword[..INFO_SYMBOLS].copy_from_slice(&info);
+        codeword[INFO_SYMBOLS..].copy_from_slice(&encode(&info));
+        codeword
+    }
+
+    #[test]
+    fn generator_roots() {
+        for j in 0..PARITY_SYMBOLS {
+            let root = alpha_pow(FIRST_ROOT + j);
+            assert_eq!(GENERATOR.iter().fold(0, |acc, &c| mul(acc, root) ^ c), 0);
+        }
+    }
+
+    #[test]
+    fn encode_syndromes() {
+        assert_eq!(syndromes(&codeword(1)), [0; PARITY_SYMBOLS]);
+    }
+
+    #[test]
+    fn erasures() {
+        // Erase any 4 of the 8 words (words 1 to 4 of 16, 14, 14 and 14
+        // symbols, and words 17 to 20 of 15 symbols), except for the first two
+        // symbols of word 1, which contain the word type and IODnav.
+        let words = [
+            2..16,
+            16..30,
+            30..44,
+            44..58,
+            58..73,
+            73..88,
+            88..103,
+            103..118,
+        ];
+        for erased_words in 0u8..=255 {
+            if erased_words.count_ones() != 4 {
+                continue;
+            }
+            let expected = codeword(u32::from(erased_words));
+            let mut erased = [false; CODEWORD_SYMBOLS];
+            for (j, word) in words.iter().enumerate() {
+                if erased_words & (1 << j) != 0 {
+                    erased[word.clone()].fill(true);
+                }
+            }
+            let mut received = expected;
+            for (r, &e) in received.iter_mut().zip(erased.iter()) {
+                if e {
+                    *r ^= 0x5a;
+                }
+            }
+            decode_erasures(&mut received, &erased).unwrap();
+            assert_eq!(received, expected);
+        }
+    }
+
+    #[test]
+    fn detect_errors() {
+        let mut received = codeword(2);
+        let mut erased = [false; CODEWORD_SYMBOLS];
+        erased[..50].fill(true);
+        received[100] ^= 1;
+        assert_eq!(decode_erasures(&mut received, &erased), Err(DecodeError));
+        let mut erased = [false; CODEWORD_SYMBOLS];
+        erased[..61].fill(true);
+        assert_eq!(decode_erasures(&mut received, &erased), Err(DecodeError));
+    }
+}
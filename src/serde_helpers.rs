@@ -0,0 +1,128 @@
+// Helpers for the implementation of the serde traits.
+//
+// serde only implements its traits for arrays of up to 32 elements, and the
+// owned message types need to store byte strings of variable length without
+// allocating. Bytes<N> stores up to N bytes in an array, and is serialized as
+// a byte string containing only the bytes that are used. Seq<T, N> is the
+// counterpart for deserializing sequences of up to N elements of other types.
+
+use core::fmt;
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Bytes<const N: usize> {
+    data: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Bytes<N> {
+    // Panics if the slice is longer than N bytes.
+    pub(crate) fn new(bytes: &[u8]) -> Bytes<N> {
+        let mut data = [0; N];
+        data[..bytes.len()].copy_from_slice(bytes);
+        Bytes {
+            data,
+            len: bytes.len(),
+        }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl<const N: usize> Serialize for Bytes<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_slice())
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Bytes<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Bytes<N>, D::Error> {
+        deserializer.deserialize_bytes(BytesVisitor::<N>)
+    }
+}
+
+struct BytesVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+    type Value = Bytes<N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a byte string of at most {N} bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Bytes<N>, E> {
+        if v.len() > N {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        Ok(Bytes::new(v))
+    }
+
+    // Formats without a byte string type, such as JSON, serialize bytes as a
+    // sequence of integers.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes<N>, A::Error> {
+        let mut bytes = Bytes {
+            data: [0; N],
+            len: 0,
+        };
+        while let Some(byte) = seq.next_element()? {
+            if bytes.len == N {
+                return Err(de::Error::invalid_length(N + 1, &self));
+            }
+            bytes.data[bytes.len] = byte;
+            bytes.len += 1;
+        }
+        Ok(bytes)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Seq<T, const N: usize> {
+    items: [Option<T>; N],
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> Seq<T, N> {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.items[..self.len].iter().flatten().copied()
+    }
+}
+
+impl<'de, T: Deserialize<'de> + Copy, const N: usize> Deserialize<'de> for Seq<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Seq<T, N>, D::Error> {
+        deserializer.deserialize_seq(SeqVisitor::<T, N>(core::marker::PhantomData))
+    }
+}
+
+struct SeqVisitor<T, const N: usize>(core::marker::PhantomData<T>);
+
+impl<'de, T: Deserialize<'de> + Copy, const N: usize> Visitor<'de> for SeqVisitor<T, N> {
+    type Value = Seq<T, N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a sequence of at most {N} elements")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Seq<T, N>, A::Error> {
+        let mut items = Seq {
+            items: [None; N],
+            len: 0,
+        };
+        while let Some(item) = seq.next_element()? {
+            if items.len == N {
+                return Err(de::Error::invalid_length(N + 1, &self));
+            }
+            items.items[items.len] = Some(item);
+            items.len += 1;
+        }
+        Ok(items)
+    }
+}
@@ -0,0 +1,258 @@
+//! Snapshots of the authenticated navigation data.
+//!
+//! This module contains the [`AuthenticatedSnapshot`] struct, which is an owned
+//! copy of all the authenticated navigation data held by the
+//! [`Osnma`](crate::Osnma) black box. It is obtained with
+//! [`Osnma::snapshot`](crate::Osnma::snapshot).
+//!
+//! Each piece of navigation data in a snapshot carries a generation
+//! counter. The counter of a satellite and type of data increments when its
+//! navigation data has changed since the previous snapshot, so a user that
+//! polls the black box periodically can find the data that is new since the
+//! last poll by comparing the generation counters of two snapshots.
+
+use crate::navmessage::{
+    CollectNavMessage, NavMessageData, OwnedNavMessageData, CED_AND_STATUS_BYTES,
+    TIMING_PARAMETERS_BYTES,
+};
+use crate::storage::StaticStorage;
+use crate::types::NUM_SVNS;
+use crate::Svn;
+
+/// Snapshot of the authenticated navigation data.
+///
+/// This contains, for each SVN, a copy of the most recent authenticated CED and
+/// health status data and timing parameters, if they are available.
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::{Osnma, PublicKey, Svn};
+/// use galileo_osnma::storage::FullStorage;
+/// use p256::ecdsa::VerifyingKey;
+///
+/// let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+///               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+///               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+/// let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+/// let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+/// let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+///
+/// let snapshot = osnma.snapshot();
+/// for svn in Svn::iter() {
+///     if let Some(ced) = snapshot.ced_and_status(svn) {
+///         println!(
+///             "{svn}: generation {}, authbits {}",
+///             ced.generation(),
+///             ced.data().authbits()
+///         );
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AuthenticatedSnapshot {
+    ced_and_status: [Option<SnapshotEntry>; NUM_SVNS],
+    timing_parameters: [Option<SnapshotEntry>; NUM_SVNS],
+}
+
+/// Authenticated navigation data in a snapshot.
+///
+/// This contains a copy of a piece of authenticated navigation data and its
+/// generation counter.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotEntry {
+    data: OwnedNavMessageData,
+    generation: u32,
+}
+
+impl AuthenticatedSnapshot {
+    // Snapshot that does not contain any data.
+    pub(crate) fn empty() -> AuthenticatedSnapshot {
+        AuthenticatedSnapshot {
+            ced_and_status: [None; NUM_SVNS],
+            timing_parameters: [None; NUM_SVNS],
+        }
+    }
+
+    /// Returns the authenticated CED and health status data for a satellite.
+    ///
+    /// This is the data that [`Osnma::get_ced_and_status`](crate::Osnma::get_ced_and_status)
+    /// returned when the snapshot was taken.
+    pub fn ced_and_status(&self, svn: Svn) -> Option<&SnapshotEntry> {
+        self.ced_and_status[usize::from(svn) - 1].as_ref()
+    }
+
+    /// Returns the authenticated timing parameters for a satellite.
+    ///
+    /// This is the data that
+    /// [`Osnma::get_timing_parameters`](crate::Osnma::get_timing_parameters)
+    /// returned when the snapshot was taken.
+    pub fn timing_parameters(&self, svn: Svn) -> Option<&SnapshotEntry> {
+        self.timing_parameters[usize::from(svn) - 1].as_ref()
+    }
+}
+
+impl SnapshotEntry {
+    /// Returns the authenticated navigation data.
+    pub fn data(&self) -> &OwnedNavMessageData {
+        &self.data
+    }
+
+    /// Returns the generation counter of the navigation data.
+    ///
+    /// The counter increments when the navigation data of this satellite is
+    /// different from the data in the previous snapshot that contained data
+    /// for this satellite. It does not change when only the number of
+    /// authentication bits or the GST of the data change. Therefore, if the
+    /// generation counter is the same as in a previous snapshot, the
+    /// navigation data is also the same. The counter wraps around on overflow.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// Generation counters of the authenticated navigation data.
+//
+// The counters are updated when a snapshot is taken. The bytes of the data of
+// each SVN in the last snapshot are kept to detect changes.
+#[derive(Debug, Clone)]
+pub(crate) struct Generations {
+    ced_and_status: [Generation<CED_AND_STATUS_BYTES>; NUM_SVNS],
+    timing_parameters: [Generation<TIMING_PARAMETERS_BYTES>; NUM_SVNS],
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Generation<const N: usize> {
+    data: Option<[u8; N]>,
+    count: u32,
+}
+
+impl<const N: usize> Generation<N> {
+    fn new() -> Generation<N> {
+        Generation {
+            data: None,
+            count: 0,
+        }
+    }
+
+    fn update(&mut self, data: Option<NavMessageData<'_>>) -> Option<SnapshotEntry> {
+        // Data that stops being available is not considered a change, since
+        // the user still holds the last authenticated data.
+        let data = OwnedNavMessageData::from(data?);
+        // Each type of navigation data always has the same length, so it is
+        // enough to compare its bytes.
+        let bytes = data.data_bytes();
+        if self.data.as_ref().map(|d| &d[..]) != Some(bytes) {
+            if self.data.is_some() {
+                self.count = self.count.wrapping_add(1);
+            }
+            self.data = bytes.try_into().ok();
+        }
+        Some(SnapshotEntry {
+            data,
+            generation: self.count,
+        })
+    }
+}
+
+impl Generations {
+    pub(crate) fn new() -> Generations {
+        Generations {
+            ced_and_status: [Generation::new(); NUM_SVNS],
+            timing_parameters: [Generation::new(); NUM_SVNS],
+        }
+    }
+
+    // Updates the generation counters with the current navigation data and
+    // returns a snapshot of it.
+    pub(crate) fn snapshot<S: StaticStorage>(
+        &mut self,
+        navmessage: &CollectNavMessage<S>,
+    ) -> AuthenticatedSnapshot {
+        let mut snapshot = AuthenticatedSnapshot::empty();
+        for svn in Svn::iter() {
+            let idx = usize::from(svn) - 1;
+            snapshot.ced_and_status[idx] =
+                self.ced_and_status[idx].update(navmessage.get_ced_and_status(svn));
+            snapshot.timing_parameters[idx] =
+                self.timing_parameters[idx].update(navmessage.get_timing_parameters(svn));
+        }
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::navmessage::test::{authenticate_all, inav_word};
+    use crate::storage::FullStorage;
+    use crate::{Gst, InavBand};
+
+    fn feed_ced(navmessage: &mut CollectNavMessage<FullStorage>, svn: Svn, gst: Gst, fill: u8) {
+        for word_type in 1..=5 {
+            navmessage.feed(&inav_word(word_type, 1, fill), svn, gst, InavBand::E1B);
+        }
+    }
+
+    #[test]
+    fn generation_counters() {
+        let svn = Svn::try_from(11).unwrap();
+        let other_svn = Svn::try_from(12).unwrap();
+        let gst = Gst::new(1177, 175740);
+        let mut navmessage = CollectNavMessage::<FullStorage>::new();
+        let mut generations = Generations::new();
+        assert_eq!(
+            generations.snapshot(&navmessage),
+            AuthenticatedSnapshot::empty()
+        );
+
+        feed_ced(&mut navmessage, svn, gst, 0xaa);
+        authenticate_all(&mut navmessage);
+        let snapshot = generations.snapshot(&navmessage);
+        let entry = snapshot.ced_and_status(svn).unwrap();
+        assert_eq!(entry.generation(), 0);
+        let authbits = entry.data().authbits();
+        assert!(snapshot.ced_and_status(other_svn).is_none());
+        assert!(snapshot.timing_parameters(svn).is_none());
+
+        // More authentication bits for the same data do not change the
+        // generation.
+        authenticate_all(&mut navmessage);
+        let snapshot = generations.snapshot(&navmessage);
+        let entry = snapshot.ced_and_status(svn).unwrap();
+        assert_eq!(entry.generation(), 0);
+        assert!(entry.data().authbits() > authbits);
+
+        // Nor does the same data broadcast in a later subframe.
+        let gst = gst.add_seconds(30);
+        feed_ced(&mut navmessage, svn, gst, 0xaa);
+        authenticate_all(&mut navmessage);
+        let entry = *generations
+            .snapshot(&navmessage)
+            .ced_and_status(svn)
+            .unwrap();
+        assert_eq!(entry.generation(), 0);
+        assert_eq!(entry.data().gst(), gst);
+
+        // New data increments the generation, which is independent for each
+        // SVN.
+        let gst = gst.add_seconds(30);
+        feed_ced(&mut navmessage, svn, gst, 0x55);
+        feed_ced(&mut navmessage, other_svn, gst, 0x55);
+        authenticate_all(&mut navmessage);
+        let snapshot = generations.snapshot(&navmessage);
+        let entry = snapshot.ced_and_status(svn).unwrap();
+        assert_eq!(entry.generation(), 1);
+        assert_eq!(entry.data().gst(), gst);
+        assert_eq!(snapshot.ced_and_status(other_svn).unwrap().generation(), 0);
+        assert_eq!(
+            generations
+                .snapshot(&navmessage)
+                .ced_and_status(svn)
+                .unwrap()
+                .generation(),
+            1
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! OSNMA receiver state.
+//!
+//! This module contains the [`OsnmaState`] enum, which represents the state of
+//! the start-up and operation of OSNMA as described in the
+//! [OSNMA Receiver Guidelines v1.3](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_Receiver_Guidelines_v1.3.pdf),
+//! and the [`StateTransition`] struct, which records a change of state and the
+//! reason for it. The state of the [`Osnma`](crate::Osnma) black box is
+//! obtained with [`Osnma::state`](crate::Osnma::state).
+
+use crate::Gst;
+use core::fmt;
+
+/// State of the OSNMA receiver.
+///
+/// The start-up states depend on the cryptographic material available. A
+/// receiver without a public key does a cold start, a receiver with a public
+/// key does a warm start, and a receiver with a TESLA key from a previous
+/// session does a hot start. When the start-up finishes, the receiver becomes
+/// operational.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OsnmaState {
+    /// Cold start.
+    ///
+    /// No public key is available. The receiver waits for a DSM-PKR that can
+    /// be verified with the Merkle tree root.
+    ColdStart,
+    /// Warm start.
+    ///
+    /// A public key is available, but there is no TESLA key. The receiver
+    /// waits for a DSM-KROOT that can be verified with the public key.
+    WarmStart,
+    /// Hot start.
+    ///
+    /// A TESLA key from a previous session has been loaded. The receiver waits
+    /// for a TESLA key in a MACK message that can be verified with it.
+    HotStart,
+    /// Start failed.
+    ///
+    /// The verification of a DSM-PKR or DSM-KROOT failed during the start-up.
+    /// The receiver keeps trying to verify the following DSM-PKRs and
+    /// DSM-KROOTs.
+    StartFailed,
+    /// Operational.
+    ///
+    /// The TESLA chain in force has been verified, and navigation data can be
+    /// authenticated.
+    Operational,
+    /// Don't use.
+    ///
+    /// The NMA status in the last verified NMA header is "don't use" (or a
+    /// reserved value). Authenticated navigation data must not be used.
+    DontUse,
+    /// Alert.
+    ///
+    /// An OSNMA Alert Message has been received, and all the cryptographic
+    /// material has been deleted. Authenticated navigation data must not be
+    /// used. The receiver cannot leave this state.
+    Alert,
+}
+
+impl OsnmaState {
+    /// Returns `true` if the state is one of the start-up states.
+    ///
+    /// These are [`OsnmaState::ColdStart`], [`OsnmaState::WarmStart`],
+    /// [`OsnmaState::HotStart`] and [`OsnmaState::StartFailed`].
+    pub fn is_start_up(&self) -> bool {
+        matches!(
+            self,
+            OsnmaState::ColdStart
+                | OsnmaState::WarmStart
+                | OsnmaState::HotStart
+                | OsnmaState::StartFailed
+        )
+    }
+
+    /// Returns `true` if authenticated navigation data can be used in this
+    /// state.
+    ///
+    /// Navigation data must not be used in the [`OsnmaState::DontUse`] and
+    /// [`OsnmaState::Alert`] states.
+    pub fn navigation_data_usable(&self) -> bool {
+        !matches!(self, OsnmaState::DontUse | OsnmaState::Alert)
+    }
+}
+
+impl fmt::Display for OsnmaState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OsnmaState::ColdStart => "cold start".fmt(f),
+            OsnmaState::WarmStart => "warm start".fmt(f),
+            OsnmaState::HotStart => "hot start".fmt(f),
+            OsnmaState::StartFailed => "start failed".fmt(f),
+            OsnmaState::Operational => "operational".fmt(f),
+            OsnmaState::DontUse => "don't use".fmt(f),
+            OsnmaState::Alert => "alert".fmt(f),
+        }
+    }
+}
+
+/// Reason for a state transition.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransitionReason {
+    /// A TESLA key from a previous session has been loaded.
+    TeslaKeyLoaded,
+    /// A public key has been verified using a DSM-PKR.
+    PublicKeyVerified,
+    /// The verification of a DSM-PKR failed.
+    PublicKeyVerificationFailed,
+    /// A DSM-KROOT has been verified, and its NMA header has an operational or
+    /// test NMA status.
+    KrootVerified,
+    /// The verification of a DSM-KROOT failed.
+    KrootVerificationFailed,
+    /// A TESLA key in a MACK message has been verified.
+    TeslaKeyVerified,
+    /// A TESLA key in a MACK message could not be verified with the TESLA key
+    /// loaded for the hot start.
+    TeslaKeyVerificationFailed,
+    /// The NMA status in a verified NMA header is "don't use" or a reserved
+    /// value.
+    NmaStatusDontUse,
+    /// A verified NMA header indicates that the TESLA chain has been revoked.
+    ChainRevoked,
+    /// A verified NMA header indicates that the public key has been revoked.
+    PublicKeyRevoked,
+    /// An OSNMA Alert Message has been received, either in the CPKS field of
+    /// a verified NMA header or in a verified DSM-PKR.
+    AlertMessage,
+}
+
+impl fmt::Display for TransitionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransitionReason::TeslaKeyLoaded => "TESLA key loaded".fmt(f),
+            TransitionReason::PublicKeyVerified => "public key verified".fmt(f),
+            TransitionReason::PublicKeyVerificationFailed => {
+                "public key verification failed".fmt(f)
+            }
+            TransitionReason::KrootVerified => "KROOT verified".fmt(f),
+            TransitionReason::KrootVerificationFailed => "KROOT verification failed".fmt(f),
+            TransitionReason::TeslaKeyVerified => "TESLA key verified".fmt(f),
+            TransitionReason::TeslaKeyVerificationFailed => "TESLA key verification failed".fmt(f),
+            TransitionReason::NmaStatusDontUse => "NMA status is don't use".fmt(f),
+            TransitionReason::ChainRevoked => "chain revoked".fmt(f),
+            TransitionReason::PublicKeyRevoked => "public key revoked".fmt(f),
+            TransitionReason::AlertMessage => "alert message".fmt(f),
+        }
+    }
+}
+
+/// State transition.
+///
+/// This records a change of the [`OsnmaState`], the reason why it happened,
+/// and the GST of the subframe in which it happened. The last transition is
+/// obtained with
+/// [`Osnma::last_state_transition`](crate::Osnma::last_state_transition).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateTransition {
+    /// State before the transition.
+    pub from: OsnmaState,
+    /// State after the transition.
+    pub to: OsnmaState,
+    /// Reason for the transition.
+    pub reason: TransitionReason,
+    /// GST of the subframe in which the transition happened.
+    pub gst: Gst,
+}
+
+impl fmt::Display for StateTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {} ({})", self.from, self.to, self.reason)
+    }
+}
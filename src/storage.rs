@@ -22,6 +22,8 @@
 //! Users can define additional storage sizes by implementing the [`StaticStorage`]
 //! trait on their own types.
 
+use crate::types::NUM_SVNS;
+use crate::{Gst, Svn};
 use generic_array::ArrayLength;
 
 /// Auxiliary trait for generic array sizes.
@@ -129,3 +131,52 @@ impl StaticStorage for SmallStorage {
     type MackDepth = typenum::U2;
     type MackDepthSats = typenum::U24;
 }
+
+// Per-row index used by the ring buffers in MackStorage and
+// CollectNavMessage. For each SVN, it gives the position inside a row of
+// NUM_SATS elements where the data for that SVN is stored, so that lookups
+// do not need to scan the whole row.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct SvnIndex([Option<u8>; NUM_SVNS]);
+
+impl SvnIndex {
+    pub(crate) fn get(&self, svn: Svn) -> Option<usize> {
+        self.0[usize::from(svn) - 1].map(usize::from)
+    }
+
+    pub(crate) fn set(&mut self, svn: Svn, slot: usize) {
+        self.0[usize::from(svn) - 1] = Some(slot.try_into().unwrap());
+    }
+
+    pub(crate) fn clear(&mut self, svn: Svn) {
+        self.0[usize::from(svn) - 1] = None;
+    }
+}
+
+impl Default for SvnIndex {
+    fn default() -> SvnIndex {
+        SvnIndex([None; NUM_SVNS])
+    }
+}
+
+// Finds the row of a ring buffer that holds the data for `gst`.
+//
+// The rows of the ring buffers are written in chronological order, so in the
+// usual case where no subframes have been skipped, the row can be computed
+// directly from the distance in subframes to the GST at the write
+// pointer. If this guess is not correct (because there are gaps in the
+// data), all the rows are searched.
+pub(crate) fn find_gst_row(gsts: &[Option<Gst>], write_pointer: usize, gst: Gst) -> Option<usize> {
+    let depth = gsts.len();
+    if let Some(newest) = gsts[write_pointer] {
+        if let Ok(delta) = usize::try_from(newest.subframes_difference(gst)) {
+            if delta < depth {
+                let row = (depth + write_pointer - delta) % depth;
+                if gsts[row] == Some(gst) {
+                    return Some(row);
+                }
+            }
+        }
+    }
+    gsts.iter().position(|&g| g == Some(gst))
+}
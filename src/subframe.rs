@@ -7,6 +7,7 @@
 //! The data for the 36 satellites in the Galileo constellation is collected in
 //! parallel.
 
+use crate::logging::Hex;
 use crate::types::{
     HkrootMessage, HkrootSection, MackMessage, MackSection, OsnmaDataMessage, HKROOT_MESSAGE_BYTES,
     HKROOT_SECTION_BYTES, MACK_MESSAGE_BYTES, MACK_SECTION_BYTES, NUM_SVNS,
@@ -65,18 +66,18 @@ impl CollectSubframe {
         let hkroot_section: HkrootSection = osnma_data[..HKROOT_SECTION_BYTES].try_into().unwrap();
         let mack_section: MackSection = osnma_data[HKROOT_SECTION_BYTES..].try_into().unwrap();
         let word_num = (gst.tow() / 2) % Tow::from(WORDS_PER_SUBFRAME);
-        log::trace!(
-            "feeding hkroot = {:02x?}, mack = {:02x?} for {} (GST = {:?}, word number = {})",
-            hkroot_section,
-            mack_section,
-            svn,
-            gst,
-            word_num
+        trace!(
+            "feeding OSNMA data";
+            hkroot = Hex(&hkroot_section),
+            mack = Hex(&mack_section),
+            svn = %svn,
+            gst = gst,
+            word_num = word_num,
         );
         let subframe = gst.tow() / SECONDS_PER_SUBFRAME;
         if gst.wn() != self.wn || subframe != self.subframe {
-            log::debug!("valid sections per SVN: {:?}", &self.num_valid);
-            log::info!("starting collection of new subframe (GST {:?})", gst);
+            debug!("valid sections per SVN"; num_valid = self.num_valid);
+            info!("starting collection of new subframe"; gst = gst);
             self.wn = gst.wn();
             self.subframe = subframe;
             for s in 0..NUM_SVNS {
@@ -85,13 +86,12 @@ impl CollectSubframe {
         }
         let svn_idx = usize::from(svn) - 1;
         if word_num != u32::from(self.num_valid[svn_idx]) {
-            log::trace!(
-                "there are missing words for {} (GST {:?}), \
-                 word number = {}, valid words = {}",
-                svn,
-                gst,
-                word_num,
-                self.num_valid[svn_idx]
+            trace!(
+                "there are missing words";
+                svn = %svn,
+                gst = gst,
+                word_num = word_num,
+                valid_words = self.num_valid[svn_idx],
             );
             return None;
         }
@@ -103,13 +103,12 @@ impl CollectSubframe {
         self.mack[svn_idx][mack_idx..mack_idx + MACK_SECTION_BYTES].copy_from_slice(&mack_section);
         self.num_valid[svn_idx] += 1;
         if self.num_valid[svn_idx] == WORDS_PER_SUBFRAME {
-            log::trace!(
-                "completed collection for {} (GST {:?})\n\
-                 hkroot = {:02x?}\nmack = {:02x?}",
-                svn,
-                gst,
-                self.hkroot[svn_idx],
-                self.mack[svn_idx],
+            trace!(
+                "completed collection";
+                svn = %svn,
+                gst = gst,
+                hkroot = Hex(&self.hkroot[svn_idx]),
+                mack = Hex(&self.mack[svn_idx]),
             );
             Some((
                 &self.hkroot[svn_idx],
@@ -23,6 +23,11 @@ use core::num::NonZeroU8;
 /// assert_eq!(format!("{}", svn), "E24");
 /// ```
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
 pub struct Svn(NonZeroU8);
 
 impl Svn {
@@ -85,6 +90,17 @@ impl fmt::Display for Svn {
     }
 }
 
+/// Formats an SVN as Exx.
+///
+/// This is the [`defmt`](https://docs.rs/defmt/latest/defmt/) counterpart of
+/// the [`Display`](fmt::Display) implementation.
+#[cfg(feature = "defmt")]
+impl defmt::Format for Svn {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "E{=u8:02}", self.0.get())
+    }
+}
+
 /// SVN construction error.
 ///
 /// The construction of an [`Svn`] can only fail if the given SVN value is
@@ -131,4 +147,14 @@ mod test {
         }
         assert_eq!(n, 36);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let svn = Svn::try_from(11).unwrap();
+        assert_eq!(serde_json::to_string(&svn).unwrap(), "11");
+        assert_eq!(serde_json::from_str::<Svn>("11").unwrap(), svn);
+        assert!(serde_json::from_str::<Svn>("0").is_err());
+        assert!(serde_json::from_str::<Svn>("37").is_err());
+    }
 }
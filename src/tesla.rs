@@ -9,7 +9,11 @@
 use crate::bitfields::{
     self, ChainAndPubkeyStatus, DsmKroot, EcdsaFunction, Mack, NmaStatus, Prnd, TagAndInfo,
 };
-use crate::maclt::{get_flx_indices, get_maclt_entry, AuthObject, MacLTError, MacLTSlot};
+use crate::gst::SECS_IN_WEEK;
+use crate::maclt::{
+    get_maclt_entry, AuthObject, MacLTEntry, MacLTError, MacLTRegistry, MacLTSlot, MAX_FLX_ENTRIES,
+};
+use crate::trace::{MacseqTrace, TagTrace};
 use crate::types::{BitSlice, VerifyingKey, NUM_SVNS};
 use crate::validation::{NotValidated, Validated};
 use crate::{Gst, PublicKey, Svn, Tow};
@@ -26,12 +30,19 @@ use sha2::{
 use sha3::Sha3_256;
 
 const MAX_KEY_BYTES: usize = 32;
+// CMAC-AES uses AES-128
+const CMAC_AES_KEY_SIZE_BYTES: usize = 16;
 
 /// TESLA chain parameters.
 ///
 /// This struct stores the parameters of a TESLA chain. It is typically
 /// constructed from a DSK-KROOT message using [`Chain::from_dsm_kroot`].
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SerdeChain")
+)]
 pub struct Chain {
     id: u8,
     hash_function: HashFunction,
@@ -42,12 +53,57 @@ pub struct Chain {
     alpha: u64,
 }
 
+// Unchecked chain parameters used for deserialization. The key and tag sizes
+// are checked against the values allowed in the DSM-KROOT when converting to a
+// Chain, since other sizes would cause panics.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Chain")]
+struct SerdeChain {
+    id: u8,
+    hash_function: HashFunction,
+    mac_function: MacFunction,
+    key_size_bytes: usize,
+    tag_size_bits: usize,
+    maclt: u8,
+    alpha: u64,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeChain> for Chain {
+    type Error = &'static str;
+
+    fn try_from(chain: SerdeChain) -> Result<Chain, &'static str> {
+        if ![12, 13, 14, 15, 16, 20, 24, 28, 32].contains(&chain.key_size_bytes) {
+            return Err("invalid TESLA key size");
+        }
+        if ![20, 24, 28, 32, 40].contains(&chain.tag_size_bits) {
+            return Err("invalid tag size");
+        }
+        if chain.mac_function == MacFunction::CmacAes
+            && chain.key_size_bytes != CMAC_AES_KEY_SIZE_BYTES
+        {
+            return Err("invalid TESLA key size for CMAC-AES");
+        }
+        Ok(Chain {
+            id: chain.id,
+            hash_function: chain.hash_function,
+            mac_function: chain.mac_function,
+            key_size_bytes: chain.key_size_bytes,
+            tag_size_bits: chain.tag_size_bits,
+            maclt: chain.maclt,
+            alpha: chain.alpha,
+        })
+    }
+}
+
 /// Hash function.
 ///
 /// This gives the hash function used by the TESLA chain. Its values correspond
 /// to those of [`bitfields::HashFunction`],
 /// minus the reserved value.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HashFunction {
     /// SHA-256.
     Sha256,
@@ -61,6 +117,7 @@ pub enum HashFunction {
 /// to those of [`bitfields::MacFunction`],
 /// minus the reserved value.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MacFunction {
     /// HMAC-SHA-256.
     HmacSha256,
@@ -84,14 +141,12 @@ impl Chain {
             bitfields::MacFunction::CmacAes => MacFunction::CmacAes,
             bitfields::MacFunction::Reserved => return Err(ChainError::ReservedField),
         };
-        let key_size_bytes = match dsm_kroot.key_size() {
-            Some(s) => {
-                assert!(s % 8 == 0);
-                s / 8
-            }
-            None => return Err(ChainError::ReservedField),
-        };
+        // all the key sizes are a multiple of 8 bits
+        let key_size_bytes = dsm_kroot.key_size().ok_or(ChainError::ReservedField)? / 8;
         let tag_size_bits = dsm_kroot.tag_size().ok_or(ChainError::ReservedField)?;
+        if mac_function == MacFunction::CmacAes && key_size_bytes != CMAC_AES_KEY_SIZE_BYTES {
+            return Err(ChainError::WrongCmacKeySize);
+        }
         Ok(Chain {
             id: dsm_kroot.kroot_chain_id(),
             hash_function,
@@ -165,6 +220,9 @@ impl Chain {
     /// `gst_tag` is the GST at the start of the subframe when the tag was
     /// transmitted.
     ///
+    /// Only the MAC look-up tables built into the crate are used. See
+    /// [`Chain::validate_adkd_with_registry`] to use a [`MacLTRegistry`].
+    ///
     /// # Panics
     ///
     /// Panics if `num_tag` is zero.
@@ -177,12 +235,50 @@ impl Chain {
     ) -> Result<(), AdkdCheckError> {
         // Half of the GST minute
         let msg = usize::try_from((gst_tag.tow() / 30) % 2).unwrap();
-        match get_maclt_entry(self.maclt, msg, num_tag)? {
+        let slot = get_maclt_entry(self.maclt, msg, num_tag)?;
+        Self::check_adkd_slot(slot, tag, prna)
+    }
+
+    /// Try to validate the ADKD field of a Tag-Info section using a MAC
+    /// look-up table registry.
+    ///
+    /// This works as [`Chain::validate_adkd`], but the MAC look-up table of the
+    /// chain is looked up in `registry`, so tables added at runtime can be
+    /// used. The `num_tags` parameter gives the number of tags in the MACK
+    /// message, as returned by
+    /// [`Mack::num_tags`](crate::bitfields::Mack::num_tags). An error is
+    /// returned if it does not match the number of tags 'nt' of the MAC
+    /// look-up table entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_tag` is zero.
+    pub fn validate_adkd_with_registry<V, const N: usize>(
+        &self,
+        num_tag: usize,
+        num_tags: usize,
+        tag: TagAndInfo<V>,
+        prna: Svn,
+        gst_tag: Gst,
+        registry: &MacLTRegistry<N>,
+    ) -> Result<(), AdkdCheckError> {
+        // Half of the GST minute
+        let msg = usize::try_from((gst_tag.tow() / 30) % 2).unwrap();
+        let slot = mack_maclt_entry(registry, self.maclt, num_tags)?.slot(msg, num_tag)?;
+        Self::check_adkd_slot(slot, tag, prna)
+    }
+
+    fn check_adkd_slot<V>(
+        slot: MacLTSlot,
+        tag: TagAndInfo<V>,
+        prna: Svn,
+    ) -> Result<(), AdkdCheckError> {
+        match slot {
             MacLTSlot::Fixed { adkd, object } => {
                 if tag.adkd() != adkd {
                     Err(AdkdCheckError::WrongAdkd)
                 } else if let Prnd::GalileoSvid(prnd) = tag.prnd() {
-                    if object == AuthObject::SelfAuth && prnd != prna.into() {
+                    if object == AuthObject::SelfAuth && prnd != u8::from(prna) {
                         Err(AdkdCheckError::WrongPrnd)
                     } else if (1..=NUM_SVNS).contains(&prnd.into()) {
                         Ok(())
@@ -202,21 +298,40 @@ impl Chain {
     }
 }
 
+// Looks up the MAC look-up table entry used by a MACK message with `num_tags`
+// tags. An error is returned if the number of tags of the entry does not match,
+// since the entry cannot be applied to the MACK message.
+fn mack_maclt_entry<const N: usize>(
+    registry: &MacLTRegistry<N>,
+    maclt: u8,
+    num_tags: usize,
+) -> Result<&MacLTEntry, MacLTError> {
+    let entry = registry.get(maclt).ok_or(MacLTError::InvalidMaclt)?;
+    if entry.nt() != num_tags {
+        return Err(MacLTError::WrongNumberOfTags);
+    }
+    Ok(entry)
+}
+
 /// Errors produced during the extraction of the chain parameters.
 ///
 /// This gives the errors that can happen during the extraction of the TESLA
 /// chain parameters from the DSM-KROOT message.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ChainError {
     /// One of the fields holding information about the TESLA chain has a
     /// reserved value.
     ReservedField,
+    /// The MAC function is CMAC-AES, but the key size is not 128 bits.
+    WrongCmacKeySize,
 }
 
 impl fmt::Display for ChainError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ChainError::ReservedField => "reserved value present in some field".fmt(f),
+            ChainError::WrongCmacKeySize => "key size not supported by CMAC-AES".fmt(f),
         }
     }
 }
@@ -229,6 +344,7 @@ impl std::error::Error for ChainError {}
 /// This gives the errors that can happen during the validation of an ADKD field
 /// using [`Chain::validate_adkd`].
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum AdkdCheckError {
     /// MAC Look-up Table error.
     MacLTError(MacLTError),
@@ -304,7 +420,7 @@ impl FixedOutput for HashDigest {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
 enum MacDigest {
     HmacSha256(Hmac<Sha256>),
@@ -446,6 +562,10 @@ impl<V> fmt::Debug for NmaHeader<V> {
 /// public key using the DSM-KROOT signature and TELA key derivations.  See
 /// [validation](crate::validation) for a description of validation type
 /// parameters.
+///
+/// The methods of a validated key that validate or compute tags and MACSEQs
+/// initialize a MAC context with the key on each call. A [`MacKey`] can be used
+/// to initialize the MAC context only once and use it for several tags.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Key<V> {
     data: [u8; MAX_KEY_BYTES],
@@ -459,6 +579,7 @@ pub struct Key<V> {
 /// This gives the errors that can happen during the validation of TESLA key
 /// using another, already validated TESLA key, and [`Key::validate_key`].
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ValidationError {
     /// The key obtained via one-way function applications differs from the
     /// expected key.
@@ -505,6 +626,14 @@ impl<V> Key<V> {
         &self.chain
     }
 
+    /// Gives the key data.
+    ///
+    /// The length of the returned slice is the key size of the chain that the
+    /// key belongs to.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.chain.key_size_bytes]
+    }
+
     fn store_gst(buffer: &mut [u8], gst: Gst) {
         let bits = BitSlice::from_slice_mut(buffer);
         bits[0..12].store_be(gst.wn());
@@ -557,7 +686,13 @@ impl Key<NotValidated> {
 }
 
 impl<V> Key<V> {
-    fn force_valid(self) -> Key<Validated> {
+    /// Forces the key validation state to [`Validated`].
+    ///
+    /// This function should only be called if the key is known to be valid,
+    /// because it has been verified externally or because it has been
+    /// generated locally (for instance, to build a TESLA chain in order to
+    /// generate OSNMA messages).
+    pub fn force_valid(self) -> Key<Validated> {
         Key {
             data: self.data,
             chain: self.chain,
@@ -595,10 +730,16 @@ impl Key<Validated> {
     ) -> Result<(Key<Validated>, NmaHeader<Validated>), KrootValidationError> {
         let chain =
             Chain::from_dsm_kroot(dsm_kroot).map_err(KrootValidationError::WrongDsmKrootChain)?;
+        let ecdsa_function = dsm_kroot
+            .try_ecdsa_function()
+            .ok_or(KrootValidationError::WrongDsmKrootSize)?;
+        let kroot = dsm_kroot
+            .try_kroot()
+            .ok_or(KrootValidationError::WrongDsmKrootSize)?;
         if !dsm_kroot.check_padding(nma_header) {
             return Err(KrootValidationError::WrongDsmKrootPadding);
         }
-        match (pubkey.verifying_key(), dsm_kroot.ecdsa_function()) {
+        match (pubkey.verifying_key(), ecdsa_function) {
             (VerifyingKey::P256(pubkey), EcdsaFunction::P256Sha256) => {
                 if !dsm_kroot.check_signature_p256(nma_header, pubkey) {
                     return Err(KrootValidationError::WrongEcdsa);
@@ -614,11 +755,14 @@ impl Key<Validated> {
         }
         let wn = dsm_kroot.kroot_wn();
         let tow = Tow::from(dsm_kroot.kroot_towh()) * 3600;
-        let gst = Gst::new(wn, tow);
-        Self::check_gst(gst);
-        let gst = gst.add_seconds(-30);
+        // The KROOT applies to the subframe before WNK and TOWHK, so this
+        // cannot be the start of GST.
+        if tow >= SECS_IN_WEEK || (wn == 0 && tow == 0) {
+            return Err(KrootValidationError::WrongKrootTime);
+        }
+        let gst = Gst::new(wn, tow).add_seconds(-30);
         Ok((
-            Key::from_slice(dsm_kroot.kroot(), gst, &chain).force_valid(),
+            Key::from_slice(kroot, gst, &chain).force_valid(),
             nma_header.force_valid(),
         ))
     }
@@ -630,6 +774,7 @@ impl Key<Validated> {
 /// This gives the errors that can happen during the extraction of the TESLA
 /// root key using [`Key::from_dsm_kroot`].
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum KrootValidationError {
     /// A valid chain could not be extracted from the DSM-KROOT message.
     ///
@@ -643,6 +788,11 @@ pub enum KrootValidationError {
     /// The type of the ECDSA key does not match the ECDSA algorithm used in the
     /// DSM-KROOT message.
     WrongEcdsaKeyType,
+    /// The size of the DSM-KROOT message does not match the size of the
+    /// KROOT and of any of the ECDSA signatures.
+    WrongDsmKrootSize,
+    /// The WNK and TOWHK fields do not contain a valid GST.
+    WrongKrootTime,
 }
 
 impl fmt::Display for KrootValidationError {
@@ -656,6 +806,8 @@ impl fmt::Display for KrootValidationError {
             KrootValidationError::WrongEcdsaKeyType => {
                 "ECDSA key type does not match DSM-KROOT".fmt(f)
             }
+            KrootValidationError::WrongDsmKrootSize => "incorrect DSM-KROOT size".fmt(f),
+            KrootValidationError::WrongKrootTime => "invalid KROOT time in DSM-KROOT".fmt(f),
         }
     }
 }
@@ -667,7 +819,9 @@ impl std::error::Error for KrootValidationError {
             KrootValidationError::WrongDsmKrootChain(e) => Some(e),
             KrootValidationError::WrongDsmKrootPadding
             | KrootValidationError::WrongEcdsa
-            | KrootValidationError::WrongEcdsaKeyType => None,
+            | KrootValidationError::WrongEcdsaKeyType
+            | KrootValidationError::WrongDsmKrootSize
+            | KrootValidationError::WrongKrootTime => None,
         }
     }
 }
@@ -679,41 +833,270 @@ impl<V: Clone> Key<V> {
     /// chain. The validation status of the returned key is inherited from the
     /// validation status of `self`.
     pub fn one_way_function(&self) -> Key<V> {
-        let mut hash = self.hash_digest();
-        let size = self.chain.key_size_bytes;
-        hash.update(&self.data[..size]);
-        let mut gst = [0; 4];
-        let previous_subframe = self.gst_subframe.add_seconds(-30);
-        Self::store_gst(&mut gst, previous_subframe);
-        hash.update(&gst);
-        hash.update(&self.chain.alpha.to_be_bytes()[2..]);
+        self.derive(1)
+    }
+
+    /// Derives a TESLA key by applying the one-way function `num_derivations` times.
+    ///
+    /// This gives the TESLA key that comes `num_derivations` subframes earlier
+    /// in the TESLA chain. The validation status of the returned key is
+    /// inherited from the validation status of `self`.
+    pub fn derive(&self, num_derivations: usize) -> Key<V> {
+        if num_derivations == 0 {
+            return self.clone();
+        }
+        let mut data = self.data;
+        let mut gst = self.gst_subframe;
+        for _ in 0..num_derivations {
+            gst = gst.add_seconds(-30);
+            data = Self::one_way_function_data(&data, gst, &self.chain);
+        }
+        Key {
+            data,
+            chain: self.chain,
+            gst_subframe: gst,
+            _validated: self._validated.clone(),
+        }
+    }
+
+    // Computes the one-way function on the key data. The gst parameter is
+    // the GST of the resulting (previous) key.
+    fn one_way_function_data(
+        data: &[u8; MAX_KEY_BYTES],
+        gst: Gst,
+        chain: &Chain,
+    ) -> [u8; MAX_KEY_BYTES] {
+        let mut hash = HashDigest::new(chain.hash_function);
+        let size = chain.key_size_bytes;
+        hash.update(&data[..size]);
+        let mut gst_bytes = [0; 4];
+        Self::store_gst(&mut gst_bytes, gst);
+        hash.update(&gst_bytes);
+        hash.update(&chain.alpha.to_be_bytes()[2..]);
         let mut hash_out = GenericArray::default();
         hash.finalize_into(&mut hash_out);
         let mut new_key = [0; MAX_KEY_BYTES];
         new_key[..size].copy_from_slice(&hash_out[..size]);
+        new_key
+    }
+}
+
+// Number of keys held by a KeyCache. Slow MAC needs the key transmitted 10
+// subframes before the current key, so the cache holds the keys of the last 11
+// subframes.
+const KEY_CACHE_SIZE: usize = 11;
+
+// Cache of recent validated keys.
+//
+// The keys are stored without their MAC context to save memory. The cache is
+// used to obtain keys that precede a validated key in the chain without
+// applying the one-way function from the validated key each time.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyCache {
+    keys: [Option<CachedKey>; KEY_CACHE_SIZE],
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct CachedKey {
+    data: [u8; MAX_KEY_BYTES],
+    chain: Chain,
+    gst_subframe: Gst,
+}
+
+impl KeyCache {
+    // Stores a validated key in the cache, replacing the oldest key if the
+    // cache is full.
+    pub(crate) fn store(&mut self, key: &Key<Validated>) {
+        self.store_data(key.data, key.chain, key.gst_subframe);
+    }
+
+    fn store_data(&mut self, data: [u8; MAX_KEY_BYTES], chain: Chain, gst_subframe: Gst) {
+        let entry = CachedKey {
+            data,
+            chain,
+            gst_subframe,
+        };
+        if self.keys.contains(&Some(entry)) {
+            return;
+        }
+        let slot = self
+            .keys
+            .iter_mut()
+            .min_by_key(|k| k.map(|k| k.gst_subframe))
+            .unwrap();
+        *slot = Some(entry);
+    }
+
+    // Derives the key `num_derivations` subframes earlier than `key`.
+    //
+    // The derivation starts from the cached key or checkpoint of the same
+    // chain that is closest to the requested GST, and the intermediate keys
+    // are stored in the cache.
+    pub(crate) fn derive<const N: usize>(
+        &mut self,
+        key: &Key<Validated>,
+        num_derivations: usize,
+        checkpoints: &Checkpoints<N>,
+    ) -> Key<Validated> {
+        let target_gst = key
+            .gst_subframe
+            .add_seconds(-30 * i32::try_from(num_derivations).unwrap());
+        let (mut data, mut gst) = self
+            .keys
+            .iter()
+            .chain(checkpoints.keys.iter())
+            .flatten()
+            .filter(|k| {
+                k.chain == key.chain
+                    && k.gst_subframe >= target_gst
+                    && k.gst_subframe < key.gst_subframe
+            })
+            .min_by_key(|k| k.gst_subframe)
+            .map_or((key.data, key.gst_subframe), |k| (k.data, k.gst_subframe));
+        while gst > target_gst {
+            gst = gst.add_seconds(-30);
+            data = Key::<Validated>::one_way_function_data(&data, gst, &key.chain);
+            self.store_data(data, key.chain, gst);
+        }
         Key {
-            data: new_key,
-            chain: self.chain,
-            gst_subframe: previous_subframe,
-            _validated: self._validated.clone(),
+            data,
+            chain: key.chain,
+            gst_subframe: gst,
+            _validated: Validated {},
         }
     }
 
-    fn hash_digest(&self) -> HashDigest {
-        HashDigest::new(self.chain.hash_function)
+    // Removes the keys of a chain from the cache.
+    pub(crate) fn revoke(&mut self, chain_id: u8) {
+        for k in &mut self.keys {
+            if k.is_some_and(|k| k.chain.id == chain_id) {
+                *k = None;
+            }
+        }
     }
+}
 
-    /// Derives a TESLA key by applying the one-way function `num_derivations` times.
-    ///
-    /// This gives the TESLA key that comes `num_derivations` subframes earlier
-    /// in the TESLA chain. The validation status of the returned key is
-    /// inherited from the validation status of `self`.
-    pub fn derive(&self, num_derivations: usize) -> Key<V> {
-        let mut derived_key = self.clone();
-        for _ in 0..num_derivations {
-            derived_key = derived_key.one_way_function();
+/// TESLA key checkpoints.
+///
+/// This holds a table of validated intermediate keys of TESLA chains. The
+/// table is filled by [`Key::validate_key_with_checkpoints`] with keys that are
+/// computed while walking the chain from the key being validated to the
+/// validating key. Later validations only need to walk the chain until the
+/// nearest checkpoint, which avoids repeating thousands of one-way function
+/// computations when validating keys after a long outage.
+///
+/// The table is statically allocated and holds up to `N` keys. If `N` is
+/// zero, no checkpoints are stored. When the table is full,
+/// the checkpoints of other chains are evicted first, and then the checkpoint
+/// that leaves the smallest gap in the table when removed, so that the
+/// checkpoints stay evenly spread along the chain.
+///
+/// The checkpoints are matched to keys by their chain parameters. A new chain
+/// can have the same parameters, including the chain ID, as the chain that it
+/// replaces, so the checkpoints of a chain should be removed with
+/// [`Checkpoints::revoke`] when a KROOT for a new chain with the same ID is
+/// received.
+#[derive(Debug, Clone)]
+pub struct Checkpoints<const N: usize> {
+    keys: [Option<CachedKey>; N],
+}
+
+impl<const N: usize> Checkpoints<N> {
+    /// Constructs a new, empty, checkpoint table.
+    pub fn new() -> Checkpoints<N> {
+        Checkpoints { keys: [None; N] }
+    }
+
+    /// Returns the number of checkpoints stored in the table.
+    pub fn len(&self) -> usize {
+        self.keys.iter().flatten().count()
+    }
+
+    /// Returns `true` if the table does not contain any checkpoints.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all the checkpoints of the chain with ID `chain_id`.
+    pub fn revoke(&mut self, chain_id: u8) {
+        for k in self.keys.iter_mut() {
+            if k.is_some_and(|k| k.chain.id == chain_id) {
+                *k = None;
+            }
+        }
+    }
+
+    // Finds the latest checkpoint of `chain` whose GST is in the interval
+    // [gst_start, gst_end].
+    fn latest_between(&self, chain: &Chain, gst_start: Gst, gst_end: Gst) -> Option<CachedKey> {
+        self.keys
+            .iter()
+            .flatten()
+            .filter(|k| {
+                k.chain == *chain && k.gst_subframe >= gst_start && k.gst_subframe <= gst_end
+            })
+            .max_by_key(|k| k.gst_subframe)
+            .copied()
+    }
+
+    fn insert(&mut self, key: CachedKey) {
+        if N == 0 || self.keys.contains(&Some(key)) {
+            return;
+        }
+        if let Some(slot) = self.keys.iter_mut().find(|k| k.is_none()) {
+            *slot = Some(key);
+            return;
+        }
+        // Evict the oldest checkpoint from another chain, if there is one
+        if let Some(slot) = self
+            .keys
+            .iter_mut()
+            .filter(|k| k.is_some_and(|k| k.chain != key.chain))
+            .min_by_key(|k| k.map(|k| k.gst_subframe))
+        {
+            *slot = Some(key);
+            return;
         }
-        derived_key
+        // All the checkpoints belong to the chain of the new key. Among the
+        // checkpoints and the new key, find the one whose removal leaves the
+        // smallest gap, excluding the oldest and the newest.
+        let gsts = || {
+            self.keys
+                .iter()
+                .flatten()
+                .map(|k| k.gst_subframe)
+                .chain(core::iter::once(key.gst_subframe))
+        };
+        let gap = |gst: Gst| {
+            let previous = gsts().filter(|&g| g < gst).max()?;
+            let next = gsts().filter(|&g| g > gst).min()?;
+            Some(next.subframes_difference(previous))
+        };
+        let new_key_gap = gap(key.gst_subframe);
+        let evict = self
+            .keys
+            .iter()
+            .enumerate()
+            .filter_map(|(j, k)| Some((j, gap(k.unwrap().gst_subframe)?)))
+            .min_by_key(|&(_, g)| g);
+        match (evict, new_key_gap) {
+            (Some((_, g)), Some(new_g)) if new_g <= g => {
+                // Dropping the new key leaves the smallest gap
+            }
+            (Some((j, _)), _) => self.keys[j] = Some(key),
+            (None, _) => {
+                // There is only one checkpoint. Keep the newest key.
+                if self.keys[0].unwrap().gst_subframe < key.gst_subframe {
+                    self.keys[0] = Some(key);
+                }
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for Checkpoints<N> {
+    fn default() -> Checkpoints<N> {
+        Checkpoints::new()
     }
 }
 
@@ -755,6 +1138,75 @@ impl Key<Validated> {
         }
     }
 
+    /// Tries to validate a TESLA key using a table of checkpoints.
+    ///
+    /// This works like [`Key::validate_key`], but the one-way function is only
+    /// applied to `other` until the nearest checkpoint of the chain between
+    /// `self` and `other` is reached, instead of until `self` is reached. If
+    /// the validation is successful, some of the intermediate keys computed
+    /// during the validation are stored as new checkpoints in `checkpoints`,
+    /// spaced evenly between `other` and the key where the computation
+    /// stopped.
+    ///
+    /// The limit of 3000 derivations that causes a
+    /// [`ValidationError::TooManyDerivations`] applies to the distance between
+    /// `self` and `other`, as in [`Key::validate_key`], regardless of the
+    /// checkpoints.
+    ///
+    /// Besides the validation result, this returns the number of one-way
+    /// function computations that were done, which gives the computational
+    /// cost of the validation.
+    pub fn validate_key_with_checkpoints<V: Clone, const N: usize>(
+        &self,
+        other: &Key<V>,
+        checkpoints: &mut Checkpoints<N>,
+    ) -> (Result<Key<Validated>, ValidationError>, usize) {
+        if self.chain != other.chain {
+            return (Err(ValidationError::DifferentChain), 0);
+        }
+        if self.gst_subframe >= other.gst_subframe {
+            return (Err(ValidationError::DoesNotFollow), 0);
+        }
+        // Same limit as in validate_key
+        if other.gst_subframe.subframes_difference(self.gst_subframe) > 3000 {
+            return (Err(ValidationError::TooManyDerivations), 0);
+        }
+        let (anchor_data, anchor_gst) = checkpoints
+            .latest_between(&self.chain, self.gst_subframe, other.gst_subframe)
+            .map_or((self.data, self.gst_subframe), |k| (k.data, k.gst_subframe));
+        let derivations =
+            usize::try_from(other.gst_subframe.subframes_difference(anchor_gst)).unwrap();
+        // Candidate checkpoints, spaced evenly along the walk. These are only
+        // stored in the table if the validation is successful.
+        let mut candidates = [None; N];
+        let spacing = ((derivations + N.max(1) - 1) / N.max(1)).max(1);
+        let mut data = other.data;
+        let mut gst = other.gst_subframe;
+        for step in 0..derivations {
+            if step % spacing == 0 {
+                if let Some(c) = candidates.get_mut(step / spacing) {
+                    *c = Some(CachedKey {
+                        data,
+                        chain: self.chain,
+                        gst_subframe: gst,
+                    });
+                }
+            }
+            gst = gst.add_seconds(-30);
+            data = Self::one_way_function_data(&data, gst, &self.chain);
+        }
+        assert!(gst == anchor_gst);
+        let size = self.chain.key_size_bytes;
+        if data[..size] == anchor_data[..size] {
+            for &c in candidates.iter().flatten() {
+                checkpoints.insert(c);
+            }
+            (Ok(other.clone().force_valid()), derivations)
+        } else {
+            (Err(ValidationError::WrongOneWayFunction), derivations)
+        }
+    }
+
     /// Tries to validate a tag and its corresponding navigation data.
     ///
     /// The algorithm in Section 6.7 of the
@@ -779,6 +1231,9 @@ impl Key<Validated> {
     ///
     /// This returns `true` if the validation was succesful. Otherwise, it
     /// returns `false`.
+    ///
+    /// The MAC context is initialized with the key on each call. Use a
+    /// [`MacKey`] to validate several tags with the same key.
     #[allow(clippy::too_many_arguments)]
     pub fn validate_tag(
         &self,
@@ -790,10 +1245,30 @@ impl Key<Validated> {
         nma_status: NmaStatus,
         navdata: &BitSlice,
     ) -> bool {
-        let mut mac = self.mac_digest();
-        mac.update(&[prnd]);
-        Self::update_mac_with_navdata(&mut mac, tag_gst, prna, ctr, nma_status, navdata);
-        self.check_common(mac, tag)
+        self.validate_tag_with_trace(tag, tag_gst, prnd, prna, ctr, nma_status, navdata, |_| {})
+    }
+
+    /// Tries to validate a tag and its corresponding navigation data, tracing
+    /// the MAC computation.
+    ///
+    /// This works as [`Key::validate_tag`], but `trace` is called with a
+    /// [`TagTrace`] that gives the MAC input, the key, and the computed and
+    /// received tags. This is useful to compare the MAC computation with that
+    /// of other implementations when a tag fails to validate.
+    #[allow(clippy::too_many_arguments)]
+    pub fn validate_tag_with_trace(
+        &self,
+        tag: &BitSlice,
+        tag_gst: Gst,
+        prnd: u8,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+        trace: impl FnOnce(&TagTrace<'_>),
+    ) -> bool {
+        MacKey::new(*self)
+            .validate_tag_with_trace(tag, tag_gst, prnd, prna, ctr, nma_status, navdata, trace)
     }
 
     /// Tries to validate a dummy tag.
@@ -816,10 +1291,15 @@ impl Key<Validated> {
         nma_status: NmaStatus,
         navdata_len_bits: usize,
     ) -> bool {
-        let mut mac = self.mac_digest();
-        mac.update(&[prnd]);
-        Self::update_mac_with_dummy(&mut mac, tag_gst, prna, ctr, nma_status, navdata_len_bits);
-        self.check_common(mac, tag)
+        MacKey::new(*self).validate_tag_dummy(
+            tag,
+            tag_gst,
+            prnd,
+            prna,
+            ctr,
+            nma_status,
+            navdata_len_bits,
+        )
     }
 
     /// Tries to validate a tag0 and its corresponding navigation data.
@@ -850,9 +1330,25 @@ impl Key<Validated> {
         nma_status: NmaStatus,
         navdata: &BitSlice,
     ) -> bool {
-        let mut mac = self.mac_digest();
-        Self::update_mac_with_navdata(&mut mac, tag_gst, prna, 1, nma_status, navdata);
-        self.check_common(mac, tag0)
+        self.validate_tag0_with_trace(tag0, tag_gst, prna, nma_status, navdata, |_| {})
+    }
+
+    /// Tries to validate a tag0 and its corresponding navigation data, tracing
+    /// the MAC computation.
+    ///
+    /// This works as [`Key::validate_tag0`], but `trace` is called with a
+    /// [`TagTrace`] that gives the MAC input, the key, and the computed and
+    /// received tags. See [`Key::validate_tag_with_trace`].
+    pub fn validate_tag0_with_trace(
+        &self,
+        tag0: &BitSlice,
+        tag_gst: Gst,
+        prna: Svn,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+        trace: impl FnOnce(&TagTrace<'_>),
+    ) -> bool {
+        MacKey::new(*self).validate_tag0_with_trace(tag0, tag_gst, prna, nma_status, navdata, trace)
     }
 
     /// Tries to validate a dummy tag0.
@@ -872,14 +1368,65 @@ impl Key<Validated> {
         nma_status: NmaStatus,
         navdata_len_bits: usize,
     ) -> bool {
-        let mut mac = self.mac_digest();
-        Self::update_mac_with_dummy(&mut mac, tag_gst, prna, 1, nma_status, navdata_len_bits);
-        self.check_common(mac, tag0)
+        MacKey::new(*self).validate_tag0_dummy(tag0, tag_gst, prna, nma_status, navdata_len_bits)
+    }
+
+    /// Computes a tag for some navigation data.
+    ///
+    /// This is the counterpart of [`Key::validate_tag`]. It uses the algorithm
+    /// in Section 6.7 of the
+    /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf)
+    /// to compute the tag that authenticates the navigation data `navdata`,
+    /// which is written into `tag`. The length of `tag` gives the tag size.
+    /// See [`Key::validate_tag`] for a description of the remaining
+    /// parameters.
+    ///
+    /// This is useful to generate OSNMA messages, for instance to test
+    /// receivers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag` is longer than the output of the MAC function.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute_tag(
+        &self,
+        tag: &mut BitSlice,
+        tag_gst: Gst,
+        prnd: u8,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+    ) {
+        MacKey::new(*self).compute_tag(tag, tag_gst, prnd, prna, ctr, nma_status, navdata)
+    }
+
+    /// Computes a tag0 for some navigation data.
+    ///
+    /// This is the counterpart of [`Key::validate_tag0`]. The tag0 that
+    /// authenticates the navigation data `navdata` is written into `tag0`,
+    /// whose length gives the tag size. See [`Key::validate_tag0`] for a
+    /// description of the remaining parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag0` is longer than the output of the MAC function.
+    pub fn compute_tag0(
+        &self,
+        tag0: &mut BitSlice,
+        tag_gst: Gst,
+        prna: Svn,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+    ) {
+        MacKey::new(*self).compute_tag0(tag0, tag_gst, prna, nma_status, navdata)
     }
 
     fn mac_digest(&self) -> MacDigest {
-        let key = &self.data[..self.chain.key_size_bytes];
-        MacDigest::new_from_slice(self.chain.mac_function, key).unwrap()
+        // The key size is checked against the MAC function when the chain is
+        // constructed.
+        MacDigest::new_from_slice(self.chain.mac_function, self.data())
+            .expect("TESLA key size not supported by the MAC function")
     }
 
     // This is large enough to fit all the message for ADKD=0 and 12
@@ -918,19 +1465,72 @@ impl Key<Validated> {
             .copy_from_bitslice(navdata);
     }
 
-    fn update_mac_with_navdata(
-        mac: &mut MacDigest,
+    // The MAC input of a tag is the PRN_D (except for the tag0) followed by
+    // the contents of the tag buffer.
+    fn new_mac_input_buffer() -> [u8; 1 + Self::TAG_BUFF_SIZE] {
+        [0u8; 1 + Self::TAG_BUFF_SIZE]
+    }
+
+    // Writes the MAC input of a tag into `buffer`, returning the part of the
+    // buffer that is used.
+    #[allow(clippy::too_many_arguments)]
+    fn tag_mac_input<'b>(
+        buffer: &'b mut [u8; 1 + Self::TAG_BUFF_SIZE],
+        prnd: Option<u8>,
         gst: Gst,
         prna: Svn,
         ctr: u8,
         nma_status: NmaStatus,
         navdata: &BitSlice,
-    ) {
-        let mut buffer = Self::new_tag_buffer();
-        Self::fill_buffer_header(&mut buffer, gst, prna, ctr, nma_status);
-        Self::fill_buffer_navdata(&mut buffer, navdata);
+    ) -> &'b [u8] {
+        let mut tag_buffer = Self::new_tag_buffer();
+        Self::fill_buffer_header(&mut tag_buffer, gst, prna, ctr, nma_status);
+        Self::fill_buffer_navdata(&mut tag_buffer, navdata);
         let message_bytes = Self::TAG_FIXED_SIZE + (Self::STATUS_BITS + navdata.len() + 7) / 8;
-        mac.update(&buffer[..message_bytes]);
+        let start = match prnd {
+            Some(prnd) => {
+                buffer[0] = prnd;
+                1
+            }
+            None => 0,
+        };
+        buffer[start..start + message_bytes].copy_from_slice(&tag_buffer[..message_bytes]);
+        &buffer[..start + message_bytes]
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn check_tag(
+        &self,
+        mac: &MacDigest,
+        tag: &BitSlice,
+        prnd: Option<u8>,
+        gst: Gst,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+        trace: impl FnOnce(&TagTrace<'_>),
+    ) -> bool {
+        let mut buffer = Self::new_mac_input_buffer();
+        let mac_input = Self::tag_mac_input(&mut buffer, prnd, gst, prna, ctr, nma_status, navdata);
+        let mut mac = mac.clone();
+        mac.update(mac_input);
+        let mut mac_out = GenericArray::default();
+        mac.finalize_into(&mut mac_out);
+        let computed = &BitSlice::from_slice(&mac_out)[..tag.len()];
+        trace(&TagTrace {
+            prnd,
+            prna,
+            gst,
+            ctr,
+            nma_status,
+            navdata,
+            mac_input,
+            key: self.data(),
+            computed_tag: computed,
+            received_tag: tag,
+        });
+        computed == tag
     }
 
     fn update_mac_with_dummy(
@@ -947,13 +1547,35 @@ impl Key<Validated> {
         mac.update(&buffer[..message_bytes]);
     }
 
-    fn check_common(&self, mac: MacDigest, tag: &BitSlice) -> bool {
+    #[allow(clippy::too_many_arguments)]
+    fn check_dummy_tag(
+        mac: &MacDigest,
+        tag: &BitSlice,
+        prnd: Option<u8>,
+        gst: Gst,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata_len_bits: usize,
+    ) -> bool {
+        let mut mac = mac.clone();
+        if let Some(prnd) = prnd {
+            mac.update(&[prnd]);
+        }
+        Self::update_mac_with_dummy(&mut mac, gst, prna, ctr, nma_status, navdata_len_bits);
         let mut mac_out = GenericArray::default();
         mac.finalize_into(&mut mac_out);
         let computed = &BitSlice::from_slice(&mac_out)[..tag.len()];
         computed == tag
     }
 
+    fn write_tag(mac: MacDigest, tag: &mut BitSlice) {
+        let mut mac_out = GenericArray::default();
+        mac.finalize_into(&mut mac_out);
+        let len = tag.len();
+        tag.copy_from_bitslice(&BitSlice::from_slice(&mac_out)[..len]);
+    }
+
     /// Tries to validate the MACSEQ field in a MACK message.
     ///
     /// The algorithm in Section 6.6 of the
@@ -969,50 +1591,461 @@ impl Key<Validated> {
     /// MACK message.
     ///
     /// The function returns `Ok` if the validation was successful, and an error
-    /// otherwise.
+    /// otherwise. Only the MAC look-up tables built into the crate are used.
+    /// See [`Key::validate_macseq_with_registry`] to use a [`MacLTRegistry`].
+    ///
+    /// The MAC context is initialized with the key on each call. Use a
+    /// [`MacKey`] to validate the MACSEQ and the tags of a MACK message with
+    /// the same MAC context.
     pub fn validate_macseq<V: Clone>(
         &self,
         mack: &Mack<V>,
         prna: Svn,
         gst_mack: Gst,
     ) -> Result<(), MacseqCheckError> {
-        let mut mac = self.mac_digest();
-        let mut buffer = [0u8; FIXED_SIZE];
-        const TAG_INFO_SIZE: usize = 2; // size of tag-info in bytes
-        const FIXED_SIZE: usize = 5; // size in bytes required for PRN_A and GST_SF
-        buffer[0] = prna.into();
-        Self::store_gst(&mut buffer[1..5], gst_mack);
-        mac.update(&buffer);
-        // update MAC with FLX tag-info's
-        let msg = usize::try_from((gst_mack.tow() / 30) % 2).unwrap(); // Half of the GST minute
-        let maclt = self.chain().mac_lookup_table();
-        for idx in get_flx_indices(maclt, msg)? {
-            let tag_and_info = mack.tag_and_info(idx);
-            let dest = BitSlice::from_slice_mut(&mut buffer[..TAG_INFO_SIZE]);
-            dest.copy_from_bitslice(tag_and_info.tag_info());
-            mac.update(&buffer[..TAG_INFO_SIZE]);
-        }
-        let mut mac_out = GenericArray::default();
-        mac.finalize_into(&mut mac_out);
-        const MACSEQ_BITS: usize = 12;
-        let computed = &BitSlice::from_slice(&mac_out)[..MACSEQ_BITS];
+        self.validate_macseq_with_registry(mack, prna, gst_mack, &MacLTRegistry::<0>::new())
+    }
+
+    /// Tries to validate the MACSEQ field in a MACK message using a MAC look-up
+    /// table registry.
+    ///
+    /// This works as [`Key::validate_macseq`], but the MAC look-up table of the
+    /// chain is looked up in `registry`, so tables added at runtime can be
+    /// used.
+    pub fn validate_macseq_with_registry<V: Clone, const N: usize>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+    ) -> Result<(), MacseqCheckError> {
+        self.validate_macseq_with_trace(mack, prna, gst_mack, registry, |_| {})
+    }
 
-        let mut macseq_buffer = [0u8; 2];
-        let macseq_bits = &mut BitSlice::from_slice_mut(&mut macseq_buffer)[..MACSEQ_BITS];
-        macseq_bits.store_be::<u16>(mack.macseq());
-        if computed == macseq_bits {
+    /// Tries to validate the MACSEQ field in a MACK message, tracing the MAC
+    /// computation.
+    ///
+    /// This works as [`Key::validate_macseq_with_registry`], but `trace` is
+    /// called with a [`MacseqTrace`] that gives the MAC input, the key, and the
+    /// computed and received MACSEQ. The trace is not produced if the MAC
+    /// look-up table of the chain is not valid, since the MAC input cannot be
+    /// built.
+    pub fn validate_macseq_with_trace<V: Clone, const N: usize>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+        trace: impl FnOnce(&MacseqTrace<'_>),
+    ) -> Result<(), MacseqCheckError> {
+        MacKey::new(*self).validate_macseq_with_trace(mack, prna, gst_mack, registry, trace)
+    }
+
+    fn check_macseq<V: Clone, const N: usize>(
+        &self,
+        mac: &MacDigest,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+        trace: impl FnOnce(&MacseqTrace<'_>),
+    ) -> Result<(), MacseqCheckError> {
+        let mut buffer = [0u8; MACSEQ_BUFF_SIZE];
+        let mac_input = self.macseq_mac_input(&mut buffer, mack, prna, gst_mack, registry)?;
+        let macseq = Self::macseq_from_mac_input(mac, mac_input);
+        trace(&MacseqTrace {
+            prna,
+            gst: gst_mack,
+            mac_input,
+            key: self.data(),
+            computed_macseq: macseq,
+            received_macseq: mack.macseq(),
+        });
+        if macseq == mack.macseq() {
             Ok(())
         } else {
             Err(MacseqCheckError::WrongMacseq)
         }
     }
+
+    /// Computes the MACSEQ field of a MACK message.
+    ///
+    /// This is the counterpart of [`Key::validate_macseq`]. It uses the
+    /// algorithm in Section 6.6 of the
+    /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf)
+    /// to compute the MACSEQ corresponding to the FLX Tag-Info sections of the
+    /// MACK message `mack`. The contents of the MACSEQ field of `mack` are
+    /// ignored. See [`Key::validate_macseq`] for a description of the remaining
+    /// parameters.
+    ///
+    /// The MACSEQ is a 12-bit integer, which is returned as a `u16`. An error
+    /// is returned if the MAC look-up table of the chain is not valid. Only the
+    /// MAC look-up tables built into the crate are used. See
+    /// [`Key::compute_macseq_with_registry`] to use a [`MacLTRegistry`].
+    pub fn compute_macseq<V: Clone>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+    ) -> Result<u16, MacLTError> {
+        self.compute_macseq_with_registry(mack, prna, gst_mack, &MacLTRegistry::<0>::new())
+    }
+
+    /// Computes the MACSEQ field of a MACK message using a MAC look-up table
+    /// registry.
+    ///
+    /// This works as [`Key::compute_macseq`], but the MAC look-up table of the
+    /// chain is looked up in `registry`, so tables added at runtime can be
+    /// used.
+    pub fn compute_macseq_with_registry<V: Clone, const N: usize>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+    ) -> Result<u16, MacLTError> {
+        MacKey::new(*self).compute_macseq_with_registry(mack, prna, gst_mack, registry)
+    }
+
+    // Writes the MAC input of the MACSEQ into `buffer`, returning the part of
+    // the buffer that is used. This contains the PRN_A, the GST_SF and the
+    // Tag-Info sections of the FLX tags.
+    fn macseq_mac_input<'b, V: Clone, const N: usize>(
+        &self,
+        buffer: &'b mut [u8; MACSEQ_BUFF_SIZE],
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+    ) -> Result<&'b [u8], MacLTError> {
+        buffer[0] = prna.into();
+        Self::store_gst(&mut buffer[1..MACSEQ_FIXED_SIZE], gst_mack);
+        let msg = usize::try_from((gst_mack.tow() / 30) % 2).unwrap(); // Half of the GST minute
+        let maclt = self.chain().mac_lookup_table();
+        let mut len = MACSEQ_FIXED_SIZE;
+        for idx in mack_maclt_entry(registry, maclt, mack.num_tags())?.flx_indices(msg) {
+            let tag_and_info = mack.tag_and_info(idx);
+            let dest = BitSlice::from_slice_mut(&mut buffer[len..len + TAG_INFO_SIZE]);
+            dest.copy_from_bitslice(tag_and_info.tag_info());
+            len += TAG_INFO_SIZE;
+        }
+        Ok(&buffer[..len])
+    }
+
+    fn macseq_from_mac_input(mac: &MacDigest, mac_input: &[u8]) -> u16 {
+        let mut mac = mac.clone();
+        mac.update(mac_input);
+        let mut mac_out = GenericArray::default();
+        mac.finalize_into(&mut mac_out);
+        const MACSEQ_BITS: usize = 12;
+        BitSlice::from_slice(&mac_out)[..MACSEQ_BITS].load_be::<u16>()
+    }
+}
+
+/// Validated TESLA key with an initialized MAC context.
+///
+/// The methods of [`Key<Validated>`] that compute a MAC set up a new MAC
+/// context with the key on each call. For HMAC-SHA-256 this involves hashing
+/// the padded key, and for CMAC-AES the AES key expansion. A `MacKey` does this
+/// setup only once, when it is constructed, and clones the MAC context for each
+/// tag or MACSEQ that it validates or computes. It should be used when several
+/// tags are validated with the same key, which is the usual case, since a MACK
+/// message contains several tags.
+///
+/// The methods of `MacKey` work in the same way as the methods of [`Key`] with
+/// the same name.
+#[derive(Debug, Clone)]
+pub struct MacKey {
+    key: Key<Validated>,
+    mac: MacDigest,
 }
 
+impl MacKey {
+    /// Constructs a `MacKey` by initializing a MAC context with a validated key.
+    pub fn new(key: Key<Validated>) -> MacKey {
+        MacKey {
+            key,
+            mac: key.mac_digest(),
+        }
+    }
+
+    /// Gives the validated key of this `MacKey`.
+    pub fn key(&self) -> &Key<Validated> {
+        &self.key
+    }
+
+    /// Tries to validate a tag and its corresponding navigation data.
+    ///
+    /// See [`Key::validate_tag`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn validate_tag(
+        &self,
+        tag: &BitSlice,
+        tag_gst: Gst,
+        prnd: u8,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+    ) -> bool {
+        self.validate_tag_with_trace(tag, tag_gst, prnd, prna, ctr, nma_status, navdata, |_| {})
+    }
+
+    /// Tries to validate a tag and its corresponding navigation data, tracing
+    /// the MAC computation.
+    ///
+    /// See [`Key::validate_tag_with_trace`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn validate_tag_with_trace(
+        &self,
+        tag: &BitSlice,
+        tag_gst: Gst,
+        prnd: u8,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+        trace: impl FnOnce(&TagTrace<'_>),
+    ) -> bool {
+        self.key.check_tag(
+            &self.mac,
+            tag,
+            Some(prnd),
+            tag_gst,
+            prna,
+            ctr,
+            nma_status,
+            navdata,
+            trace,
+        )
+    }
+
+    /// Tries to validate a dummy tag.
+    ///
+    /// See [`Key::validate_tag_dummy`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn validate_tag_dummy(
+        &self,
+        tag: &BitSlice,
+        tag_gst: Gst,
+        prnd: u8,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata_len_bits: usize,
+    ) -> bool {
+        Key::check_dummy_tag(
+            &self.mac,
+            tag,
+            Some(prnd),
+            tag_gst,
+            prna,
+            ctr,
+            nma_status,
+            navdata_len_bits,
+        )
+    }
+
+    /// Tries to validate a tag0 and its corresponding navigation data.
+    ///
+    /// See [`Key::validate_tag0`].
+    pub fn validate_tag0(
+        &self,
+        tag0: &BitSlice,
+        tag_gst: Gst,
+        prna: Svn,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+    ) -> bool {
+        self.validate_tag0_with_trace(tag0, tag_gst, prna, nma_status, navdata, |_| {})
+    }
+
+    /// Tries to validate a tag0 and its corresponding navigation data, tracing
+    /// the MAC computation.
+    ///
+    /// See [`Key::validate_tag0_with_trace`].
+    pub fn validate_tag0_with_trace(
+        &self,
+        tag0: &BitSlice,
+        tag_gst: Gst,
+        prna: Svn,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+        trace: impl FnOnce(&TagTrace<'_>),
+    ) -> bool {
+        self.key.check_tag(
+            &self.mac, tag0, None, tag_gst, prna, 1, nma_status, navdata, trace,
+        )
+    }
+
+    /// Tries to validate a dummy tag0.
+    ///
+    /// See [`Key::validate_tag0_dummy`].
+    pub fn validate_tag0_dummy(
+        &self,
+        tag0: &BitSlice,
+        tag_gst: Gst,
+        prna: Svn,
+        nma_status: NmaStatus,
+        navdata_len_bits: usize,
+    ) -> bool {
+        Key::check_dummy_tag(
+            &self.mac,
+            tag0,
+            None,
+            tag_gst,
+            prna,
+            1,
+            nma_status,
+            navdata_len_bits,
+        )
+    }
+
+    /// Computes a tag for some navigation data.
+    ///
+    /// See [`Key::compute_tag`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag` is longer than the output of the MAC function.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute_tag(
+        &self,
+        tag: &mut BitSlice,
+        tag_gst: Gst,
+        prnd: u8,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+    ) {
+        let mut buffer = Key::new_mac_input_buffer();
+        let mac_input = Key::tag_mac_input(
+            &mut buffer,
+            Some(prnd),
+            tag_gst,
+            prna,
+            ctr,
+            nma_status,
+            navdata,
+        );
+        let mut mac = self.mac.clone();
+        mac.update(mac_input);
+        Key::write_tag(mac, tag);
+    }
+
+    /// Computes a tag0 for some navigation data.
+    ///
+    /// See [`Key::compute_tag0`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag0` is longer than the output of the MAC function.
+    pub fn compute_tag0(
+        &self,
+        tag0: &mut BitSlice,
+        tag_gst: Gst,
+        prna: Svn,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+    ) {
+        let mut buffer = Key::new_mac_input_buffer();
+        let mac_input =
+            Key::tag_mac_input(&mut buffer, None, tag_gst, prna, 1, nma_status, navdata);
+        let mut mac = self.mac.clone();
+        mac.update(mac_input);
+        Key::write_tag(mac, tag0);
+    }
+
+    /// Tries to validate the MACSEQ field in a MACK message.
+    ///
+    /// See [`Key::validate_macseq`].
+    pub fn validate_macseq<V: Clone>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+    ) -> Result<(), MacseqCheckError> {
+        self.validate_macseq_with_registry(mack, prna, gst_mack, &MacLTRegistry::<0>::new())
+    }
+
+    /// Tries to validate the MACSEQ field in a MACK message using a MAC look-up
+    /// table registry.
+    ///
+    /// See [`Key::validate_macseq_with_registry`].
+    pub fn validate_macseq_with_registry<V: Clone, const N: usize>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+    ) -> Result<(), MacseqCheckError> {
+        self.validate_macseq_with_trace(mack, prna, gst_mack, registry, |_| {})
+    }
+
+    /// Tries to validate the MACSEQ field in a MACK message, tracing the MAC
+    /// computation.
+    ///
+    /// See [`Key::validate_macseq_with_trace`].
+    pub fn validate_macseq_with_trace<V: Clone, const N: usize>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+        trace: impl FnOnce(&MacseqTrace<'_>),
+    ) -> Result<(), MacseqCheckError> {
+        self.key
+            .check_macseq(&self.mac, mack, prna, gst_mack, registry, trace)
+    }
+
+    /// Computes the MACSEQ field of a MACK message.
+    ///
+    /// See [`Key::compute_macseq`].
+    pub fn compute_macseq<V: Clone>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+    ) -> Result<u16, MacLTError> {
+        self.compute_macseq_with_registry(mack, prna, gst_mack, &MacLTRegistry::<0>::new())
+    }
+
+    /// Computes the MACSEQ field of a MACK message using a MAC look-up table
+    /// registry.
+    ///
+    /// See [`Key::compute_macseq_with_registry`].
+    pub fn compute_macseq_with_registry<V: Clone, const N: usize>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+        registry: &MacLTRegistry<N>,
+    ) -> Result<u16, MacLTError> {
+        let mut buffer = [0u8; MACSEQ_BUFF_SIZE];
+        let mac_input = self
+            .key
+            .macseq_mac_input(&mut buffer, mack, prna, gst_mack, registry)?;
+        Ok(Key::macseq_from_mac_input(&self.mac, mac_input))
+    }
+}
+
+impl From<Key<Validated>> for MacKey {
+    fn from(key: Key<Validated>) -> MacKey {
+        MacKey::new(key)
+    }
+}
+
+const TAG_INFO_SIZE: usize = 2; // size of tag-info in bytes
+const MACSEQ_FIXED_SIZE: usize = 5; // size in bytes required for PRN_A and GST_SF
+const MACSEQ_BUFF_SIZE: usize = MACSEQ_FIXED_SIZE + MAX_FLX_ENTRIES * TAG_INFO_SIZE;
+
 /// Errors produced during the validation of a MACSEQ field.
 ///
 /// This gives the errors that can happen during the validation of a MACSEQ field
 /// using [`Key::validate_macseq`].
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MacseqCheckError {
     /// MAC Look-up Table error.
     MacLTError(MacLTError),
@@ -1093,6 +2126,92 @@ mod test {
         assert_eq!(k1.one_way_function(), k0);
     }
 
+    #[test]
+    fn key_cache() {
+        // KROOT broadcast on 2022-03-07 ~9:00 UTC
+        let chain = test_chain();
+        let kroot = Key::from_slice(
+            &hex!("84 1e 1d e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3"),
+            Gst::new(1176, 0x21 * 3600 - 30), // towh in DSM-KROOT was 0x21
+            &chain,
+        );
+        let key = Key::from_slice(
+            &hex!("42 b4 19 da 6a da 1c 0a 3d 6f 56 a5 e5 dc 59 a7"),
+            Gst::new(1176, 120930),
+            &chain,
+        )
+        .force_valid();
+        let derivations = key
+            .gst_subframe()
+            .subframes_difference(kroot.gst_subframe());
+        let derivations = usize::try_from(derivations).unwrap();
+        let mut cache = KeyCache::default();
+        assert_eq!(
+            cache.derive(&key, derivations, &Checkpoints::<0>::new()),
+            kroot.force_valid()
+        );
+        // The cache now holds the keys immediately following the KROOT
+        for n in (derivations - KEY_CACHE_SIZE + 1)..=derivations {
+            assert_eq!(
+                cache.derive(&key, n, &Checkpoints::<0>::new()),
+                key.derive(n)
+            );
+        }
+        assert_eq!(cache.derive(&key, 0, &Checkpoints::<0>::new()), key);
+    }
+
+    #[test]
+    fn validation_checkpoints() {
+        // Keys broadcast on 2022-03-07 ~9:00 UTC
+        let chain = test_chain();
+        let kroot = Key::from_slice(
+            &hex!("84 1e 1d e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3"),
+            Gst::new(1176, 0x21 * 3600 - 30), // towh in DSM-KROOT was 0x21
+            &chain,
+        )
+        .force_valid();
+        let key0 = Key::from_slice(
+            &hex!("42 b4 19 da 6a da 1c 0a 3d 6f 56 a5 e5 dc 59 a7"),
+            Gst::new(1176, 120930),
+            &chain,
+        );
+        let key1 = Key::from_slice(
+            &hex!("19 58 e7 76 6f b4 08 cb d6 a8 de fc e4 c7 d5 66"),
+            Gst::new(1176, 121080),
+            &chain,
+        );
+        let mut checkpoints = Checkpoints::<4>::new();
+        assert!(checkpoints.is_empty());
+        let (result, hashes) = kroot.validate_key_with_checkpoints(&key0, &mut checkpoints);
+        assert_eq!(result, Ok(key0.force_valid()));
+        assert_eq!(hashes, 72);
+        assert_eq!(checkpoints.len(), 4);
+        // The validation of the next key starts at the checkpoint for key0
+        let (result, hashes) = kroot.validate_key_with_checkpoints(&key1, &mut checkpoints);
+        let key1 = key1.force_valid();
+        assert_eq!(result, Ok(key1));
+        assert_eq!(hashes, 5);
+        // A wrong key is not validated and does not modify the checkpoints
+        let wrong = Key::from_slice(&[0; 16], Gst::new(1176, 121110), &chain);
+        let checkpoints_before = checkpoints.clone();
+        let (result, hashes) = kroot.validate_key_with_checkpoints(&wrong, &mut checkpoints);
+        assert_eq!(result, Err(ValidationError::WrongOneWayFunction));
+        assert_eq!(hashes, 1);
+        assert_eq!(checkpoints.keys, checkpoints_before.keys);
+        // Derivations start at the checkpoint closest to the requested key, so
+        // the keys that precede key1 are not computed
+        let mut cache = KeyCache::default();
+        assert_eq!(cache.derive(&key1, 77, &checkpoints), kroot);
+        let previous = key1.one_way_function();
+        assert!(!cache.keys.iter().flatten().any(|k| k.data == previous.data));
+        // Without checkpoints, the validation walks until the validating key
+        let mut checkpoints = Checkpoints::<0>::new();
+        let (result, hashes) = kroot.validate_key_with_checkpoints(&key1, &mut checkpoints);
+        assert_eq!(result, Ok(key1));
+        assert_eq!(hashes, 77);
+        assert!(checkpoints.is_empty());
+    }
+
     #[test]
     fn validation_kroot() {
         // KROOT broadcast on 2022-03-07 ~9:00 UTC
@@ -1112,6 +2231,75 @@ mod test {
         assert!(kroot.validate_key(&key).is_ok());
     }
 
+    #[test]
+    fn malformed_dsm_kroot() {
+        use crate::bitfields::{self, DsmKroot};
+        use crate::builders::DsmKrootBuilder;
+        use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+        let signing_key = SigningKey::from_bytes(&[0x5a; 32].into()).unwrap();
+        let pubkey = PublicKey::from_p256(*signing_key.verifying_key(), 1).force_valid();
+        let nma_header = NmaHeader::new(0x52);
+        let kroot = [0xa5; 32];
+        let sign_with_mac = |mac_function, key_size: usize, kroot_wn, kroot_towh| {
+            let builder = DsmKrootBuilder {
+                public_key_id: 1,
+                kroot_chain_id: 1,
+                hash_function: bitfields::HashFunction::Sha256,
+                mac_function,
+                key_size,
+                tag_size: 40,
+                mac_lookup_table: 0x21,
+                kroot_wn,
+                kroot_towh,
+                alpha: 0x25d3964da3a2,
+                kroot: &kroot[..key_size / 8],
+            };
+            let message = builder.signature_message(nma_header).unwrap();
+            let signature: Signature = signing_key.sign(message.as_ref());
+            builder.build(nma_header, &signature.to_bytes()).unwrap()
+        };
+        let sign = |kroot_wn, kroot_towh| {
+            sign_with_mac(
+                bitfields::MacFunction::HmacSha256,
+                128,
+                kroot_wn,
+                kroot_towh,
+            )
+        };
+
+        let dsm = sign(1176, 0x21);
+        let (key, _) = Key::from_dsm_kroot(nma_header, dsm.dsm_kroot(), &pubkey).unwrap();
+        assert_eq!(key.gst_subframe(), Gst::new(1176, 0x21 * 3600 - 30));
+        // Truncated message
+        assert_eq!(
+            Key::from_dsm_kroot(nma_header, DsmKroot(&dsm.dsm_kroot().0[..7 * 13]), &pubkey),
+            Err(KrootValidationError::WrongDsmKrootSize)
+        );
+        // TOWHK beyond the end of the week
+        let dsm = sign(1176, 168);
+        assert_eq!(
+            Key::from_dsm_kroot(nma_header, dsm.dsm_kroot(), &pubkey),
+            Err(KrootValidationError::WrongKrootTime)
+        );
+        // KROOT applying before the start of GST
+        let dsm = sign(0, 0);
+        assert_eq!(
+            Key::from_dsm_kroot(nma_header, dsm.dsm_kroot(), &pubkey),
+            Err(KrootValidationError::WrongKrootTime)
+        );
+        // CMAC-AES with a key size other than 128 bits
+        let dsm = sign_with_mac(bitfields::MacFunction::CmacAes, 256, 1176, 0x21);
+        assert_eq!(
+            Key::from_dsm_kroot(nma_header, dsm.dsm_kroot(), &pubkey),
+            Err(KrootValidationError::WrongDsmKrootChain(
+                ChainError::WrongCmacKeySize
+            ))
+        );
+        let dsm = sign_with_mac(bitfields::MacFunction::CmacAes, 128, 1176, 0x21);
+        assert!(Key::from_dsm_kroot(nma_header, dsm.dsm_kroot(), &pubkey).is_ok());
+    }
+
     #[test]
     fn tag0() {
         // Data corresponding to E21 on 2022-03-07 ~9:00 UTC
@@ -1134,6 +2322,108 @@ mod test {
             48 00 50 14 00"
         ))[..549];
         assert!(key.validate_tag0(tag0, tag0_gst, prna, NmaStatus::Test, navdata_adkd0));
+
+        let mut computed = [0u8; 5];
+        let computed = BitSlice::from_slice_mut(&mut computed);
+        key.compute_tag0(computed, tag0_gst, prna, NmaStatus::Test, navdata_adkd0);
+        assert_eq!(computed, tag0);
+    }
+
+    #[test]
+    fn mac_key() {
+        let key = test_key().force_valid();
+        let mac_key = MacKey::new(key);
+        assert_eq!(mac_key.key(), &key);
+
+        // The MAC context is reused for several tags
+        let tag_gst = Gst::new(1176, 121050);
+        let prna = Svn::try_from(21).unwrap();
+        let navdata = BitSlice::from_slice(&hex!("12 07 d0 ec 19 90 2e 00 1f e1"));
+        let mut tags = [[0u8; 5]; 3];
+        for (ctr, tag) in (1..).zip(tags.iter_mut()) {
+            let tag = BitSlice::from_slice_mut(tag);
+            mac_key.compute_tag(tag, tag_gst, 4, prna, ctr, NmaStatus::Test, navdata);
+            let mut expected = [0u8; 5];
+            let expected = BitSlice::from_slice_mut(&mut expected);
+            key.compute_tag(expected, tag_gst, 4, prna, ctr, NmaStatus::Test, navdata);
+            assert_eq!(tag, expected);
+        }
+        for (ctr, tag) in (1..).zip(tags.iter()) {
+            let tag = BitSlice::from_slice(tag);
+            assert!(mac_key.validate_tag(tag, tag_gst, 4, prna, ctr, NmaStatus::Test, navdata));
+            assert!(!mac_key.validate_tag(
+                tag,
+                tag_gst,
+                4,
+                prna,
+                ctr + 1,
+                NmaStatus::Test,
+                navdata
+            ));
+        }
+
+        let key = test_key_2023().force_valid();
+        let mac_key = MacKey::from(key);
+        let mack = test_mack_2023();
+        let prna = Svn::try_from(3).unwrap();
+        let gst = Gst::new(1268, 208890);
+        assert_eq!(mac_key.validate_macseq(&mack, prna, gst), Ok(()));
+        assert_eq!(
+            mac_key.compute_macseq(&mack, prna, gst),
+            key.compute_macseq(&mack, prna, gst)
+        );
+    }
+
+    #[test]
+    fn tag0_trace() {
+        let tag0 = BitSlice::from_slice(&hex!("8f 54 58 88 71"));
+        let tag0_gst = Gst::new(1176, 121050);
+        let prna = Svn::try_from(21).unwrap();
+        let chain = test_chain();
+        let key = Key::from_slice(
+            &hex!("19 58 e7 76 6f b4 08 cb d6 a8 de fc e4 c7 d5 66"),
+            Gst::new(1176, 121080),
+            &chain,
+        )
+        .force_valid();
+        let navdata = &BitSlice::from_slice(&[0xaa; 69])[..549];
+        let mut traced = false;
+        let valid =
+            key.validate_tag0_with_trace(tag0, tag0_gst, prna, NmaStatus::Test, navdata, |trace| {
+                traced = true;
+                assert_eq!(trace.prnd, None);
+                assert_eq!(trace.ctr, 1);
+                assert_eq!(trace.navdata, navdata);
+                // PRN_A, GST_SF, CTR, and NMAS followed by the navigation data
+                assert_eq!(trace.mac_input.len(), 6 + (2 + 549 + 7) / 8);
+                assert_eq!(trace.mac_input[..6], hex!("15 49 81 d8 da 01"));
+                assert_eq!(trace.mac_input[6], 0x6a);
+                assert_eq!(trace.key, key.data());
+                assert_eq!(trace.received_tag, tag0);
+                assert!(!trace.is_valid());
+            });
+        assert!(traced);
+        assert!(!valid);
+
+        let prnd = 2;
+        key.validate_tag_with_trace(
+            tag0,
+            tag0_gst,
+            prnd,
+            prna,
+            3,
+            NmaStatus::Test,
+            navdata,
+            |trace| {
+                assert_eq!(trace.prnd, Some(prnd));
+                assert_eq!(trace.mac_input.len(), 1 + 6 + (2 + 549 + 7) / 8);
+                assert_eq!(trace.mac_input[..7], hex!("02 15 49 81 d8 da 03"));
+                let mut computed = [0u8; 5];
+                let computed = BitSlice::from_slice_mut(&mut computed);
+                key.compute_tag(computed, tag0_gst, prnd, prna, 3, NmaStatus::Test, navdata);
+                assert_eq!(trace.computed_tag, computed);
+            },
+        );
     }
 
     fn test_mack() -> Mack<'static, NotValidated> {
@@ -1153,21 +2443,19 @@ mod test {
         )
     }
 
+    // Data broadcast by E03 on 2023-12-12 ~10:00 UTC
+    const MACK_2023: [u8; 60] = hex!(
+        "
+        88 36 af a3 5b eb b1 32 bf 2f 08 e9 24 0f 0a d4
+        c0 4f a2 08 0f 1d 02 fb 7f 53 03 c1 d4 a6 c5 3b
+        4a 05 0f 82 b1 53 4c fe 08 cf b3 2c df 02 5f 50
+        cf 39 04 d2 78 26 30 39 10 bf 00 00"
+    );
+
     fn test_mack_2023() -> Mack<'static, NotValidated> {
-        // Data broadcast by E03 on 2023-12-12 ~10:00 UTC
         let key_size = 128;
         let tag_size = 40;
-        Mack::new(
-            &hex!(
-                "
-                88 36 af a3 5b eb b1 32 bf 2f 08 e9 24 0f 0a d4
-                c0 4f a2 08 0f 1d 02 fb 7f 53 03 c1 d4 a6 c5 3b
-                4a 05 0f 82 b1 53 4c fe 08 cf b3 2c df 02 5f 50
-                cf 39 04 d2 78 26 30 39 10 bf 00 00"
-            ),
-            key_size,
-            tag_size,
-        )
+        Mack::new(&MACK_2023, key_size, tag_size)
     }
 
     fn test_key() -> Key<NotValidated> {
@@ -1220,6 +2508,10 @@ mod test {
             key.validate_macseq(&mack, prna, Gst::new(1176, 121050)),
             Ok(())
         );
+        assert_eq!(
+            key.compute_macseq(&mack, prna, Gst::new(1176, 121050)),
+            Ok(mack.macseq())
+        );
     }
 
     #[test]
@@ -1232,5 +2524,136 @@ mod test {
             key.validate_macseq(&mack, prna, Gst::new(1268, 208890)),
             Ok(())
         );
+
+        let mut traced = false;
+        let result = key.validate_macseq_with_trace(
+            &mack,
+            prna,
+            Gst::new(1268, 208890),
+            &MacLTRegistry::<0>::new(),
+            |trace| {
+                traced = true;
+                assert!(trace.is_valid());
+                assert_eq!(trace.received_macseq, mack.macseq());
+                assert_eq!(trace.key, key.data());
+                // PRN_A and GST_SF followed by the Tag-Info of the FLX tags
+                assert_eq!(trace.mac_input[0], 3);
+                assert_eq!((trace.mac_input.len() - 5) % 2, 0);
+                assert!(trace.mac_input.len() > 5);
+            },
+        );
+        assert!(traced);
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn maclt_registry() {
+        // Chain using an ID that is not built in, which is registered at
+        // runtime with the contents of MACLT 34
+        let chain = Chain {
+            maclt: 200,
+            ..test_chain_2023()
+        };
+        let key = Key::from_slice(
+            &hex!("33 4f d3 e5 68 c0 4e 2a 44 db a7 8a 03 01 c3 4a"),
+            Gst::new(1268, 208920),
+            &chain,
+        )
+        .force_valid();
+        let mack = test_mack_2023();
+        let prna = Svn::try_from(3).unwrap();
+        let gst = Gst::new(1268, 208890);
+        assert_eq!(
+            key.validate_macseq(&mack, prna, gst),
+            Err(MacseqCheckError::MacLTError(MacLTError::InvalidMaclt))
+        );
+        let mut registry = MacLTRegistry::<1>::new();
+        registry
+            .load("200 00S FLX 04S FLX 12S 00E / 00S FLX 00E 12S 00E 12E")
+            .unwrap();
+        assert_eq!(
+            key.validate_macseq_with_registry(&mack, prna, gst, &registry),
+            Ok(())
+        );
+        for j in 1..mack.num_tags() {
+            assert!(chain
+                .validate_adkd_with_registry(
+                    j,
+                    mack.num_tags(),
+                    mack.tag_and_info(j),
+                    prna,
+                    gst,
+                    &registry
+                )
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn maclt_builtin_wrong_nt() {
+        // MACLT 34 has nt = 6. Interpreting the MACK message with 32-bit tags
+        // gives 7 tags, which do not match the built-in entry.
+        let key = test_key_2023().force_valid();
+        let mack = Mack::new(&MACK_2023, 128, 32);
+        assert_eq!(mack.num_tags(), 7);
+        let prna = Svn::try_from(3).unwrap();
+        let gst = Gst::new(1268, 208890);
+        assert_eq!(
+            key.validate_macseq(&mack, prna, gst),
+            Err(MacseqCheckError::MacLTError(MacLTError::WrongNumberOfTags))
+        );
+        assert_eq!(
+            key.compute_macseq(&mack, prna, gst),
+            Err(MacLTError::WrongNumberOfTags)
+        );
+    }
+
+    #[test]
+    fn maclt_registry_wrong_nt() {
+        // Chain with a 128-bit key and 40-bit tags, which gives MACK messages
+        // with 6 tags, using a runtime entry with nt = 10
+        let chain = Chain {
+            maclt: 200,
+            ..test_chain_2023()
+        };
+        let key = Key::from_slice(
+            &hex!("33 4f d3 e5 68 c0 4e 2a 44 db a7 8a 03 01 c3 4a"),
+            Gst::new(1268, 208920),
+            &chain,
+        )
+        .force_valid();
+        let mack = test_mack_2023();
+        let prna = Svn::try_from(3).unwrap();
+        let gst = Gst::new(1268, 208890);
+        let mut registry = MacLTRegistry::<1>::new();
+        registry
+            .load("200 00S 00E 00E FLX FLX FLX FLX 04S 12S 00E")
+            .unwrap();
+        assert_eq!(
+            key.validate_macseq_with_registry(&mack, prna, gst, &registry),
+            Err(MacseqCheckError::MacLTError(MacLTError::WrongNumberOfTags))
+        );
+        assert_eq!(
+            key.compute_macseq_with_registry(&mack, prna, gst, &registry),
+            Err(MacLTError::WrongNumberOfTags)
+        );
+        assert_eq!(
+            chain.validate_adkd_with_registry(
+                1,
+                mack.num_tags(),
+                mack.tag_and_info(1),
+                prna,
+                gst,
+                &registry
+            ),
+            Err(AdkdCheckError::MacLTError(MacLTError::WrongNumberOfTags))
+        );
+        assert_eq!(
+            mack.validate_with_registry(&key, prna, gst, &registry)
+                .err(),
+            Some(crate::bitfields::MackValidationError::MacseqError(
+                MacseqCheckError::MacLTError(MacLTError::WrongNumberOfTags)
+            ))
+        );
     }
 }
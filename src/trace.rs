@@ -0,0 +1,286 @@
+//! MAC computation traces.
+//!
+//! This module contains the traces of the MAC computations done to validate
+//! tags and MACSEQ fields. They give the exact MAC input bytes, the TESLA key,
+//! and the computed and received values, so that they can be compared with
+//! those of other OSNMA implementations when debugging interoperability
+//! problems.
+//!
+//! The traces are obtained with
+//! [`Key::validate_tag_with_trace`](crate::tesla::Key::validate_tag_with_trace),
+//! [`Key::validate_tag0_with_trace`](crate::tesla::Key::validate_tag0_with_trace)
+//! and
+//! [`Key::validate_macseq_with_trace`](crate::tesla::Key::validate_macseq_with_trace).
+//! The [`Osnma`](crate::Osnma) black box can give the trace of each tag and
+//! MACSEQ that it validates to a [`MacTraceHook`] (see
+//! [`Osnma::with_mac_trace`](crate::Osnma::with_mac_trace)).
+
+use crate::bitfields::NmaStatus;
+use crate::types::BitSlice;
+use crate::{Gst, Svn};
+use bitvec::prelude::*;
+use core::fmt;
+
+/// Trace of the validation of a tag.
+///
+/// This gives the fields used to build the MAC input, as described in Section
+/// 6.7 of the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf),
+/// the MAC input itself, the TESLA key, and the computed and received tags.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TagTrace<'a> {
+    /// PRN_D field. This is `None` for the tag0, since the PRN_D is not part
+    /// of its MAC input.
+    pub prnd: Option<u8>,
+    /// PRN_A field.
+    pub prna: Svn,
+    /// GST_SF field, which is the GST at the start of the subframe in which the
+    /// tag was transmitted.
+    pub gst: Gst,
+    /// CTR field.
+    pub ctr: u8,
+    /// NMA status (NMAS field).
+    pub nma_status: NmaStatus,
+    /// Navigation data authenticated by the tag.
+    pub navdata: &'a BitSlice,
+    /// MAC input bytes, including the zero padding at the end.
+    pub mac_input: &'a [u8],
+    /// TESLA key used as MAC key.
+    pub key: &'a [u8],
+    /// Tag computed from the MAC input.
+    pub computed_tag: &'a BitSlice,
+    /// Tag received in the MACK message.
+    pub received_tag: &'a BitSlice,
+}
+
+impl TagTrace<'_> {
+    /// Returns `true` if the computed tag matches the received tag.
+    pub fn is_valid(&self) -> bool {
+        self.computed_tag == self.received_tag
+    }
+}
+
+impl fmt::Display for TagTrace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.prnd {
+            Some(prnd) => write!(f, "PRN_D = {prnd}, ")?,
+            None => "tag0, ".fmt(f)?,
+        }
+        write!(
+            f,
+            "PRN_A = {}, GST_SF = {}:{}, CTR = {}, NMAS = {:?}, MAC input = ",
+            u8::from(self.prna),
+            self.gst.wn(),
+            self.gst.tow(),
+            self.ctr,
+            self.nma_status
+        )?;
+        write_hex(f, self.mac_input)?;
+        ", key = ".fmt(f)?;
+        write_hex(f, self.key)?;
+        ", computed tag = ".fmt(f)?;
+        write_hex_bits(f, self.computed_tag)?;
+        ", received tag = ".fmt(f)?;
+        write_hex_bits(f, self.received_tag)
+    }
+}
+
+/// Trace of the validation of a MACSEQ field.
+///
+/// This gives the MAC input described in Section 6.6 of the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf),
+/// which contains the PRN_A, the GST_SF and the Tag-Info sections of the FLX
+/// tags, the TESLA key, and the computed and received MACSEQ.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MacseqTrace<'a> {
+    /// PRN_A field.
+    pub prna: Svn,
+    /// GST_SF field, which is the GST at the start of the subframe in which the
+    /// MACK message was transmitted.
+    pub gst: Gst,
+    /// MAC input bytes.
+    pub mac_input: &'a [u8],
+    /// TESLA key used as MAC key.
+    pub key: &'a [u8],
+    /// MACSEQ computed from the MAC input.
+    pub computed_macseq: u16,
+    /// MACSEQ received in the MACK message.
+    pub received_macseq: u16,
+}
+
+impl MacseqTrace<'_> {
+    /// Returns `true` if the computed MACSEQ matches the received MACSEQ.
+    pub fn is_valid(&self) -> bool {
+        self.computed_macseq == self.received_macseq
+    }
+}
+
+impl fmt::Display for MacseqTrace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PRN_A = {}, GST_SF = {}:{}, MAC input = ",
+            u8::from(self.prna),
+            self.gst.wn(),
+            self.gst.tow()
+        )?;
+        write_hex(f, self.mac_input)?;
+        ", key = ".fmt(f)?;
+        write_hex(f, self.key)?;
+        write!(
+            f,
+            ", computed MACSEQ = {:03x}, received MACSEQ = {:03x}",
+            self.computed_macseq, self.received_macseq
+        )
+    }
+}
+
+/// Trace of a MAC computation.
+///
+/// This is the argument of a [`MacTraceHook`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MacTrace<'a> {
+    /// Validation of a tag or tag0.
+    Tag(TagTrace<'a>),
+    /// Validation of the MACSEQ field of a MACK message.
+    Macseq(MacseqTrace<'a>),
+}
+
+impl MacTrace<'_> {
+    /// Returns `true` if the computed value matches the received value.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            MacTrace::Tag(trace) => trace.is_valid(),
+            MacTrace::Macseq(trace) => trace.is_valid(),
+        }
+    }
+}
+
+impl fmt::Display for MacTrace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacTrace::Tag(trace) => write!(f, "tag: {trace}"),
+            MacTrace::Macseq(trace) => write!(f, "MACSEQ: {trace}"),
+        }
+    }
+}
+
+/// Hook that receives MAC computation traces.
+///
+/// The [`Osnma`](crate::Osnma) black box stores the hook by value, so the
+/// hook can keep any state that it needs to process the traces, such as the
+/// writer where they are dumped, without requiring an allocator.
+///
+/// This trait is implemented for closures taking a `&MacTrace`, for
+/// `Option<H>`, which ignores the traces when it is `None`, and for `()`, which
+/// ignores all the traces and is the hook used by default.
+pub trait MacTraceHook {
+    /// Receives the trace of a MAC computation.
+    fn mac_trace(&mut self, trace: &MacTrace<'_>);
+
+    /// Returns `true` if the hook uses the traces.
+    ///
+    /// With the `parallel` feature, the traces of the tags validated
+    /// concurrently are only recorded if this returns `true`. The default
+    /// implementation returns `true`.
+    fn is_enabled(&self) -> bool {
+        true
+    }
+}
+
+impl<F: FnMut(&MacTrace<'_>)> MacTraceHook for F {
+    fn mac_trace(&mut self, trace: &MacTrace<'_>) {
+        self(trace)
+    }
+}
+
+impl<H: MacTraceHook> MacTraceHook for Option<H> {
+    fn mac_trace(&mut self, trace: &MacTrace<'_>) {
+        if let Some(hook) = self {
+            hook.mac_trace(trace);
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.as_ref().is_some_and(|hook| hook.is_enabled())
+    }
+}
+
+impl MacTraceHook for () {
+    fn mac_trace(&mut self, _trace: &MacTrace<'_>) {}
+
+    fn is_enabled(&self) -> bool {
+        false
+    }
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for b in bytes {
+        write!(f, "{b:02x}")?;
+    }
+    Ok(())
+}
+
+// Writes a bit slice in hexadecimal. If the length is not a multiple of 4, the
+// last digit is padded with zeros on the right.
+fn write_hex_bits(f: &mut fmt::Formatter<'_>, bits: &BitSlice) -> fmt::Result {
+    for nibble in bits.chunks(4) {
+        let value = nibble.load_be::<u8>() << (4 - nibble.len());
+        write!(f, "{value:x}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let tag = [0x8f, 0x54, 0x58];
+        let tag = &BitSlice::from_slice(&tag)[..20];
+        let trace = TagTrace {
+            prnd: Some(2),
+            prna: Svn::try_from(21).unwrap(),
+            gst: Gst::new(1176, 121050),
+            ctr: 3,
+            nma_status: NmaStatus::Test,
+            navdata: BitSlice::empty(),
+            mac_input: &[0x02, 0x15],
+            key: &[0x19, 0x58],
+            computed_tag: tag,
+            received_tag: tag,
+        };
+        assert_eq!(
+            MacTrace::Tag(trace).to_string(),
+            "tag: PRN_D = 2, PRN_A = 21, GST_SF = 1176:121050, CTR = 3, NMAS = Test, \
+             MAC input = 0215, key = 1958, computed tag = 8f545, received tag = 8f545"
+        );
+        let tag = &BitSlice::from_slice(&[0xff])[..6];
+        assert_eq!(
+            TagTrace {
+                prnd: None,
+                computed_tag: tag,
+                ..trace
+            }
+            .to_string(),
+            "tag0, PRN_A = 21, GST_SF = 1176:121050, CTR = 3, NMAS = Test, \
+             MAC input = 0215, key = 1958, computed tag = fc, received tag = 8f545"
+        );
+
+        let trace = MacseqTrace {
+            prna: Svn::try_from(3).unwrap(),
+            gst: Gst::new(1268, 208890),
+            mac_input: &[0x03],
+            key: &[0xab],
+            computed_macseq: 0x12,
+            received_macseq: 0xabc,
+        };
+        assert!(!trace.is_valid());
+        assert_eq!(
+            MacTrace::Macseq(trace).to_string(),
+            "MACSEQ: PRN_A = 3, GST_SF = 1268:208890, MAC input = 03, key = ab, \
+             computed MACSEQ = 012, received MACSEQ = abc"
+        );
+    }
+}
@@ -90,6 +90,8 @@ pub type Towh = u8;
 /// This is used because for ADKD = 4 OSNMA only applies to INAV data received
 /// on E1B, so we need to be able to distinguish the band of INAV frames.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InavBand {
     /// E1B band.
     E1B,
@@ -97,6 +99,14 @@ pub enum InavBand {
     E5B,
 }
 
+// Index of a band in arrays that store data for both bands.
+pub(crate) fn band_index(band: InavBand) -> usize {
+    match band {
+        InavBand::E1B => 0,
+        InavBand::E5B => 1,
+    }
+}
+
 /// ECDSA verifying key.
 ///
 /// This enum is either a P256 ECDSA key or a P521 ECDSA key.